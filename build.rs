@@ -0,0 +1,12 @@
+use vergen_gitcl::{Emitter, GitclBuilder, RustcBuilder};
+
+/// Embed the git commit and rustc version for `neko version --verbose`.
+/// Outside a git checkout (e.g. a source tarball) the values fall back to
+/// placeholders rather than failing the build.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    Emitter::default()
+        .add_instructions(&GitclBuilder::default().sha(true).dirty(true).build()?)?
+        .add_instructions(&RustcBuilder::default().semver(true).host_triple(true).build()?)?
+        .emit()?;
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use crate::llm::{Item, Role};
+
+/// Number of most recent items kept verbatim when compacting.
+pub const KEEP_RECENT_ITEMS: usize = 6;
+
+/// Maximum characters of a single tool output included in the summary input.
+const MAX_TOOL_OUTPUT_CHARS: usize = 500;
+
+pub const COMPACTION_INSTRUCTIONS: &str = "\
+You are compacting a conversation between a user and an AI assistant so it \
+can continue with less context. Write a concise summary that preserves: the \
+user's goals and preferences, decisions made, facts learned, files or \
+commands involved, and any open tasks or pending questions. Use short \
+bullet points. Do not add commentary.";
+
+/// Prefix marking the synthetic summary message in compacted history.
+pub const SUMMARY_PREFIX: &str = "[Summary of earlier conversation]";
+
+/// Index where the verbatim tail starts. The tail always begins at a user
+/// message so function calls are never separated from their outputs.
+/// Returns 0 when there is nothing worth compacting.
+pub fn split_point(history: &[Item], keep: usize) -> usize {
+    let mut idx = history.len().saturating_sub(keep);
+    while idx > 0 {
+        if matches!(history[idx], Item::Message { role: Role::User, .. }) {
+            return idx;
+        }
+        idx -= 1;
+    }
+    0
+}
+
+/// Render history items as plain text for the summarizer.
+pub fn render_transcript(items: &[Item]) -> String {
+    let mut out = String::new();
+    for item in items {
+        match item {
+            Item::Message { role, content } => {
+                let who = match role {
+                    Role::User => "User",
+                    Role::Assistant => "Assistant",
+                    Role::System => "System",
+                };
+                let image = if content.has_images() { " [image]" } else { "" };
+                out.push_str(&format!("{who}: {content}{image}\n\n"));
+            }
+            Item::FunctionCall { name, arguments, .. } => {
+                out.push_str(&format!("[tool call] {name}({arguments})\n"));
+            }
+            Item::FunctionCallOutput { output, .. } => {
+                let truncated: String = output.chars().take(MAX_TOOL_OUTPUT_CHARS).collect();
+                out.push_str(&format!("[tool output] {truncated}\n\n"));
+            }
+            Item::Reasoning(_) | Item::Other(_) => {}
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(role: Role, content: &str) -> Item {
+        Item::Message {
+            role,
+            content: content.into(),
+        }
+    }
+
+    #[test]
+    fn test_split_point_starts_at_user_message() {
+        let history = vec![
+            msg(Role::User, "a"),
+            msg(Role::Assistant, "b"),
+            msg(Role::User, "c"),
+            Item::FunctionCall {
+                id: "1".into(),
+                call_id: "c1".into(),
+                name: "exec".into(),
+                arguments: "{}".into(),
+            },
+            Item::FunctionCallOutput {
+                call_id: "c1".into(),
+                output: "ok".into(),
+            },
+            msg(Role::Assistant, "d"),
+        ];
+        // Tail of 2 would start at the function output; back up to "c".
+        assert_eq!(split_point(&history, 2), 2);
+        assert_eq!(split_point(&history, 10), 0);
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+
+/// Tool calls paused until the user replies yes or no, keyed by session ID.
+/// Shared between the turns that wait and the gateway that routes replies.
+#[derive(Debug, Default)]
+pub struct PendingConfirmations {
+    waiting: Mutex<HashMap<String, Pending>>,
+}
+
+#[derive(Debug)]
+struct Pending {
+    /// Only this sender may answer; `None` means nobody can.
+    requester: Option<String>,
+    tx: oneshot::Sender<bool>,
+}
+
+impl PendingConfirmations {
+    /// Start waiting for `requester`'s reply in `session_id`. An earlier
+    /// confirmation still pending in the same session is dropped, which
+    /// denies it.
+    pub fn register(&self, session_id: &str, requester: Option<&str>) -> oneshot::Receiver<bool> {
+        let (tx, rx) = oneshot::channel();
+        let pending = Pending {
+            requester: requester.map(String::from),
+            tx,
+        };
+        self.waiting.lock().unwrap().insert(session_id.to_string(), pending);
+        rx
+    }
+
+    /// Hand `sender_id`'s answer to the waiting turn. Returns false, leaving
+    /// the confirmation pending, if nothing in `session_id` was waiting or
+    /// `sender_id` is not the one who was asked. In a group anyone can read
+    /// the prompt, but only the requester gets to approve it.
+    pub fn resolve(&self, session_id: &str, sender_id: &str, approved: bool) -> bool {
+        let mut waiting = self.waiting.lock().unwrap();
+        if waiting.get(session_id).and_then(|p| p.requester.as_deref()) != Some(sender_id) {
+            return false;
+        }
+        match waiting.remove(session_id) {
+            Some(pending) => pending.tx.send(approved).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Deny whatever is pending in `session_id`, regardless of who was asked.
+    pub fn cancel(&self, session_id: &str) {
+        if let Some(pending) = self.waiting.lock().unwrap().remove(session_id) {
+            let _ = pending.tx.send(false);
+        }
+    }
+
+    pub fn is_waiting(&self, session_id: &str) -> bool {
+        self.waiting
+            .lock()
+            .unwrap()
+            .get(session_id)
+            .is_some_and(|pending| !pending.tx.is_closed())
+    }
+}
+
+/// Lets a turn pause on risky tool calls and ask its session for approval.
+#[derive(Debug, Clone)]
+pub struct ConfirmationGate {
+    pub session_id: String,
+    /// The sender whose message started the turn; only they can approve.
+    pub requester: Option<String>,
+    pub pending: Arc<PendingConfirmations>,
+    /// Unanswered confirmations are denied after this long.
+    pub timeout: Duration,
+}
+
+/// Read a reply to a confirmation prompt: `Some(true)` for yes,
+/// `Some(false)` for no, `None` for anything else.
+pub fn parse_reply(text: &str) -> Option<bool> {
+    let text = text.trim().trim_end_matches(['.', '!']).to_lowercase();
+    match text.as_str() {
+        "yes" | "y" | "ok" | "approve" | "/yes" => Some(true),
+        "no" | "n" | "cancel" | "deny" | "/no" => Some(false),
+        _ => None,
+    }
+}
+
+/// The prompt sent to the channel for a pending `tool` call.
+pub fn prompt(tool: &str, arguments: &str) -> String {
+    let arguments = match serde_json::from_str::<serde_json::Value>(arguments) {
+        Ok(value) => serde_json::to_string_pretty(&value).unwrap_or_else(|_| arguments.to_string()),
+        Err(_) => arguments.to_string(),
+    };
+    format!("Run `{tool}` with:\n{arguments}\n\nReply yes or no.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reply() {
+        assert_eq!(parse_reply(" Yes! "), Some(true));
+        assert_eq!(parse_reply("n"), Some(false));
+        assert_eq!(parse_reply("yes please"), None);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_wakes_waiting_turn() {
+        let pending = PendingConfirmations::default();
+        let rx = pending.register("s1", Some("alice"));
+        assert!(pending.is_waiting("s1"));
+        assert!(!pending.resolve("s2", "alice", true));
+        assert!(pending.resolve("s1", "alice", true));
+        assert_eq!(rx.await, Ok(true));
+        assert!(!pending.is_waiting("s1"));
+    }
+
+    #[tokio::test]
+    async fn test_only_requester_can_answer() {
+        let pending = PendingConfirmations::default();
+        let rx = pending.register("group", Some("alice"));
+        assert!(!pending.resolve("group", "mallory", true));
+        assert!(pending.is_waiting("group"));
+        assert!(pending.resolve("group", "alice", false));
+        assert_eq!(rx.await, Ok(false));
+
+        let _rx = pending.register("api", None);
+        assert!(!pending.resolve("api", "anyone", true));
+        pending.cancel("api");
+        assert!(!pending.is_waiting("api"));
+    }
+}
@@ -2,10 +2,12 @@ use std::path::Path;
 
 use walkdir::WalkDir;
 
+use super::TurnOverrides;
 use crate::config::AgentConfig;
 use crate::skills::{self, Skill};
 
-const MAX_CORE_MEMORY_CHARS: usize = 2000;
+/// Size budget for MEMORY.md; past it the prompt asks the model to compact.
+pub const MAX_CORE_MEMORY_CHARS: usize = 2000;
 
 const DEFAULT_INSTRUCTIONS: &str = "\
 You are Neko, a helpful AI assistant with persistent memory.
@@ -19,27 +21,33 @@ Your memory is file-based in the `memory/` directory:
 ### Memory Tools
 - `memory_write(file, content, append)` \u{2014} Write/append to a memory file
 - `memory_replace(file, old_text, new_text)` \u{2014} Update or delete facts (empty new_text = delete)
-- `memory_search(query)` \u{2014} Search across all memory files
+- `memory_search(query, since, until)` \u{2014} Search across all memory files (since/until scope recall logs by date)
+- `kv(action, namespace, key, value)` \u{2014} Structured JSON state (counters, flags, small records) in memory/kv.json
 
 ### Guidelines
 - Update MEMORY.md when you learn important facts about the user
 - Use `memory_replace` to correct outdated info \u{2014} don't let stale facts accumulate
-- Use daily logs for ephemeral notes, MEMORY.md for durable facts
+- Use daily logs for ephemeral notes, MEMORY.md for durable facts, `kv` for values you update programmatically
 - Search recall logs when you need context from past conversations
 
+During long multi-step tasks, call `status_update` with a brief note so the user knows you are still working.
+
 Be concise and helpful.";
 
-/// Build the memory file tree listing with char counts.
-fn build_memory_file_tree(workspace: &Path) -> Option<String> {
+/// Files under `memory/` (workspace-relative, sorted) with their char
+/// counts. Hidden entries such as `.backups/` are left out.
+pub fn memory_files(workspace: &Path) -> Vec<(String, usize)> {
     let memory_dir = workspace.join("memory");
     if !memory_dir.exists() {
-        return None;
+        return Vec::new();
     }
 
     let mut entries: Vec<(String, usize)> = Vec::new();
 
     for entry in WalkDir::new(&memory_dir)
         .into_iter()
+        // Skip hidden entries such as `.backups/`
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
         .filter_map(|e| e.ok())
     {
         let path = entry.path();
@@ -60,12 +68,17 @@ fn build_memory_file_tree(workspace: &Path) -> Option<String> {
         entries.push((rel_path, chars));
     }
 
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    entries
+}
+
+/// Build the memory file tree listing with char counts.
+fn build_memory_file_tree(workspace: &Path) -> Option<String> {
+    let entries = memory_files(workspace);
     if entries.is_empty() {
         return None;
     }
 
-    entries.sort_by(|a, b| a.0.cmp(&b.0));
-
     let mut lines = vec!["## Memory Files".to_string()];
     for (path, chars) in &entries {
         lines.push(format!("  {path} ({chars} chars)"));
@@ -74,12 +87,71 @@ fn build_memory_file_tree(workspace: &Path) -> Option<String> {
     Some(lines.join("\n"))
 }
 
+/// `[agent] context_files` under a "## Reference" heading, within
+/// `context_files_max_chars`. Files outside the workspace or missing are
+/// skipped.
+fn build_reference_section(config: &AgentConfig, workspace: &Path) -> Option<String> {
+    if config.context_files.is_empty() {
+        return None;
+    }
+    let root = workspace.canonicalize().ok()?;
+    let mut remaining = match config.context_files_max_chars {
+        0 => usize::MAX,
+        n => n,
+    };
+    let mut section = String::from("\n## Reference\n");
+    let mut included = 0;
+    for relative in &config.context_files {
+        if remaining == 0 {
+            break;
+        }
+        let Ok(path) = root.join(relative).canonicalize() else {
+            continue;
+        };
+        if !path.starts_with(&root) {
+            tracing::warn!("Skipping context file outside the workspace: {relative}");
+            continue;
+        }
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let chars = content.chars().count();
+        let content = if chars > remaining {
+            let cut: String = content.chars().take(remaining).collect();
+            format!("{cut}\n\n[... truncated: context_files_max_chars reached]")
+        } else {
+            content
+        };
+        remaining = remaining.saturating_sub(chars);
+        section.push_str(&format!("\n### {relative}\n\n{}\n", content.trim_end()));
+        included += 1;
+    }
+    (included > 0).then_some(section)
+}
+
 /// Build the system instructions for the agent.
 pub fn build_instructions(config: &AgentConfig, workspace: &Path, skills: &[Skill]) -> String {
+    let skills: Vec<&Skill> = skills.iter().collect();
+    build_turn_instructions(config, workspace, workspace, &skills, 0, &TurnOverrides::default())
+}
+
+/// Build the system instructions, applying per-session overrides.
+/// `omitted_skills` is how many installed skills were left out of
+/// `skills` by `[skills] max_in_prompt`.
+pub fn build_turn_instructions(
+    config: &AgentConfig,
+    workspace: &Path,
+    file_root: &Path,
+    skills: &[&Skill],
+    omitted_skills: usize,
+    overrides: &TurnOverrides,
+) -> String {
     let mut parts = Vec::new();
 
-    // Base instructions
-    if let Some(instructions) = &config.instructions {
+    // Base instructions — a session override supersedes the config
+    if let Some(instructions) = &overrides.instructions {
+        parts.push(instructions.clone());
+    } else if let Some(instructions) = &config.instructions {
         parts.push(instructions.clone());
     } else {
         parts.push(DEFAULT_INSTRUCTIONS.to_string());
@@ -110,35 +182,34 @@ pub fn build_instructions(config: &AgentConfig, workspace: &Path, skills: &[Skil
         parts.push(section);
     }
 
-    // Load today's daily log
-    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
-    let daily_log_path = memory_dir.join(format!("{today}.md"));
-
-    // Ensure daily log exists
-    if !daily_log_path.exists() {
-        let _ = std::fs::create_dir_all(&memory_dir);
-        let _ = std::fs::write(&daily_log_path, format!("# Daily Log: {today}\n\n"));
-    }
+    if config.daily_logs {
+        // Load today's daily log
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        let daily_log_path = memory_dir.join(format!("{today}.md"));
+        if let Ok(daily_log) = std::fs::read_to_string(&daily_log_path) {
+            if daily_log.lines().count() > 2 {
+                parts.push(format!("\n## Today's Log\n\n{daily_log}"));
+            }
+        }
 
-    if let Ok(daily_log) = std::fs::read_to_string(&daily_log_path) {
-        if daily_log.lines().count() > 2 {
-            parts.push(format!("\n## Today's Log\n\n{daily_log}"));
+        // Load also yesterday's log (OpenClaw pattern)
+        let yesterday = (chrono::Local::now() - chrono::Duration::days(1))
+            .format("%Y-%m-%d")
+            .to_string();
+        let yesterday_log_path = memory_dir.join(format!("{yesterday}.md"));
+        if let Ok(yesterday_log) = std::fs::read_to_string(&yesterday_log_path) {
+            if yesterday_log.lines().count() > 2 {
+                parts.push(format!("\n## Yesterday's Log\n\n{yesterday_log}"));
+            }
         }
     }
 
-    // Load also yesterday's log (OpenClaw pattern)
-    let yesterday = (chrono::Local::now() - chrono::Duration::days(1))
-        .format("%Y-%m-%d")
-        .to_string();
-    let yesterday_log_path = memory_dir.join(format!("{yesterday}.md"));
-    if let Ok(yesterday_log) = std::fs::read_to_string(&yesterday_log_path) {
-        if yesterday_log.lines().count() > 2 {
-            parts.push(format!("\n## Yesterday's Log\n\n{yesterday_log}"));
-        }
+    if let Some(reference) = build_reference_section(config, workspace) {
+        parts.push(reference);
     }
 
     // Available skills (progressive disclosure — just metadata)
-    let xml = skills::skills_to_prompt_xml(skills);
+    let xml = skills::skills_to_prompt_xml(skills.iter().copied());
     if !xml.is_empty() {
         parts.push(format!(
             "\n## Available Skills\n\n\
@@ -146,6 +217,35 @@ pub fn build_instructions(config: &AgentConfig, workspace: &Path, skills: &[Skil
              {xml}"
         ));
     }
+    if omitted_skills > 0 {
+        parts.push(format!(
+            "\n{omitted_skills} more skill(s) are installed but not listed here. \
+             Use `skill_search` to find one that fits the task."
+        ));
+    }
+
+    if let Some(locale) = &overrides.locale {
+        parts.push(format!(
+            "\n## Language\n\nRespond in {locale}, even if earlier messages were in another language."
+        ));
+    }
+
+    if !overrides.attachments.is_empty() {
+        let files: Vec<String> = overrides
+            .attachments
+            .iter()
+            // File tools resolve paths against the file root
+            .map(|a| {
+                let path = a.path.strip_prefix(file_root).unwrap_or(&a.path);
+                format!("- {} ({})", path.display(), a.mime_type)
+            })
+            .collect();
+        parts.push(format!(
+            "\n## Attachments\n\nThe user's message came with these files:\n{}\n\n\
+             Open them with the file tools when the message refers to them. To reply with a file (e.g. an edited image or a report), use `send_file`.",
+            files.join("\n")
+        ));
+    }
 
     parts.join("\n")
 }
@@ -165,6 +265,60 @@ mod tests {
         assert!(instructions.contains("memory_replace"));
     }
 
+    #[test]
+    fn test_attachments_listed() {
+        let tmp = TempDir::new().unwrap();
+        let overrides = TurnOverrides {
+            attachments: vec![crate::channels::Attachment {
+                path: tmp.path().join("inbox/telegram/42/7.jpg"),
+                mime_type: "image/jpeg".to_string(),
+            }],
+            ..TurnOverrides::default()
+        };
+        let config = AgentConfig::default();
+        let instructions =
+            build_turn_instructions(&config, tmp.path(), tmp.path(), &[], 0, &overrides);
+        assert!(instructions.contains("- inbox/telegram/42/7.jpg (image/jpeg)"));
+        assert!(instructions.contains("send_file"));
+
+        // Listed relative to the file root the tools resolve against
+        let root = tmp.path().join("inbox");
+        let instructions = build_turn_instructions(&config, tmp.path(), &root, &[], 0, &overrides);
+        assert!(instructions.contains("- telegram/42/7.jpg (image/jpeg)"));
+
+        let plain =
+            build_turn_instructions(&config, tmp.path(), tmp.path(), &[], 0, &TurnOverrides::default());
+        assert!(!plain.contains("## Attachments"));
+    }
+
+    #[test]
+    fn test_context_files() {
+        let tmp = TempDir::new().unwrap();
+        let workspace = tmp.path().join("ws");
+        std::fs::create_dir_all(workspace.join("docs")).unwrap();
+        std::fs::write(workspace.join("docs/glossary.md"), "Neko: a cat.").unwrap();
+        std::fs::write(workspace.join("conventions.md"), "x".repeat(50)).unwrap();
+        std::fs::write(tmp.path().join("secret.md"), "outside").unwrap();
+
+        let config = AgentConfig {
+            context_files: vec![
+                "docs/glossary.md".to_string(),
+                "../secret.md".to_string(),
+                "missing.md".to_string(),
+                "conventions.md".to_string(),
+            ],
+            context_files_max_chars: 20,
+            ..AgentConfig::default()
+        };
+        let instructions = build_instructions(&config, &workspace, &[]);
+        assert!(instructions.contains("## Reference"));
+        assert!(instructions.contains("### docs/glossary.md\n\nNeko: a cat."));
+        assert!(!instructions.contains("outside"));
+        // 12 chars of budget used by the glossary; 8 left for conventions.md
+        assert!(instructions.contains(&format!("{}\n\n[... truncated", "x".repeat(8))));
+        assert!(!instructions.contains(&"x".repeat(9)));
+    }
+
     #[test]
     fn test_build_instructions_with_memory() {
         let tmp = TempDir::new().unwrap();
@@ -212,6 +366,19 @@ mod tests {
         assert!(instructions.contains("chars)"));
     }
 
+    #[test]
+    fn test_memory_file_tree_skips_backups() {
+        let tmp = TempDir::new().unwrap();
+        let backups = tmp.path().join("memory").join(".backups");
+        std::fs::create_dir_all(&backups).unwrap();
+        std::fs::write(backups.join("MEMORY.md.20260101T000000000"), "old").unwrap();
+        std::fs::write(tmp.path().join("memory").join("MEMORY.md"), "new").unwrap();
+
+        let tree = build_memory_file_tree(tmp.path()).unwrap();
+        assert!(tree.contains("memory/MEMORY.md"));
+        assert!(!tree.contains(".backups"));
+    }
+
     #[test]
     fn test_memory_size_warning() {
         let tmp = TempDir::new().unwrap();
@@ -1,14 +1,19 @@
+use std::collections::HashMap;
+
 use tracing::{debug, warn};
 
 use crate::error::{NekoError, Result};
+use crate::llm;
 use crate::tools::{ToolContext, ToolRegistry, ToolResult};
 
-/// Execute a single tool call.
+/// Execute a single tool call. Output longer than `max_output_bytes`
+/// (0 = unlimited) is truncated with [`truncate_output`].
 pub async fn execute_tool(
     registry: &ToolRegistry,
     tool_name: &str,
     arguments_json: &str,
     ctx: &ToolContext,
+    max_output_bytes: usize,
 ) -> Result<ToolResult> {
     let tool = registry
         .get(tool_name)
@@ -21,7 +26,15 @@ pub async fn execute_tool(
     })?;
 
     debug!("Executing tool: {tool_name}");
-    let result = tool.execute(params, ctx).await?;
+    let mut result = tool.execute(params, ctx).await?;
+
+    if max_output_bytes > 0 && result.output.len() > max_output_bytes {
+        debug!(
+            "Truncating {tool_name} output: {} -> {max_output_bytes} bytes",
+            result.output.len()
+        );
+        result.output = truncate_output(&result.output, max_output_bytes);
+    }
 
     if result.is_error {
         warn!("Tool {tool_name} returned error: {}", &result.output[..result.output.len().min(200)]);
@@ -29,3 +42,220 @@ pub async fn execute_tool(
 
     Ok(result)
 }
+
+/// Whether the named tool wants approval before running with these
+/// arguments. Unknown tools and unparsable arguments fail in
+/// [`execute_tool`] anyway, so they don't ask.
+pub fn requires_confirmation(registry: &ToolRegistry, tool_name: &str, arguments_json: &str) -> bool {
+    let Some(tool) = registry.get(tool_name) else {
+        return false;
+    };
+    serde_json::from_str::<serde_json::Value>(arguments_json)
+        .is_ok_and(|params| tool.requires_confirmation(&params))
+}
+
+/// Items since the last user message, newest first.
+fn current_turn(history: &[llm::Item]) -> Vec<&llm::Item> {
+    history
+        .iter()
+        .rev()
+        .take_while(|item| !matches!(item, llm::Item::Message { role: llm::Role::User, .. }))
+        .collect()
+}
+
+/// Whether a tool already reached the user this turn (`send_message`,
+/// `status_update` or `send_file` without an error), in which case an
+/// empty final reply is expected rather than a failure.
+pub fn delivered_this_turn(history: &[llm::Item]) -> bool {
+    let turn = current_turn(history);
+    turn.iter().any(|item| match item {
+        llm::Item::FunctionCall { name, call_id, .. }
+            if matches!(name.as_str(), "send_message" | "status_update" | "send_file") =>
+        {
+            turn.iter().any(|item| {
+                matches!(item, llm::Item::FunctionCallOutput { call_id: id, output }
+                    if id == call_id && !output.starts_with("[ERROR]"))
+            })
+        }
+        _ => false,
+    })
+}
+
+/// Stand-in for an empty final reply: `fallback`, the tools called since
+/// the last user message and the last error one of them returned.
+pub fn fallback_reply(fallback: &str, history: &[llm::Item]) -> String {
+    let turn = current_turn(history);
+    let mut tried: Vec<&str> = Vec::new();
+    for item in turn.iter().rev() {
+        if let llm::Item::FunctionCall { name, .. } = item {
+            if !tried.contains(&name.as_str()) {
+                tried.push(name);
+            }
+        }
+    }
+    let last_error = turn.iter().find_map(|item| match item {
+        llm::Item::FunctionCallOutput { output, .. } => output.strip_prefix("[ERROR] "),
+        _ => None,
+    });
+
+    let mut reply = fallback.to_string();
+    if !tried.is_empty() {
+        reply.push_str(&format!(" Here's what I tried: {}.", tried.join(", ")));
+    }
+    if let Some(error) = last_error {
+        let error: String = error.chars().take(300).collect();
+        reply.push_str(&format!("\nLast error: {}", error.trim()));
+    }
+    reply
+}
+
+/// Keep the first two thirds and last third of `max` bytes of `output`
+/// (on char boundaries), joined by a `[truncated, N of M bytes]` marker.
+/// Heads carry headers and the start of a listing; tails carry the error
+/// or summary line a command printed last.
+pub fn truncate_output(output: &str, max: usize) -> String {
+    let total = output.len();
+    if total <= max {
+        return output.to_string();
+    }
+
+    let mut head_end = max * 2 / 3;
+    while !output.is_char_boundary(head_end) {
+        head_end -= 1;
+    }
+    let mut tail_start = total - (max - head_end);
+    while !output.is_char_boundary(tail_start) {
+        tail_start += 1;
+    }
+
+    let kept = head_end + (total - tail_start);
+    format!(
+        "{}\n\n[truncated, {kept} of {total} bytes]\n\n{}",
+        &output[..head_end],
+        &output[tail_start..]
+    )
+}
+
+/// Failures of identical tool calls (same tool, same arguments) within one
+/// turn, to catch the model retrying a call that can't succeed.
+#[derive(Debug, Default)]
+pub struct RepeatedFailures {
+    counts: HashMap<(String, u64), u32>,
+}
+
+impl RepeatedFailures {
+    fn key(tool_name: &str, arguments_json: &str) -> (String, u64) {
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        arguments_json.trim().hash(&mut hasher);
+        (tool_name.to_string(), hasher.finish())
+    }
+
+    /// Record the outcome of a call. Returns how many times in a row it has
+    /// now failed; a success starts the count over.
+    pub fn record(&mut self, tool_name: &str, arguments_json: &str, failed: bool) -> u32 {
+        let key = Self::key(tool_name, arguments_json);
+        if !failed {
+            self.counts.remove(&key);
+            return 0;
+        }
+        let count = self.counts.entry(key).or_insert(0);
+        *count += 1;
+        *count
+    }
+
+    pub fn count(&self, tool_name: &str, arguments_json: &str) -> u32 {
+        self.counts
+            .get(&Self::key(tool_name, arguments_json))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fallback_reply_names_tools_and_last_error() {
+        let call = |name: &str| llm::Item::FunctionCall {
+            id: String::new(),
+            call_id: name.to_string(),
+            name: name.to_string(),
+            arguments: "{}".to_string(),
+        };
+        let output = |text: &str| llm::Item::FunctionCallOutput {
+            call_id: String::new(),
+            output: text.to_string(),
+        };
+        let history = vec![
+            call("read_file"),
+            llm::Item::Message {
+                role: llm::Role::User,
+                content: "build it".into(),
+            },
+            call("exec"),
+            output("[ERROR] cargo: command not found"),
+            call("exec"),
+            output("ok"),
+        ];
+        assert_eq!(
+            fallback_reply("Sorry.", &history),
+            "Sorry. Here's what I tried: exec.\nLast error: cargo: command not found"
+        );
+    }
+
+    #[test]
+    fn test_delivered_this_turn() {
+        let call = |call_id: &str, name: &str| llm::Item::FunctionCall {
+            id: String::new(),
+            call_id: call_id.to_string(),
+            name: name.to_string(),
+            arguments: "{}".to_string(),
+        };
+        let output = |call_id: &str, text: &str| llm::Item::FunctionCallOutput {
+            call_id: call_id.to_string(),
+            output: text.to_string(),
+        };
+        let user = llm::Item::Message {
+            role: llm::Role::User,
+            content: "send me the report".into(),
+        };
+        let failed = vec![
+            user.clone(),
+            call("1", "send_file"),
+            output("1", "[ERROR] file not found"),
+        ];
+        assert!(!delivered_this_turn(&failed));
+        let sent = vec![user, call("1", "send_message"), output("1", "Message sent")];
+        assert!(delivered_this_turn(&sent));
+    }
+
+    #[test]
+    fn test_truncate_output_keeps_head_and_tail() {
+        let output = format!("HEAD{}TAIL", "x".repeat(10_000));
+        let truncated = truncate_output(&output, 300);
+        assert!(truncated.starts_with("HEAD"));
+        assert!(truncated.ends_with("TAIL"));
+        assert!(truncated.contains("[truncated, 300 of 10008 bytes]"));
+
+        assert_eq!(truncate_output("short", 300), "short");
+
+        // Never splits a multi-byte char
+        let wide = "é".repeat(1000);
+        assert!(truncate_output(&wide, 101).contains("[truncated,"));
+    }
+
+    #[test]
+    fn test_repeated_failures_counts_identical_calls() {
+        let mut failures = RepeatedFailures::default();
+        assert_eq!(failures.record("exec", r#"{"command":"make"}"#, true), 1);
+        assert_eq!(failures.record("exec", r#"{"command":"make"}"#, true), 2);
+        assert_eq!(failures.record("exec", r#"{"command":"make all"}"#, true), 1);
+        assert_eq!(failures.count("exec", r#"{"command":"make"}"#), 2);
+
+        failures.record("exec", r#"{"command":"make"}"#, false);
+        assert_eq!(failures.count("exec", r#"{"command":"make"}"#), 0);
+    }
+}
@@ -1,22 +1,57 @@
+pub mod compaction;
+pub mod confirm;
 pub mod context;
 pub mod loop_runner;
 
+use std::collections::HashMap;
+use std::future::Future;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
+use regex::Regex;
+use tokio::sync::{mpsc, Semaphore};
 use tracing::{debug, info, warn};
 
-use crate::channels::Attachment;
-use crate::config::AgentConfig;
+use crate::budget::DailyBudget;
+use crate::channels::{Attachment, OutboundMessage};
+use crate::config::{AgentConfig, SkillsConfig};
 use crate::error::{NekoError, Result};
 use crate::llm;
+use crate::memory_index::{self, Embedder};
+use crate::session::{SessionKey, SessionStore};
 use crate::tools::{ChannelContext, ToolContext, ToolRegistry};
-use crate::skills::Skill;
+use crate::skills::{self, Skill};
+
+/// How many times a turn shrinks its history and retries after the
+/// provider reports the context window is exceeded.
+const MAX_CONTEXT_RETRIES: u32 = 1;
+
+/// Tool outputs are cut to this when history is shrunk without compaction.
+const SHRUNK_TOOL_OUTPUT_BYTES: usize = 4096;
+
+/// Per-session settings that adjust a single turn.
+#[derive(Debug, Clone, Default)]
+pub struct TurnOverrides {
+    /// Replaces `agent.instructions` (the base prompt).
+    pub instructions: Option<String>,
+    /// Language to respond in, e.g. "Spanish".
+    pub locale: Option<String>,
+    /// Set when the session wants risky tool calls approved first.
+    pub confirmation: Option<confirm::ConfirmationGate>,
+    /// Only these tools may be used, e.g. for a restricted API token.
+    pub allowed_tools: Option<Vec<String>>,
+    /// Files the user sent with this message.
+    pub attachments: Vec<Attachment>,
+    /// Replaces `agent.max_history` for this session.
+    pub max_history: Option<u32>,
+}
 
 /// Return value from a completed agent turn.
 pub struct TurnResult {
     pub text: String,
     pub history: Vec<llm::Item>,
+    /// Token usage summed over every model call of the turn.
     pub usage: Option<llm::Usage>,
     /// The last response ID — pass back on the next turn for seamless
     /// reasoning-item chaining via `previous_response_id`.
@@ -28,9 +63,34 @@ pub struct TurnResult {
 pub struct Agent {
     llm_client: llm::Client,
     tools: ToolRegistry,
-    config: AgentConfig,
+    /// Swapped by `set_config` when settings change at runtime.
+    config: RwLock<Arc<AgentConfig>>,
+    /// The provider's `model_map`, applied to `agent.model` per request.
+    model_map: HashMap<String, String>,
+    /// Tools offered to turns, set at runtime; `None` = all registered.
+    runtime_tools: RwLock<Option<Vec<String>>>,
     workspace: PathBuf,
-    skills: Vec<Skill>,
+    /// Installed skills; swapped wholesale by `reload_skills`.
+    skills: RwLock<Arc<Vec<Skill>>>,
+    /// How active skills' `allowed-tools` restrict the tool set.
+    skills_config: SkillsConfig,
+    /// Bounds concurrent in-flight LLM requests (`max_concurrent_requests`).
+    llm_permits: Arc<Semaphore>,
+    workspace_quota: Option<u64>,
+    /// `[tools] file_root`; `None` = the workspace.
+    file_root: Option<PathBuf>,
+    /// `[tools] allow_internal_paths`.
+    allow_internal_paths: bool,
+    /// `tools.max_output_bytes`; 0 = unlimited.
+    max_tool_output: usize,
+    /// Memory embeddings index, refreshed after each logged turn.
+    embedder: Option<Arc<Embedder>>,
+    /// Compiled `output_filters`, applied to the final response text.
+    output_filters: Vec<Regex>,
+    /// Gateway-wide daily token cap, shared with the other agents.
+    daily_budget: Option<Arc<DailyBudget>>,
+    /// Append each turn to `memory/recall/`.
+    recall_log: bool,
 }
 
 impl Agent {
@@ -39,30 +99,232 @@ impl Agent {
         tools: ToolRegistry,
         config: AgentConfig,
     ) -> Self {
+        let permits = match config.max_concurrent_requests {
+            0 => Semaphore::MAX_PERMITS,
+            n => n,
+        };
+        let output_filters = config
+            .output_filters
+            .iter()
+            .filter_map(|pattern| match Regex::new(pattern) {
+                Ok(re) => Some(re),
+                Err(e) => {
+                    warn!("Ignoring invalid output filter '{pattern}': {e}");
+                    None
+                }
+            })
+            .collect();
         Self {
             llm_client,
             tools,
-            config,
+            config: RwLock::new(Arc::new(config)),
+            runtime_tools: RwLock::new(None),
+            model_map: HashMap::new(),
             workspace: PathBuf::new(),
-            skills: Vec::new(),
+            skills: RwLock::new(Arc::new(Vec::new())),
+            skills_config: SkillsConfig::default(),
+            llm_permits: Arc::new(Semaphore::new(permits)),
+            workspace_quota: None,
+            file_root: None,
+            allow_internal_paths: false,
+            max_tool_output: 0,
+            embedder: None,
+            output_filters,
+            daily_budget: None,
+            recall_log: true,
         }
     }
 
+    /// Translate logical model names (`agent.model = "fast"`) to the
+    /// provider's own.
+    pub fn with_model_map(mut self, model_map: HashMap<String, String>) -> Self {
+        self.model_map = model_map;
+        self
+    }
+
+    /// The name sent to the provider for `model`.
+    fn resolve_model(&self, model: &str) -> String {
+        self.model_map.get(model).map_or(model, String::as_str).to_string()
+    }
+
     pub fn with_workspace(mut self, workspace: PathBuf) -> Self {
         self.workspace = workspace;
         self
     }
 
     pub fn with_skills(mut self, skills: Vec<Skill>) -> Self {
-        self.skills = skills;
+        self.skills = RwLock::new(Arc::new(skills));
+        self
+    }
+
+    /// Re-scan `workspace/skills` and `[skills] paths` so edited or newly installed skills apply
+    /// from the next turn. Returns how many skills are loaded.
+    pub fn reload_skills(&self) -> Result<usize> {
+        let skills = skills::load_skills(&self.workspace, &self.skills_config.extra_dirs())?;
+        let count = skills.len();
+        *self.skills.write().unwrap() = Arc::new(skills);
+        Ok(count)
+    }
+
+    pub fn with_skills_config(mut self, skills_config: SkillsConfig) -> Self {
+        self.skills_config = skills_config;
+        self
+    }
+
+    /// Confine file tools to this directory inside the workspace. Turns
+    /// also start there.
+    pub fn with_file_root(mut self, file_root: Option<PathBuf>) -> Self {
+        self.file_root = file_root;
+        self
+    }
+
+    /// Let file tools into `sessions/`, `cron/` and the memory internals.
+    pub fn with_internal_paths(mut self, allow: bool) -> Self {
+        self.allow_internal_paths = allow;
+        self
+    }
+
+    /// Soft workspace quota (bytes) enforced by file-writing tools.
+    pub fn with_workspace_quota(mut self, quota: Option<u64>) -> Self {
+        self.workspace_quota = quota;
+        self
+    }
+
+    /// Truncate tool outputs longer than `bytes` (0 = unlimited).
+    pub fn with_max_tool_output(mut self, bytes: usize) -> Self {
+        self.max_tool_output = bytes;
+        self
+    }
+
+    /// Share `permits` with other agents so `max_concurrent_requests`
+    /// bounds LLM requests across the whole gateway.
+    pub fn with_llm_permits(mut self, permits: Arc<Semaphore>) -> Self {
+        self.llm_permits = permits;
+        self
+    }
+
+    /// The semaphore bounding this agent's in-flight LLM requests.
+    pub fn llm_permits(&self) -> Arc<Semaphore> {
+        self.llm_permits.clone()
+    }
+
+    /// Count LLM usage against `budget` and refuse turns once it is spent.
+    pub fn with_daily_budget(mut self, budget: Option<Arc<DailyBudget>>) -> Self {
+        self.daily_budget = budget;
+        self
+    }
+
+    /// Log turns to `memory/recall/` (on by default; `neko bench` turns
+    /// it off).
+    pub fn with_recall_log(mut self, enabled: bool) -> Self {
+        self.recall_log = enabled;
+        self
+    }
+
+    /// Keep the semantic memory index up to date after each turn.
+    pub fn with_embedder(mut self, embedder: Option<Arc<Embedder>>) -> Self {
+        self.embedder = embedder;
         self
     }
 
-    /// Backward-compatible single-shot turn (no session, ephemeral history).
-    /// Used by `neko message` and the cron scheduler.
-    pub async fn run_turn(&self, user_message: &str) -> Result<String> {
+    /// The workspace this agent reads memory and skills from.
+    pub fn workspace(&self) -> &std::path::Path {
+        &self.workspace
+    }
+
+    /// Where channels save files sent to this agent: `inbox/` under the
+    /// file root, so its file tools can open them.
+    pub fn inbox_dir(&self) -> PathBuf {
+        self.file_root.as_deref().unwrap_or(&self.workspace).join("inbox")
+    }
+
+    pub fn workspace_quota(&self) -> Option<u64> {
+        self.workspace_quota
+    }
+
+    pub fn llm_client(&self) -> &llm::Client {
+        &self.llm_client
+    }
+
+    /// The current `[agent]` settings.
+    pub fn config(&self) -> Arc<AgentConfig> {
+        Arc::clone(&self.config.read().unwrap())
+    }
+
+    /// Replace the settings; turns already running keep the old ones.
+    pub fn set_config(&self, config: AgentConfig) {
+        *self.config.write().unwrap() = Arc::new(config);
+    }
+
+    /// Offer and run only `tools` from the next turn (`None` = all).
+    pub fn set_runtime_tools(&self, tools: Option<Vec<String>>) {
+        *self.runtime_tools.write().unwrap() = tools;
+    }
+
+    pub fn tool_names(&self) -> Vec<&str> {
+        self.tools.names()
+    }
+
+    /// Single-shot turn with no channel egress, used by `neko message`.
+    /// With `session`, the turn continues that persistent session;
+    /// without it, history is ephemeral.
+    pub async fn run_turn(
+        &self,
+        user_message: &str,
+        session: Option<(&SessionStore, &SessionKey)>,
+    ) -> Result<String> {
+        if let Some((store, key)) = session {
+            return self
+                .run_session_turn(store, key, "cli", user_message, None, None)
+                .await;
+        }
         let result = self
-            .run_turn_with_history(Vec::new(), user_message, None, None)
+            .run_turn_with_history(
+                Vec::new(),
+                user_message,
+                None,
+                None,
+                None,
+                &TurnOverrides::default(),
+            )
+            .await?;
+        Ok(result.text)
+    }
+
+    /// Run a turn in the persistent session `key`: load its history and
+    /// per-session overrides from `store`, then save the result back.
+    pub async fn run_session_turn(
+        &self,
+        store: &SessionStore,
+        key: &SessionKey,
+        channel: &str,
+        user_message: &str,
+        channel_context: Option<ChannelContext>,
+        outbound_tx: Option<mpsc::Sender<OutboundMessage>>,
+    ) -> Result<String> {
+        let session_id = store.get_or_create(key, Some(channel), None).await?;
+        let (history, prev_response_id) = store.get_history(&session_id).await?;
+        let meta = store.meta(&session_id).await?;
+        let overrides = TurnOverrides {
+            instructions: meta.instructions_override,
+            locale: meta.locale,
+            confirmation: None,
+            allowed_tools: None,
+            attachments: Vec::new(),
+            max_history: meta.max_history_override,
+        };
+        let result = self
+            .run_turn_with_history(
+                history,
+                user_message,
+                prev_response_id,
+                channel_context,
+                outbound_tx,
+                &overrides,
+            )
+            .await?;
+        store
+            .update_history(&session_id, result.history, result.usage.as_ref(), result.last_response_id)
             .await?;
         Ok(result.text)
     }
@@ -76,43 +338,97 @@ impl Agent {
     ///
     /// When `previous_response_id` is `None` (first message or after restart),
     /// the full history is sent as input and the model re-reasons from scratch.
+    ///
+    /// `outbound_tx` lets tools (e.g. `status_update`) message the channel
+    /// mid-turn; `overrides` carries per-session settings such as a persona.
     pub async fn run_turn_with_history(
         &self,
         mut history: Vec<llm::Item>,
         user_message: &str,
         previous_response_id: Option<String>,
         channel_context: Option<ChannelContext>,
+        outbound_tx: Option<mpsc::Sender<OutboundMessage>>,
+        overrides: &TurnOverrides,
     ) -> Result<TurnResult> {
+        if let Some(resets_at) = self.daily_budget.as_ref().and_then(|b| b.exhausted_until()) {
+            return Err(NekoError::Agent(format!(
+                "Daily token budget reached; turns resume at {}",
+                resets_at.format("%Y-%m-%d %H:%M UTC")
+            )));
+        }
+
+        // Settings can change mid-turn (`PATCH /api/v1/config`); a turn
+        // keeps the ones it started with.
+        let config = self.config();
+        let runtime_tools = self.runtime_tools.read().unwrap().clone();
+        let max_history = overrides.max_history.unwrap_or(config.max_history) as usize;
         let user_item = llm::Item::Message {
             role: llm::Role::User,
-            content: user_message.to_string(),
+            content: user_message.into(),
         };
         history.push(user_item.clone());
 
-        let instructions =
-            context::build_instructions(&self.config, &self.workspace, &self.skills);
-        let tool_defs = self.tools.tool_definitions();
+        let installed_skills = Arc::clone(&self.skills.read().unwrap());
+        let prompt_skills = skills::select_for_prompt(
+            &installed_skills,
+            self.skills_config.max_in_prompt,
+            user_message,
+        );
+        let file_root = self.file_root.clone().unwrap_or_else(|| self.workspace.clone());
+        let instructions = context::build_turn_instructions(
+            &config,
+            &self.workspace,
+            &file_root,
+            &prompt_skills,
+            installed_skills.len() - prompt_skills.len(),
+            overrides,
+        );
 
-        let max_iterations = self.config.max_iterations as usize;
-        let mut last_usage: Option<llm::Usage>;
+        let max_iterations = config.max_iterations as usize;
+        let mut usage: Option<llm::Usage> = None;
         let mut current_prev_id = previous_response_id;
         // Function-call outputs produced by the previous iteration,
         // sent as the sole input when chaining via previous_response_id.
         let mut pending_fc_outputs: Vec<llm::Item> = Vec::new();
+        // Set after a context-length error shrank `history`: the next
+        // request starts a fresh chain from the full (now shorter) history.
+        let mut resend_history = false;
+        let mut context_retries = 0;
 
         // Shared cwd — persists across iterations within a turn.
-        let cwd = Arc::new(Mutex::new(self.workspace.clone()));
+        let cwd = Arc::new(Mutex::new(file_root.clone()));
         // Attachments queued by send_file tool calls across iterations.
         let pending_attachments = Arc::new(Mutex::new(Vec::<Attachment>::new()));
+        // Turn watchdog — each LLM call and tool call must finish within this.
+        let idle_timeout = (config.turn_idle_timeout_secs > 0)
+            .then(|| Duration::from_secs(config.turn_idle_timeout_secs));
+        // Identical tool calls that keep failing this turn.
+        let mut failures = loop_runner::RepeatedFailures::default();
+        let failure_limit = config.repeated_failure_limit;
 
         for iteration in 0..max_iterations {
             debug!("Agent loop iteration {iteration}");
 
+            // Re-resolved each iteration: reading a SKILL.md activates it.
+            let active = skills::active_skills(&installed_skills, &history);
+            let allowed_tools = skills::resolve_allowed_tools(&self.skills_config, &active);
+            let tool_defs: Vec<_> = self
+                .tools
+                .tool_definitions()
+                .into_iter()
+                .filter(|d| allowed_tools.as_ref().is_none_or(|a| a.contains(&d.name)))
+                .filter(|d| overrides.allowed_tools.as_ref().is_none_or(|a| a.contains(&d.name)))
+                .filter(|d| runtime_tools.as_ref().is_none_or(|a| a.contains(&d.name)))
+                .collect();
+
             // Build input:
             //   iteration 0 + has prev_id  → just the new user message
             //   iteration 0 + no prev_id   → full history (fallback)
             //   iteration N (tool follow-up)→ only the new function_call_outputs
-            let input = if iteration == 0 {
+            //   after shrinking history     → full history
+            let input = if std::mem::take(&mut resend_history) {
+                llm::Input::Items(history.clone())
+            } else if iteration == 0 {
                 if current_prev_id.is_some() {
                     llm::Input::Items(vec![user_item.clone()])
                 } else {
@@ -123,51 +439,98 @@ impl Agent {
             };
 
             let request = llm::Request {
-                model: self.config.model.clone(),
+                model: self.resolve_model(&config.model),
                 input,
                 instructions: Some(instructions.clone()),
                 tools: if tool_defs.is_empty() {
                     None
                 } else {
-                    Some(tool_defs.clone())
+                    Some(tool_defs)
                 },
                 tool_choice: None,
                 stream: false,
-                temperature: None,
-                max_output_tokens: Some(self.config.max_tokens),
+                temperature: config.temperature,
+                max_output_tokens: Some(config.max_tokens),
                 previous_response_id: current_prev_id.clone(),
             };
 
-            let response = self.llm_client.create_response(&request).await?;
-
-            if response.status == llm::ResponseStatus::Failed {
-                let err_msg = response
-                    .error
-                    .map(|e| e.message)
-                    .unwrap_or_else(|| "Unknown LLM error".to_string());
-                return Err(NekoError::Llm(err_msg));
-            }
+            let response = {
+                // Queue here rather than fail when the provider is saturated.
+                let _permit = self
+                    .llm_permits
+                    .acquire()
+                    .await
+                    .map_err(|e| NekoError::Agent(format!("LLM semaphore closed: {e}")))?;
+                match with_idle_timeout(idle_timeout, self.llm_client.create_response(&request)).await {
+                    Some(result) => result,
+                    None => {
+                        return Ok(self.abort_idle_turn(
+                            history,
+                            usage,
+                            max_history,
+                            "waiting for the model",
+                            idle_timeout,
+                            &pending_attachments,
+                        ));
+                    }
+                }
+            };
+            let response = response.and_then(|r| {
+                if r.status == llm::ResponseStatus::Failed {
+                    Err(failed_response_error(r.error.as_ref()).into())
+                } else {
+                    Ok(r)
+                }
+            });
+
+            let response = match response {
+                Err(NekoError::Llm(llm::LlmError::ContextLengthExceeded(detail)))
+                    if context_retries < MAX_CONTEXT_RETRIES =>
+                {
+                    context_retries += 1;
+                    warn!("Context length exceeded ({detail}); shrinking history and retrying");
+                    history = self.shrink_history(history).await;
+                    current_prev_id = None;
+                    resend_history = true;
+                    continue;
+                }
+                other => other?,
+            };
 
             // Chain subsequent requests through this response.
             current_prev_id = Some(response.id.clone());
-            last_usage = response.usage.clone();
+            self.record_usage(response.usage.as_ref());
+            if let Some(u) = &response.usage {
+                let total = usage.get_or_insert_with(llm::Usage::default);
+                total.input_tokens += u.input_tokens;
+                total.output_tokens += u.output_tokens;
+                total.total_tokens += u.total_tokens;
+            }
 
             let function_calls = response.function_calls();
 
             if function_calls.is_empty() {
-                let text = response.text();
+                let mut text = self.filter_output(response.text());
+                // A turn that already sent a file or message may end silently.
+                let delivered = !pending_attachments.lock().unwrap().is_empty()
+                    || loop_runner::delivered_this_turn(&history);
+                if text.trim().is_empty() && !config.empty_reply_fallback.is_empty() && !delivered {
+                    warn!("Model ended the turn without a reply; sending the fallback");
+                    text = loop_runner::fallback_reply(&config.empty_reply_fallback, &history);
+                }
                 // Append simplified output for the persistent transcript —
                 // reasoning items are NOT included; the API handles them via
                 // previous_response_id on the next turn.
                 append_output_to_history(&mut history, &response.output);
                 strip_reasoning(&mut history);
-                trim_history(&mut history, self.config.max_history as usize);
+                trim_history(&mut history, max_history);
                 self.log_to_recall(user_message, &text);
+                self.spawn_index_sync();
                 let attachments = std::mem::take(&mut *pending_attachments.lock().unwrap());
                 return Ok(TurnResult {
                     text,
                     history,
-                    usage: last_usage,
+                    usage,
                     last_response_id: current_prev_id,
                     attachments,
                 });
@@ -182,6 +545,11 @@ impl Agent {
                 cwd: Arc::clone(&cwd),
                 pending_attachments: Arc::clone(&pending_attachments),
                 channel: channel_context.clone(),
+                outbound_tx: outbound_tx.clone(),
+                workspace_quota: self.workspace_quota,
+                file_root: file_root.clone(),
+                allow_internal_paths: self.allow_internal_paths,
+                read_only_dirs: self.skills_config.extra_dirs(),
             };
 
             let calls: Vec<(String, String, String)> = function_calls
@@ -189,16 +557,104 @@ impl Agent {
                 .map(|(id, name, args)| (id.to_string(), name.to_string(), args.to_string()))
                 .collect();
 
+            let mut stalled_tool: Option<String> = None;
             for (call_id, name, arguments) in calls {
-                let result = loop_runner::execute_tool(
+                if stalled_tool.is_some() {
+                    // Every function_call needs an output to keep history valid.
+                    history.push(llm::Item::FunctionCallOutput {
+                        call_id,
+                        output: "[ERROR] Skipped: turn aborted".to_string(),
+                    });
+                    continue;
+                }
+
+                if allowed_tools.as_ref().is_some_and(|a| !a.contains(&name)) {
+                    warn!("Model called {name}, which the active skills don't allow");
+                    let fc_output = llm::Item::FunctionCallOutput {
+                        call_id,
+                        output: format!(
+                            "[ERROR] Tool {name} is not available while the active skills restrict tools"
+                        ),
+                    };
+                    history.push(fc_output.clone());
+                    pending_fc_outputs.push(fc_output);
+                    continue;
+                }
+
+                if runtime_tools.as_ref().is_some_and(|a| !a.contains(&name)) {
+                    warn!("Model called {name}, which the runtime tool settings don't allow");
+                    let fc_output = llm::Item::FunctionCallOutput {
+                        call_id,
+                        output: format!("[ERROR] Tool {name} is not available here"),
+                    };
+                    history.push(fc_output.clone());
+                    pending_fc_outputs.push(fc_output);
+                    continue;
+                }
+
+                if overrides.allowed_tools.as_ref().is_some_and(|a| !a.contains(&name)) {
+                    warn!("Model called {name}, which this turn's tool whitelist doesn't allow");
+                    let fc_output = llm::Item::FunctionCallOutput {
+                        call_id,
+                        output: format!("[ERROR] Tool {name} is not available here"),
+                    };
+                    history.push(fc_output.clone());
+                    pending_fc_outputs.push(fc_output);
+                    continue;
+                }
+
+                if let Some(gate) = &overrides.confirmation {
+                    if loop_runner::requires_confirmation(&self.tools, &name, &arguments) {
+                        if let Err(reason) =
+                            await_confirmation(gate, &tool_ctx, &name, &arguments).await
+                        {
+                            info!("Tool {name} not run: {reason}");
+                            let fc_output = llm::Item::FunctionCallOutput {
+                                call_id,
+                                output: format!("[ERROR] Not run: {reason}"),
+                            };
+                            history.push(fc_output.clone());
+                            pending_fc_outputs.push(fc_output);
+                            continue;
+                        }
+                    }
+                }
+
+                if config.block_repeated_failures
+                    && failure_limit > 0
+                    && failures.count(&name, &arguments) >= failure_limit
+                {
+                    warn!("Not running {name} again: it failed {failure_limit} times with these arguments");
+                    let fc_output = llm::Item::FunctionCallOutput {
+                        call_id,
+                        output: format!(
+                            "[ERROR] Not run: this call already failed {failure_limit} times this turn. {}",
+                            config.repeated_failure_note
+                        ),
+                    };
+                    history.push(fc_output.clone());
+                    pending_fc_outputs.push(fc_output);
+                    continue;
+                }
+
+                let execution = loop_runner::execute_tool(
                     &self.tools,
                     &name,
                     &arguments,
                     &tool_ctx,
-                )
-                .await;
+                    self.max_tool_output,
+                );
+                let Some(result) = with_idle_timeout(idle_timeout, execution).await else {
+                    warn!("Tool {name} made no progress; aborting turn");
+                    history.push(llm::Item::FunctionCallOutput {
+                        call_id,
+                        output: "[ERROR] Tool timed out (turn idle timeout)".to_string(),
+                    });
+                    stalled_tool = Some(name);
+                    continue;
+                };
 
-                let output = match result {
+                let mut output = match result {
                     Ok(r) => {
                         if r.is_error {
                             format!("[ERROR] {}", r.output)
@@ -209,6 +665,13 @@ impl Agent {
                     Err(e) => format!("[ERROR] {e}"),
                 };
 
+                let failed = failures.record(&name, &arguments, output.starts_with("[ERROR] "));
+                if failure_limit > 0 && failed >= failure_limit {
+                    warn!("Tool {name} has failed {failed} times with the same arguments");
+                    output.push_str("\n\n[NOTE] ");
+                    output.push_str(&config.repeated_failure_note);
+                }
+
                 debug!("Tool {name} returned {} bytes", output.len());
 
                 let fc_output = llm::Item::FunctionCallOutput {
@@ -218,6 +681,17 @@ impl Agent {
                 history.push(fc_output.clone());
                 pending_fc_outputs.push(fc_output);
             }
+
+            if let Some(name) = stalled_tool {
+                return Ok(self.abort_idle_turn(
+                    history,
+                    usage,
+                    max_history,
+                    &format!("waiting on tool `{name}`"),
+                    idle_timeout,
+                    &pending_attachments,
+                ));
+            }
         }
 
         Err(NekoError::Agent(format!(
@@ -225,9 +699,152 @@ impl Agent {
         )))
     }
 
+    /// Summarize older history into a single message, keeping the most
+    /// recent items verbatim. Returns the history unchanged if it is too
+    /// short to be worth compacting.
+    pub async fn compact_history(&self, history: Vec<llm::Item>) -> Result<Vec<llm::Item>> {
+        let split = compaction::split_point(&history, compaction::KEEP_RECENT_ITEMS);
+        if split == 0 {
+            return Ok(history);
+        }
+
+        let request = llm::Request {
+            model: self.resolve_model(&self.config().model),
+            input: llm::Input::Text(compaction::render_transcript(&history[..split])),
+            instructions: Some(compaction::COMPACTION_INSTRUCTIONS.to_string()),
+            tools: None,
+            tool_choice: None,
+            stream: false,
+            temperature: None,
+            max_output_tokens: Some(self.config().max_tokens),
+            previous_response_id: None,
+        };
+        let response = {
+            let _permit = self
+                .llm_permits
+                .acquire()
+                .await
+                .map_err(|e| NekoError::Agent(format!("LLM semaphore closed: {e}")))?;
+            self.llm_client.create_response(&request).await?
+        };
+        self.record_usage(response.usage.as_ref());
+        if response.status == llm::ResponseStatus::Failed {
+            return Err(failed_response_error(response.error.as_ref()).into());
+        }
+        let summary = response.text();
+        if summary.trim().is_empty() {
+            return Err(NekoError::Agent("Compaction produced an empty summary".to_string()));
+        }
+
+        let mut compacted = Vec::with_capacity(history.len() - split + 1);
+        compacted.push(llm::Item::Message {
+            role: llm::Role::User,
+            content: format!("{}\n{}", compaction::SUMMARY_PREFIX, summary.trim()).into(),
+        });
+        compacted.extend(history.into_iter().skip(split));
+        Ok(compacted)
+    }
+
+    /// Count a response's tokens against the daily budget, if any.
+    fn record_usage(&self, usage: Option<&llm::Usage>) {
+        if let (Some(budget), Some(usage)) = (&self.daily_budget, usage) {
+            budget.record(u64::from(usage.input_tokens) + u64::from(usage.output_tokens));
+        }
+    }
+
+    /// Make the history fit after a context-length error: summarize older
+    /// items, or, if there is too little to summarize or that fails, drop
+    /// everything before the latest user message and cut long tool outputs.
+    async fn shrink_history(&self, history: Vec<llm::Item>) -> Vec<llm::Item> {
+        match self.compact_history(history.clone()).await {
+            Ok(compacted) if compacted.len() < history.len() => {
+                info!("Compacted history {} -> {} items", history.len(), compacted.len());
+                return compacted;
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Compaction failed: {e}"),
+        }
+
+        let start = compaction::split_point(&history, 1);
+        history
+            .into_iter()
+            .skip(start)
+            .map(|item| match item {
+                llm::Item::FunctionCallOutput { call_id, output } => llm::Item::FunctionCallOutput {
+                    call_id,
+                    output: loop_runner::truncate_output(&output, SHRUNK_TOOL_OUTPUT_BYTES),
+                },
+                other => other,
+            })
+            .collect()
+    }
+
+    /// Strip `output_filters` matches from user-facing text.
+    fn filter_output(&self, text: String) -> String {
+        if self.output_filters.is_empty() {
+            return text;
+        }
+        let mut filtered = text;
+        for re in &self.output_filters {
+            if let std::borrow::Cow::Owned(s) = re.replace_all(&filtered, "") {
+                filtered = s;
+            }
+        }
+        filtered.trim().to_string()
+    }
+
+    /// Build the partial result for a turn stopped by the idle watchdog.
+    /// The response chain is dropped (`last_response_id: None`) so the next
+    /// turn resends full history instead of chaining onto a half-finished one.
+    fn abort_idle_turn(
+        &self,
+        mut history: Vec<llm::Item>,
+        usage: Option<llm::Usage>,
+        max_history: usize,
+        waiting_on: &str,
+        idle_timeout: Option<Duration>,
+        pending_attachments: &Mutex<Vec<Attachment>>,
+    ) -> TurnResult {
+        let secs = idle_timeout.map(|d| d.as_secs()).unwrap_or_default();
+        warn!("Turn aborted: no progress for {secs}s while {waiting_on}");
+        let text = format!(
+            "(Turn stopped: no progress for {secs}s while {waiting_on}. Some work may be incomplete \u{2014} ask me to continue or try again.)"
+        );
+        history.push(llm::Item::Message {
+            role: llm::Role::Assistant,
+            content: text.clone().into(),
+        });
+        strip_reasoning(&mut history);
+        trim_history(&mut history, max_history);
+        TurnResult {
+            text,
+            history,
+            usage,
+            last_response_id: None,
+            attachments: std::mem::take(&mut *pending_attachments.lock().unwrap()),
+        }
+    }
+
+    /// Embed new memory chunks (this turn's recall entry, memory writes) in
+    /// the background so semantic searches don't pay for it later.
+    fn spawn_index_sync(&self) {
+        let Some(embedder) = self.embedder.clone() else {
+            return;
+        };
+        if self.workspace == PathBuf::new() {
+            return;
+        }
+        let workspace = self.workspace.clone();
+        tokio::spawn(async move {
+            if let Err(e) = memory_index::sync(&workspace, &embedder).await {
+                warn!("Memory index sync failed: {e}");
+            }
+        });
+    }
+
     /// Log conversation turn to recall file for future search.
     fn log_to_recall(&self, user_message: &str, assistant_response: &str) {
-        if self.workspace == PathBuf::new() {
+        if !self.recall_log || self.workspace == PathBuf::new() {
             return;
         }
 
@@ -303,7 +920,7 @@ pub fn append_output_to_history(history: &mut Vec<llm::Item>, output: &[llm::Out
                 if !text.is_empty() {
                     history.push(llm::Item::Message {
                         role: *role,
-                        content: text,
+                        content: text.into(),
                     });
                 }
             }
@@ -328,3 +945,49 @@ pub fn trim_history(history: &mut Vec<llm::Item>, max: usize) {
 pub fn strip_reasoning(history: &mut Vec<llm::Item>) {
     history.retain(|item| !matches!(item, llm::Item::Reasoning(_) | llm::Item::Other(_)));
 }
+
+/// Error for a response that came back with `status: "failed"`.
+fn failed_response_error(error: Option<&llm::ApiError>) -> llm::LlmError {
+    match error {
+        Some(e) => llm::LlmError::from_api_error(e),
+        None => llm::LlmError::Other("Unknown LLM error".to_string()),
+    }
+}
+
+/// Await `fut`, giving up after `idle` (if set). `None` means it timed out.
+async fn with_idle_timeout<F: Future>(idle: Option<Duration>, fut: F) -> Option<F::Output> {
+    match idle {
+        Some(d) => tokio::time::timeout(d, fut).await.ok(),
+        None => Some(fut.await),
+    }
+}
+
+/// Ask the session's channel to approve a `tool` call and wait for the
+/// reply. `Err` carries why the call must not run.
+async fn await_confirmation(
+    gate: &confirm::ConfirmationGate,
+    ctx: &ToolContext,
+    tool: &str,
+    arguments: &str,
+) -> std::result::Result<(), String> {
+    let Some(channel) = &ctx.channel else {
+        return Err("confirmation required, but this turn has no channel to ask".to_string());
+    };
+    let reply = gate.pending.register(&gate.session_id, gate.requester.as_deref());
+    if let Err(e) = ctx.send_message(
+        &channel.channel,
+        &channel.recipient_id,
+        confirm::prompt(tool, arguments),
+    ) {
+        gate.pending.cancel(&gate.session_id);
+        return Err(format!("could not ask the user for confirmation: {e}"));
+    }
+    match tokio::time::timeout(gate.timeout, reply).await {
+        Ok(Ok(true)) => Ok(()),
+        Ok(Ok(false)) | Ok(Err(_)) => Err("the user declined".to_string()),
+        Err(_) => {
+            gate.pending.cancel(&gate.session_id);
+            Err("the user did not confirm in time".to_string())
+        }
+    }
+}
@@ -1,24 +1,69 @@
-use std::sync::Arc;
+pub mod openapi;
 
-use axum::extract::{Path, State};
-use axum::http::{header, StatusCode};
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+
+use axum::extract::{ConnectInfo, Path, Query, State};
+use axum::http::{header, HeaderValue, Method, StatusCode};
 use axum::middleware::{self, Next};
 use axum::response::{IntoResponse, Response};
-use axum::routing::{delete, get, post};
-use axum::{Json, Router};
+use axum::routing::{delete, get, post, put};
+use axum::{Extension, Json, Router};
 use serde::{Deserialize, Serialize};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_http::limit::RequestBodyLimitLayer;
 
-use crate::gateway::Gateway;
+use crate::channels::Attachment;
+use crate::config::SettingsPatch;
+use crate::error::NekoError;
+use crate::gateway::{AgentHandle, Gateway, HttpPeer};
+use crate::llm::CircuitState;
+use crate::tools::ToolContext;
 
 pub struct AppState {
     pub gateway: Arc<Gateway>,
     pub api_token: Option<String>,
+    /// In-flight request count per client IP.
+    inflight: Mutex<HashMap<IpAddr, usize>>,
+}
+
+impl AppState {
+    pub fn new(gateway: Arc<Gateway>, api_token: Option<String>) -> Self {
+        Self {
+            gateway,
+            api_token,
+            inflight: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// Releases a per-IP concurrency slot when the request finishes.
+struct InflightGuard<'a> {
+    state: &'a AppState,
+    ip: IpAddr,
+}
+
+impl Drop for InflightGuard<'_> {
+    fn drop(&mut self) {
+        let mut inflight = self.state.inflight.lock().unwrap();
+        if let Some(count) = inflight.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                inflight.remove(&self.ip);
+            }
+        }
+    }
 }
 
 #[derive(Serialize)]
 struct HealthResponse {
     status: &'static str,
     version: &'static str,
+    /// The default agent's provider circuit.
+    llm_circuit: &'static str,
+    /// Every agent's provider circuit, by agent name.
+    agents: BTreeMap<String, &'static str>,
 }
 
 #[derive(Deserialize)]
@@ -26,17 +71,60 @@ pub struct MessageRequest {
     pub text: String,
     #[serde(default)]
     pub session_id: Option<String>,
+    /// Channel name used for session routing (default `http`).
+    #[serde(default)]
+    pub channel: Option<String>,
+    #[serde(default)]
+    pub sender_id: Option<String>,
+    #[serde(default)]
+    pub is_group: bool,
+    #[serde(default)]
+    pub group_id: Option<String>,
 }
 
 #[derive(Serialize)]
 pub struct MessageResponse {
     pub response: String,
     pub session_id: String,
+    pub attachments: Vec<AttachmentInfo>,
+}
+
+/// A file the agent sent with its reply.
+#[derive(Serialize)]
+pub struct AttachmentInfo {
+    /// Relative to the workspace when the file is inside it.
+    pub path: String,
+    pub mime_type: String,
+    pub size_bytes: u64,
+    /// Where to download it (`GET /api/v1/files/...`), if it is in the
+    /// workspace.
+    pub url: Option<String>,
+}
+
+impl AttachmentInfo {
+    /// `workspace` is the workspace of `agent`, the agent that sent it.
+    fn new(attachment: &Attachment, workspace: &std::path::Path, agent: &str) -> Self {
+        let workspace = workspace.canonicalize().unwrap_or_else(|_| workspace.to_path_buf());
+        let relative = attachment.path.strip_prefix(&workspace).ok();
+        let path = relative.unwrap_or(&attachment.path).to_string_lossy().into_owned();
+        let query = match agent {
+            "default" => String::new(),
+            name => format!("?agent={}", encode_path(name)),
+        };
+        Self {
+            url: relative.map(|_| format!("/api/v1/files/{}{query}", encode_path(&path))),
+            path,
+            mime_type: attachment.mime_type.clone(),
+            size_bytes: std::fs::metadata(&attachment.path).map(|m| m.len()).unwrap_or(0),
+        }
+    }
 }
 
 #[derive(Serialize)]
 struct SessionListEntry {
     session_id: String,
+    /// Agent whose workspace holds the session (`default` or a name).
+    agent: String,
     key: String,
     turn_count: u32,
     input_tokens: u32,
@@ -51,37 +139,163 @@ struct SessionListResponse {
     sessions: Vec<SessionListEntry>,
 }
 
-async fn health() -> Json<HealthResponse> {
+#[derive(Deserialize)]
+struct PatchConfigQuery {
+    /// Also write the changes to the config file.
+    #[serde(default)]
+    persist: bool,
+    /// Named agent to change; the default agent otherwise.
+    #[serde(default)]
+    agent: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ConfigQuery {
+    /// Named agent whose view of the config to return.
+    #[serde(default)]
+    agent: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FileQuery {
+    /// Named agent whose workspace holds the file.
+    #[serde(default)]
+    agent: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ClearSessionsQuery {
+    /// Also delete recall logs and reset MEMORY.md.
+    #[serde(default)]
+    hard: bool,
+}
+
+#[derive(Deserialize)]
+struct PersonaRequest {
+    /// New instructions for the session; `null` clears the override.
+    instructions: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct HistoryRequest {
+    /// History items to keep; `null` goes back to `agent.max_history`.
+    max_history: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct CompactResponse {
+    session_id: String,
+    items_before: usize,
+    items_after: usize,
+}
+
+async fn health(State(state): State<Arc<AppState>>) -> Json<HealthResponse> {
+    let circuits: Vec<(&str, CircuitState)> = state
+        .gateway
+        .agents()
+        .map(|a| (a.name, a.agent.llm_client().circuit_state()))
+        .collect();
+    let degraded = circuits.iter().any(|(_, c)| *c == CircuitState::Open);
     Json(HealthResponse {
-        status: "ok",
+        status: if degraded { "degraded" } else { "ok" },
         version: env!("CARGO_PKG_VERSION"),
+        llm_circuit: circuits[0].1.as_str(),
+        agents: circuits
+            .iter()
+            .map(|(name, c)| (name.to_string(), c.as_str()))
+            .collect(),
     })
 }
 
+async fn openapi_json() -> Json<serde_json::Value> {
+    Json(openapi::spec())
+}
+
+/// The tool whitelist of the scoped token that authenticated a request.
+#[derive(Clone)]
+struct TokenTools(Vec<String>);
+
+/// Who authenticated a request, for audit logs: `api_token` or a scoped
+/// token's first characters. Absent when the API has no auth.
+#[derive(Clone)]
+struct Caller(String);
+
 async fn send_message(
     State(state): State<Arc<AppState>>,
+    token_tools: Option<Extension<TokenTools>>,
     Json(req): Json<MessageRequest>,
 ) -> Result<Json<MessageResponse>, (StatusCode, String)> {
-    let (response, session_id) = state
+    let max_chars = state.gateway.config().gateway.max_message_chars;
+    if req.text.chars().count() > max_chars {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("text exceeds the {max_chars}-character limit"),
+        ));
+    }
+
+    // Session keys are `neko:<channel>:...`, so the parts can't contain ':'
+    let ids = [&req.channel, &req.sender_id, &req.group_id];
+    if ids.iter().any(|id| id.as_deref().is_some_and(|id| id.is_empty() || id.contains(':'))) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "channel, sender_id and group_id must be non-empty and must not contain ':'"
+                .to_string(),
+        ));
+    }
+    if req.is_group && req.group_id.is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "group_id is required when is_group is true".to_string(),
+        ));
+    }
+
+    let peer = HttpPeer {
+        channel: req.channel.as_deref(),
+        sender_id: req.sender_id.as_deref(),
+        is_group: req.is_group,
+        group_id: req.group_id.as_deref(),
+    };
+    let reply = state
         .gateway
-        .handle_http_message(&req.text, req.session_id.as_deref(), None)
+        .handle_http_message(
+            &req.text,
+            req.session_id.as_deref(),
+            &peer,
+            token_tools.as_ref().map(|Extension(TokenTools(tools))| tools.as_slice()),
+        )
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
+    let workspace = state
+        .gateway
+        .agent_config(&reply.agent)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .workspace_path();
     Ok(Json(MessageResponse {
-        response,
-        session_id,
+        response: reply.text,
+        session_id: reply.session_id,
+        attachments: reply
+            .attachments
+            .iter()
+            .map(|a| AttachmentInfo::new(a, &workspace, &reply.agent))
+            .collect(),
     }))
 }
 
 async fn list_sessions(
     State(state): State<Arc<AppState>>,
 ) -> Json<SessionListResponse> {
-    let metas = state.gateway.session_store.list().await;
+    let mut metas = Vec::new();
+    for handle in state.gateway.agents() {
+        let agent = handle.name.to_string();
+        metas.extend(handle.sessions.list().await.into_iter().map(|m| (agent.clone(), m)));
+    }
+    metas.sort_by_key(|(_, m)| std::cmp::Reverse(m.updated_at));
     let sessions = metas
         .into_iter()
-        .map(|m| SessionListEntry {
+        .map(|(agent, m)| SessionListEntry {
             session_id: m.session_id,
+            agent,
             key: m.key,
             turn_count: m.turn_count,
             input_tokens: m.input_tokens,
@@ -94,47 +308,438 @@ async fn list_sessions(
     Json(SessionListResponse { sessions })
 }
 
+/// The gateway's (or a named agent's) effective config with secrets
+/// redacted.
+async fn get_config(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ConfigQuery>,
+) -> Result<Json<crate::config::Config>, (StatusCode, String)> {
+    let config = state
+        .gateway
+        .agent_config(query.agent.as_deref().unwrap_or("default"))
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+    Ok(Json(config.redacted()))
+}
+
+/// Change runtime-safe `[agent]` settings; anything else is a conflict.
+async fn patch_config(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<PatchConfigQuery>,
+    caller: Option<Extension<Caller>>,
+    Json(body): Json<serde_json::Map<String, serde_json::Value>>,
+) -> Result<Json<crate::config::Config>, (StatusCode, String)> {
+    let fixed: Vec<&str> = body
+        .keys()
+        .map(String::as_str)
+        .filter(|k| !SettingsPatch::FIELDS.contains(k))
+        .collect();
+    if !fixed.is_empty() {
+        return Err((
+            StatusCode::CONFLICT,
+            format!(
+                "{} can't be changed at runtime; only {} can",
+                fixed.join(", "),
+                SettingsPatch::FIELDS.join(", ")
+            ),
+        ));
+    }
+    let patch: SettingsPatch = serde_json::from_value(serde_json::Value::Object(body))
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let caller = caller.map_or("anonymous".to_string(), |Extension(Caller(c))| c);
+    let config = state
+        .gateway
+        .update_settings(
+            query.agent.as_deref().unwrap_or("default"),
+            &patch,
+            query.persist,
+            &caller,
+        )
+        .map_err(|e| match e {
+            NekoError::Config(_) => (StatusCode::BAD_REQUEST, e.to_string()),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })?;
+    Ok(Json(config.redacted()))
+}
+
+async fn clear_sessions(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ClearSessionsQuery>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let internal = |e: crate::error::NekoError| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    for handle in state.gateway.agents() {
+        handle.sessions.clear_all().await.map_err(internal)?;
+        if query.hard {
+            let removed =
+                crate::workspace::reset_memory(handle.agent.workspace()).map_err(internal)?;
+            tracing::info!(
+                "Hard reset of agent '{}': removed {removed} recall log(s) and reset MEMORY.md",
+                handle.name
+            );
+        }
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
 async fn delete_session(
     State(state): State<Arc<AppState>>,
     Path(session_id): Path<String>,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    session_agent(&state, &session_id)
+        .await?
+        .sessions
+        .delete(&session_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn compact_session(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+) -> Result<Json<CompactResponse>, (StatusCode, String)> {
+    session_agent(&state, &session_id).await?;
+    let (items_before, items_after) = state
+        .gateway
+        .compact_session(&session_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(CompactResponse {
+        session_id,
+        items_before,
+        items_after,
+    }))
+}
+
+/// The agent holding `session_id`, or 404.
+async fn session_agent<'a>(
+    state: &'a AppState,
+    session_id: &str,
+) -> Result<AgentHandle<'a>, (StatusCode, String)> {
     state
         .gateway
-        .session_store
-        .delete(&session_id)
+        .agent_for_session(session_id)
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))
+}
+
+/// Percent-encode a relative path for use in a URL, keeping the `/`s.
+fn encode_path(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for b in path.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~/".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+    out
+}
+
+/// Resolve `relative` inside the workspace, refusing anything the file
+/// tools couldn't open either (escapes via `..`, absolute paths or
+/// symlinks, paths outside `file_root`, Neko's internal files) or that
+/// isn't a regular file.
+fn resolve_workspace_file(
+    ctx: &ToolContext,
+    relative: &str,
+) -> Result<std::path::PathBuf, (StatusCode, String)> {
+    let not_found = || (StatusCode::NOT_FOUND, "File not found".to_string());
+    if std::path::Path::new(relative).is_absolute() {
+        return Err(not_found());
+    }
+    let workspace = ctx.workspace.canonicalize().map_err(|_| not_found())?;
+    let canonical = workspace.join(relative).canonicalize().map_err(|_| not_found())?;
+    if ctx.check_file_access(&canonical).is_err() || !canonical.is_file() {
+        return Err(not_found());
+    }
+    Ok(canonical)
+}
+
+/// Serve a workspace file, e.g. a chart or report the agent wrote.
+async fn get_file(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    Query(query): Query<FileQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let config = state
+        .gateway
+        .agent_config(query.agent.as_deref().unwrap_or("default"))
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+    let workspace = config.workspace_path();
+    let file_root = config
+        .file_root_path()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let ctx = ToolContext {
+        file_root: file_root.unwrap_or_else(|| workspace.clone()),
+        allow_internal_paths: config.tools.allow_internal_paths,
+        ..ToolContext::new(workspace)
+    };
+    let file = resolve_workspace_file(&ctx, &path)?;
+    let body = tokio::fs::read(&file)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let ext = file
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let mime = crate::tools::send_file::guess_mime(&ext).unwrap_or("application/octet-stream");
+    Ok((
+        [
+            (header::CONTENT_TYPE, mime),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff"),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Scope a token needs for a route.
+fn required_scope(method: &Method, path: &str) -> &'static str {
+    if path.starts_with("/api/v1/files/") {
+        "files:read"
+    } else if path == "/api/v1/config" {
+        if method == Method::GET {
+            "config:read"
+        } else {
+            "config:write"
+        }
+    } else if path.starts_with("/api/v1/sessions") {
+        if method == Method::GET {
+            "sessions:read"
+        } else {
+            "sessions:write"
+        }
+    } else {
+        "message"
+    }
+}
+
+async fn set_persona(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+    Json(req): Json<PersonaRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let instructions = req.instructions.filter(|i| !i.trim().is_empty());
+    session_agent(&state, &session_id)
+        .await?
+        .sessions
+        .set_instructions_override(&session_id, instructions)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn set_history(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+    Json(req): Json<HistoryRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if req.max_history == Some(0) {
+        return Err((StatusCode::BAD_REQUEST, "max_history must be at least 1".to_string()));
+    }
+    session_agent(&state, &session_id)
+        .await?
+        .sessions
+        .set_max_history_override(&session_id, req.max_history)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     Ok(StatusCode::NO_CONTENT)
 }
 
 async fn auth_middleware(
+    State(state): State<Arc<AppState>>,
+    mut request: axum::extract::Request,
+    next: Next,
+) -> Response {
+    let config = state.gateway.config();
+    let tokens = &config.gateway.tokens;
+    if state.api_token.is_none() && tokens.is_empty() {
+        return next.run(request).await;
+    }
+
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .filter(|t| !t.is_empty());
+    let Some(token) = bearer else {
+        return (StatusCode::UNAUTHORIZED, "Unauthorized").into_response();
+    };
+
+    // The primary api_token has full access.
+    if state.api_token.as_deref() == Some(token) {
+        request.extensions_mut().insert(Caller("api_token".to_string()));
+        return next.run(request).await;
+    }
+
+    let Some(entry) = tokens.get(token) else {
+        return (StatusCode::UNAUTHORIZED, "Unauthorized").into_response();
+    };
+    let needed = required_scope(request.method(), request.uri().path());
+    if !entry.scopes().iter().any(|s| s == "*" || s == needed) {
+        return (
+            StatusCode::FORBIDDEN,
+            format!("Token lacks the '{needed}' scope"),
+        )
+            .into_response();
+    }
+    let prefix: String = token.chars().take(4).collect();
+    request.extensions_mut().insert(Caller(format!("token {prefix}…")));
+    if let Some(tools) = entry.tools() {
+        request.extensions_mut().insert(TokenTools(tools.to_vec()));
+    }
+    next.run(request).await
+}
+
+/// Cap concurrent requests per client IP (`max_concurrent_per_ip`).
+async fn concurrency_middleware(
     State(state): State<Arc<AppState>>,
     request: axum::extract::Request,
     next: Next,
 ) -> Response {
-    if let Some(expected) = &state.api_token {
-        let auth = request
-            .headers()
-            .get(header::AUTHORIZATION)
-            .and_then(|v| v.to_str().ok());
-
-        match auth {
-            Some(val) if val.starts_with("Bearer ") && &val[7..] == expected.as_str() => {}
-            _ => return (StatusCode::UNAUTHORIZED, "Unauthorized").into_response(),
+    let limit = state.gateway.config().gateway.max_concurrent_per_ip;
+    let ip = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let (Some(ip), true) = (ip, limit > 0) else {
+        return next.run(request).await;
+    };
+
+    {
+        let mut inflight = state.inflight.lock().unwrap();
+        let count = inflight.entry(ip).or_insert(0);
+        if *count >= limit {
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many concurrent requests from this address",
+            )
+                .into_response();
         }
+        *count += 1;
     }
+    let _guard = InflightGuard { state: &state, ip };
     next.run(request).await
 }
 
 pub fn router(state: Arc<AppState>) -> Router {
     let protected = Router::new()
         .route("/api/v1/message", post(send_message))
-        .route("/api/v1/sessions", get(list_sessions))
+        .route("/api/v1/sessions", get(list_sessions).delete(clear_sessions))
         .route("/api/v1/sessions/{id}", delete(delete_session))
+        .route("/api/v1/sessions/{id}/compact", post(compact_session))
+        .route("/api/v1/sessions/{id}/persona", put(set_persona))
+        .route("/api/v1/sessions/{id}/history", put(set_history))
+        .route("/api/v1/files/{*path}", get(get_file))
+        .route("/api/v1/config", get(get_config).patch(patch_config))
         .layer(middleware::from_fn_with_state(state.clone(), auth_middleware));
 
-    Router::new()
+    let cors = cors_layer(&state.gateway.config().gateway.cors_origins);
+    let max_body_bytes = state.gateway.config().gateway.max_body_bytes;
+
+    let router = Router::new()
         .route("/health", get(health))
+        .route("/openapi.json", get(openapi_json))
         .merge(protected)
-        .with_state(state)
+        .layer(middleware::from_fn_with_state(state.clone(), concurrency_middleware))
+        .layer(RequestBodyLimitLayer::new(max_body_bytes))
+        .with_state(state);
+
+    match cors {
+        Some(layer) => router.layer(layer),
+        None => router,
+    }
+}
+
+/// CORS for browser clients. `None` when no origins are configured.
+fn cors_layer(origins: &[String]) -> Option<CorsLayer> {
+    if origins.is_empty() {
+        return None;
+    }
+    let allow_origin = if origins.iter().any(|o| o == "*") {
+        AllowOrigin::any()
+    } else {
+        let values: Vec<HeaderValue> = origins
+            .iter()
+            .filter_map(|o| match HeaderValue::from_str(o.trim_end_matches('/')) {
+                Ok(v) => Some(v),
+                Err(e) => {
+                    tracing::warn!("Ignoring invalid CORS origin '{o}': {e}");
+                    None
+                }
+            })
+            .collect();
+        AllowOrigin::list(values)
+    };
+    Some(
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE, Method::OPTIONS])
+            .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE]),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_workspace_file_stays_inside() {
+        let root = tempfile::tempdir().unwrap();
+        let workspace = root.path().join("ws");
+        std::fs::create_dir_all(workspace.join("out")).unwrap();
+        std::fs::write(workspace.join("out/chart.png"), b"png").unwrap();
+        std::fs::write(root.path().join("secret.txt"), b"secret").unwrap();
+
+        let ctx = ToolContext::new(workspace);
+        assert!(resolve_workspace_file(&ctx, "out/chart.png").is_ok());
+        assert!(resolve_workspace_file(&ctx, "../secret.txt").is_err());
+        assert!(resolve_workspace_file(&ctx, "out/../../secret.txt").is_err());
+        let absolute = root.path().join("secret.txt");
+        assert!(resolve_workspace_file(&ctx, absolute.to_str().unwrap()).is_err());
+        assert!(resolve_workspace_file(&ctx, "out").is_err());
+        assert_eq!(encode_path("out/my chart.png"), "out/my%20chart.png");
+    }
+
+    #[test]
+    fn test_attachment_url_names_the_agent() {
+        let workspace = tempfile::tempdir().unwrap();
+        let path = workspace.path().canonicalize().unwrap().join("out/chart.png");
+        let attachment = Attachment {
+            path,
+            mime_type: "image/png".to_string(),
+        };
+        let info = AttachmentInfo::new(&attachment, workspace.path(), "default");
+        assert_eq!(info.url.as_deref(), Some("/api/v1/files/out/chart.png"));
+        let info = AttachmentInfo::new(&attachment, workspace.path(), "coder");
+        assert_eq!(info.url.as_deref(), Some("/api/v1/files/out/chart.png?agent=coder"));
+        assert_eq!(info.path, "out/chart.png");
+    }
+
+    #[test]
+    fn test_resolve_workspace_file_hides_internal_paths() {
+        let root = tempfile::tempdir().unwrap();
+        let workspace = root.path().to_path_buf();
+        std::fs::create_dir_all(workspace.join("sessions")).unwrap();
+        std::fs::write(workspace.join("sessions/abc.jsonl"), b"{}").unwrap();
+        std::fs::create_dir_all(workspace.join("channels")).unwrap();
+        std::fs::write(workspace.join("channels/undelivered.jsonl"), b"{}").unwrap();
+        std::fs::create_dir_all(workspace.join("projects")).unwrap();
+        std::fs::write(workspace.join("projects/report.md"), b"# hi").unwrap();
+        std::fs::write(workspace.join("notes.md"), b"outside root").unwrap();
+
+        let ctx = ToolContext::new(workspace.clone());
+        let err = resolve_workspace_file(&ctx, "sessions/abc.jsonl").unwrap_err();
+        assert_eq!(err.0, StatusCode::NOT_FOUND);
+        assert!(resolve_workspace_file(&ctx, "channels/undelivered.jsonl").is_err());
+
+        let ctx = ToolContext {
+            file_root: workspace.join("projects"),
+            ..ToolContext::new(workspace)
+        };
+        assert!(resolve_workspace_file(&ctx, "projects/report.md").is_ok());
+        assert!(resolve_workspace_file(&ctx, "notes.md").is_err());
+    }
 }
@@ -0,0 +1,365 @@
+use serde_json::{json, Value};
+
+/// OpenAPI 3 description of the HTTP API. Kept next to the handlers in
+/// `api/mod.rs` — update both together.
+pub fn spec() -> Value {
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Neko API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "HTTP API for the Neko agent gateway."
+        },
+        "components": {
+            "securitySchemes": {
+                "bearer": { "type": "http", "scheme": "bearer" }
+            },
+            "schemas": {
+                "HealthResponse": {
+                    "type": "object",
+                    "required": ["status", "version", "llm_circuit", "agents"],
+                    "properties": {
+                        "status": { "type": "string", "enum": ["ok", "degraded"], "description": "degraded while any agent's provider circuit is open" },
+                        "version": { "type": "string" },
+                        "llm_circuit": { "type": "string", "enum": ["closed", "open", "half_open"], "description": "The default agent's provider circuit" },
+                        "agents": {
+                            "type": "object",
+                            "description": "Provider circuit state of every agent, by name (default included)",
+                            "additionalProperties": { "type": "string", "enum": ["closed", "open", "half_open"] }
+                        }
+                    }
+                },
+                "MessageRequest": {
+                    "type": "object",
+                    "required": ["text"],
+                    "properties": {
+                        "text": { "type": "string" },
+                        "session_id": { "type": "string", "nullable": true },
+                        "channel": { "type": "string", "nullable": true, "description": "Channel for session routing (default http); kept under http- so it never matches a real channel such as telegram" },
+                        "sender_id": { "type": "string", "nullable": true },
+                        "is_group": { "type": "boolean", "default": false },
+                        "group_id": { "type": "string", "nullable": true }
+                    }
+                },
+                "MessageResponse": {
+                    "type": "object",
+                    "required": ["response", "session_id", "attachments"],
+                    "properties": {
+                        "response": { "type": "string" },
+                        "session_id": { "type": "string" },
+                        "attachments": {
+                            "type": "array",
+                            "items": { "$ref": "#/components/schemas/Attachment" }
+                        }
+                    }
+                },
+                "Attachment": {
+                    "type": "object",
+                    "required": ["path", "mime_type", "size_bytes"],
+                    "properties": {
+                        "path": { "type": "string", "description": "Relative to the workspace when inside it" },
+                        "mime_type": { "type": "string" },
+                        "size_bytes": { "type": "integer" },
+                        "url": { "type": "string", "nullable": true, "description": "Download path when the file is in the workspace" }
+                    }
+                },
+                "SessionListEntry": {
+                    "type": "object",
+                    "required": ["session_id", "agent", "key", "turn_count", "input_tokens", "output_tokens", "updated_at"],
+                    "properties": {
+                        "session_id": { "type": "string" },
+                        "agent": { "type": "string", "description": "Agent holding the session: default or an [[agents]] name" },
+                        "key": { "type": "string" },
+                        "turn_count": { "type": "integer" },
+                        "input_tokens": { "type": "integer" },
+                        "output_tokens": { "type": "integer" },
+                        "updated_at": { "type": "string", "format": "date-time" },
+                        "channel": { "type": "string", "nullable": true },
+                        "display_name": { "type": "string", "nullable": true }
+                    }
+                },
+                "SessionListResponse": {
+                    "type": "object",
+                    "required": ["sessions"],
+                    "properties": {
+                        "sessions": {
+                            "type": "array",
+                            "items": { "$ref": "#/components/schemas/SessionListEntry" }
+                        }
+                    }
+                },
+                "PersonaRequest": {
+                    "type": "object",
+                    "properties": {
+                        "instructions": {
+                            "type": "string",
+                            "nullable": true,
+                            "description": "Replaces the agent instructions for this session; null clears it"
+                        }
+                    }
+                },
+                "SettingsPatch": {
+                    "type": "object",
+                    "properties": {
+                        "model": { "type": "string" },
+                        "instructions": { "type": "string", "description": "Empty string clears them" },
+                        "max_tokens": { "type": "integer", "minimum": 1 },
+                        "temperature": { "type": "number", "minimum": 0, "maximum": 2 },
+                        "tools": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Registered tools offered to the model"
+                        }
+                    }
+                },
+                "HistoryRequest": {
+                    "type": "object",
+                    "properties": {
+                        "max_history": {
+                            "type": "integer",
+                            "minimum": 1,
+                            "nullable": true,
+                            "description": "History items to keep for this session; null goes back to agent.max_history"
+                        }
+                    }
+                },
+                "CompactResponse": {
+                    "type": "object",
+                    "required": ["session_id", "items_before", "items_after"],
+                    "properties": {
+                        "session_id": { "type": "string" },
+                        "items_before": { "type": "integer" },
+                        "items_after": { "type": "integer" }
+                    }
+                }
+            }
+        },
+        "paths": {
+            "/health": {
+                "get": {
+                    "summary": "Gateway health",
+                    "responses": {
+                        "200": json_response("Health status", "HealthResponse")
+                    }
+                }
+            },
+            "/api/v1/message": {
+                "post": {
+                    "summary": "Send a message to the agent",
+                    "security": [{ "bearer": [] }],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": { "$ref": "#/components/schemas/MessageRequest" }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": json_response("Agent response", "MessageResponse"),
+                        "401": { "description": "Missing or invalid bearer token" },
+                        "500": { "description": "Agent error" }
+                    }
+                }
+            },
+            "/api/v1/sessions": {
+                "get": {
+                    "summary": "List sessions",
+                    "security": [{ "bearer": [] }],
+                    "responses": {
+                        "200": json_response("Sessions", "SessionListResponse"),
+                        "401": { "description": "Missing or invalid bearer token" }
+                    }
+                },
+                "delete": {
+                    "summary": "Clear all sessions",
+                    "description": "Deletes every session transcript. With hard=true, also deletes recall logs (memory/recall/*.md) and resets memory/MEMORY.md to the default stub; other memory files are kept.",
+                    "security": [{ "bearer": [] }],
+                    "parameters": [{
+                        "name": "hard",
+                        "in": "query",
+                        "required": false,
+                        "schema": { "type": "boolean", "default": false }
+                    }],
+                    "responses": {
+                        "204": { "description": "Cleared" },
+                        "401": { "description": "Missing or invalid bearer token" },
+                        "500": { "description": "Session or filesystem error" }
+                    }
+                }
+            },
+            "/api/v1/sessions/{id}": {
+                "delete": {
+                    "summary": "Delete a session",
+                    "security": [{ "bearer": [] }],
+                    "parameters": [session_id_param()],
+                    "responses": {
+                        "204": { "description": "Deleted" },
+                        "401": { "description": "Missing or invalid bearer token" },
+                        "500": { "description": "Session error" }
+                    }
+                }
+            },
+            "/api/v1/sessions/{id}/compact": {
+                "post": {
+                    "summary": "Summarize a session's history in place",
+                    "security": [{ "bearer": [] }],
+                    "parameters": [session_id_param()],
+                    "responses": {
+                        "200": json_response("Item counts before and after", "CompactResponse"),
+                        "401": { "description": "Missing or invalid bearer token" },
+                        "404": { "description": "Session not found" },
+                        "500": { "description": "Compaction error" }
+                    }
+                }
+            },
+            "/api/v1/sessions/{id}/persona": {
+                "put": {
+                    "summary": "Set or clear the session's instructions override",
+                    "security": [{ "bearer": [] }],
+                    "parameters": [session_id_param()],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": { "$ref": "#/components/schemas/PersonaRequest" }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "204": { "description": "Updated" },
+                        "401": { "description": "Missing or invalid bearer token" },
+                        "500": { "description": "Session error" }
+                    }
+                }
+            },
+            "/api/v1/config": {
+                "get": {
+                    "summary": "Effective config with API keys, key commands, tokens, headers, MCP args and env values redacted",
+                    "security": [{ "bearer": [] }],
+                    "parameters": [{
+                        "name": "agent",
+                        "in": "query",
+                        "required": false,
+                        "description": "Named agent whose config to return (its profile applied); the default agent otherwise",
+                        "schema": { "type": "string" }
+                    }],
+                    "responses": {
+                        "200": {
+                            "description": "The config as JSON, in the same shape as config.toml",
+                            "content": { "application/json": { "schema": { "type": "object" } } }
+                        },
+                        "401": { "description": "Missing or invalid bearer token" },
+                        "403": { "description": "Token lacks the config:read scope" },
+                        "404": { "description": "No agent with that name" }
+                    }
+                },
+                "patch": {
+                    "summary": "Change model, instructions, max_tokens, temperature or tools of an agent",
+                    "description": "Applies to new turns. Other fields can't change at runtime and are rejected with 409. An empty instructions string clears them. tools limits which tools turns may offer and run ([\"*\"] lifts the limit); it is runtime-only and can't be combined with persist=true.",
+                    "security": [{ "bearer": [] }],
+                    "parameters": [{
+                        "name": "persist",
+                        "in": "query",
+                        "required": false,
+                        "description": "Also write the changes to the config file (default agent only)",
+                        "schema": { "type": "boolean", "default": false }
+                    }, {
+                        "name": "agent",
+                        "in": "query",
+                        "required": false,
+                        "description": "Named agent to change; the default agent otherwise",
+                        "schema": { "type": "string" }
+                    }],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": { "$ref": "#/components/schemas/SettingsPatch" }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "The updated config, redacted as for GET",
+                            "content": { "application/json": { "schema": { "type": "object" } } }
+                        },
+                        "400": { "description": "Invalid value" },
+                        "401": { "description": "Missing or invalid bearer token" },
+                        "403": { "description": "Token lacks the config:write scope" },
+                        "409": { "description": "The body names a setting that can't change at runtime" },
+                        "500": { "description": "Failed to write the config file" }
+                    }
+                }
+            },
+            "/api/v1/sessions/{id}/history": {
+                "put": {
+                    "summary": "Set or clear the session's history depth",
+                    "security": [{ "bearer": [] }],
+                    "parameters": [session_id_param()],
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": { "$ref": "#/components/schemas/HistoryRequest" }
+                            }
+                        }
+                    },
+                    "responses": {
+                        "204": { "description": "Updated" },
+                        "400": { "description": "max_history is 0" },
+                        "401": { "description": "Missing or invalid bearer token" },
+                        "500": { "description": "Session error" }
+                    }
+                }
+            },
+            "/api/v1/files/{path}": {
+                "get": {
+                    "summary": "Download a workspace file, e.g. an attachment's url",
+                    "security": [{ "bearer": [] }],
+                    "parameters": [{
+                        "name": "path",
+                        "in": "path",
+                        "required": true,
+                        "description": "Path relative to the workspace; only files the file tools may read are served",
+                        "schema": { "type": "string" }
+                    }, {
+                        "name": "agent",
+                        "in": "query",
+                        "required": false,
+                        "description": "Named agent whose workspace holds the file; the default agent otherwise",
+                        "schema": { "type": "string" }
+                    }],
+                    "responses": {
+                        "200": {
+                            "description": "File contents, with a Content-Type guessed from the extension",
+                            "content": { "*/*": { "schema": { "type": "string", "format": "binary" } } }
+                        },
+                        "401": { "description": "Missing or invalid bearer token" },
+                        "404": { "description": "No such file in the workspace" }
+                    }
+                }
+            }
+        }
+    })
+}
+
+fn json_response(description: &str, schema: &str) -> Value {
+    json!({
+        "description": description,
+        "content": {
+            "application/json": {
+                "schema": { "$ref": format!("#/components/schemas/{schema}") }
+            }
+        }
+    })
+}
+
+fn session_id_param() -> Value {
+    json!({
+        "name": "id",
+        "in": "path",
+        "required": true,
+        "schema": { "type": "string" }
+    })
+}
@@ -0,0 +1,128 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Tokens used on one UTC day, as persisted to disk.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct DailyUsage {
+    day: Option<NaiveDate>,
+    tokens: u64,
+}
+
+/// Gateway-wide daily token cap (`[gateway] daily_token_budget`), counted
+/// across every agent, session and cron job. Once spent, new turns are
+/// refused until the next UTC day. The running total is saved in the
+/// workspace so a restart doesn't reset it.
+#[derive(Debug)]
+pub struct DailyBudget {
+    limit: u64,
+    path: PathBuf,
+    usage: Mutex<DailyUsage>,
+}
+
+impl DailyBudget {
+    /// Budget of `limit` tokens per day, resuming today's total from
+    /// `workspace/usage/daily_tokens.json`.
+    pub fn load(workspace: &Path, limit: u64) -> Self {
+        let path = workspace.join("usage").join("daily_tokens.json");
+        let usage = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!("Ignoring malformed {}: {e}", path.display());
+                DailyUsage::default()
+            }),
+            Err(_) => DailyUsage::default(),
+        };
+        Self {
+            limit,
+            path,
+            usage: Mutex::new(usage),
+        }
+    }
+
+    /// Add `tokens` to today's total and persist it. The file is written
+    /// under the lock so concurrent turns can't save an older total last.
+    pub fn record(&self, tokens: u64) {
+        let today = Utc::now().date_naive();
+        let mut usage = self.usage.lock().unwrap();
+        if usage.day != Some(today) {
+            usage.day = Some(today);
+            usage.tokens = 0;
+        }
+        usage.tokens += tokens;
+        if let Err(e) = self.save(&usage) {
+            warn!("Failed to persist daily token usage: {e}");
+        }
+    }
+
+    /// Tokens used so far today.
+    pub fn used_today(&self) -> u64 {
+        let usage = self.usage.lock().unwrap();
+        if usage.day == Some(Utc::now().date_naive()) {
+            usage.tokens
+        } else {
+            0
+        }
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// If today's budget is spent, when it resets.
+    pub fn exhausted_until(&self) -> Option<DateTime<Utc>> {
+        (self.used_today() >= self.limit).then(|| next_utc_midnight(Utc::now()))
+    }
+
+    fn save(&self, usage: &DailyUsage) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        crate::fs_util::write_atomic(&self.path, &serde_json::to_vec(usage)?)
+    }
+}
+
+/// Start of the UTC day after `now` — when daily budgets reset.
+pub fn next_utc_midnight(now: DateTime<Utc>) -> DateTime<Utc> {
+    let tomorrow = now.date_naive().succ_opt().unwrap_or(NaiveDate::MAX);
+    tomorrow.and_time(chrono::NaiveTime::MIN).and_utc()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_daily_budget_persists_and_caps() {
+        let dir = tempfile::tempdir().unwrap();
+        let budget = DailyBudget::load(dir.path(), 100);
+        budget.record(60);
+        assert!(budget.exhausted_until().is_none());
+
+        // A restart picks up today's total.
+        let budget = DailyBudget::load(dir.path(), 100);
+        assert_eq!(budget.used_today(), 60);
+        budget.record(40);
+        let until = budget.exhausted_until().unwrap();
+        assert_eq!(until, next_utc_midnight(Utc::now()));
+    }
+
+    #[test]
+    fn test_concurrent_records_persist_the_full_total() {
+        let dir = tempfile::tempdir().unwrap();
+        let budget = DailyBudget::load(dir.path(), 1_000_000);
+        std::thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    for _ in 0..25 {
+                        budget.record(1);
+                    }
+                });
+            }
+        });
+        assert_eq!(budget.used_today(), 200);
+        assert_eq!(DailyBudget::load(dir.path(), 1_000_000).used_today(), 200);
+    }
+}
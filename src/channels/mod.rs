@@ -1,12 +1,18 @@
+#[cfg(feature = "telegram")]
 pub mod telegram;
 
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
 use async_trait::async_trait;
+use serde::Serialize;
 use tokio::sync::mpsc;
 
 use crate::error::Result;
 
 /// A file attachment to send through a channel.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Attachment {
     pub path: std::path::PathBuf,
     pub mime_type: String,
@@ -22,9 +28,12 @@ pub struct InboundMessage {
     pub display_name: Option<String>,
     /// The chat/recipient ID to reply to (may differ from sender_id in groups).
     pub reply_to: String,
+    /// Files sent with the message, already saved in the workspace.
+    pub attachments: Vec<Attachment>,
 }
 
 /// An outbound message to send back through a channel.
+#[derive(Serialize)]
 pub struct OutboundMessage {
     pub channel: String,
     pub recipient_id: String,
@@ -32,6 +41,19 @@ pub struct OutboundMessage {
     pub attachments: Vec<Attachment>,
 }
 
+/// Where a channel saves an inbound file: `dir`, counted against the quota
+/// of `workspace`.
+#[derive(Debug, Clone)]
+pub struct InboxTarget {
+    pub dir: PathBuf,
+    pub workspace: PathBuf,
+    pub quota: Option<u64>,
+}
+
+/// Picks the [`InboxTarget`] for a message involving `ids` (sender, chat),
+/// so files land with the agent that will handle it.
+pub type InboxRouter = Arc<dyn Fn(&[&str]) -> InboxTarget + Send + Sync>;
+
 /// Trait for external channel integrations.
 #[async_trait]
 pub trait Channel: Send + Sync {
@@ -45,3 +67,38 @@ pub trait Channel: Send + Sync {
 
     async fn stop(&self) -> Result<()>;
 }
+
+/// Append an undeliverable message to `workspace/channels/undelivered.jsonl`
+/// so it can be recovered after a channel outage.
+pub fn record_undelivered(workspace: &Path, msg: &OutboundMessage, error: &str) -> Result<()> {
+    let entry = serde_json::json!({
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "error": error,
+        "message": msg,
+    });
+    let dir = workspace.join("channels");
+    std::fs::create_dir_all(&dir)?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join("undelivered.jsonl"))?;
+    writeln!(file, "{entry}")?;
+    Ok(())
+}
+
+/// Append a message a channel would have sent to
+/// `workspace/channels/<channel>.dry-run.jsonl` (`dry_run` mode).
+pub fn record_dry_run(workspace: &Path, channel: &str, msg: &OutboundMessage) -> Result<()> {
+    let entry = serde_json::json!({
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "message": msg,
+    });
+    let dir = workspace.join("channels");
+    std::fs::create_dir_all(&dir)?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(format!("{channel}.dry-run.jsonl")))?;
+    writeln!(file, "{entry}")?;
+    Ok(())
+}
@@ -1,24 +1,38 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::future::Future;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
-use teloxide::net::default_reqwest_settings;
+use teloxide::net::{default_reqwest_settings, Download};
 use teloxide::payloads::GetUpdatesSetters;
 use teloxide::payloads::{SendAudioSetters, SendDocumentSetters, SendPhotoSetters, SendVideoSetters};
 use teloxide::requests::Requester;
-use teloxide::types::{ChatId, ChatKind, InputFile, UpdateKind};
-use teloxide::Bot;
+use teloxide::payloads::SendMessageSetters;
+use teloxide::types::{ChatId, ChatKind, InputFile, Message, ParseMode, UpdateKind};
+use teloxide::{ApiError, Bot, RequestError};
 use tokio::sync::mpsc;
 use tracing::{debug, error, info, warn};
 
-use crate::channels::{Channel, InboundMessage, OutboundMessage};
-use crate::config::TelegramConfig;
+use crate::channels::{
+    record_dry_run, record_undelivered, Attachment, Channel, InboundMessage, InboxRouter,
+    InboxTarget, OutboundMessage,
+};
+use crate::config::{TelegramConfig, TelegramUser};
 use crate::error::{NekoError, Result};
 
 pub struct TelegramChannel {
     config: TelegramConfig,
+    /// `parse_mode` from the config, validated.
+    parse_mode: Option<ParseMode>,
     bot: Bot,
     running: Arc<AtomicBool>,
+    workspace: PathBuf,
+    /// Picks where inbound files go (`<inbox>/telegram/<chat_id>/`).
+    inbox: Option<InboxRouter>,
+    workspace_quota: Option<u64>,
 }
 
 impl TelegramChannel {
@@ -35,14 +49,264 @@ impl TelegramChannel {
             .build()
             .map_err(|e| NekoError::Channel(format!("Failed to build HTTP client: {e}")))?;
 
+        let parse_mode = match config.parse_mode.as_deref().filter(|m| !m.is_empty()) {
+            None => None,
+            Some(mode) if mode.eq_ignore_ascii_case("html") => Some(ParseMode::Html),
+            Some(mode) if mode.eq_ignore_ascii_case("markdownv2") => Some(ParseMode::MarkdownV2),
+            Some(other) => {
+                return Err(NekoError::Config(format!(
+                    "Unsupported Telegram parse_mode '{other}' (use HTML or MarkdownV2)"
+                )))
+            }
+        };
+
         let bot = Bot::with_client(token, client);
         Ok(Self {
             config,
+            parse_mode,
             bot,
             running: Arc::new(AtomicBool::new(false)),
+            workspace: PathBuf::new(),
+            inbox: None,
+            workspace_quota: None,
         })
     }
 
+    /// Workspace used to persist users approved via `approval_mode`.
+    pub fn with_workspace(mut self, workspace: PathBuf) -> Self {
+        self.workspace = workspace;
+        self
+    }
+
+    /// Where inbound files are saved; defaults to `workspace/inbox`. Route
+    /// them under the file root of the agent handling the chat so its file
+    /// tools can open them.
+    pub fn with_inbox(mut self, inbox: InboxRouter) -> Self {
+        self.inbox = Some(inbox);
+        self
+    }
+
+    /// Refuse inbound files that would take the workspace past this size.
+    pub fn with_workspace_quota(mut self, quota: Option<u64>) -> Self {
+        self.workspace_quota = quota;
+        self
+    }
+}
+
+fn approved_users_path(workspace: &Path) -> PathBuf {
+    workspace.join("telegram").join("approved_users.json")
+}
+
+fn load_approved_users(workspace: &Path) -> Vec<i64> {
+    let path = approved_users_path(workspace);
+    match std::fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            warn!("Ignoring malformed {}: {e}", path.display());
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn save_approved_users(workspace: &Path, ids: &[i64]) -> Result<()> {
+    let path = approved_users_path(workspace);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, serde_json::to_string_pretty(ids)?)?;
+    Ok(())
+}
+
+/// State for `approval_mode`: users the admin has approved, persisted under
+/// the workspace, and those still waiting for an answer.
+struct Approvals {
+    workspace: PathBuf,
+    admin: Option<i64>,
+    approved: Vec<i64>,
+    pending: HashSet<i64>,
+}
+
+impl Approvals {
+    /// Load earlier approvals and admit them, and the admin, to `allowlist`.
+    fn load(workspace: &Path, admin: Option<i64>, allowlist: &mut Allowlist) -> Self {
+        let approved = load_approved_users(workspace);
+        allowlist.approval_mode = true;
+        allowlist.ids.extend(approved.iter().copied());
+        allowlist.ids.extend(admin);
+        Self {
+            workspace: workspace.to_path_buf(),
+            admin,
+            approved,
+            pending: HashSet::new(),
+        }
+    }
+
+    fn is_admin(&self, user_id: i64) -> bool {
+        self.admin == Some(user_id)
+    }
+
+    /// Hold an unknown user for approval. Returns the admin to ask the first
+    /// time `user_id` writes in, and None while the request is pending.
+    fn request(&mut self, user_id: i64) -> Option<i64> {
+        let admin = self.admin?;
+        self.pending.insert(user_id).then_some(admin)
+    }
+
+    /// Admit `id` on behalf of `from` and save the approval. Returns false,
+    /// changing nothing, unless `from` is the admin.
+    fn approve(&mut self, from: i64, id: i64, allowlist: &mut Allowlist) -> bool {
+        if !self.is_admin(from) {
+            return false;
+        }
+        self.pending.remove(&id);
+        allowlist.ids.insert(id);
+        if !self.approved.contains(&id) {
+            self.approved.push(id);
+        }
+        if let Err(e) = save_approved_users(&self.workspace, &self.approved) {
+            error!("Failed to persist approved Telegram users: {e}");
+        }
+        true
+    }
+}
+
+fn offset_path(workspace: &Path) -> PathBuf {
+    workspace.join("channels").join("telegram.offset")
+}
+
+/// The `getUpdates` offset saved by the last run, or 0 to start fresh.
+fn load_offset(workspace: &Path) -> i32 {
+    let path = offset_path(workspace);
+    match std::fs::read_to_string(&path) {
+        Ok(content) => content.trim().parse().unwrap_or_else(|e| {
+            warn!("Ignoring malformed {}: {e}", path.display());
+            0
+        }),
+        Err(_) => 0,
+    }
+}
+
+fn save_offset(workspace: &Path, offset: i32) -> Result<()> {
+    let path = offset_path(workspace);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    crate::fs_util::write_atomic(&path, offset.to_string().as_bytes())?;
+    Ok(())
+}
+
+/// Persist `offset` (the next update to handle) unless it is already
+/// saved. Called as each update is dispatched, so a crash mid-batch only
+/// redelivers the updates not handled yet.
+fn save_progress(workspace: &Path, saved: &mut i32, offset: i32) {
+    if offset == *saved {
+        return;
+    }
+    match save_offset(workspace, offset) {
+        Ok(()) => *saved = offset,
+        Err(e) => error!("Failed to persist Telegram offset: {e}"),
+    }
+}
+
+/// Offset just past the newest pending update, if any. Requesting offset
+/// -1 returns only the last update without acknowledging it.
+async fn latest_update_offset(bot: &Bot) -> Option<i32> {
+    match bot.get_updates().offset(-1).timeout(0).await {
+        Ok(updates) => updates.last().map(|u| u.id.0 as i32 + 1),
+        Err(e) => {
+            warn!("Telegram getUpdates error while skipping pending updates: {e}");
+            None
+        }
+    }
+}
+
+/// How many recent update IDs are remembered to catch redeliveries.
+const RECENT_UPDATES: usize = 256;
+
+/// Ring buffer of recently handled update IDs. Telegram redelivers updates
+/// whose offset wasn't acknowledged (e.g. a network error mid-batch); these
+/// are skipped rather than answered twice.
+struct RecentUpdates {
+    ids: VecDeque<u32>,
+}
+
+impl RecentUpdates {
+    fn new() -> Self {
+        Self {
+            ids: VecDeque::with_capacity(RECENT_UPDATES),
+        }
+    }
+
+    /// Record `id`. Returns false if it was already seen.
+    fn insert(&mut self, id: u32) -> bool {
+        if self.ids.contains(&id) {
+            return false;
+        }
+        if self.ids.len() == RECENT_UPDATES {
+            self.ids.pop_front();
+        }
+        self.ids.push_back(id);
+        true
+    }
+}
+
+/// `allowed_users` split into numeric IDs and lowercased usernames (no `@`).
+/// Usernames are matched against `from.username`; IDs take precedence.
+struct Allowlist {
+    ids: HashSet<i64>,
+    usernames: HashSet<String>,
+    /// Usernames seen so far, mapped to their numeric IDs.
+    resolved: HashMap<String, i64>,
+    /// In approval mode an empty allowlist admits nobody.
+    approval_mode: bool,
+}
+
+impl Allowlist {
+    fn new(users: &[TelegramUser]) -> Self {
+        let mut ids = HashSet::new();
+        let mut usernames = HashSet::new();
+        for user in users {
+            match user {
+                TelegramUser::Id(id) => {
+                    ids.insert(*id);
+                }
+                TelegramUser::Username(name) => {
+                    let name = name.trim().trim_start_matches('@').to_lowercase();
+                    if let Ok(id) = name.parse::<i64>() {
+                        ids.insert(id);
+                    } else if !name.is_empty() {
+                        usernames.insert(name);
+                    }
+                }
+            }
+        }
+        Self {
+            ids,
+            usernames,
+            resolved: HashMap::new(),
+            approval_mode: false,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ids.is_empty() && self.usernames.is_empty()
+    }
+
+    fn allows(&mut self, user_id: i64, username: Option<&str>) -> bool {
+        if (self.is_empty() && !self.approval_mode) || self.ids.contains(&user_id) {
+            return true;
+        }
+        let Some(name) = username.map(|n| n.to_lowercase()) else {
+            return false;
+        };
+        if !self.usernames.contains(&name) {
+            return false;
+        }
+        if self.resolved.insert(name.clone(), user_id) != Some(user_id) {
+            info!("Telegram allowlist: resolved @{name} to user ID {user_id}");
+        }
+        true
+    }
 }
 
 #[async_trait]
@@ -59,64 +323,73 @@ impl Channel for TelegramChannel {
         self.running.store(true, Ordering::SeqCst);
         let running = self.running.clone();
         let bot = self.bot.clone();
-        let allowed_users = self.config.allowed_users.clone();
+        let mut allowlist = Allowlist::new(&self.config.allowed_users);
+        let command_prefix = self.config.command_prefix.clone();
+        let workspace = self.workspace.clone();
+        let inbox = self.inbox.clone();
+        let workspace_quota = self.workspace_quota;
+        let mut approvals = None;
+        if self.config.approval_mode {
+            let admin_id = self.config.admin_user_id;
+            approvals = Some(Approvals::load(&workspace, admin_id, &mut allowlist));
+            if admin_id.is_none() {
+                warn!("Telegram approval_mode is on but admin_user_id is not set; unknown users will be ignored");
+            }
+        }
+        if !allowlist.usernames.is_empty() {
+            let mut names: Vec<&String> = allowlist.usernames.iter().collect();
+            names.sort();
+            info!(
+                "Telegram allowlist: {} user IDs, usernames pending resolution: {}",
+                allowlist.ids.len(),
+                names.iter().map(|n| format!("@{n}")).collect::<Vec<_>>().join(", ")
+            );
+        }
 
         // Spawn outbound message sender
         let send_bot = bot.clone();
+        let dead_letter_dir = self.workspace.clone();
+        let dry_run = self.config.dry_run;
+        let parse_mode = self.parse_mode;
+        if dry_run {
+            warn!("Telegram dry_run is on: outbound messages are logged, not sent");
+        }
         tokio::spawn(async move {
             while let Some(msg) = outbound_rx.recv().await {
-                let chat_id: i64 = match msg.recipient_id.parse() {
-                    Ok(id) => id,
-                    Err(e) => {
-                        error!("Invalid chat_id '{}': {e}", msg.recipient_id);
-                        continue;
-                    }
-                };
-
-                let cid = ChatId(chat_id);
-
-                if msg.attachments.is_empty() {
-                    // Text-only message
-                    if let Err(e) = send_bot.send_message(cid, &msg.text).await {
-                        error!("Failed to send Telegram message: {e}");
+                if dry_run {
+                    info!(
+                        "[dry run] Telegram message to {} ({} attachment(s)):\n{}",
+                        msg.recipient_id,
+                        msg.attachments.len(),
+                        msg.text
+                    );
+                    if let Err(e) = record_dry_run(&dead_letter_dir, "telegram", &msg) {
+                        error!("Failed to record dry-run message: {e}");
                     }
                     continue;
                 }
 
-                // Has attachments — decide caption strategy.
-                // Telegram captions are limited to 1024 chars.
-                let text = msg.text.trim();
-                let text_fits_caption = text.len() <= 1024;
-
-                // If text is too long for a caption, send it as a separate message first.
-                if !text.is_empty() && !text_fits_caption {
-                    if let Err(e) = send_bot.send_message(cid, text).await {
-                        error!("Failed to send Telegram text message: {e}");
-                    }
-                }
-
-                for (i, attachment) in msg.attachments.iter().enumerate() {
-                    // First attachment gets caption if text fits
-                    let caption = if i == 0 && !text.is_empty() && text_fits_caption {
-                        Some(text)
-                    } else {
-                        None
-                    };
+                deliver(&send_bot, &msg, parse_mode, &dead_letter_dir).await;
+            }
+        });
 
-                    let input_file = InputFile::file(&attachment.path);
-                    let result = send_media(&send_bot, cid, input_file, &attachment.mime_type, caption).await;
-                    if let Err(e) = result {
-                        error!(
-                            "Failed to send Telegram media {}: {e}",
-                            attachment.path.display()
-                        );
+        // Long-poll loop for updates, resuming where the last run stopped
+        let mut offset = load_offset(&workspace);
+        if self.config.skip_pending_on_start {
+            if let Some(latest) = latest_update_offset(&bot).await {
+                if latest > offset {
+                    info!("Skipping pending Telegram updates up to offset {latest}");
+                    offset = latest;
+                    if let Err(e) = save_offset(&workspace, offset) {
+                        error!("Failed to persist Telegram offset: {e}");
                     }
                 }
             }
-        });
-
-        // Long-poll loop for updates
-        let mut offset: i32 = 0;
+        } else if offset > 0 {
+            info!("Resuming Telegram updates from offset {offset}");
+        }
+        let mut saved_offset = offset;
+        let mut seen = RecentUpdates::new();
 
         while running.load(Ordering::SeqCst) {
             let updates = match bot
@@ -133,16 +406,29 @@ impl Channel for TelegramChannel {
                 }
             };
 
+            // Acknowledged on the next poll; saved to disk only as each
+            // update is handled
+            if let Some(last) = updates.last() {
+                offset = last.id.0 as i32 + 1;
+            }
+
             for update in &updates {
-                offset = update.id.0 as i32 + 1;
+                // Everything before this update has been dispatched
+                save_progress(&workspace, &mut saved_offset, update.id.0 as i32);
+                if !seen.insert(update.id.0) {
+                    debug!("Skipping duplicate Telegram update {}", update.id.0);
+                    continue;
+                }
 
                 let UpdateKind::Message(ref message) = update.kind else {
                     continue;
                 };
 
-                let Some(text) = message.text() else {
+                let media = inbound_media(message);
+                let text = message.text().or(message.caption()).unwrap_or_default();
+                if text.is_empty() && media.is_none() {
                     continue;
-                };
+                }
 
                 let Some(from) = &message.from else {
                     continue;
@@ -150,8 +436,57 @@ impl Channel for TelegramChannel {
 
                 let user_id = from.id.0 as i64;
 
+                // Admin approval commands
+                if let Some(approvals) = approvals.as_mut().filter(|a| a.is_admin(user_id)) {
+                    if let Some(arg) = text.strip_prefix("/approve ") {
+                        let reply = match arg.trim().parse::<i64>() {
+                            Ok(id) if approvals.approve(user_id, id, &mut allowlist) => {
+                                info!("Telegram user {id} approved by admin");
+                                let _ = bot
+                                    .send_message(ChatId(id), "Your access request was approved. You can now talk to me.")
+                                    .await;
+                                format!("Approved user {id}.")
+                            }
+                            _ => "Usage: /approve <user_id>".to_string(),
+                        };
+                        let _ = bot.send_message(message.chat.id, reply).await;
+                        continue;
+                    }
+                }
+
+                // Require the command prefix, if configured
+                let text = if command_prefix.is_empty() {
+                    text
+                } else {
+                    match text.strip_prefix(command_prefix.as_str()) {
+                        Some(rest) if !rest.trim().is_empty() => rest.trim_start(),
+                        _ => continue,
+                    }
+                };
+
                 // Check allowed_users
-                if !allowed_users.is_empty() && !allowed_users.contains(&user_id) {
+                if !allowlist.allows(user_id, from.username.as_deref()) {
+                    if let Some(approvals) = approvals.as_mut().filter(|a| a.admin.is_some()) {
+                        if let Some(admin) = approvals.request(user_id) {
+                            let who = match &from.username {
+                                Some(u) => format!("{} (@{u})", from.first_name),
+                                None => from.first_name.clone(),
+                            };
+                            let request = format!(
+                                "Access request from {who}, user ID {user_id}:\n\"{text}\"\n\nReply /approve {user_id} to grant access."
+                            );
+                            if let Err(e) = bot.send_message(ChatId(admin), request).await {
+                                error!("Failed to send access request to admin: {e}");
+                            }
+                            let _ = bot
+                                .send_message(
+                                    message.chat.id,
+                                    "Your access request has been sent to the admin.",
+                                )
+                                .await;
+                        }
+                        continue;
+                    }
                     debug!("Ignoring message from unauthorized user {user_id}");
                     continue;
                 }
@@ -171,6 +506,27 @@ impl Channel for TelegramChannel {
                     (None, chat_id.to_string())
                 };
 
+                let mut attachments = Vec::new();
+                if let Some((file_id, name, mime_type)) = media {
+                    let target = match &inbox {
+                        Some(route) => route(&[sender_id.as_str(), reply_to.as_str()]),
+                        None => InboxTarget {
+                            dir: workspace.join("inbox"),
+                            workspace: workspace.clone(),
+                            quota: workspace_quota,
+                        },
+                    };
+                    match download_inbound(&bot, &target, chat_id, &file_id, &name).await {
+                        Ok(path) => attachments.push(Attachment { path, mime_type }),
+                        Err(e) => {
+                            warn!("Failed to download Telegram attachment: {e}");
+                            if text.is_empty() {
+                                continue;
+                            }
+                        }
+                    }
+                }
+
                 let inbound = InboundMessage {
                     channel: "telegram".to_string(),
                     sender_id,
@@ -179,12 +535,14 @@ impl Channel for TelegramChannel {
                     group_id,
                     display_name: Some(display_name),
                     reply_to,
+                    attachments,
                 };
 
                 if let Err(e) = inbound_tx.send(inbound).await {
                     error!("Failed to forward inbound message: {e}");
                 }
             }
+            save_progress(&workspace, &mut saved_offset, offset);
         }
 
         Ok(())
@@ -197,6 +555,166 @@ impl Channel for TelegramChannel {
     }
 }
 
+/// Send one outbound message. Whatever still fails after retries is
+/// appended to the dead-letter log under `dead_letter_dir`.
+async fn deliver(
+    bot: &Bot,
+    msg: &OutboundMessage,
+    parse_mode: Option<ParseMode>,
+    dead_letter_dir: &Path,
+) {
+    let chat_id: i64 = match msg.recipient_id.parse() {
+        Ok(id) => id,
+        Err(e) => {
+            error!("Invalid chat_id '{}': {e}", msg.recipient_id);
+            return;
+        }
+    };
+
+    let cid = ChatId(chat_id);
+    let mut failure: Option<String> = None;
+
+    if msg.attachments.is_empty() {
+        // Text-only message
+        if let Err(e) = send_text(bot, cid, &msg.text, parse_mode).await {
+            error!("Failed to send Telegram message: {e}");
+            failure = Some(e.to_string());
+        }
+    } else {
+        // Has attachments — decide caption strategy.
+        // Telegram captions are limited to 1024 chars.
+        let text = msg.text.trim();
+        let text_fits_caption = text.len() <= 1024;
+
+        // If text is too long for a caption, send it as a separate message first.
+        if !text.is_empty() && !text_fits_caption {
+            if let Err(e) = send_text(bot, cid, text, parse_mode).await {
+                error!("Failed to send Telegram text message: {e}");
+                failure = Some(e.to_string());
+            }
+        }
+
+        for (i, attachment) in msg.attachments.iter().enumerate() {
+            // First attachment gets caption if text fits
+            let caption = if i == 0 && !text.is_empty() && text_fits_caption {
+                Some(text)
+            } else {
+                None
+            };
+
+            let result = send_attachment(
+                bot,
+                cid,
+                &attachment.path,
+                &attachment.mime_type,
+                caption,
+                parse_mode,
+            )
+            .await;
+            if let Err(e) = result {
+                error!(
+                    "Failed to send Telegram media {}: {e}",
+                    attachment.path.display()
+                );
+                failure = Some(e.to_string());
+            }
+        }
+    }
+
+    if let Some(err) = failure {
+        if let Err(e) = record_undelivered(dead_letter_dir, msg, &err) {
+            error!("Failed to record undelivered message: {e}");
+        }
+    }
+}
+
+/// Maximum send attempts for an outbound Telegram request.
+const SEND_ATTEMPTS: u32 = 4;
+
+/// Wait before the first retry, doubling after each one.
+const SEND_BACKOFF: Duration = if cfg!(test) {
+    Duration::from_millis(10)
+} else {
+    Duration::from_secs(1)
+};
+
+/// Run a Telegram request, retrying transient failures (network errors and
+/// flood control) with exponential backoff. API errors are not retried.
+async fn send_with_retry<F, Fut>(mut send: F) -> std::result::Result<(), RequestError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = std::result::Result<(), RequestError>>,
+{
+    let mut backoff = SEND_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        let err = match send().await {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        let delay = match &err {
+            RequestError::RetryAfter(secs) => secs.duration(),
+            RequestError::Network(_) | RequestError::Io(_) => backoff,
+            _ => return Err(err),
+        };
+        if attempt >= SEND_ATTEMPTS {
+            return Err(err);
+        }
+        warn!("Telegram send failed (attempt {attempt}/{SEND_ATTEMPTS}): {err}; retrying in {delay:?}");
+        tokio::time::sleep(delay).await;
+        backoff *= 2;
+        attempt += 1;
+    }
+}
+
+/// Send a text message formatted with `parse_mode`. If Telegram can't
+/// parse the formatting, log why and resend it as plain text so the reply
+/// still gets through.
+async fn send_text(
+    bot: &Bot,
+    chat_id: ChatId,
+    text: &str,
+    parse_mode: Option<ParseMode>,
+) -> std::result::Result<(), RequestError> {
+    if let Some(mode) = parse_mode {
+        let formatted = send_with_retry(|| async {
+            bot.send_message(chat_id, text).parse_mode(mode).await.map(|_| ())
+        })
+        .await;
+        match formatted {
+            Err(RequestError::Api(ApiError::CantParseEntities(e))) => {
+                warn!("Telegram rejected the message's {mode:?} formatting ({e}); resending it as plain text");
+            }
+            other => return other,
+        }
+    }
+    send_with_retry(|| async { bot.send_message(chat_id, text).await.map(|_| ()) }).await
+}
+
+/// Send a media file with retries, falling back to a plain caption when
+/// Telegram can't parse the formatted one.
+async fn send_attachment(
+    bot: &Bot,
+    chat_id: ChatId,
+    path: &Path,
+    mime_type: &str,
+    caption: Option<&str>,
+    parse_mode: Option<ParseMode>,
+) -> std::result::Result<(), RequestError> {
+    let send = |mode| {
+        send_with_retry(move || {
+            send_media(bot, chat_id, InputFile::file(path), mime_type, caption, mode)
+        })
+    };
+    match send(parse_mode).await {
+        Err(RequestError::Api(ApiError::CantParseEntities(e))) => {
+            warn!("Telegram rejected the caption's formatting ({e}); resending it as plain text");
+            send(None).await
+        }
+        other => other,
+    }
+}
+
 /// Dispatch a media file via the appropriate Telegram API based on MIME type.
 async fn send_media(
     bot: &Bot,
@@ -204,31 +722,223 @@ async fn send_media(
     file: InputFile,
     mime_type: &str,
     caption: Option<&str>,
-) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    parse_mode: Option<ParseMode>,
+) -> std::result::Result<(), RequestError> {
+    // Formatting only applies to captions
+    let parse_mode = parse_mode.filter(|_| caption.is_some());
     if mime_type.starts_with("image/") {
         let mut req = bot.send_photo(chat_id, file);
         if let Some(c) = caption {
             req = req.caption(c);
         }
+        if let Some(mode) = parse_mode {
+            req = req.parse_mode(mode);
+        }
         req.await?;
     } else if mime_type.starts_with("audio/") {
         let mut req = bot.send_audio(chat_id, file);
         if let Some(c) = caption {
             req = req.caption(c);
         }
+        if let Some(mode) = parse_mode {
+            req = req.parse_mode(mode);
+        }
         req.await?;
     } else if mime_type.starts_with("video/") {
         let mut req = bot.send_video(chat_id, file);
         if let Some(c) = caption {
             req = req.caption(c);
         }
+        if let Some(mode) = parse_mode {
+            req = req.parse_mode(mode);
+        }
         req.await?;
     } else {
         let mut req = bot.send_document(chat_id, file);
         if let Some(c) = caption {
             req = req.caption(c);
         }
+        if let Some(mode) = parse_mode {
+            req = req.parse_mode(mode);
+        }
         req.await?;
     }
     Ok(())
 }
+
+/// The photo (largest size) or document on a message, as
+/// (file ID, local file name, MIME type). Names are prefixed with the
+/// message ID so repeated uploads don't collide.
+fn inbound_media(message: &Message) -> Option<(String, String, String)> {
+    let id = message.id.0;
+    let largest_photo = message
+        .photo()
+        .and_then(|sizes| sizes.iter().max_by_key(|p| p.width * p.height));
+    if let Some(photo) = largest_photo {
+        return Some((photo.file.id.clone(), format!("{id}.jpg"), "image/jpeg".to_string()));
+    }
+    let document = message.document()?;
+    // The name comes from the sender; keep only its final component
+    let name = document
+        .file_name
+        .as_deref()
+        .and_then(|n| Path::new(n).file_name())
+        .map_or_else(|| "file".to_string(), |n| n.to_string_lossy().into_owned());
+    let mime_type = document
+        .mime_type
+        .as_ref()
+        .map_or_else(|| "application/octet-stream".to_string(), |m| m.to_string());
+    Some((document.file.id.clone(), format!("{id}-{name}"), mime_type))
+}
+
+/// Save an inbound file to `<inbox>/telegram/<chat_id>/<name>`, unless it
+/// would exceed the target workspace's quota.
+async fn download_inbound(
+    bot: &Bot,
+    inbox: &InboxTarget,
+    chat_id: i64,
+    file_id: &str,
+    name: &str,
+) -> Result<PathBuf> {
+    let file = bot
+        .get_file(file_id)
+        .await
+        .map_err(|e| NekoError::Channel(format!("getFile failed: {e}")))?;
+    // Telegram reports u32::MAX when it doesn't know the size.
+    let size = if file.size == u32::MAX { 0 } else { u64::from(file.size) };
+    if let Some(msg) = crate::workspace::check_quota_for(&inbox.workspace, inbox.quota, size) {
+        return Err(NekoError::Channel(msg));
+    }
+    let dir = inbox.dir.join("telegram").join(chat_id.to_string());
+    tokio::fs::create_dir_all(&dir).await?;
+    let path = dir.join(name);
+    let mut dst = tokio::fs::File::create(&path).await?;
+    if let Err(e) = bot.download_file(&file.path, &mut dst).await {
+        let _ = tokio::fs::remove_file(&path).await;
+        return Err(NekoError::Channel(format!("Download failed: {e}")));
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_updates_skips_duplicates() {
+        let mut seen = RecentUpdates::new();
+        assert!(seen.insert(1));
+        assert!(!seen.insert(1));
+        for id in 2..=RECENT_UPDATES as u32 + 1 {
+            assert!(seen.insert(id));
+        }
+        // The oldest ID has been evicted.
+        assert!(seen.insert(1));
+    }
+
+    #[tokio::test]
+    async fn test_failed_send_retries_then_dead_letters() {
+        // A "Telegram API" that drops every connection, counting attempts
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let attempts = Arc::new(std::sync::atomic::AtomicU32::new(0));
+        let counter = attempts.clone();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                drop(socket);
+            }
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let bot = Bot::new("123:token").set_api_url(reqwest::Url::parse(&url).unwrap());
+        let msg = OutboundMessage {
+            channel: "telegram".to_string(),
+            recipient_id: "42".to_string(),
+            text: "hello".to_string(),
+            attachments: vec![],
+        };
+        deliver(&bot, &msg, None, dir.path()).await;
+
+        assert_eq!(attempts.load(Ordering::SeqCst), SEND_ATTEMPTS);
+        let log = std::fs::read_to_string(dir.path().join("channels/undelivered.jsonl")).unwrap();
+        let entries: Vec<serde_json::Value> =
+            log.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["message"]["recipient_id"], "42");
+        assert_eq!(entries[0]["message"]["text"], "hello");
+        assert!(!dir.path().join("undelivered.jsonl").exists());
+    }
+
+    const ADMIN: i64 = 100;
+    const STRANGER: i64 = 7;
+
+    #[test]
+    fn test_unknown_user_held_for_approval() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut allowlist = Allowlist::new(&[]);
+        let mut approvals = Approvals::load(dir.path(), Some(ADMIN), &mut allowlist);
+
+        assert!(allowlist.allows(ADMIN, None));
+        assert!(!allowlist.allows(STRANGER, None));
+        // The admin is asked once, not on every message
+        assert_eq!(approvals.request(STRANGER), Some(ADMIN));
+        assert_eq!(approvals.request(STRANGER), None);
+        assert!(!allowlist.allows(STRANGER, None));
+
+        // Without an admin nobody can be asked
+        let mut allowlist = Allowlist::new(&[]);
+        let mut approvals = Approvals::load(dir.path(), None, &mut allowlist);
+        assert_eq!(approvals.request(STRANGER), None);
+    }
+
+    #[test]
+    fn test_admin_approval_persists_across_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut allowlist = Allowlist::new(&[]);
+        let mut approvals = Approvals::load(dir.path(), Some(ADMIN), &mut allowlist);
+        approvals.request(STRANGER);
+
+        assert!(approvals.approve(ADMIN, STRANGER, &mut allowlist));
+        assert!(allowlist.allows(STRANGER, None));
+        assert!(approvals.pending.is_empty());
+        assert_eq!(load_approved_users(dir.path()), vec![STRANGER]);
+
+        // A fresh start admits the user without asking again
+        let mut allowlist = Allowlist::new(&[]);
+        Approvals::load(dir.path(), Some(ADMIN), &mut allowlist);
+        assert!(allowlist.allows(STRANGER, None));
+    }
+
+    #[test]
+    fn test_non_admin_cannot_approve() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut allowlist = Allowlist::new(&[TelegramUser::Id(42)]);
+        let mut approvals = Approvals::load(dir.path(), Some(ADMIN), &mut allowlist);
+        approvals.request(STRANGER);
+
+        // Neither an allowed user nor the stranger themselves
+        assert!(!approvals.approve(42, STRANGER, &mut allowlist));
+        assert!(!approvals.approve(STRANGER, STRANGER, &mut allowlist));
+        assert!(!allowlist.allows(STRANGER, None));
+        assert!(approvals.pending.contains(&STRANGER));
+        assert!(!approved_users_path(dir.path()).exists());
+    }
+
+    #[test]
+    fn test_offset_load_and_save() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = dir.path();
+        assert_eq!(load_offset(ws), 0);
+
+        let mut saved = 0;
+        save_progress(ws, &mut saved, 41);
+        assert_eq!((saved, load_offset(ws)), (41, 41));
+        // A crash after dispatching update 41 resumes at 42
+        save_progress(ws, &mut saved, 42);
+        assert_eq!(load_offset(ws), 42);
+
+        std::fs::write(offset_path(ws), "garbage").unwrap();
+        assert_eq!(load_offset(ws), 0);
+    }
+}
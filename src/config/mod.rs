@@ -23,7 +23,35 @@ pub struct Config {
     #[serde(default)]
     pub heartbeat: HeartbeatConfig,
     #[serde(default)]
+    pub skills: SkillsConfig,
+    #[serde(default)]
     pub mcp: HashMap<String, McpServerConfig>,
+    /// Additional named agents served by the same gateway (`[[agents]]`).
+    #[serde(default)]
+    pub agents: Vec<AgentProfile>,
+}
+
+/// A named agent with its own workspace (memory, skills) and overrides of
+/// the top-level `[agent]` settings. Inbound messages matching one of its
+/// `routes` go to this agent; everything else goes to the default agent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentProfile {
+    pub name: String,
+    /// Defaults to `~/.neko/agents/<name>`.
+    #[serde(default)]
+    pub workspace: Option<String>,
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub tools: Option<Vec<String>>,
+    #[serde(default)]
+    pub instructions: Option<String>,
+    /// `channel` or `channel:id`, where id is a sender, chat or group ID
+    /// (e.g. "telegram", "telegram:-100123", "http:ci-bot").
+    #[serde(default)]
+    pub routes: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,8 +60,39 @@ pub struct GatewayConfig {
     pub bind: String,
     #[serde(default)]
     pub api_token: Option<String>,
+    /// Additional bearer tokens mapped to their scopes (`message`,
+    /// `sessions:read`, `sessions:write`, `files:read`, `config:read`,
+    /// `config:write`, or `*`), optionally with a tool whitelist. `api_token` keeps full access.
+    #[serde(default)]
+    pub tokens: HashMap<String, ApiToken>,
     #[serde(default = "default_workspace")]
     pub workspace: String,
+    /// Soft quota for the workspace in MB. When exceeded, file-writing tools
+    /// refuse to write. Unset = no limit.
+    #[serde(default)]
+    pub workspace_max_mb: Option<u64>,
+    /// Tokens all sessions and cron jobs together may use per UTC day.
+    /// Once spent, new turns are refused until the next day. Unset = no cap.
+    #[serde(default)]
+    pub daily_token_budget: Option<u64>,
+    /// Origins allowed to call the HTTP API from a browser (e.g.
+    /// "https://app.example.com"), or ["*"] for any. Empty = no CORS headers.
+    #[serde(default)]
+    pub cors_origins: Vec<String>,
+    /// Maximum HTTP request body size in bytes.
+    #[serde(default = "default_max_body_bytes")]
+    pub max_body_bytes: usize,
+    /// Maximum characters of `text` accepted by `POST /api/v1/message`.
+    #[serde(default = "default_max_message_chars")]
+    pub max_message_chars: usize,
+    /// Maximum in-flight API requests per client IP. 0 = unlimited.
+    #[serde(default = "default_max_concurrent_per_ip")]
+    pub max_concurrent_per_ip: usize,
+    /// Canned prompts keyed by shorthand (e.g. `"/summary"`). A message
+    /// that is exactly a key is replaced by its expansion before the agent
+    /// sees it. Built-in commands like `/new` take precedence.
+    #[serde(default)]
+    pub macros: HashMap<String, String>,
 }
 
 fn default_bind() -> String {
@@ -44,16 +103,43 @@ fn default_workspace() -> String {
     "~/.neko/workspace".to_string()
 }
 
+fn default_max_body_bytes() -> usize {
+    1024 * 1024
+}
+
+fn default_max_message_chars() -> usize {
+    100_000
+}
+
+fn default_max_concurrent_per_ip() -> usize {
+    4
+}
+
 impl Default for GatewayConfig {
     fn default() -> Self {
         Self {
             bind: default_bind(),
             api_token: None,
+            tokens: HashMap::new(),
             workspace: default_workspace(),
+            workspace_max_mb: None,
+            daily_token_budget: None,
+            cors_origins: Vec::new(),
+            max_body_bytes: default_max_body_bytes(),
+            max_message_chars: default_max_message_chars(),
+            max_concurrent_per_ip: default_max_concurrent_per_ip(),
+            macros: HashMap::new(),
         }
     }
 }
 
+impl GatewayConfig {
+    /// The expansion of the macro `text` names, if any.
+    pub fn expand_macro(&self, text: &str) -> Option<&str> {
+        self.macros.get(text.trim()).map(String::as_str)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentConfig {
     #[serde(default = "default_model")]
@@ -62,6 +148,9 @@ pub struct AgentConfig {
     pub provider: String,
     #[serde(default = "default_max_tokens")]
     pub max_tokens: u32,
+    /// Sampling temperature. Unset = the provider's default.
+    #[serde(default)]
+    pub temperature: Option<f32>,
     #[serde(default)]
     pub tools: Vec<String>,
     #[serde(default = "default_compaction_threshold")]
@@ -72,6 +161,85 @@ pub struct AgentConfig {
     pub max_iterations: u32,
     #[serde(default)]
     pub instructions: Option<String>,
+    /// Maximum in-flight LLM requests across all sessions and cron jobs.
+    /// Further requests queue until a slot frees up. 0 = unlimited.
+    #[serde(default = "default_max_concurrent_requests")]
+    pub max_concurrent_requests: usize,
+    /// Abort a turn with a partial response if neither the LLM nor a tool
+    /// makes progress for this long. 0 = disabled.
+    #[serde(default = "default_turn_idle_timeout")]
+    pub turn_idle_timeout_secs: u64,
+    /// Regexes whose matches are removed from the final response text
+    /// (e.g. leaked `<thinking>` blocks). Set to `[]` to disable.
+    #[serde(default = "default_output_filters")]
+    pub output_filters: Vec<String>,
+    /// Detect the language of inbound messages and ask the agent to reply in
+    /// it. A language set with `/lang` always wins.
+    #[serde(default = "default_detect_language")]
+    pub detect_language: bool,
+    /// Reply used when the model ends a turn without any text, followed
+    /// by the tools it tried and the last tool error. Not used when the
+    /// turn already sent a file or message. Empty = send the empty reply
+    /// as is.
+    #[serde(default = "default_empty_reply_fallback")]
+    pub empty_reply_fallback: String,
+    /// Sent to a peer before the agent's first reply in a new session,
+    /// e.g. to introduce what the agent can do. Not repeated after resets.
+    #[serde(default)]
+    pub greeting: Option<String>,
+    /// Once the same tool call (same tool and arguments) has failed this
+    /// many times in a turn, `repeated_failure_note` is added to its
+    /// output. 0 = disabled.
+    #[serde(default = "default_repeated_failure_limit")]
+    pub repeated_failure_limit: u32,
+    /// Tells the model what to do about a call that keeps failing.
+    #[serde(default = "default_repeated_failure_note")]
+    pub repeated_failure_note: String,
+    /// Past the limit, refuse to run the identical call again this turn.
+    #[serde(default)]
+    pub block_repeated_failures: bool,
+    /// Load today's and yesterday's `memory/YYYY-MM-DD.md` logs into the
+    /// prompt.
+    #[serde(default = "default_daily_logs")]
+    pub daily_logs: bool,
+    /// Workspace-relative files loaded into every turn under
+    /// "## Reference" (e.g. project conventions, a glossary).
+    #[serde(default)]
+    pub context_files: Vec<String>,
+    /// Combined size cap for `context_files`; later files are cut off
+    /// past it. 0 = unlimited.
+    #[serde(default = "default_context_files_max_chars")]
+    pub context_files_max_chars: usize,
+    #[serde(default)]
+    pub memory: MemoryConfig,
+}
+
+/// `[agent.memory]` — optional embeddings index for semantic memory search.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryConfig {
+    /// Embed memory files so `memory_search` can rank by meaning
+    /// (`semantic=true`). Costs an embeddings call per new memory chunk.
+    #[serde(default)]
+    pub embeddings: bool,
+    #[serde(default = "default_embedding_model")]
+    pub embedding_model: String,
+    /// Provider serving `/v1/embeddings`. Defaults to `agent.provider`.
+    #[serde(default)]
+    pub embedding_provider: Option<String>,
+}
+
+fn default_embedding_model() -> String {
+    "text-embedding-3-small".to_string()
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            embeddings: false,
+            embedding_model: default_embedding_model(),
+            embedding_provider: None,
+        }
+    }
 }
 
 fn default_model() -> String {
@@ -92,6 +260,38 @@ fn default_max_history() -> u32 {
 fn default_max_iterations() -> u32 {
     10
 }
+fn default_turn_idle_timeout() -> u64 {
+    600
+}
+fn default_output_filters() -> Vec<String> {
+    vec![
+        r"(?s)<thinking>.*?</thinking>".to_string(),
+        r"(?s)<think>.*?</think>".to_string(),
+        r"(?s)<scratchpad>.*?</scratchpad>".to_string(),
+    ]
+}
+fn default_detect_language() -> bool {
+    true
+}
+fn default_context_files_max_chars() -> usize {
+    8000
+}
+
+fn default_daily_logs() -> bool {
+    true
+}
+fn default_empty_reply_fallback() -> String {
+    "I wasn't able to complete that.".to_string()
+}
+fn default_repeated_failure_limit() -> u32 {
+    3
+}
+fn default_repeated_failure_note() -> String {
+    "This tool keeps failing with the same arguments. Try a different approach, or stop and explain the problem.".to_string()
+}
+fn default_max_concurrent_requests() -> usize {
+    4
+}
 
 impl Default for AgentConfig {
     fn default() -> Self {
@@ -99,6 +299,7 @@ impl Default for AgentConfig {
             model: default_model(),
             provider: default_provider(),
             max_tokens: default_max_tokens(),
+            temperature: None,
             tools: vec![
                 "read_file".into(),
                 "write_file".into(),
@@ -111,6 +312,162 @@ impl Default for AgentConfig {
             max_history: default_max_history(),
             max_iterations: default_max_iterations(),
             instructions: None,
+            max_concurrent_requests: default_max_concurrent_requests(),
+            turn_idle_timeout_secs: default_turn_idle_timeout(),
+            output_filters: default_output_filters(),
+            detect_language: default_detect_language(),
+            empty_reply_fallback: default_empty_reply_fallback(),
+            greeting: None,
+            repeated_failure_limit: default_repeated_failure_limit(),
+            repeated_failure_note: default_repeated_failure_note(),
+            block_repeated_failures: false,
+            daily_logs: default_daily_logs(),
+            context_files: Vec::new(),
+            context_files_max_chars: default_context_files_max_chars(),
+            memory: MemoryConfig::default(),
+        }
+    }
+}
+
+/// `[agent]` settings that `PATCH /api/v1/config` may change while the
+/// gateway runs. Everything else needs an edit and a restart.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SettingsPatch {
+    #[serde(default)]
+    pub model: Option<String>,
+    /// An empty string clears the instructions.
+    #[serde(default)]
+    pub instructions: Option<String>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    /// Tools the agent's turns may use; `["*"]` allows all of them again.
+    /// Not persisted.
+    #[serde(default)]
+    pub tools: Option<Vec<String>>,
+}
+
+/// The runtime tool whitelist for a patched `tools` list: `None` (no
+/// restriction) for `["*"]`.
+pub fn tool_whitelist(tools: &[String]) -> Option<Vec<String>> {
+    (tools != ["*"]).then(|| tools.to_vec())
+}
+
+impl SettingsPatch {
+    pub const FIELDS: &[&str] = &["model", "instructions", "max_tokens", "temperature", "tools"];
+
+    /// Reject values the agent couldn't run with; `known_tools` are the
+    /// registered tool names.
+    pub fn validate(&self, known_tools: &[&str]) -> Result<()> {
+        if self.model.as_deref().is_some_and(|m| m.trim().is_empty()) {
+            return Err(NekoError::Config("model must not be empty".to_string()));
+        }
+        if self.max_tokens == Some(0) {
+            return Err(NekoError::Config("max_tokens must be greater than 0".to_string()));
+        }
+        if let Some(t) = self.temperature.filter(|t| !(0.0..=2.0).contains(t)) {
+            return Err(NekoError::Config(format!(
+                "temperature must be between 0 and 2, got {t}"
+            )));
+        }
+        if let Some(tools) = self.tools.as_deref().and_then(tool_whitelist) {
+            let unknown: Vec<&str> = tools
+                .iter()
+                .map(String::as_str)
+                .filter(|t| !known_tools.contains(t))
+                .collect();
+            if !unknown.is_empty() {
+                return Err(NekoError::Config(format!(
+                    "Unknown tool(s): {}",
+                    unknown.join(", ")
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply to `agent`, returning a `field: old -> new` line per change
+    /// for the audit log.
+    pub fn apply(&self, agent: &mut AgentConfig) -> Vec<String> {
+        let mut changes = Vec::new();
+        if let Some(model) = &self.model {
+            changes.push(format!("model: {} -> {model}", agent.model));
+            agent.model = model.clone();
+        }
+        if let Some(instructions) = &self.instructions {
+            let old = agent.instructions.as_deref().map_or(0, |i| i.chars().count());
+            changes.push(format!(
+                "instructions: {old} chars -> {} chars",
+                instructions.trim().chars().count()
+            ));
+            agent.instructions = Some(instructions.clone()).filter(|i| !i.trim().is_empty());
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            changes.push(format!("max_tokens: {} -> {max_tokens}", agent.max_tokens));
+            agent.max_tokens = max_tokens;
+        }
+        if let Some(temperature) = self.temperature {
+            let old = agent.temperature.map_or("default".to_string(), |t| t.to_string());
+            changes.push(format!("temperature: {old} -> {temperature}"));
+            agent.temperature = Some(temperature);
+        }
+        if let Some(tools) = &self.tools {
+            changes.push(format!("tools: {:?} -> {tools:?}", agent.tools));
+            agent.tools = tool_whitelist(tools).unwrap_or_default();
+        }
+        changes
+    }
+
+    /// Write the patched `[agent]` keys to the config file at `path`,
+    /// keeping its comments, layout and `${ENV}` references.
+    pub fn persist(&self, path: &Path) -> Result<()> {
+        if self.tools.is_some() {
+            return Err(NekoError::Config(
+                "tools can only be changed for the running gateway, not persisted".to_string(),
+            ));
+        }
+        let content = std::fs::read_to_string(path)?;
+        let mut doc: toml_edit::DocumentMut = content
+            .parse()
+            .map_err(|e| NekoError::Config(format!("Failed to parse {}: {e}", path.display())))?;
+        let agent = doc
+            .entry("agent")
+            .or_insert_with(toml_edit::table)
+            .as_table_like_mut()
+            .ok_or_else(|| NekoError::Config("[agent] is not a table".to_string()))?;
+        if let Some(model) = &self.model {
+            set_toml(agent, "model", toml_edit::value(model.as_str()));
+        }
+        match self.instructions.as_deref() {
+            Some(i) if i.trim().is_empty() => {
+                agent.remove("instructions");
+            }
+            Some(i) => {
+                set_toml(agent, "instructions", toml_edit::value(i));
+            }
+            None => {}
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            set_toml(agent, "max_tokens", toml_edit::value(i64::from(max_tokens)));
+        }
+        if let Some(temperature) = self.temperature {
+            // Via the shortest decimal form, so 0.7 isn't written as 0.699999988
+            let temperature: f64 = temperature.to_string().parse().unwrap_or(f64::from(temperature));
+            set_toml(agent, "temperature", toml_edit::value(temperature));
+        }
+        std::fs::write(path, doc.to_string())?;
+        Ok(())
+    }
+}
+
+/// Set `key`, keeping the comment above it if it already exists.
+fn set_toml(table: &mut dyn toml_edit::TableLike, key: &str, item: toml_edit::Item) {
+    match table.get_mut(key) {
+        Some(existing) => *existing = item,
+        None => {
+            table.insert(key, item);
         }
     }
 }
@@ -118,9 +475,99 @@ impl Default for AgentConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProviderConfig {
     pub api_key: Option<String>,
+    /// Read the API key from this file (contents trimmed), e.g. a Docker/K8s secret.
+    #[serde(default)]
+    pub api_key_file: Option<String>,
+    /// Run this shell command and use its trimmed stdout as the API key
+    /// (e.g. `op read op://vault/openai/key`).
+    #[serde(default)]
+    pub api_key_command: Option<String>,
     pub base_url: String,
     #[serde(default)]
     pub models: Vec<String>,
+    /// Extra headers sent with every request (e.g. `OpenAI-Organization`,
+    /// `anthropic-version`, or custom gateway auth).
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Logical model names mapped to this provider's own (e.g. `fast =
+    /// "gpt-5-mini"`), so `agent.model = "fast"` works with any provider.
+    /// Names not in the map are sent unchanged.
+    #[serde(default)]
+    pub model_map: HashMap<String, String>,
+    /// Total timeout for non-streaming requests.
+    #[serde(default = "default_provider_timeout")]
+    pub timeout_secs: u64,
+    /// Timeout for establishing the connection (applies to streaming too).
+    #[serde(default = "default_provider_connect_timeout")]
+    pub connect_timeout_secs: u64,
+    /// Consecutive failures before the circuit opens (0 = disabled).
+    #[serde(default = "default_circuit_breaker_threshold")]
+    pub circuit_breaker_threshold: u32,
+    /// How long an open circuit fails fast before probing again.
+    #[serde(default = "default_circuit_breaker_cooldown")]
+    pub circuit_breaker_cooldown_secs: u64,
+}
+
+fn default_provider_timeout() -> u64 {
+    120
+}
+
+fn default_provider_connect_timeout() -> u64 {
+    10
+}
+
+fn default_circuit_breaker_threshold() -> u32 {
+    5
+}
+
+fn default_circuit_breaker_cooldown() -> u64 {
+    60
+}
+
+impl ProviderConfig {
+    /// The provider's name for `model`, via `model_map`.
+    pub fn resolve_model<'a>(&'a self, model: &'a str) -> &'a str {
+        self.model_map.get(model).map_or(model, String::as_str)
+    }
+
+    /// Resolve the API key. Precedence: inline `api_key` (including
+    /// `${ENV}` substitution) > `api_key_file` > `api_key_command`.
+    /// An inline key that substituted to an empty string counts as unset.
+    pub fn resolve_key(&self) -> Result<Option<String>> {
+        if let Some(key) = self.api_key.as_deref().filter(|k| !k.is_empty()) {
+            return Ok(Some(key.to_string()));
+        }
+
+        if let Some(path) = &self.api_key_file {
+            let path = expand_home(path);
+            let key = std::fs::read_to_string(&path).map_err(|e| {
+                NekoError::Config(format!(
+                    "Failed to read api_key_file {}: {e}",
+                    path.display()
+                ))
+            })?;
+            return Ok(Some(key.trim().to_string()));
+        }
+
+        if let Some(command) = &self.api_key_command {
+            let output = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .stderr(std::process::Stdio::inherit())
+                .output()
+                .map_err(|e| NekoError::Config(format!("Failed to run api_key_command: {e}")))?;
+            if !output.status.success() {
+                return Err(NekoError::Config(format!(
+                    "api_key_command exited with {}",
+                    output.status
+                )));
+            }
+            let key = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            return Ok(Some(key));
+        }
+
+        Ok(None)
+    }
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -134,8 +581,76 @@ pub struct TelegramConfig {
     #[serde(default)]
     pub enabled: bool,
     pub bot_token: Option<String>,
+    /// Numeric user IDs and/or `@usernames`. Empty = allow everyone
+    /// (unless `approval_mode` is on).
+    #[serde(default)]
+    pub allowed_users: Vec<TelegramUser>,
+    /// Unknown users must be approved by `admin_user_id` before they can
+    /// talk to the agent. Approvals persist in the workspace.
+    #[serde(default)]
+    pub approval_mode: bool,
+    /// Telegram user ID that receives access requests in `approval_mode`.
+    #[serde(default)]
+    pub admin_user_id: Option<i64>,
+    /// When set (e.g. "!neko "), only messages starting with this prefix are
+    /// processed; the prefix is stripped. Empty = process every message.
     #[serde(default)]
-    pub allowed_users: Vec<i64>,
+    pub command_prefix: String,
+    /// Drop updates that queued up while neko was stopped instead of
+    /// answering them on startup.
+    #[serde(default)]
+    pub skip_pending_on_start: bool,
+    /// Log outbound messages and append them to
+    /// `workspace/channels/telegram.dry-run.jsonl` instead of sending them.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// `HTML` or `MarkdownV2`: let Telegram render formatting in replies.
+    /// A reply Telegram can't parse is resent as plain text.
+    #[serde(default)]
+    pub parse_mode: Option<String>,
+}
+
+/// A `[gateway.tokens]` entry: a list of scopes, or a table that can also
+/// restrict which tools the token's messages may use.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ApiToken {
+    Scopes(Vec<String>),
+    Scoped {
+        /// Defaults to `["message"]`.
+        #[serde(default = "default_token_scopes")]
+        scopes: Vec<String>,
+        /// Tools offered to turns started with this token. Unset = all.
+        #[serde(default)]
+        tools: Option<Vec<String>>,
+    },
+}
+
+fn default_token_scopes() -> Vec<String> {
+    vec!["message".to_string()]
+}
+
+impl ApiToken {
+    pub fn scopes(&self) -> &[String] {
+        match self {
+            ApiToken::Scopes(scopes) | ApiToken::Scoped { scopes, .. } => scopes,
+        }
+    }
+
+    pub fn tools(&self) -> Option<&[String]> {
+        match self {
+            ApiToken::Scopes(_) => None,
+            ApiToken::Scoped { tools, .. } => tools.as_deref(),
+        }
+    }
+}
+
+/// An `allowed_users` entry: a numeric Telegram user ID or an `@username`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TelegramUser {
+    Id(i64),
+    Username(String),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -150,8 +665,44 @@ pub struct ToolsConfig {
     pub exec_timeout_secs: u64,
     #[serde(default = "default_exec_yield_ms")]
     pub exec_yield_ms: u64,
+    /// Variables inherited from Neko's own environment by exec'd commands.
+    /// Everything else is cleared so secrets (API keys) don't leak.
+    #[serde(default = "default_exec_env_passthrough")]
+    pub exec_env_passthrough: Vec<String>,
+    /// Extra variables set for exec'd commands (overrides passthrough,
+    /// e.g. a custom PATH).
+    #[serde(default)]
+    pub exec_env: HashMap<String, String>,
     #[serde(default)]
     pub python: PythonConfig,
+    /// MIME types `send_file` may send (e.g. "image/png", "image/*").
+    /// Empty = all types allowed.
+    #[serde(default)]
+    pub send_file_allowed_mime: Vec<String>,
+    #[serde(default)]
+    pub send_message: SendMessageConfig,
+    /// Tool output longer than this is cut down to its head and tail before
+    /// it enters the conversation. 0 = unlimited.
+    #[serde(default = "default_max_output_bytes")]
+    pub max_output_bytes: usize,
+    /// Largest file `memory_write` will leave behind in `memory/`.
+    /// 0 = unlimited.
+    #[serde(default = "default_memory_max_file_bytes")]
+    pub memory_max_file_bytes: u64,
+    /// Directory (relative to the workspace) that `read_file`,
+    /// `write_file`, `list_files` and `cd` are confined to. Unset = the
+    /// whole workspace.
+    #[serde(default)]
+    pub file_root: Option<String>,
+    /// Let file tools read and write Neko's own state (`sessions/`,
+    /// `cron/`, `channels/`, `usage/`, memory indexes and the like). Off by
+    /// default.
+    #[serde(default)]
+    pub allow_internal_paths: bool,
+    /// Tool name -> description shown to the model instead of the built-in
+    /// one (`[tools.descriptions]`).
+    #[serde(default)]
+    pub descriptions: HashMap<String, String>,
 }
 
 fn default_exec_timeout() -> u64 {
@@ -162,6 +713,18 @@ fn default_exec_yield_ms() -> u64 {
     10_000
 }
 
+fn default_max_output_bytes() -> usize {
+    16_384
+}
+
+fn default_memory_max_file_bytes() -> u64 {
+    65_536
+}
+
+fn default_exec_env_passthrough() -> Vec<String> {
+    vec!["PATH".to_string()]
+}
+
 impl Default for ToolsConfig {
     fn default() -> Self {
         Self {
@@ -170,11 +733,32 @@ impl Default for ToolsConfig {
             http_allowed_domains: vec![],
             exec_timeout_secs: default_exec_timeout(),
             exec_yield_ms: default_exec_yield_ms(),
+            exec_env_passthrough: default_exec_env_passthrough(),
+            exec_env: HashMap::new(),
             python: PythonConfig::default(),
+            send_file_allowed_mime: vec![],
+            send_message: SendMessageConfig::default(),
+            max_output_bytes: default_max_output_bytes(),
+            memory_max_file_bytes: default_memory_max_file_bytes(),
+            file_root: None,
+            allow_internal_paths: false,
+            descriptions: HashMap::new(),
         }
     }
 }
 
+/// `send_message` tool: lets the agent message arbitrary `channel:recipient`
+/// targets. Off by default since it can spam.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SendMessageConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Senders allowed to trigger it, as `channel:sender_id`
+    /// (e.g. "telegram:123456"), or `"*"` for any sender. Empty = nobody.
+    #[serde(default)]
+    pub admins: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PythonConfig {
     #[serde(default)]
@@ -244,6 +828,61 @@ impl Default for HeartbeatConfig {
     }
 }
 
+/// How the `allowed-tools` of active skills (those whose SKILL.md the agent
+/// has read this session) restrict the tools offered to the model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkillsConfig {
+    #[serde(default)]
+    pub tool_policy: SkillToolPolicy,
+    /// Tools kept available whenever skills restrict the tool set, so the
+    /// agent can still read other skills and files.
+    #[serde(default = "default_skill_base_tools")]
+    pub base_tools: Vec<String>,
+    /// List at most this many skills in the system prompt, picking those
+    /// relevant to the message; the rest are found via `skill_search`.
+    /// 0 = list all.
+    #[serde(default)]
+    pub max_in_prompt: usize,
+    /// Extra directories of shared skills, searched after
+    /// `workspace/skills`; a workspace skill with the same name wins.
+    #[serde(default)]
+    pub paths: Vec<String>,
+}
+
+impl SkillsConfig {
+    /// `paths` with `~` expanded.
+    pub fn extra_dirs(&self) -> Vec<PathBuf> {
+        self.paths.iter().map(|p| expand_home(p)).collect()
+    }
+}
+
+fn default_skill_base_tools() -> Vec<String> {
+    vec!["read_file".to_string()]
+}
+
+impl Default for SkillsConfig {
+    fn default() -> Self {
+        Self {
+            tool_policy: SkillToolPolicy::default(),
+            base_tools: default_skill_base_tools(),
+            max_in_prompt: 0,
+            paths: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SkillToolPolicy {
+    /// `allowed-tools` is informational only; every tool stays available.
+    #[default]
+    Ignore,
+    /// Offer tools allowed by any active skill.
+    Union,
+    /// Offer only tools allowed by every active skill.
+    Intersection,
+}
+
 // ---------------------------------------------------------------------------
 // Session config
 // ---------------------------------------------------------------------------
@@ -256,12 +895,106 @@ pub struct SessionConfig {
     pub reset_mode: ResetMode,
     #[serde(default = "default_reset_at_hour")]
     pub reset_at_hour: u32,
+    /// Several local hours to reset at each day (e.g. `[4, 16]`). Takes
+    /// precedence over `reset_at_hour` when non-empty.
+    #[serde(default)]
+    pub reset_at_hours: Vec<u32>,
     #[serde(default)]
     pub idle_minutes: Option<u32>,
     #[serde(default = "default_max_history")]
     pub max_history: u32,
     #[serde(default = "default_max_cached")]
     pub max_cached: usize,
+    /// How long a stored `last_response_id` is trusted. Older IDs are
+    /// discarded so the next turn falls back to sending full history.
+    #[serde(default = "default_response_id_ttl_minutes")]
+    pub response_id_ttl_minutes: u32,
+    /// Tokens (input + output) a single session may use per UTC day before
+    /// further turns are refused. Unset = no limit.
+    #[serde(default)]
+    pub max_tokens_per_day: Option<u64>,
+    /// Reset settings for sessions on particular channels
+    /// (`[session.channel_reset.<channel>]`). Unset fields fall back to the
+    /// global ones.
+    #[serde(default)]
+    pub channel_reset: HashMap<String, ChannelResetConfig>,
+}
+
+impl SessionConfig {
+    /// The reset policy for sessions on `channel`, with any per-channel
+    /// override applied.
+    pub fn reset_policy(&self, channel: Option<&str>) -> ResetPolicy {
+        let global = ResetPolicy {
+            mode: self.reset_mode.clone(),
+            at_hours: reset_hours(self.reset_at_hours.clone(), Some(self.reset_at_hour)),
+            idle_minutes: self.idle_minutes,
+        };
+        let Some(over) = channel.and_then(|c| self.channel_reset.get(c)) else {
+            return global;
+        };
+        let at_hours = match (&over.reset_at_hours, over.reset_at_hour) {
+            (None, None) => global.at_hours,
+            (hours, hour) => reset_hours(hours.clone().unwrap_or_default(), hour),
+        };
+        ResetPolicy {
+            mode: over.reset_mode.clone().unwrap_or(global.mode),
+            at_hours,
+            idle_minutes: over.idle_minutes.or(global.idle_minutes),
+        }
+    }
+
+    /// Reject reset hours outside 0–23, which would never trigger.
+    pub fn validate(&self) -> Result<()> {
+        check_reset_hours("session", Some(self.reset_at_hour), &self.reset_at_hours)?;
+        for (channel, over) in &self.channel_reset {
+            check_reset_hours(
+                &format!("session.channel_reset.{channel}"),
+                over.reset_at_hour,
+                over.reset_at_hours.as_deref().unwrap_or_default(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn check_reset_hours(section: &str, hour: Option<u32>, hours: &[u32]) -> Result<()> {
+    match hour.iter().chain(hours).find(|h| **h > 23) {
+        Some(h) => Err(NekoError::Config(format!(
+            "[{section}] reset hours must be 0-23, got {h}"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// `reset_at_hours` if set, otherwise the single `reset_at_hour`.
+fn reset_hours(hours: Vec<u32>, hour: Option<u32>) -> Vec<u32> {
+    if hours.is_empty() {
+        hour.into_iter().collect()
+    } else {
+        hours
+    }
+}
+
+/// Per-channel override of the `[session]` reset settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChannelResetConfig {
+    #[serde(default)]
+    pub reset_mode: Option<ResetMode>,
+    #[serde(default)]
+    pub reset_at_hour: Option<u32>,
+    #[serde(default)]
+    pub reset_at_hours: Option<Vec<u32>>,
+    #[serde(default)]
+    pub idle_minutes: Option<u32>,
+}
+
+/// Reset settings resolved for one session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResetPolicy {
+    pub mode: ResetMode,
+    /// Local hours of the day at which daily resets happen.
+    pub at_hours: Vec<u32>,
+    pub idle_minutes: Option<u32>,
 }
 
 fn default_reset_at_hour() -> u32 {
@@ -272,15 +1005,23 @@ fn default_max_cached() -> usize {
     8
 }
 
+fn default_response_id_ttl_minutes() -> u32 {
+    30
+}
+
 impl Default for SessionConfig {
     fn default() -> Self {
         Self {
             dm_scope: DmScope::default(),
             reset_mode: ResetMode::default(),
             reset_at_hour: default_reset_at_hour(),
+            reset_at_hours: Vec::new(),
             idle_minutes: None,
             max_history: default_max_history(),
             max_cached: default_max_cached(),
+            response_id_ttl_minutes: default_response_id_ttl_minutes(),
+            max_tokens_per_day: None,
+            channel_reset: HashMap::new(),
         }
     }
 }
@@ -300,6 +1041,8 @@ pub enum ResetMode {
     Daily,
     Idle,
     Both,
+    /// Sessions are only reset explicitly (e.g. `/reset`).
+    Never,
 }
 
 /// MCP server configuration (stdio transport).
@@ -312,12 +1055,16 @@ pub struct McpServerConfig {
     pub env: HashMap<String, String>,
 }
 
+/// Placeholder for secrets in [`Config::redacted`].
+pub const REDACTED: &str = "[redacted]";
+
 impl Config {
     pub fn load(path: &Path) -> Result<Self> {
         let content = std::fs::read_to_string(path)
             .map_err(|e| NekoError::Config(format!("Failed to read config: {e}")))?;
         let content = substitute_env_vars(&content);
         let config: Config = toml::from_str(&content)?;
+        config.session.validate()?;
         Ok(config)
     }
 
@@ -329,37 +1076,158 @@ impl Config {
     }
 
     pub fn workspace_path(&self) -> PathBuf {
-        let path = self.gateway.workspace.replace('~', &dirs::home_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .to_string_lossy());
-        PathBuf::from(path)
+        expand_home(&self.gateway.workspace)
+    }
+
+    /// Config for a named agent: the top-level config with the profile's
+    /// workspace and `[agent]` overrides applied.
+    pub fn for_profile(&self, profile: &AgentProfile) -> Config {
+        let mut config = self.clone();
+        config.agents.clear();
+        config.gateway.workspace = profile
+            .workspace
+            .clone()
+            .unwrap_or_else(|| format!("~/.neko/agents/{}", profile.name));
+        if let Some(provider) = &profile.provider {
+            config.agent.provider = provider.clone();
+        }
+        if let Some(model) = &profile.model {
+            config.agent.model = model.clone();
+        }
+        if let Some(tools) = &profile.tools {
+            config.agent.tools = tools.clone();
+        }
+        if profile.instructions.is_some() {
+            config.agent.instructions = profile.instructions.clone();
+        }
+        config
+    }
+
+    /// A copy safe to show outside the process: API keys and key commands,
+    /// bot tokens, bearer tokens, provider headers, MCP server arguments and
+    /// env values replaced with `REDACTED`.
+    pub fn redacted(&self) -> Config {
+        let mut config = self.clone();
+        let redact = |value: &mut Option<String>| {
+            if value.is_some() {
+                *value = Some(REDACTED.to_string());
+            }
+        };
+        let redact_values = |map: &mut HashMap<String, String>| {
+            map.values_mut().for_each(|v| *v = REDACTED.to_string());
+        };
+
+        redact(&mut config.gateway.api_token);
+        // Scoped tokens are the map keys; number them so the scopes stay visible.
+        config.gateway.tokens = std::mem::take(&mut config.gateway.tokens)
+            .into_values()
+            .enumerate()
+            .map(|(i, token)| (format!("{REDACTED}-{}", i + 1), token))
+            .collect();
+        for provider in config.providers.values_mut() {
+            redact(&mut provider.api_key);
+            // The command line often carries the secret or a vault path to it.
+            redact(&mut provider.api_key_command);
+            redact_values(&mut provider.headers);
+        }
+        if let Some(telegram) = config.channels.telegram.as_mut() {
+            redact(&mut telegram.bot_token);
+        }
+        redact_values(&mut config.tools.exec_env);
+        for server in config.mcp.values_mut() {
+            // Servers commonly take tokens as flags (`--token ...`).
+            server.args.iter_mut().for_each(|a| *a = REDACTED.to_string());
+            redact_values(&mut server.env);
+        }
+        config
+    }
+
+    /// `[tools] file_root` resolved against the workspace. It must be a
+    /// relative path that stays inside it.
+    pub fn file_root_path(&self) -> Result<Option<PathBuf>> {
+        let Some(root) = self.tools.file_root.as_deref().filter(|r| !r.is_empty()) else {
+            return Ok(None);
+        };
+        let relative = Path::new(root);
+        let escapes = relative
+            .components()
+            .any(|c| !matches!(c, std::path::Component::Normal(_) | std::path::Component::CurDir));
+        if escapes {
+            return Err(NekoError::Config(format!(
+                "tools.file_root must be a relative path inside the workspace, got '{root}'"
+            )));
+        }
+        Ok(Some(self.workspace_path().join(relative)))
+    }
+
+    /// Workspace quota in bytes, if configured.
+    pub fn workspace_quota_bytes(&self) -> Option<u64> {
+        self.gateway.workspace_max_mb.map(|mb| mb * 1024 * 1024)
     }
 
     pub fn default_toml() -> &'static str {
         r#"[gateway]
 bind = "127.0.0.1:3000"
 workspace = "~/.neko/workspace"
+# workspace_max_mb = 1024  # soft quota; file writes are refused once exceeded
+# daily_token_budget = 2000000  # all sessions + cron; turns are refused until 00:00 UTC once spent
+# cors_origins = ["http://localhost:5173"]  # browser origins allowed to call the API
+
+# Scoped API tokens: token = [scopes] (message, sessions:read, sessions:write, files:read, config:read, config:write, *)
+# [gateway.tokens]
+# "${DASHBOARD_TOKEN}" = ["sessions:read"]
+# "${PUBLIC_TOKEN}" = { scopes = ["message"], tools = ["read_file", "web_search"] }  # only these tools
+
+# Shorthands expanded before the agent sees them
+# [gateway.macros]
+# "/summary" = "Summarize our conversation so far as a short bulleted list."
 
 [agent]
 model = "gpt-5-mini"
 provider = "openai"
 max_tokens = 4096
 tools = ["read_file", "write_file", "list_files", "exec", "http_request", "memory_write"]
+# greeting = "Hi! I can search the web, manage files and set reminders."  # first message of a new session
+# empty_reply_fallback = "I wasn't able to complete that."  # sent when the model ends a turn with no text; "" = off
+# repeated_failure_limit = 3        # after N identical failing tool calls in a turn, tell the model to change approach; 0 = off
+# block_repeated_failures = false   # and refuse further identical calls that turn
+# context_files = ["memory/conventions.md", "docs/glossary.md"]  # loaded into every turn (capped by context_files_max_chars)
+
+# Semantic memory search via the provider's embeddings endpoint
+# [agent.memory]
+# embeddings = true
+# embedding_model = "text-embedding-3-small"
 
 [providers.openai]
 api_key = "${OPENAI_API_KEY}"
+# api_key_file = "/run/secrets/openai"
+# api_key_command = "op read op://vault/openai/key"
 base_url = "https://api.openai.com"
 models = ["gpt-5-mini", "gpt-5"]
+# model_map = { fast = "gpt-5-mini", smart = "gpt-5" }  # logical names for agent.model
 
 [tools]
 sandbox = false
 exec_timeout_secs = 1800
 exec_yield_ms = 10000
+# exec commands get a clean environment: only these variables are inherited
+# exec_env_passthrough = ["PATH", "HOME"]
+# exec_env = { LANG = "C.UTF-8" }
+# file_root = "projects"  # confine read_file/write_file/list_files/cd to workspace/projects
+# allow_internal_paths = true  # let file tools touch sessions/, cron/, channels/, usage/ and memory indexes
+# descriptions = { exec = "Run a shell command." }  # override tool descriptions shown to the model
 
 [heartbeat]
 enabled = false
 interval_secs = 3600
 
+# How active skills' allowed-tools restrict the tool set: ignore | union | intersection
+# [skills]
+# tool_policy = "union"
+# base_tools = ["read_file"]  # always available while skills restrict tools
+# max_in_prompt = 20  # list only the most relevant skills; the rest via skill_search
+# paths = ["~/.neko/shared-skills"]  # shared skill libraries; workspace skills override them
+
 # MCP servers — uncomment to enable
 # [mcp.filesystem]
 # command = "npx"
@@ -373,6 +1241,14 @@ interval_secs = 3600
     }
 }
 
+/// Expand `~` to the user's home directory.
+pub fn expand_home(path: &str) -> PathBuf {
+    let path = path.replace('~', &dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .to_string_lossy());
+    PathBuf::from(path)
+}
+
 /// Substitute `${VAR_NAME}` patterns with environment variable values.
 pub fn substitute_env_vars(input: &str) -> String {
     let re = Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap();
@@ -431,4 +1307,233 @@ API_KEY = "test123"
         assert_eq!(mcp.args, vec!["server.js"]);
         assert_eq!(mcp.env.get("API_KEY").unwrap(), "test123");
     }
+
+    #[test]
+    fn test_provider_headers_parse() {
+        let toml_str = r#"
+[providers.openai]
+base_url = "https://api.openai.com"
+
+[providers.openai.headers]
+OpenAI-Organization = "org-123"
+"#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        let provider = config.providers.get("openai").unwrap();
+        assert_eq!(provider.headers.get("OpenAI-Organization").unwrap(), "org-123");
+    }
+
+    #[test]
+    fn test_reset_hours() {
+        let toml_str = r#"
+[session]
+reset_at_hours = [4, 16]
+
+[session.channel_reset.http]
+reset_at_hour = 2
+"#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        config.session.validate().unwrap();
+        assert_eq!(config.session.reset_policy(None).at_hours, vec![4, 16]);
+        assert_eq!(config.session.reset_policy(Some("http")).at_hours, vec![2]);
+
+        let mut session = config.session.clone();
+        session.reset_at_hours = vec![4, 24];
+        assert!(session.validate().is_err());
+    }
+
+    #[test]
+    fn test_file_root_must_stay_in_workspace() {
+        let mut config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.file_root_path().unwrap(), None);
+        config.tools.file_root = Some("projects".to_string());
+        assert_eq!(
+            config.file_root_path().unwrap(),
+            Some(config.workspace_path().join("projects"))
+        );
+        config.tools.file_root = Some("../elsewhere".to_string());
+        assert!(config.file_root_path().is_err());
+        config.tools.file_root = Some("/etc".to_string());
+        assert!(config.file_root_path().is_err());
+    }
+
+    #[test]
+    fn test_api_tokens_parse_both_forms() {
+        let toml_str = r#"
+[gateway.tokens]
+dashboard = ["sessions:read"]
+public = { tools = ["read_file"] }
+"#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        let dashboard = config.gateway.tokens.get("dashboard").unwrap();
+        assert_eq!(dashboard.scopes(), ["sessions:read"]);
+        assert_eq!(dashboard.tools(), None);
+        let public = config.gateway.tokens.get("public").unwrap();
+        assert_eq!(public.scopes(), ["message"]);
+        assert_eq!(public.tools(), Some(&["read_file".to_string()][..]));
+    }
+
+    fn provider(api_key: Option<&str>, file: Option<&str>, command: Option<&str>) -> ProviderConfig {
+        ProviderConfig {
+            api_key: api_key.map(String::from),
+            api_key_file: file.map(String::from),
+            api_key_command: command.map(String::from),
+            base_url: "https://api.openai.com".to_string(),
+            models: vec![],
+            headers: HashMap::new(),
+            model_map: HashMap::new(),
+            timeout_secs: default_provider_timeout(),
+            connect_timeout_secs: default_provider_connect_timeout(),
+            circuit_breaker_threshold: default_circuit_breaker_threshold(),
+            circuit_breaker_cooldown_secs: default_circuit_breaker_cooldown(),
+        }
+    }
+
+    #[test]
+    fn test_resolve_model() {
+        let mut openai = provider(None, None, None);
+        openai.model_map.insert("fast".to_string(), "gpt-5-mini".to_string());
+        assert_eq!(openai.resolve_model("fast"), "gpt-5-mini");
+        assert_eq!(openai.resolve_model("gpt-5"), "gpt-5");
+    }
+
+    #[test]
+    fn test_resolve_key_precedence() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let key_file = tmp.path().join("key");
+        std::fs::write(&key_file, "sk-file\n").unwrap();
+        let file = key_file.to_str();
+
+        let p = provider(Some("sk-inline"), file, Some("echo sk-cmd"));
+        assert_eq!(p.resolve_key().unwrap().as_deref(), Some("sk-inline"));
+
+        let p = provider(Some(""), file, Some("echo sk-cmd"));
+        assert_eq!(p.resolve_key().unwrap().as_deref(), Some("sk-file"));
+
+        let p = provider(None, None, Some("echo sk-cmd"));
+        assert_eq!(p.resolve_key().unwrap().as_deref(), Some("sk-cmd"));
+
+        let p = provider(None, None, None);
+        assert_eq!(p.resolve_key().unwrap(), None);
+    }
+
+    #[test]
+    fn test_telegram_allowed_users_mixed() {
+        let toml_str = r#"
+[channels.telegram]
+enabled = true
+allowed_users = [12345, "@alice"]
+"#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        let tg = config.channels.telegram.unwrap();
+        assert_eq!(
+            tg.allowed_users,
+            vec![
+                TelegramUser::Id(12345),
+                TelegramUser::Username("@alice".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_gateway_macros() {
+        let toml_str = r#"
+[gateway.macros]
+"/summary" = "Summarize our conversation so far."
+"#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        let gw = &config.gateway;
+        assert_eq!(gw.expand_macro(" /summary\n"), Some("Summarize our conversation so far."));
+        assert_eq!(gw.expand_macro("/summary please"), None);
+        assert_eq!(gw.expand_macro("/Summary"), None);
+    }
+
+    #[test]
+    fn test_agent_profiles() {
+        let toml_str = r#"
+[agent]
+model = "gpt-5-mini"
+
+[[agents]]
+name = "coder"
+model = "gpt-5"
+routes = ["telegram:-100123"]
+"#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.agents.len(), 1);
+        let coder = config.for_profile(&config.agents[0]);
+        assert_eq!(coder.agent.model, "gpt-5");
+        assert_eq!(coder.gateway.workspace, "~/.neko/agents/coder");
+        assert!(coder.agents.is_empty());
+    }
+
+    #[test]
+    fn test_settings_patch() {
+        let patch: SettingsPatch =
+            serde_json::from_str(r#"{"model": "gpt-5", "temperature": 0.7, "instructions": ""}"#)
+                .unwrap();
+        patch.validate(&[]).unwrap();
+        let bad: SettingsPatch = serde_json::from_str(r#"{"tools": ["nope"]}"#).unwrap();
+        assert!(bad.validate(&["read_file"]).is_err());
+        let all: SettingsPatch = serde_json::from_str(r#"{"tools": ["*"]}"#).unwrap();
+        all.validate(&["read_file"]).unwrap();
+        assert_eq!(tool_whitelist(all.tools.as_deref().unwrap()), None);
+        let mixed: SettingsPatch = serde_json::from_str(r#"{"tools": ["*", "exec"]}"#).unwrap();
+        assert!(mixed.validate(&["exec"]).is_err());
+
+        let mut agent = AgentConfig {
+            instructions: Some("Be brief.".to_string()),
+            ..AgentConfig::default()
+        };
+        assert_eq!(patch.apply(&mut agent).len(), 3);
+        assert_eq!(agent.model, "gpt-5");
+        assert_eq!(agent.temperature, Some(0.7));
+        assert!(agent.instructions.is_none());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "[agent]\n# main model\nmodel = \"gpt-5-mini\"\ninstructions = \"x\"\n").unwrap();
+        patch.persist(&path).unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.contains("# main model\nmodel = \"gpt-5\""), "{written}");
+        assert!(written.contains("temperature = 0.7\n"), "{written}");
+        assert!(!written.contains("instructions"), "{written}");
+    }
+
+    #[test]
+    fn test_redacted_hides_secrets() {
+        let toml_str = r#"
+[gateway]
+api_token = "admin-secret"
+
+[gateway.tokens]
+ci-secret = ["message"]
+
+[providers.openai]
+api_key = "sk-secret"
+api_key_command = "op read op://secret/openai"
+base_url = "https://api.openai.com/v1"
+headers = { OpenAI-Organization = "org-secret" }
+
+[channels.telegram]
+enabled = true
+bot_token = "123:secret"
+
+[mcp.github]
+command = "github-mcp"
+args = ["--token", "ghp-secret"]
+env = { GITHUB_TOKEN = "ghp-secret" }
+
+[tools.exec_env]
+DEPLOY_KEY = "deploy-secret"
+"#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        let redacted = serde_json::to_string(&config.redacted()).unwrap();
+        assert!(!redacted.contains("secret"), "{redacted}");
+        assert!(redacted.contains("https://api.openai.com/v1"));
+        assert!(redacted.contains("github-mcp"));
+        assert_eq!(
+            config.redacted().gateway.tokens.values().next(),
+            config.gateway.tokens.values().next()
+        );
+    }
 }
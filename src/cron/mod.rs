@@ -7,9 +7,11 @@ use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 use tracing::{error, info, warn};
 
-use crate::agent::Agent;
+use crate::agent::{Agent, TurnOverrides};
 use crate::channels::OutboundMessage;
 use crate::error::{NekoError, Result};
+use crate::session::{SessionKey, SessionStore};
+use crate::tools::ChannelContext;
 
 // ---------------------------------------------------------------------------
 // Data model
@@ -25,10 +27,26 @@ pub struct CronJob {
     pub enabled: bool,
     pub keep_after_run: bool,
     pub created_at: DateTime<Utc>,
+    /// Last attempt, successful or not; drives scheduling.
     pub last_run_at: Option<DateTime<Utc>>,
+    /// Last successful run, for `{{last_run}}` in the prompt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_success_at: Option<DateTime<Utc>>,
     pub retry: RetryState,
+    /// Run in the persistent session `neko:cron:<key>` instead of a fresh
+    /// history, so recurring jobs see their earlier runs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_key: Option<String>,
+    /// Delay each run by up to this many seconds, so jobs scheduled for the
+    /// same minute don't all start at once. Capped at [`MAX_JITTER_SECS`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jitter_secs: Option<u32>,
 }
 
+/// Upper bound on `jitter_secs`. Larger values would let a run drift past
+/// the next scheduled time and widen the lookback window every tick.
+pub const MAX_JITTER_SECS: u32 = 3600;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum Schedule {
@@ -85,15 +103,69 @@ fn history_path(workspace: &Path) -> PathBuf {
     cron_dir(workspace).join("history.jsonl")
 }
 
+fn rejected_path(workspace: &Path) -> PathBuf {
+    cron_dir(workspace).join("jobs.rejected.json")
+}
+
 pub fn load_jobs(workspace: &Path) -> Result<Vec<CronJob>> {
+    load_jobs_counting_skipped(workspace).map(|(jobs, _)| jobs)
+}
+
+/// Load jobs, skipping entries that don't deserialize so one bad job
+/// doesn't disable the rest. Returns the valid jobs and the number skipped.
+///
+/// Skipped entries are copied to `cron/jobs.rejected.json`, since the next
+/// `save_jobs` rewrites `jobs.json` without them.
+pub fn load_jobs_counting_skipped(workspace: &Path) -> Result<(Vec<CronJob>, usize)> {
     let path = jobs_path(workspace);
     if !path.exists() {
-        return Ok(Vec::new());
+        return Ok((Vec::new(), 0));
     }
     let data = std::fs::read_to_string(&path)?;
-    let jobs: Vec<CronJob> =
+    let entries: Vec<serde_json::Value> =
         serde_json::from_str(&data).map_err(|e| NekoError::Cron(format!("parse jobs.json: {e}")))?;
-    Ok(jobs)
+
+    let mut jobs = Vec::with_capacity(entries.len());
+    let mut rejected = Vec::new();
+    for (i, entry) in entries.into_iter().enumerate() {
+        match serde_json::from_value::<CronJob>(entry.clone()) {
+            Ok(job) => jobs.push(job),
+            Err(e) => {
+                let id = entry["id"].as_str().unwrap_or("?");
+                rejected.push((format!("entry {i} (id {id}): {e}"), entry));
+            }
+        }
+    }
+
+    let skipped = rejected.len();
+    if skipped > 0 {
+        keep_rejected(workspace, rejected);
+    }
+    Ok((jobs, skipped))
+}
+
+/// Write skipped entries to `jobs.rejected.json`, warning only when they
+/// change so the scheduler's 15s reload doesn't repeat the same warning.
+fn keep_rejected(workspace: &Path, rejected: Vec<(String, serde_json::Value)>) {
+    let path = rejected_path(workspace);
+    let (reasons, entries): (Vec<String>, Vec<serde_json::Value>) = rejected.into_iter().unzip();
+    let Ok(data) = serde_json::to_string_pretty(&entries) else {
+        return;
+    };
+    if std::fs::read_to_string(&path).is_ok_and(|existing| existing == data) {
+        return;
+    }
+    for reason in &reasons {
+        warn!("Skipping malformed cron job {reason}");
+    }
+    match std::fs::write(&path, data) {
+        Ok(()) => warn!(
+            "{} malformed cron job(s) copied to {}",
+            reasons.len(),
+            path.display()
+        ),
+        Err(e) => error!("Failed to write {}: {e}", path.display()),
+    }
 }
 
 pub fn save_jobs(workspace: &Path, jobs: &[CronJob]) -> Result<()> {
@@ -144,6 +216,28 @@ pub fn read_history(workspace: &Path, lines: usize) -> Result<Vec<HistoryEntry>>
 // Scheduling logic
 // ---------------------------------------------------------------------------
 
+/// How long after `scheduled` this run of `job` actually fires, within
+/// `jitter_secs`. Derived from the job ID and scheduled time rather than
+/// drawn fresh, so every tick agrees on it.
+fn jitter_offset(job: &CronJob, scheduled: DateTime<Utc>) -> chrono::Duration {
+    use std::hash::{Hash, Hasher};
+
+    let max = max_jitter(job);
+    if max == 0 {
+        return chrono::Duration::zero();
+    }
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    job.id.hash(&mut hasher);
+    scheduled.timestamp().hash(&mut hasher);
+    chrono::Duration::seconds((hasher.finish() % (u64::from(max) + 1)) as i64)
+}
+
+/// `jitter_secs` clamped to [`MAX_JITTER_SECS`], so hand-edited jobs.json
+/// values stay bounded too.
+fn max_jitter(job: &CronJob) -> u32 {
+    job.jitter_secs.unwrap_or(0).min(MAX_JITTER_SECS)
+}
+
 fn should_fire(job: &CronJob, now: DateTime<Utc>) -> bool {
     if !job.enabled {
         return false;
@@ -163,24 +257,26 @@ fn should_fire(job: &CronJob, now: DateTime<Utc>) -> bool {
                 return false;
             };
 
-            // Find the most recent scheduled time before `now`
-            let Some(prev) = schedule.after(&(now - chrono::Duration::seconds(16))).next() else {
+            // Find the most recent scheduled time whose (jittered) firing
+            // time falls within our tick window (15s) and which we haven't
+            // already run.
+            let lookback = chrono::Duration::seconds(16 + i64::from(max_jitter(job)));
+            let Some(prev) = schedule
+                .after(&(now - lookback))
+                .take_while(|t| *t <= now)
+                .filter(|t| *t + jitter_offset(job, *t) <= now)
+                .last()
+            else {
                 return false;
             };
 
-            // Fire if the scheduled time is within our tick window (15s) and
-            // we haven't already run it.
-            if prev > now {
-                return false;
-            }
-
             match job.last_run_at {
                 Some(last) => prev > last,
                 None => true,
             }
         }
         Schedule::At { datetime } => {
-            if now < *datetime {
+            if now < *datetime + jitter_offset(job, *datetime) {
                 return false;
             }
             // Fire if we haven't run yet
@@ -205,9 +301,64 @@ fn backoff_duration(consecutive_failures: u32) -> chrono::Duration {
 // Scheduler
 // ---------------------------------------------------------------------------
 
+/// Format a stored UTC time for display in local time, the zone `--at`
+/// times are entered in.
+pub fn local_time(t: DateTime<Utc>) -> String {
+    t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// Expand `{{date}}` (today) and `{{last_run}}` (the previous successful
+/// run, or "never") in a job prompt. Both are local time.
+pub fn render_prompt(prompt: &str, last_success_at: Option<DateTime<Utc>>) -> String {
+    let last_run = last_success_at
+        .map(local_time)
+        .unwrap_or_else(|| "never".to_string());
+    prompt
+        .replace("{{date}}", &chrono::Local::now().format("%Y-%m-%d").to_string())
+        .replace("{{last_run}}", &last_run)
+}
+
+/// Run a job's prompt, inside its persistent session if it has a
+/// `session_key`.
+async fn run_job_turn(
+    agent: &Agent,
+    session_store: &SessionStore,
+    session_key: Option<&str>,
+    prompt: &str,
+    channel_ctx: Option<ChannelContext>,
+    outbound_tx: Option<mpsc::Sender<OutboundMessage>>,
+) -> Result<String> {
+    match session_key {
+        Some(key) => {
+            agent
+                .run_session_turn(
+                    session_store,
+                    &SessionKey::cron(key),
+                    "cron",
+                    prompt,
+                    channel_ctx,
+                    outbound_tx,
+                )
+                .await
+        }
+        None => agent
+            .run_turn_with_history(
+                Vec::new(),
+                prompt,
+                None,
+                channel_ctx,
+                outbound_tx,
+                &TurnOverrides::default(),
+            )
+            .await
+            .map(|r| r.text),
+    }
+}
+
 pub fn spawn_scheduler(
     agent: Arc<Agent>,
     workspace: PathBuf,
+    session_store: Arc<SessionStore>,
     outbound_tx: Option<mpsc::Sender<OutboundMessage>>,
 ) {
     tokio::spawn(async move {
@@ -242,7 +393,8 @@ pub fn spawn_scheduler(
                 let job = &updated_jobs[i];
                 let job_id = job.id.clone();
                 let job_name = job.name.clone();
-                let job_prompt = job.prompt.clone();
+                let job_prompt = render_prompt(&job.prompt, job.last_success_at);
+                let job_session = job.session_key.clone();
                 let job_announce = job.announce.clone();
                 let is_one_shot = matches!(job.schedule, Schedule::At { .. });
                 let keep = job.keep_after_run;
@@ -252,7 +404,23 @@ pub fn spawn_scheduler(
                 info!("Firing cron job: {label}");
 
                 let started_at = Utc::now();
-                let result = agent.run_turn(&job_prompt).await;
+                // Jobs with an announce target run "in" that chat, so tools
+                // like status_update can message it mid-turn.
+                let channel_ctx = job_announce.as_ref().map(|a| ChannelContext {
+                    channel: a.channel.clone(),
+                    recipient_id: a.recipient_id.clone(),
+                    sender_id: None,
+                });
+                let tool_outbound = channel_ctx.as_ref().and(outbound_tx.clone());
+                let result = run_job_turn(
+                    &agent,
+                    &session_store,
+                    job_session.as_deref(),
+                    &job_prompt,
+                    channel_ctx,
+                    tool_outbound,
+                )
+                .await;
                 let finished_at = Utc::now();
 
                 match &result {
@@ -293,6 +461,7 @@ pub fn spawn_scheduler(
 
                         // Reset retry state on success
                         updated_jobs[i].last_run_at = Some(finished_at);
+                        updated_jobs[i].last_success_at = Some(finished_at);
                         updated_jobs[i].retry = RetryState::default();
                         jobs_modified = true;
 
@@ -389,3 +558,235 @@ pub fn validate_cron_expr(expr: &str) -> Result<()> {
         .map_err(|e| NekoError::Cron(format!("invalid cron expression '{expr}': {e}")))?;
     Ok(())
 }
+
+/// Named schedules for `--preset`, so common cases don't need the 6-field
+/// (seconds-first) cron syntax. Like all cron expressions they fire in UTC,
+/// which the names say so nobody expects 9am local.
+pub const SCHEDULE_PRESETS: &[(&str, &str)] = &[
+    ("hourly", "0 0 * * * *"),
+    ("daily-9am-utc", "0 0 9 * * *"),
+    ("weekdays-8am-utc", "0 0 8 * * Mon-Fri"),
+    ("weekly-monday-9am-utc", "0 0 9 * * Mon"),
+];
+
+/// Cron expression for a schedule preset name.
+pub fn preset_expr(name: &str) -> Result<&'static str> {
+    SCHEDULE_PRESETS
+        .iter()
+        .find(|(preset, _)| *preset == name)
+        .map(|(_, expr)| *expr)
+        .ok_or_else(|| {
+            let names: Vec<&str> = SCHEDULE_PRESETS.iter().map(|(n, _)| *n).collect();
+            NekoError::Cron(format!(
+                "unknown preset '{name}' (available: {})",
+                names.join(", ")
+            ))
+        })
+}
+
+/// Plain-English reading of a cron expression, e.g. "0 0 9 * * Mon-Fri" ->
+/// "At 09:00:00 on weekdays (UTC)". Best effort: fields it doesn't
+/// recognize are quoted as written rather than interpreted.
+pub fn describe_cron_expr(expr: &str) -> String {
+    let fields: Vec<&str> = expr.split_whitespace().collect();
+    if !(6..=7).contains(&fields.len()) {
+        return expr.to_string();
+    }
+    let (dom, month, dow) = (fields[3], fields[4], fields[5]);
+    let any = |f: &str| f == "*" || f == "?";
+
+    let mut desc = describe_time(fields[0], fields[1], fields[2]);
+    // "Every 15 minutes every day" reads badly; only a fixed hour needs it.
+    let daily = fields[2].parse::<u32>().is_ok();
+    match (any(dom), any(dow)) {
+        (true, true) if daily => desc.push_str(" every day"),
+        (true, true) => {}
+        (true, false) => desc.push_str(&format!(" on {}", describe_weekdays(dow))),
+        (false, true) => desc.push_str(&format!(" on day {dom} of the month")),
+        (false, false) => desc.push_str(&format!(
+            " on day {dom} of the month, {}",
+            describe_weekdays(dow)
+        )),
+    }
+    if !any(month) {
+        desc.push_str(&format!(" in {month}"));
+    }
+    if let Some(year) = fields.get(6).filter(|y| !any(y)) {
+        desc.push_str(&format!(" in {year}"));
+    }
+    desc.push_str(" (UTC)");
+    desc
+}
+
+fn describe_time(sec: &str, min: &str, hour: &str) -> String {
+    let num = |f: &str| f.parse::<u32>().ok();
+    let every = |f: &str| f.strip_prefix("*/").and_then(|n| n.parse::<u32>().ok());
+
+    match (num(sec), num(min), num(hour)) {
+        (Some(s), Some(m), Some(h)) => format!("At {h:02}:{m:02}:{s:02}"),
+        (Some(s), Some(m), None) => {
+            let past = if s == 0 {
+                format!("At minute {m}")
+            } else {
+                format!("At {m:02}:{s:02}")
+            };
+            match (hour, every(hour)) {
+                ("*", _) => format!("{past} past every hour"),
+                (_, Some(n)) => format!("{past} past every {n} hours"),
+                _ => format!("{past} past hour(s) {hour}"),
+            }
+        }
+        (Some(0), None, _) => {
+            let base = match (min, every(min)) {
+                ("*", _) => "Every minute".to_string(),
+                (_, Some(n)) => format!("Every {n} minutes"),
+                _ => format!("At minute(s) {min}"),
+            };
+            if hour == "*" {
+                base
+            } else {
+                format!("{base} during hour(s) {hour}")
+            }
+        }
+        _ if sec == "*" && min == "*" && hour == "*" => "Every second".to_string(),
+        _ => match every(sec) {
+            Some(n) if min == "*" && hour == "*" => format!("Every {n} seconds"),
+            _ => format!("At second {sec}, minute {min}, hour {hour}"),
+        },
+    }
+}
+
+fn describe_weekdays(dow: &str) -> String {
+    match dow.to_ascii_lowercase().as_str() {
+        "mon-fri" => "weekdays".to_string(),
+        "sat,sun" | "sun,sat" | "sat-sun" => "weekends".to_string(),
+        "mon" => "Mondays".to_string(),
+        "tue" => "Tuesdays".to_string(),
+        "wed" => "Wednesdays".to_string(),
+        "thu" => "Thursdays".to_string(),
+        "fri" => "Fridays".to_string(),
+        "sat" => "Saturdays".to_string(),
+        "sun" => "Sundays".to_string(),
+        _ => dow.to_string(),
+    }
+}
+
+/// The next `count` times a cron expression fires after now (UTC).
+pub fn next_fire_times(expr: &str, count: usize) -> Result<Vec<DateTime<Utc>>> {
+    let schedule = cron::Schedule::from_str(expr)
+        .map_err(|e| NekoError::Cron(format!("invalid cron expression '{expr}': {e}")))?;
+    Ok(schedule.upcoming(Utc).take(count).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_jobs_skips_malformed_entries() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let ws = tmp.path();
+        let good = CronJob {
+            id: "good".to_string(),
+            name: None,
+            prompt: "hi".to_string(),
+            schedule: Schedule::Cron {
+                expr: "0 0 9 * * *".to_string(),
+            },
+            announce: None,
+            enabled: true,
+            keep_after_run: false,
+            created_at: Utc::now(),
+            last_run_at: None,
+            last_success_at: None,
+            retry: RetryState::default(),
+            session_key: None,
+            jitter_secs: None,
+        };
+        let entries = serde_json::json!([good, {"id": "bad", "prompt": 42}]);
+        std::fs::create_dir_all(cron_dir(ws)).unwrap();
+        std::fs::write(jobs_path(ws), entries.to_string()).unwrap();
+
+        let (jobs, skipped) = load_jobs_counting_skipped(ws).unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].id, "good");
+        assert_eq!(skipped, 1);
+        assert!(std::fs::read_to_string(rejected_path(ws)).unwrap().contains("\"bad\""));
+    }
+
+    #[test]
+    fn test_jitter_delays_firing() {
+        let job = CronJob {
+            id: "jittery".to_string(),
+            name: None,
+            prompt: "hi".to_string(),
+            schedule: Schedule::Cron {
+                expr: "0 0 9 * * *".to_string(),
+            },
+            announce: None,
+            enabled: true,
+            keep_after_run: false,
+            created_at: Utc::now(),
+            last_run_at: None,
+            last_success_at: None,
+            retry: RetryState::default(),
+            session_key: None,
+            jitter_secs: Some(600),
+        };
+        let scheduled = "2026-03-02T09:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let fires_at = scheduled + jitter_offset(&job, scheduled);
+        assert!(fires_at <= scheduled + chrono::Duration::seconds(600));
+        assert_eq!(fires_at, scheduled + jitter_offset(&job, scheduled));
+
+        assert!(!should_fire(&job, fires_at - chrono::Duration::seconds(1)));
+        assert!(should_fire(&job, fires_at));
+        assert!(should_fire(&job, fires_at + chrono::Duration::seconds(10)));
+
+        let job = CronJob {
+            jitter_secs: Some(u32::MAX),
+            ..job
+        };
+        for day in 1..=28 {
+            let scheduled = format!("2026-03-{day:02}T09:00:00Z")
+                .parse::<DateTime<Utc>>()
+                .unwrap();
+            let offset = jitter_offset(&job, scheduled);
+            assert!(offset <= chrono::Duration::seconds(i64::from(MAX_JITTER_SECS)));
+        }
+    }
+
+    #[test]
+    fn test_render_prompt() {
+        assert_eq!(
+            render_prompt("changes since {{last_run}}", None),
+            "changes since never"
+        );
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        assert_eq!(render_prompt("digest for {{date}}", None), format!("digest for {today}"));
+        assert_eq!(render_prompt("no vars", Some(Utc::now())), "no vars");
+    }
+
+    #[test]
+    fn test_describe_cron_expr() {
+        assert_eq!(describe_cron_expr("0 0 9 * * *"), "At 09:00:00 every day (UTC)");
+        assert_eq!(describe_cron_expr("0 0 8 * * Mon-Fri"), "At 08:00:00 on weekdays (UTC)");
+        assert_eq!(describe_cron_expr("0 30 * * * *"), "At minute 30 past every hour (UTC)");
+        assert_eq!(describe_cron_expr("0 */15 * * * *"), "Every 15 minutes (UTC)");
+        assert_eq!(
+            describe_cron_expr("0 0 12 1 Jan *"),
+            "At 12:00:00 on day 1 of the month in Jan (UTC)"
+        );
+        assert_eq!(describe_cron_expr("not cron"), "not cron");
+        assert_eq!(next_fire_times("0 0 9 * * *", 3).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_schedule_presets() {
+        for (name, expr) in SCHEDULE_PRESETS {
+            assert!(validate_cron_expr(expr).is_ok(), "preset {name}");
+        }
+        assert_eq!(preset_expr("daily-9am-utc").unwrap(), "0 0 9 * * *");
+        assert!(preset_expr("daily").is_err());
+        assert!(preset_expr("daily-9am").is_err());
+    }
+}
@@ -1,12 +1,14 @@
 use thiserror::Error;
 
+use crate::llm::LlmError;
+
 #[derive(Error, Debug)]
 pub enum NekoError {
     #[error("Config error: {0}")]
     Config(String),
 
     #[error("LLM error: {0}")]
-    Llm(String),
+    Llm(#[from] LlmError),
 
     #[error("Tool error: {0}")]
     Tool(String),
@@ -0,0 +1,67 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Distinguishes temp files of concurrent writers within this process.
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Write via a temp file + rename so a crash mid-write never leaves a
+/// truncated file behind. Each call uses its own temp file next to `path`
+/// (`<path>.<pid>.<n>.tmp`), so concurrent writers don't collide; the last
+/// rename wins.
+pub fn write_atomic(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let n = TMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".{}.{n}.tmp", std::process::id()));
+    let tmp = PathBuf::from(tmp);
+    let result = std::fs::write(&tmp, content).and_then(|()| std::fs::rename(&tmp, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_atomic_replaces_and_cleans_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        std::fs::write(&path, "old contents that are longer").unwrap();
+
+        write_atomic(&path, b"new").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        let names: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(names, ["state.json"]);
+
+        // A failed write leaves the old file and no temp file behind.
+        assert!(write_atomic(&dir.path().join("missing/state.json"), b"x").is_err());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_concurrent_writers_do_not_collide() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        std::thread::scope(|s| {
+            for i in 0..8 {
+                let path = &path;
+                s.spawn(move || {
+                    let content = format!("writer {i}").repeat(1000);
+                    for _ in 0..20 {
+                        write_atomic(path, content.as_bytes()).unwrap();
+                    }
+                });
+            }
+        });
+        // Whole contents of one writer, never a mix
+        let content = std::fs::read_to_string(&path).unwrap();
+        let first = &content[..8];
+        assert_eq!(content, first.repeat(1000));
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
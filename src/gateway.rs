@@ -1,18 +1,91 @@
-use std::sync::Arc;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
-use tracing::{debug, info};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
 
-use crate::agent::Agent;
-use crate::channels::{InboundMessage, OutboundMessage};
-use crate::config::Config;
-use crate::error::Result;
+use crate::agent::confirm::{self, ConfirmationGate, PendingConfirmations};
+use crate::agent::{Agent, TurnOverrides};
+use crate::budget::DailyBudget;
+use crate::channels::{Attachment, InboundMessage, InboxTarget, OutboundMessage};
+use crate::config::{tool_whitelist, Config, SettingsPatch};
+use crate::error::{NekoError, Result};
 use crate::session::SessionStore;
 use crate::tools::ChannelContext;
 
+/// How long a paused tool call waits for the user's yes/no.
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Channel an HTTP peer is keyed and routed under. Callers may name their
+/// own channel, but it is kept apart from the real ones (`telegram` becomes
+/// `http-telegram`), so an API token can't continue a chat user's session
+/// or pass as that user in admin checks.
+fn http_channel(channel: Option<&str>) -> String {
+    match channel {
+        None | Some("http") => "http".to_string(),
+        Some(c) if c.starts_with("http-") => c.to_string(),
+        Some(c) => format!("http-{c}"),
+    }
+}
+
+/// A named agent and the inbound routes it serves.
+struct RoutedAgent {
+    name: String,
+    routes: Vec<String>,
+    agent: Arc<Agent>,
+    /// Sessions in the agent's own workspace.
+    sessions: Arc<SessionStore>,
+}
+
+/// An agent together with the store holding its conversations.
+#[derive(Clone, Copy)]
+pub struct AgentHandle<'a> {
+    /// `default` for the top-level agent.
+    pub name: &'a str,
+    pub agent: &'a Arc<Agent>,
+    pub sessions: &'a Arc<SessionStore>,
+}
+
+/// Who an HTTP API message comes from. Integrations fronting their own
+/// channel fill these in so sessions are keyed per peer, as for Telegram;
+/// unset fields fall back to channel `http` and sender `http-default`.
+/// The channel is always namespaced under `http` (see [`http_channel`]).
+#[derive(Debug, Clone, Default)]
+pub struct HttpPeer<'a> {
+    pub channel: Option<&'a str>,
+    pub sender_id: Option<&'a str>,
+    pub is_group: bool,
+    pub group_id: Option<&'a str>,
+}
+
+/// The agent's answer to an HTTP API message.
+#[derive(Debug)]
+pub struct HttpReply {
+    pub text: String,
+    pub session_id: String,
+    /// Files the agent queued with `send_file`.
+    pub attachments: Vec<Attachment>,
+    /// Agent that answered (`default` or a profile name); attachments live
+    /// in its workspace.
+    pub agent: String,
+}
+
 pub struct Gateway {
+    /// Default agent — handles anything not matched by a named agent's routes.
     pub agent: Arc<Agent>,
+    routed: Vec<RoutedAgent>,
     pub session_store: Arc<SessionStore>,
-    pub config: Arc<Config>,
+    /// Effective config; `update_settings` swaps in patched copies.
+    config: RwLock<Arc<Config>>,
+    /// File `update_settings` persists to.
+    config_path: Option<PathBuf>,
+    /// Channel egress handed to tools for mid-turn messages.
+    outbound_tx: Option<mpsc::Sender<OutboundMessage>>,
+    /// Tool calls waiting on a yes/no reply (sessions with `/confirm on`).
+    confirmations: Arc<PendingConfirmations>,
+    /// `daily_token_budget`; once spent, the gateway rejects new turns.
+    daily_budget: Option<Arc<DailyBudget>>,
 }
 
 impl Gateway {
@@ -24,16 +97,238 @@ impl Gateway {
         Self {
             agent,
             session_store,
-            config,
+            config: RwLock::new(config),
+            config_path: None,
+            outbound_tx: None,
+            routed: Vec::new(),
+            confirmations: Arc::new(PendingConfirmations::default()),
+            daily_budget: None,
+        }
+    }
+
+    /// Register a named agent serving the given routes (`channel` or
+    /// `channel:id`), keeping its conversations in `sessions`.
+    pub fn with_agent(
+        mut self,
+        name: String,
+        routes: Vec<String>,
+        agent: Arc<Agent>,
+        sessions: Arc<SessionStore>,
+    ) -> Self {
+        self.routed.push(RoutedAgent {
+            name,
+            routes,
+            agent,
+            sessions,
+        });
+        self
+    }
+
+    /// The default agent, then every named agent.
+    pub fn agents(&self) -> impl Iterator<Item = AgentHandle<'_>> {
+        std::iter::once(AgentHandle {
+            name: "default",
+            agent: &self.agent,
+            sessions: &self.session_store,
+        })
+        .chain(self.routed.iter().map(|r| AgentHandle {
+            name: &r.name,
+            agent: &r.agent,
+            sessions: &r.sessions,
+        }))
+    }
+
+    /// The agent registered as `name` (`default` for the top-level one).
+    pub fn agent_named(&self, name: &str) -> Option<AgentHandle<'_>> {
+        self.agents().find(|a| a.name == name)
+    }
+
+    /// The agent whose store holds `session_id`.
+    pub async fn agent_for_session(&self, session_id: &str) -> Result<AgentHandle<'_>> {
+        for handle in self.agents() {
+            if handle.sessions.contains(session_id).await {
+                return Ok(handle);
+            }
+        }
+        Err(NekoError::Session(format!("Session not found: {session_id}")))
+    }
+
+    /// Pick the agent for a message on `channel` involving `ids` (sender,
+    /// chat, group). `channel:id` routes beat bare `channel` routes; the
+    /// default agent handles everything else.
+    fn select_agent(&self, channel: &str, ids: &[&str]) -> AgentHandle<'_> {
+        let exact = self.routed.iter().find(|r| {
+            r.routes.iter().any(|route| {
+                route
+                    .split_once(':')
+                    .is_some_and(|(ch, id)| ch == channel && ids.contains(&id))
+            })
+        });
+        let matched = exact.or_else(|| {
+            self.routed
+                .iter()
+                .find(|r| r.routes.iter().any(|route| route == channel))
+        });
+        match matched {
+            Some(r) => {
+                debug!("Routing {channel} message to agent '{}'", r.name);
+                AgentHandle {
+                    name: &r.name,
+                    agent: &r.agent,
+                    sessions: &r.sessions,
+                }
+            }
+            None => AgentHandle {
+                name: "default",
+                agent: &self.agent,
+                sessions: &self.session_store,
+            },
+        }
+    }
+
+    /// Where to save a file arriving on `channel` from `ids`: the inbox of
+    /// the agent [`select_agent`](Self::select_agent) will hand it to.
+    pub fn inbox_for(&self, channel: &str, ids: &[&str]) -> InboxTarget {
+        let agent = self.select_agent(channel, ids).agent;
+        InboxTarget {
+            dir: agent.inbox_dir(),
+            workspace: agent.workspace().to_path_buf(),
+            quota: agent.workspace_quota(),
         }
     }
 
+    /// Reject new turns while the shared daily token budget is spent.
+    pub fn with_daily_budget(mut self, budget: Option<Arc<DailyBudget>>) -> Self {
+        self.daily_budget = budget;
+        self
+    }
+
+    /// Where the config was loaded from, so runtime changes can be saved.
+    pub fn with_config_path(mut self, path: PathBuf) -> Self {
+        self.config_path = Some(path);
+        self
+    }
+
+    /// The current effective config.
+    pub fn config(&self) -> Arc<Config> {
+        Arc::clone(&self.config.read().unwrap())
+    }
+
+    /// The effective config as seen by `agent`: the top-level config for
+    /// `default`, otherwise the profile's, with the agent's current
+    /// (possibly runtime-changed) `[agent]` settings.
+    pub fn agent_config(&self, agent: &str) -> Result<Arc<Config>> {
+        let config = self.config();
+        if agent == "default" {
+            return Ok(config);
+        }
+        let handle = self
+            .agent_named(agent)
+            .ok_or_else(|| NekoError::Config(format!("Unknown agent '{agent}'")))?;
+        let profile = config
+            .agents
+            .iter()
+            .find(|p| p.name == agent)
+            .ok_or_else(|| NekoError::Config(format!("Unknown agent '{agent}'")))?;
+        let mut profile_config = config.for_profile(profile);
+        profile_config.agent = (*handle.agent.config()).clone();
+        Ok(Arc::new(profile_config))
+    }
+
+    /// Change an agent's settings while running (`PATCH /api/v1/config`).
+    /// New turns pick them up; with `persist` they are also written to the
+    /// config file (default agent only). `caller` identifies who asked, for
+    /// the log.
+    pub fn update_settings(
+        &self,
+        agent: &str,
+        patch: &SettingsPatch,
+        persist: bool,
+        caller: &str,
+    ) -> Result<Arc<Config>> {
+        if agent != "default" {
+            return self.update_agent_settings(agent, patch, persist, caller);
+        }
+        patch.validate(&self.agent.tool_names())?;
+        let mut current = self.config.write().unwrap();
+        if persist {
+            let path = self.config_path.as_deref().ok_or_else(|| {
+                NekoError::Config("The gateway doesn't know its config file".to_string())
+            })?;
+            patch.persist(path)?;
+        }
+        let mut config = (**current).clone();
+        let changes = patch.apply(&mut config.agent);
+        self.agent.set_config(config.agent.clone());
+        if let Some(tools) = &patch.tools {
+            self.agent.set_runtime_tools(tool_whitelist(tools));
+        }
+        let config = Arc::new(config);
+        *current = Arc::clone(&config);
+        if !changes.is_empty() {
+            let saved = if persist { " (saved)" } else { "" };
+            info!("Settings changed by {caller}{saved}: {}", changes.join("; "));
+        }
+        Ok(config)
+    }
+
+    /// [`update_settings`](Self::update_settings) for a named agent. Its
+    /// settings live in an `[[agents]]` entry, so they aren't persisted.
+    fn update_agent_settings(
+        &self,
+        agent: &str,
+        patch: &SettingsPatch,
+        persist: bool,
+        caller: &str,
+    ) -> Result<Arc<Config>> {
+        let handle = self
+            .agent_named(agent)
+            .ok_or_else(|| NekoError::Config(format!("Unknown agent '{agent}'")))?;
+        if persist {
+            return Err(NekoError::Config(format!(
+                "Settings of agent '{agent}' can't be persisted; edit its [[agents]] entry instead"
+            )));
+        }
+        patch.validate(&handle.agent.tool_names())?;
+        let mut config = (*handle.agent.config()).clone();
+        let changes = patch.apply(&mut config);
+        handle.agent.set_config(config);
+        if let Some(tools) = &patch.tools {
+            handle.agent.set_runtime_tools(tool_whitelist(tools));
+        }
+        if !changes.is_empty() {
+            info!("Settings of agent '{agent}' changed by {caller}: {}", changes.join("; "));
+        }
+        self.agent_config(agent)
+    }
+
+    /// Re-scan skills for every agent (on SIGHUP / `neko skills reload`).
+    pub fn reload_skills(&self) {
+        for AgentHandle { name, agent, .. } in self.agents() {
+            match agent.reload_skills() {
+                Ok(count) => info!("Reloaded {count} skill(s) for agent '{name}'"),
+                Err(e) => warn!("Failed to reload skills for agent '{name}': {e}"),
+            }
+        }
+    }
+
+    /// Let tools send messages to channels mid-turn (e.g. `status_update`).
+    pub fn with_outbound(mut self, tx: mpsc::Sender<OutboundMessage>) -> Self {
+        self.outbound_tx = Some(tx);
+        self
+    }
+
     /// Core routing: inbound message → session → agent → outbound message.
     pub async fn handle_message(&self, inbound: InboundMessage) -> Result<OutboundMessage> {
         let text = inbound.text.trim().to_string();
 
+        let mut ids = vec![inbound.sender_id.as_str(), inbound.reply_to.as_str()];
+        ids.extend(inbound.group_id.as_deref());
+        let route = self.select_agent(&inbound.channel, &ids);
+        let sessions = route.sessions;
+
         // Resolve session key
-        let key = self.session_store.resolve_key(
+        let key = sessions.resolve_key(
             &inbound.channel,
             &inbound.sender_id,
             inbound.is_group,
@@ -43,14 +338,36 @@ impl Gateway {
         debug!("Resolved session key: {}", key);
 
         // Get or create session
-        let session_id = self
-            .session_store
+        let session_id = sessions
             .get_or_create(&key, Some(&inbound.channel), inbound.display_name.as_deref())
             .await?;
 
+        // A reply to a paused tool call resumes that turn instead of
+        // starting a new one.
+        if self.confirmations.is_waiting(&session_id) {
+            let reply = match confirm::parse_reply(&text) {
+                Some(approved) => {
+                    if !self.confirmations.resolve(&session_id, &inbound.sender_id, approved) {
+                        "Only the person who asked can approve or cancel the action above."
+                    } else if approved {
+                        "Approved."
+                    } else {
+                        "Cancelled."
+                    }
+                }
+                None => "Waiting on the action above \u{2014} reply yes or no.",
+            };
+            return Ok(OutboundMessage {
+                channel: inbound.channel,
+                recipient_id: inbound.reply_to,
+                text: reply.to_string(),
+                attachments: Vec::new(),
+            });
+        }
+
         // Handle /new and /reset commands
         if text == "/new" || text == "/reset" {
-            self.session_store.reset(&session_id).await?;
+            sessions.reset(&session_id).await?;
             return Ok(OutboundMessage {
                 channel: inbound.channel,
                 recipient_id: inbound.reply_to,
@@ -59,27 +376,223 @@ impl Gateway {
             });
         }
 
+        if text == "/persona" || text.starts_with("/persona ") {
+            let arg = text["/persona".len()..].trim();
+            let reply = match arg {
+                "" => {
+                    let meta = sessions.meta(&session_id).await?;
+                    match meta.instructions_override {
+                        Some(p) => format!("Current persona:\n{p}\n\nUse /persona reset to clear it."),
+                        None => "No persona set. Use /persona <instructions> to set one.".to_string(),
+                    }
+                }
+                "reset" | "clear" => {
+                    sessions.set_instructions_override(&session_id, None).await?;
+                    "Persona cleared. Using the default instructions.".to_string()
+                }
+                instructions => {
+                    sessions
+                        .set_instructions_override(&session_id, Some(instructions.to_string()))
+                        .await?;
+                    "Persona set for this conversation.".to_string()
+                }
+            };
+            return Ok(OutboundMessage {
+                channel: inbound.channel,
+                recipient_id: inbound.reply_to,
+                text: reply,
+                attachments: Vec::new(),
+            });
+        }
+
+        if text == "/lang" || text.starts_with("/lang ") {
+            let arg = text["/lang".len()..].trim();
+            let reply = match arg {
+                "" => {
+                    let meta = sessions.meta(&session_id).await?;
+                    match (meta.locale, meta.locale_pinned) {
+                        (Some(l), true) => format!("Responding in {l}. Use /lang auto to detect it instead."),
+                        (Some(l), false) => format!("Responding in {l} (detected)."),
+                        (None, _) => "No language set. Use /lang <language> to choose one.".to_string(),
+                    }
+                }
+                "auto" | "reset" => {
+                    sessions.set_locale(&session_id, None, false).await?;
+                    "Language will be detected from your messages.".to_string()
+                }
+                lang => {
+                    let name = crate::locale::language_name(lang);
+                    sessions
+                        .set_locale(&session_id, Some(name.clone()), true)
+                        .await?;
+                    format!("I'll respond in {name}.")
+                }
+            };
+            return Ok(OutboundMessage {
+                channel: inbound.channel,
+                recipient_id: inbound.reply_to,
+                text: reply,
+                attachments: Vec::new(),
+            });
+        }
+
+        if text == "/history" || text.starts_with("/history ") {
+            let reply = match text["/history".len()..].trim() {
+                "" => {
+                    let meta = sessions.meta(&session_id).await?;
+                    match meta.max_history_override {
+                        Some(n) => format!("Keeping the last {n} history items. Use /history reset for the default."),
+                        None => format!(
+                            "Keeping the default {} history items. Use /history <n> to change it.",
+                            route.agent.config().max_history
+                        ),
+                    }
+                }
+                "reset" | "default" => {
+                    sessions.set_max_history_override(&session_id, None).await?;
+                    "History depth back to the default.".to_string()
+                }
+                arg => match arg.parse::<u32>() {
+                    Ok(n) if n > 0 => {
+                        sessions
+                            .set_max_history_override(&session_id, Some(n))
+                            .await?;
+                        format!("Keeping the last {n} history items in this conversation.")
+                    }
+                    _ => "Usage: /history <n>|reset".to_string(),
+                },
+            };
+            return Ok(OutboundMessage {
+                channel: inbound.channel,
+                recipient_id: inbound.reply_to,
+                text: reply,
+                attachments: Vec::new(),
+            });
+        }
+
+        if text == "/confirm" || text.starts_with("/confirm ") {
+            let reply = match text["/confirm".len()..].trim() {
+                "" => {
+                    let meta = sessions.meta(&session_id).await?;
+                    if meta.confirm_tools {
+                        "Risky tool calls wait for your yes/no. Use /confirm off to stop asking."
+                    } else {
+                        "Tool calls run without asking. Use /confirm on to approve risky ones first."
+                    }
+                }
+                "on" => {
+                    sessions.set_confirm_tools(&session_id, true).await?;
+                    "I'll ask before running risky commands or writing sensitive files."
+                }
+                "off" => {
+                    sessions.set_confirm_tools(&session_id, false).await?;
+                    "Tool calls will run without asking."
+                }
+                _ => "Usage: /confirm on|off",
+            };
+            return Ok(OutboundMessage {
+                channel: inbound.channel,
+                recipient_id: inbound.reply_to,
+                text: reply.to_string(),
+                attachments: Vec::new(),
+            });
+        }
+
+        if text == "/compact" {
+            let reply = match self.compact_in(route, &session_id).await {
+                Ok((before, after)) if before == after => {
+                    "Nothing to compact yet.".to_string()
+                }
+                Ok((before, after)) => format!("Session compacted: {before} \u{2192} {after} items."),
+                Err(e) => format!("Compaction failed: {e}"),
+            };
+            return Ok(OutboundMessage {
+                channel: inbound.channel,
+                recipient_id: inbound.reply_to,
+                text: reply,
+                attachments: Vec::new(),
+            });
+        }
+
+        if let Some(resets_at) = self.daily_budget.as_ref().and_then(|b| b.exhausted_until()) {
+            return Ok(OutboundMessage {
+                channel: inbound.channel,
+                recipient_id: inbound.reply_to,
+                text: format!(
+                    "Daily usage limit reached, back at {}.",
+                    resets_at.format("%Y-%m-%d %H:%M UTC")
+                ),
+                attachments: Vec::new(),
+            });
+        }
+
+        if let Some(resets_at) = sessions.budget_reset_at(&session_id).await? {
+            info!("Session {session_id} is over its daily token budget");
+            return Ok(OutboundMessage {
+                channel: inbound.channel,
+                recipient_id: inbound.reply_to,
+                text: format!(
+                    "Session budget reached, resets at {}.",
+                    resets_at.format("%Y-%m-%d %H:%M UTC")
+                ),
+                attachments: Vec::new(),
+            });
+        }
+
+        let greeting = match &route.agent.config().greeting {
+            Some(greeting) if sessions.needs_greeting(&session_id).await? => {
+                Some(greeting.clone())
+            }
+            _ => None,
+        };
+
         // Check automatic reset (daily/idle)
-        if self.session_store.check_reset(&session_id).await? {
+        if sessions.check_reset(&session_id).await? {
             info!("Auto-reset triggered for session {session_id}");
         }
 
         // Get history + previous response ID for reasoning chaining
         let (history, prev_response_id) =
-            self.session_store.get_history(&session_id).await?;
+            sessions.get_history(&session_id).await?;
 
         let channel_ctx = ChannelContext {
             channel: inbound.channel.clone(),
             recipient_id: inbound.reply_to.clone(),
+            sender_id: Some(inbound.sender_id.clone()),
         };
 
-        let result = self
+        let text = match self.config().gateway.expand_macro(&text) {
+            Some(expansion) => {
+                debug!("Expanded macro {text}");
+                expansion.to_string()
+            }
+            None => text,
+        };
+
+        let mut overrides = self
+            .turn_overrides(sessions, route.agent, &session_id, Some(&inbound.sender_id), &text)
+            .await?;
+        overrides.attachments = inbound.attachments;
+        // A file sent without a caption still needs a user message
+        let text = if text.is_empty() && !overrides.attachments.is_empty() {
+            "(sent a file)".to_string()
+        } else {
+            text
+        };
+        let result = route
             .agent
-            .run_turn_with_history(history, &text, prev_response_id, Some(channel_ctx))
+            .run_turn_with_history(
+                history,
+                &text,
+                prev_response_id,
+                Some(channel_ctx),
+                self.outbound_tx.clone(),
+                &overrides,
+            )
             .await?;
 
         // Persist updated history + new response ID
-        self.session_store
+        sessions
             .update_history(
                 &session_id,
                 result.history,
@@ -88,29 +601,116 @@ impl Gateway {
             )
             .await?;
 
+        // A new peer gets the greeting ahead of the first reply.
+        let text = match greeting {
+            Some(greeting) => {
+                sessions.mark_greeted(&session_id).await?;
+                format!("{greeting}\n\n{}", result.text)
+            }
+            None => result.text,
+        };
         Ok(OutboundMessage {
             channel: inbound.channel,
             recipient_id: inbound.reply_to,
-            text: result.text,
+            text,
             attachments: result.attachments,
         })
     }
 
+    /// Per-session turn settings stored on the session metadata. Unless the
+    /// user pinned a language with `/lang`, the locale follows the language
+    /// detected in `text`. Confirmations go to `sender_id` alone.
+    async fn turn_overrides(
+        &self,
+        sessions: &SessionStore,
+        agent: &Agent,
+        session_id: &str,
+        sender_id: Option<&str>,
+        text: &str,
+    ) -> Result<TurnOverrides> {
+        let mut meta = sessions.meta(session_id).await?;
+        if agent.config().detect_language && !meta.locale_pinned {
+            if let Some(detected) = crate::locale::detect_language(text) {
+                if meta.locale.as_deref() != Some(detected.as_str()) {
+                    debug!("Session {session_id} language detected: {detected}");
+                    sessions
+                        .set_locale(session_id, Some(detected.clone()), false)
+                        .await?;
+                    meta.locale = Some(detected);
+                }
+            }
+        }
+        let confirmation = meta.confirm_tools.then(|| ConfirmationGate {
+            session_id: session_id.to_string(),
+            requester: sender_id.map(String::from),
+            pending: Arc::clone(&self.confirmations),
+            timeout: CONFIRMATION_TIMEOUT,
+        });
+        Ok(TurnOverrides {
+            instructions: meta.instructions_override,
+            locale: meta.locale,
+            confirmation,
+            allowed_tools: None,
+            attachments: Vec::new(),
+            max_history: meta.max_history_override,
+        })
+    }
+
+    /// Summarize a session's history in place. Returns (before, after)
+    /// item counts.
+    pub async fn compact_session(&self, session_id: &str) -> Result<(usize, usize)> {
+        let route = self.agent_for_session(session_id).await?;
+        self.compact_in(route, session_id).await
+    }
+
+    /// [`compact_session`](Self::compact_session) with the owning agent
+    /// already known.
+    async fn compact_in(&self, route: AgentHandle<'_>, session_id: &str) -> Result<(usize, usize)> {
+        let (before, after) = route
+            .sessions
+            .compact_with(session_id, |history| route.agent.compact_history(history))
+            .await?;
+        if after < before {
+            info!("Compacted session {session_id}: {before} -> {after} items");
+        }
+        Ok((before, after))
+    }
+
+    /// Refuse a turn for a session over its daily token budget
+    /// (`session.max_tokens_per_day`).
+    async fn check_session_budget(&self, sessions: &SessionStore, session_id: &str) -> Result<()> {
+        match sessions.budget_reset_at(session_id).await? {
+            Some(resets_at) => {
+                info!("Session {session_id} is over its daily token budget");
+                Err(NekoError::Session(format!(
+                    "Session budget reached, resets at {}",
+                    resets_at.format("%Y-%m-%d %H:%M UTC")
+                )))
+            }
+            None => Ok(()),
+        }
+    }
+
     /// Handle a message for an explicitly specified session ID (HTTP API).
     pub async fn handle_message_with_session(
         &self,
         session_id: &str,
         text: &str,
     ) -> Result<(String, String)> {
-        let (history, prev_response_id) =
-            self.session_store.get_history(session_id).await?;
+        let route = self.agent_for_session(session_id).await?;
+        self.check_session_budget(route.sessions, session_id).await?;
+        let (history, prev_response_id) = route.sessions.get_history(session_id).await?;
 
-        let result = self
+        let overrides = self
+            .turn_overrides(route.sessions, route.agent, session_id, None, text)
+            .await?;
+        let result = route
             .agent
-            .run_turn_with_history(history, text, prev_response_id, None)
+            .run_turn_with_history(history, text, prev_response_id, None, None, &overrides)
             .await?;
 
-        self.session_store
+        route
+            .sessions
             .update_history(
                 session_id,
                 result.history,
@@ -122,46 +722,188 @@ impl Gateway {
         Ok((result.text, session_id.to_string()))
     }
 
+    /// The agent and session an HTTP message goes to: `session_id` if given
+    /// (it must exist), otherwise the one keyed by the peer under its HTTP
+    /// channel, with the agent routed as for any other channel.
+    async fn http_session(
+        &self,
+        session_id: Option<&str>,
+        peer: &HttpPeer<'_>,
+    ) -> Result<(AgentHandle<'_>, String)> {
+        if let Some(id) = session_id {
+            return Ok((self.agent_for_session(id).await?, id.to_string()));
+        }
+        // Create/get a session for the caller's channel and peer
+        let channel = http_channel(peer.channel);
+        let sender_id = peer.sender_id.unwrap_or("http-default");
+        let mut ids = vec![sender_id];
+        ids.extend(peer.group_id);
+        let route = self.select_agent(&channel, &ids);
+        let key = route
+            .sessions
+            .resolve_key(&channel, sender_id, peer.is_group, peer.group_id);
+        let sid = route.sessions.get_or_create(&key, Some(&channel), None).await?;
+        Ok((route, sid))
+    }
+
     /// Handle message from HTTP channel (may or may not have session_id).
     pub async fn handle_http_message(
         &self,
         text: &str,
         session_id: Option<&str>,
-        sender_id: Option<&str>,
-    ) -> Result<(String, String)> {
-        let sid = if let Some(id) = session_id {
-            // Verify it exists
-            let _ = self.session_store.get_history(id).await?;
-            id.to_string()
-        } else {
-            // Create/get a session for the HTTP channel
-            let peer = sender_id.unwrap_or("http-default");
-            let key = self.session_store.resolve_key("http", peer, false, None);
-            self.session_store
-                .get_or_create(&key, Some("http"), None)
-                .await?
-        };
+        peer: &HttpPeer<'_>,
+        allowed_tools: Option<&[String]>,
+    ) -> Result<HttpReply> {
+        let channel = http_channel(peer.channel);
+        let sender_id = peer.sender_id.unwrap_or("http-default");
+        let (route, sid) = self.http_session(session_id, peer).await?;
+        let sessions = route.sessions;
+
+        self.check_session_budget(sessions, &sid).await?;
 
         // Check automatic reset
-        let _ = self.session_store.check_reset(&sid).await;
+        let _ = sessions.check_reset(&sid).await;
 
-        let (history, prev_response_id) =
-            self.session_store.get_history(&sid).await?;
+        let (history, prev_response_id) = sessions.get_history(&sid).await?;
 
+        let recipient_id = match peer.group_id {
+            Some(gid) if peer.is_group => gid,
+            _ => sender_id,
+        };
         let channel_ctx = ChannelContext {
-            channel: "http".to_string(),
-            recipient_id: sender_id.unwrap_or("http-default").to_string(),
+            channel,
+            recipient_id: recipient_id.to_string(),
+            sender_id: peer.sender_id.map(String::from),
         };
 
-        let result = self
+        let mut overrides = self
+            .turn_overrides(sessions, route.agent, &sid, Some(sender_id), text)
+            .await?;
+        overrides.allowed_tools = allowed_tools.map(<[String]>::to_vec);
+        let result = route
             .agent
-            .run_turn_with_history(history, text, prev_response_id, Some(channel_ctx))
+            .run_turn_with_history(
+                history,
+                text,
+                prev_response_id,
+                Some(channel_ctx),
+                None,
+                &overrides,
+            )
             .await?;
 
-        self.session_store
+        sessions
             .update_history(&sid, result.history, result.usage.as_ref(), result.last_response_id)
             .await?;
 
-        Ok((result.text, sid))
+        Ok(HttpReply {
+            text: result.text,
+            session_id: sid,
+            attachments: result.attachments,
+            agent: route.name.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::ToolRegistry;
+
+    fn gateway(workspace: &std::path::Path) -> Gateway {
+        let config: Config = toml::from_str(
+            "[session]\ndm_scope = \"per_channel_peer\"\nmax_tokens_per_day = 100",
+        )
+        .unwrap();
+        let agent = Agent::new(
+            crate::llm::Client::new("http://127.0.0.1:9", None),
+            ToolRegistry::new(),
+            config.agent.clone(),
+        );
+        let store = SessionStore::new(workspace.join("sessions"), config.session.clone());
+        Gateway::new(Arc::new(agent), Arc::new(store), Arc::new(config))
+    }
+
+    #[tokio::test]
+    async fn test_http_peer_cannot_reach_telegram_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let gw = gateway(dir.path());
+        let key = gw.session_store.resolve_key("telegram", "42", false, None);
+        let telegram = gw.session_store.get_or_create(&key, Some("telegram"), None).await.unwrap();
+
+        let peer = HttpPeer {
+            channel: Some("telegram"),
+            sender_id: Some("42"),
+            ..Default::default()
+        };
+        let (_, sid) = gw.http_session(None, &peer).await.unwrap();
+        assert_ne!(sid, telegram);
+        let meta = gw.session_store.meta(&sid).await.unwrap();
+        assert_eq!(meta.channel.as_deref(), Some("http-telegram"));
+
+        assert_eq!(http_channel(None), "http");
+        assert_eq!(http_channel(Some("http-dashboard")), "http-dashboard");
+    }
+
+    #[tokio::test]
+    async fn test_http_turns_respect_session_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let gw = gateway(dir.path());
+        let (_, sid) = gw.http_session(None, &HttpPeer::default()).await.unwrap();
+        let usage = crate::llm::Usage {
+            input_tokens: 90,
+            output_tokens: 20,
+            total_tokens: 110,
+        };
+        gw.session_store.update_history(&sid, Vec::new(), Some(&usage), None).await.unwrap();
+
+        let err = gw
+            .handle_http_message("hi", Some(&sid), &HttpPeer::default(), None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Session budget reached"), "{err}");
+        let err = gw.handle_message_with_session(&sid, "hi").await.unwrap_err();
+        assert!(err.to_string().contains("Session budget reached"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_named_agent_keeps_its_own_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = gateway(dir.path());
+        let config = base.config();
+        let helper = Agent::new(
+            crate::llm::Client::new("http://127.0.0.1:9", None),
+            ToolRegistry::new(),
+            config.agent.clone(),
+        );
+        let helper_sessions = Arc::new(SessionStore::new(
+            dir.path().join("helper/sessions"),
+            config.session.clone(),
+        ));
+        let gw = base.with_agent(
+            "helper".to_string(),
+            vec!["telegram".to_string()],
+            Arc::new(helper),
+            Arc::clone(&helper_sessions),
+        );
+
+        let reply = gw
+            .handle_message(InboundMessage {
+                channel: "telegram".to_string(),
+                sender_id: "42".to_string(),
+                text: "/persona be brief".to_string(),
+                is_group: false,
+                group_id: None,
+                display_name: None,
+                reply_to: "42".to_string(),
+                attachments: Vec::new(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(reply.text, "Persona set for this conversation.");
+        assert!(gw.session_store.list().await.is_empty());
+        let sid = helper_sessions.list().await[0].session_id.clone();
+        assert_eq!(gw.agent_for_session(&sid).await.unwrap().name, "helper");
+        assert!(gw.agent_for_session("missing").await.is_err());
     }
 }
@@ -1,12 +1,22 @@
+#[cfg(feature = "server")]
 pub mod api;
 pub mod config;
 pub mod error;
 pub mod llm;
 pub mod tools;
 pub mod agent;
+pub mod budget;
 pub mod skills;
+#[cfg(feature = "mcp")]
 pub mod mcp;
 pub mod session;
+#[cfg(feature = "systemd")]
+pub mod systemd;
 pub mod channels;
 pub mod cron;
 pub mod gateway;
+pub mod workspace;
+pub mod fs_util;
+pub mod locale;
+pub mod output;
+pub mod memory_index;
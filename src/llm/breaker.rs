@@ -0,0 +1,190 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Observable state of a [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests flow normally.
+    Closed,
+    /// Too many consecutive failures — requests fail fast until cooldown ends.
+    Open,
+    /// Cooldown elapsed — a single probe request is allowed through.
+    HalfOpen,
+}
+
+impl CircuitState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half_open",
+        }
+    }
+}
+
+struct Inner {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    probe_in_flight: bool,
+}
+
+/// Per-provider circuit breaker.
+///
+/// After `threshold` consecutive failures the circuit opens and requests
+/// fail fast for `cooldown`. Once the cooldown elapses one probe request is
+/// let through; success closes the circuit, failure re-opens it.
+/// A threshold of 0 disables the breaker.
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            inner: Mutex::new(Inner {
+                consecutive_failures: 0,
+                opened_at: None,
+                probe_in_flight: false,
+            }),
+        }
+    }
+
+    /// Ask permission to send a request. Returns the remaining cooldown
+    /// when the circuit is open.
+    pub fn try_acquire(self: &Arc<Self>) -> Result<Permit, Duration> {
+        let permit = |probe| Permit {
+            breaker: Arc::clone(self),
+            probe,
+        };
+        if self.threshold == 0 {
+            return Ok(permit(false));
+        }
+        let mut inner = self.inner.lock().unwrap();
+        let Some(opened_at) = inner.opened_at else {
+            return Ok(permit(false));
+        };
+        let elapsed = opened_at.elapsed();
+        if elapsed < self.cooldown {
+            return Err(self.cooldown - elapsed);
+        }
+        // Half-open: allow exactly one probe at a time.
+        if inner.probe_in_flight {
+            return Err(Duration::ZERO);
+        }
+        inner.probe_in_flight = true;
+        Ok(permit(true))
+    }
+
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures = 0;
+        inner.opened_at = None;
+        inner.probe_in_flight = false;
+    }
+
+    pub fn record_failure(&self) {
+        if self.threshold == 0 {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures += 1;
+        inner.probe_in_flight = false;
+        if inner.consecutive_failures >= self.threshold {
+            inner.opened_at = Some(Instant::now());
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        let inner = self.inner.lock().unwrap();
+        match inner.opened_at {
+            None => CircuitState::Closed,
+            Some(at) if at.elapsed() < self.cooldown => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+}
+
+/// Permission to send one request, from [`CircuitBreaker::try_acquire`].
+/// Report how it went with [`success`](Self::success) or
+/// [`failure`](Self::failure). A permit dropped without either (the request
+/// was cancelled or timed out on our side) counts as neither, but frees the
+/// half-open probe slot for the next caller.
+pub struct Permit {
+    breaker: Arc<CircuitBreaker>,
+    probe: bool,
+}
+
+impl Permit {
+    pub fn success(mut self) {
+        self.probe = false;
+        self.breaker.record_success();
+    }
+
+    pub fn failure(mut self) {
+        self.probe = false;
+        self.breaker.record_failure();
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if self.probe {
+            self.breaker.inner.lock().unwrap().probe_in_flight = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opens_after_threshold() {
+        let cb = Arc::new(CircuitBreaker::new(2, Duration::from_secs(60)));
+        assert!(cb.try_acquire().is_ok());
+        cb.record_failure();
+        assert_eq!(cb.state(), CircuitState::Closed);
+        cb.record_failure();
+        assert_eq!(cb.state(), CircuitState::Open);
+        assert!(cb.try_acquire().is_err());
+    }
+
+    #[test]
+    fn test_half_open_probe() {
+        let cb = Arc::new(CircuitBreaker::new(1, Duration::ZERO));
+        cb.record_failure();
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        let probe = cb.try_acquire().unwrap();
+        // Second caller is rejected while the probe is in flight.
+        assert!(cb.try_acquire().is_err());
+        probe.success();
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert!(cb.try_acquire().is_ok());
+    }
+
+    #[test]
+    fn test_dropped_probe_frees_slot() {
+        let cb = Arc::new(CircuitBreaker::new(1, Duration::ZERO));
+        cb.record_failure();
+        let probe = cb.try_acquire().unwrap();
+        assert!(cb.try_acquire().is_err());
+        // e.g. the turn timed out and the request future was dropped
+        drop(probe);
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+        assert!(cb.try_acquire().is_ok());
+    }
+
+    #[test]
+    fn test_disabled_with_zero_threshold() {
+        let cb = Arc::new(CircuitBreaker::new(0, Duration::from_secs(60)));
+        for _ in 0..10 {
+            cb.record_failure();
+        }
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert!(cb.try_acquire().is_ok());
+    }
+}
@@ -1,3 +1,7 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
 use futures::StreamExt;
 use reqwest_eventsource::{Event, EventSource};
 use tokio::sync::mpsc;
@@ -5,74 +9,204 @@ use tracing::{debug, error, warn};
 
 use crate::error::{NekoError, Result};
 
-use super::types::{Request, Response, StreamEvent};
+use super::breaker::{CircuitBreaker, CircuitState};
+use super::error::LlmError;
+use super::types::{EmbeddingRequest, EmbeddingResponse, Request, Response, StreamEvent};
 
 pub struct Client {
     http: reqwest::Client,
+    /// Separate client for SSE — long-lived, so no total request timeout.
+    stream_http: reqwest::Client,
     base_url: String,
     api_key: Option<String>,
+    headers: HashMap<String, String>,
+    breaker: Arc<CircuitBreaker>,
 }
 
 impl Client {
     pub fn new(base_url: &str, api_key: Option<&str>) -> Self {
         Self {
             http: reqwest::Client::new(),
+            stream_http: reqwest::Client::new(),
             base_url: base_url.trim_end_matches('/').to_string(),
             api_key: api_key.map(|s| s.to_string()),
+            headers: HashMap::new(),
+            breaker: Arc::new(CircuitBreaker::new(0, Duration::ZERO)),
         }
     }
 
-    /// Send a non-streaming request and get the full response.
-    pub async fn create_response(&self, request: &Request) -> Result<Response> {
-        let url = format!("{}/v1/responses", self.base_url);
+    /// Extra headers attached to every request (provider-specific auth,
+    /// organization/project IDs, API versions).
+    pub fn with_headers(mut self, headers: HashMap<String, String>) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Set the total request timeout for non-streaming calls and the
+    /// connect timeout for all calls. Streaming requests get no read timeout.
+    pub fn with_timeouts(mut self, timeout: Duration, connect_timeout: Duration) -> Self {
+        self.http = reqwest::Client::builder()
+            .timeout(timeout)
+            .connect_timeout(connect_timeout)
+            .build()
+            .unwrap_or_default();
+        self.stream_http = reqwest::Client::builder()
+            .connect_timeout(connect_timeout)
+            .build()
+            .unwrap_or_default();
+        self
+    }
 
-        let mut req = self.http.post(&url).json(request);
+    /// Fail fast after `threshold` consecutive provider failures, probing
+    /// again once `cooldown` has elapsed. A threshold of 0 disables it.
+    pub fn with_circuit_breaker(mut self, threshold: u32, cooldown: Duration) -> Self {
+        self.breaker = Arc::new(CircuitBreaker::new(threshold, cooldown));
+        self
+    }
+
+    /// Current circuit breaker state for this provider.
+    pub fn circuit_state(&self) -> CircuitState {
+        self.breaker.state()
+    }
 
+    /// Build a POST request with auth and default headers applied.
+    fn post(&self, url: &str) -> reqwest::RequestBuilder {
+        self.with_auth(self.http.post(url))
+    }
+
+    fn with_auth(&self, mut req: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
         if let Some(key) = &self.api_key {
             req = req.header("Authorization", format!("Bearer {key}"));
         }
+        for (name, value) in &self.headers {
+            req = req.header(name, value);
+        }
+        req
+    }
+
+    /// Send a non-streaming request and get the full response.
+    pub async fn create_response(&self, request: &Request) -> Result<Response> {
+        let permit = match self.breaker.try_acquire() {
+            Ok(permit) => permit,
+            Err(remaining) => {
+                return Err(LlmError::Other(format!(
+                    "Provider circuit open after repeated failures; retry in {}s",
+                    remaining.as_secs()
+                ))
+                .into())
+            }
+        };
+
+        let url = format!("{}/v1/responses", self.base_url);
+
+        let req = self.post(&url).json(request);
 
         debug!("POST {url} model={}", request.model);
 
-        let resp = req.send().await?;
+        let resp = match req.send().await {
+            Ok(r) => r,
+            Err(e) => {
+                permit.failure();
+                if e.is_timeout() {
+                    return Err(LlmError::Timeout(format!("request to {url}")).into());
+                }
+                return Err(NekoError::Http(e));
+            }
+        };
 
         if !resp.status().is_success() {
             let status = resp.status();
+            // Only provider-side trouble counts against the breaker;
+            // a 4xx means the provider is up and rejected our request.
+            if provider_fault(status) {
+                permit.failure();
+            } else {
+                permit.success();
+            }
             let body = resp.text().await.unwrap_or_default();
-            return Err(NekoError::Llm(format!(
-                "API returned {status}: {body}"
-            )));
+            return Err(LlmError::from_status(status.as_u16(), &body).into());
         }
 
-        let response: Response = resp.json().await?;
+        let response: Response = match resp.json().await {
+            Ok(r) => r,
+            Err(e) => {
+                permit.failure();
+                return Err(NekoError::Http(e));
+            }
+        };
+        permit.success();
         Ok(response)
     }
 
+    /// Embed `input` with `model`, returning one vector per input in order.
+    /// Not counted against the circuit breaker — it guards chat turns.
+    pub async fn create_embeddings(&self, model: &str, input: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let url = format!("{}/v1/embeddings", self.base_url);
+        let count = input.len();
+        let request = EmbeddingRequest {
+            model: model.to_string(),
+            input,
+        };
+
+        debug!("POST {url} model={model} inputs={count}");
+
+        let resp = self.post(&url).json(&request).send().await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(LlmError::from_status(status.as_u16(), &body).into());
+        }
+
+        let mut response: EmbeddingResponse = resp.json().await?;
+        if response.data.len() != count {
+            return Err(LlmError::Other(format!(
+                "Embeddings API returned {} vectors for {count} inputs",
+                response.data.len()
+            ))
+            .into());
+        }
+        response.data.sort_by_key(|e| e.index);
+        Ok(response.data.into_iter().map(|e| e.embedding).collect())
+    }
+
     /// Send a streaming request, returning a channel of stream events.
     pub async fn create_response_stream(
         &self,
         request: &Request,
     ) -> Result<mpsc::Receiver<StreamEvent>> {
-        let url = format!("{}/v1/responses", self.base_url);
+        let permit = match self.breaker.try_acquire() {
+            Ok(permit) => permit,
+            Err(remaining) => {
+                return Err(LlmError::Other(format!(
+                    "Provider circuit open after repeated failures; retry in {}s",
+                    remaining.as_secs()
+                ))
+                .into())
+            }
+        };
 
-        let mut req_builder = self.http.post(&url).json(request);
+        let url = format!("{}/v1/responses", self.base_url);
 
-        if let Some(key) = &self.api_key {
-            req_builder = req_builder.header("Authorization", format!("Bearer {key}"));
-        }
+        let req_builder = self.with_auth(self.stream_http.post(&url)).json(request);
 
         debug!("POST {url} (streaming) model={}", request.model);
 
         let (tx, rx) = mpsc::channel(256);
 
         let mut es = EventSource::new(req_builder)
-            .map_err(|e| NekoError::Llm(format!("Failed to create event source: {e}")))?;
+            .map_err(|e| LlmError::Other(format!("Failed to create event source: {e}")))?;
 
         tokio::spawn(async move {
+            // Settled once the provider answers; dropping it unsettled (the
+            // receiver went away first) just frees a half-open probe.
+            let mut permit = Some(permit);
             while let Some(event) = es.next().await {
                 match event {
                     Ok(Event::Open) => {
                         debug!("SSE stream opened");
+                        if let Some(permit) = permit.take() {
+                            permit.success();
+                        }
                     }
                     Ok(Event::Message(msg)) => {
                         if msg.data == "[DONE]" {
@@ -91,6 +225,16 @@ impl Client {
                     }
                     Err(e) => {
                         error!("SSE error: {e}");
+                        if let Some(permit) = permit.take() {
+                            match &e {
+                                reqwest_eventsource::Error::InvalidStatusCode(status, _)
+                                    if !provider_fault(*status) =>
+                                {
+                                    permit.success()
+                                }
+                                _ => permit.failure(),
+                            }
+                        }
                         break;
                     }
                 }
@@ -102,6 +246,12 @@ impl Client {
     }
 }
 
+/// Whether an error status means the provider is in trouble. Only that
+/// counts against the breaker; a 4xx means it is up and rejected our request.
+fn provider_fault(status: reqwest::StatusCode) -> bool {
+    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -114,6 +264,16 @@ mod tests {
         assert_eq!(client.api_key.as_deref(), Some("sk-test"));
     }
 
+    #[test]
+    fn test_default_headers_applied() {
+        let mut headers = HashMap::new();
+        headers.insert("OpenAI-Project".to_string(), "proj-1".to_string());
+        let client = Client::new("https://api.openai.com/", Some("sk-test")).with_headers(headers);
+        let req = client.post("https://api.openai.com/v1/responses").build().unwrap();
+        assert_eq!(req.headers().get("OpenAI-Project").unwrap(), "proj-1");
+        assert_eq!(req.headers().get("Authorization").unwrap(), "Bearer sk-test");
+    }
+
     #[test]
     fn test_request_serialization() {
         let req = Request {
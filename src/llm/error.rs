@@ -0,0 +1,126 @@
+use thiserror::Error;
+
+use super::types::ApiError;
+
+/// Provider failure, classified so callers can tell a retryable hiccup from
+/// a request that will never succeed as sent.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum LlmError {
+    /// 429 — back off and retry.
+    #[error("rate limited: {0}")]
+    RateLimited(String),
+    /// 5xx — the provider is having trouble; retryable.
+    #[error("server error: {0}")]
+    ServerError(String),
+    /// The request was rejected as malformed; retrying won't help.
+    #[error("bad request: {0}")]
+    BadRequest(String),
+    /// The input doesn't fit the model's context window; shorten the
+    /// history and retry.
+    #[error("context length exceeded: {0}")]
+    ContextLengthExceeded(String),
+    /// 401/403 — bad or missing API key.
+    #[error("authentication failed: {0}")]
+    AuthFailed(String),
+    #[error("timed out: {0}")]
+    Timeout(String),
+    /// Anything else (circuit open, malformed response, ...).
+    #[error("{0}")]
+    Other(String),
+}
+
+impl LlmError {
+    /// Classify a non-2xx HTTP response from the provider.
+    pub fn from_status(status: u16, body: &str) -> Self {
+        let (code, message) = parse_error_body(body);
+        let detail = format!("{status}: {message}");
+        if is_context_length(code.as_deref(), &message) {
+            return Self::ContextLengthExceeded(detail);
+        }
+        match status {
+            429 => Self::RateLimited(detail),
+            401 | 403 => Self::AuthFailed(detail),
+            408 => Self::Timeout(detail),
+            500..=599 => Self::ServerError(detail),
+            400..=499 => Self::BadRequest(detail),
+            _ => Self::Other(detail),
+        }
+    }
+
+    /// Classify the `error` of a response that came back with
+    /// `status: "failed"`.
+    pub fn from_api_error(error: &ApiError) -> Self {
+        let message = error.message.clone();
+        if is_context_length(Some(&error.code), &message) {
+            return Self::ContextLengthExceeded(message);
+        }
+        match error.code.as_str() {
+            "rate_limit_exceeded" => Self::RateLimited(message),
+            "server_error" => Self::ServerError(message),
+            "invalid_request_error" | "invalid_prompt" => Self::BadRequest(message),
+            _ => Self::Other(message),
+        }
+    }
+
+    /// Worth retrying the same request later.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::RateLimited(_) | Self::ServerError(_) | Self::Timeout(_))
+    }
+}
+
+/// Pull `error.code` and `error.message` out of an OpenAI-style error body,
+/// falling back to the raw body.
+fn parse_error_body(body: &str) -> (Option<String>, String) {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(body) else {
+        return (None, body.trim().to_string());
+    };
+    let error = &value["error"];
+    let code = error["code"].as_str().or(error["type"].as_str()).map(String::from);
+    let message = error["message"]
+        .as_str()
+        .map(String::from)
+        .unwrap_or_else(|| body.trim().to_string());
+    (code, message)
+}
+
+fn is_context_length(code: Option<&str>, message: &str) -> bool {
+    if code == Some("context_length_exceeded") {
+        return true;
+    }
+    let message = message.to_lowercase();
+    message.contains("context length")
+        || message.contains("context window")
+        || message.contains("maximum context")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_status() {
+        assert!(matches!(LlmError::from_status(429, "slow down"), LlmError::RateLimited(_)));
+        assert!(matches!(LlmError::from_status(401, ""), LlmError::AuthFailed(_)));
+        assert!(matches!(LlmError::from_status(503, "busy"), LlmError::ServerError(_)));
+        assert_eq!(
+            LlmError::from_status(
+                400,
+                r#"{"error": {"message": "Unknown parameter", "type": "invalid_request_error"}}"#
+            ),
+            LlmError::BadRequest("400: Unknown parameter".to_string())
+        );
+        assert!(matches!(
+            LlmError::from_status(
+                400,
+                r#"{"error": {"message": "too long", "code": "context_length_exceeded"}}"#
+            ),
+            LlmError::ContextLengthExceeded(_)
+        ));
+        assert!(matches!(
+            LlmError::from_status(400, "This model's maximum context length is 8192 tokens"),
+            LlmError::ContextLengthExceeded(_)
+        ));
+        assert!(LlmError::from_status(502, "").is_retryable());
+        assert!(!LlmError::from_status(400, "").is_retryable());
+    }
+}
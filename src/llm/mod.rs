@@ -1,5 +1,9 @@
+pub mod breaker;
 pub mod client;
+pub mod error;
 pub mod types;
 
+pub use breaker::CircuitState;
 pub use client::Client;
+pub use error::LlmError;
 pub use types::*;
@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 /// OpenResponses-compatible request
@@ -42,7 +44,7 @@ pub enum Input {
 pub enum Item {
     Message {
         role: Role,
-        content: String,
+        content: MessageContent,
     },
     FunctionCall {
         id: String,
@@ -108,11 +110,11 @@ impl<'de> Deserialize<'de> for Item {
                     value.get("role").cloned().unwrap_or_default(),
                 )
                 .map_err(serde::de::Error::custom)?;
-                let content = value
-                    .get("content")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or_default()
-                    .to_string();
+                let content = match value.get("content") {
+                    Some(content) => serde_json::from_value(content.clone())
+                        .map_err(serde::de::Error::custom)?,
+                    None => MessageContent::default(),
+                };
                 Ok(Item::Message { role, content })
             }
             "function_call" => {
@@ -251,20 +253,112 @@ impl<'de> Deserialize<'de> for OutputItem {
 }
 
 // ---------------------------------------------------------------------------
-// ContentPart — content within a message output item
+// MessageContent — the content of an input message
 // ---------------------------------------------------------------------------
 
+/// A message's `content`: a plain string, or a list of parts for
+/// multimodal input (`input_text` + `input_image`). Transcripts written
+/// before parts existed hold strings, which still load as `Text`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+impl MessageContent {
+    /// The message's text; parts without text (images) are left out.
+    pub fn text(&self) -> Cow<'_, str> {
+        match self {
+            MessageContent::Text(text) => Cow::Borrowed(text),
+            MessageContent::Parts(parts) => Cow::Owned(
+                parts
+                    .iter()
+                    .filter_map(ContentPart::text)
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+        }
+    }
+
+    pub fn has_images(&self) -> bool {
+        matches!(self, MessageContent::Parts(parts)
+            if parts.iter().any(|p| matches!(p, ContentPart::InputImage { .. })))
+    }
+}
+
+impl Default for MessageContent {
+    fn default() -> Self {
+        MessageContent::Text(String::new())
+    }
+}
+
+impl From<String> for MessageContent {
+    fn from(text: String) -> Self {
+        MessageContent::Text(text)
+    }
+}
+
+impl From<&str> for MessageContent {
+    fn from(text: &str) -> Self {
+        MessageContent::Text(text.to_string())
+    }
+}
+
+impl std::fmt::Display for MessageContent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// ContentPart — content within a message
+// ---------------------------------------------------------------------------
+
+/// A content part. `InputText`/`InputImage` go into input messages;
+/// `OutputText`/`Refusal` come back in output messages.
 #[derive(Debug, Clone)]
 pub enum ContentPart {
+    InputText { text: String },
+    /// `image_url` is an `https://` URL or a `data:<mime>;base64,...` URL.
+    /// `detail` is `low`, `high` or `auto` (the API default when unset).
+    InputImage {
+        image_url: String,
+        detail: Option<String>,
+    },
     OutputText { text: String },
     Refusal { refusal: String },
     Other(serde_json::Value),
 }
 
+impl ContentPart {
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            ContentPart::InputText { text } | ContentPart::OutputText { text } => Some(text),
+            _ => None,
+        }
+    }
+}
+
 impl Serialize for ContentPart {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         use serde::ser::SerializeMap;
         match self {
+            ContentPart::InputText { text } => {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("type", "input_text")?;
+                map.serialize_entry("text", text)?;
+                map.end()
+            }
+            ContentPart::InputImage { image_url, detail } => {
+                let mut map = serializer.serialize_map(Some(3))?;
+                map.serialize_entry("type", "input_image")?;
+                map.serialize_entry("image_url", image_url)?;
+                if let Some(detail) = detail {
+                    map.serialize_entry("detail", detail)?;
+                }
+                map.end()
+            }
             ContentPart::OutputText { text } => {
                 let mut map = serializer.serialize_map(Some(2))?;
                 map.serialize_entry("type", "output_text")?;
@@ -288,6 +382,15 @@ impl<'de> Deserialize<'de> for ContentPart {
         let part_type = value.get("type").and_then(|v| v.as_str()).unwrap_or("");
 
         match part_type {
+            "input_text" => {
+                let text = str_field(&value, "text");
+                Ok(ContentPart::InputText { text })
+            }
+            "input_image" => {
+                let image_url = str_field(&value, "image_url");
+                let detail = value.get("detail").and_then(|v| v.as_str()).map(str::to_string);
+                Ok(ContentPart::InputImage { image_url, detail })
+            }
             "output_text" => {
                 let text = str_field(&value, "text");
                 Ok(ContentPart::OutputText { text })
@@ -305,7 +408,7 @@ impl<'de> Deserialize<'de> for ContentPart {
 // Usage / Error
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct Usage {
     #[serde(default)]
     pub input_tokens: u32,
@@ -430,6 +533,28 @@ impl Response {
     }
 }
 
+// ---------------------------------------------------------------------------
+// Embeddings
+// ---------------------------------------------------------------------------
+
+/// `POST /v1/embeddings` request
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbeddingRequest {
+    pub model: String,
+    pub input: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmbeddingResponse {
+    pub data: Vec<Embedding>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Embedding {
+    pub index: usize,
+    pub embedding: Vec<f32>,
+}
+
 // ---------------------------------------------------------------------------
 // Internal helpers
 // ---------------------------------------------------------------------------
@@ -441,3 +566,47 @@ fn str_field(value: &serde_json::Value, key: &str) -> String {
         .unwrap_or_default()
         .to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_content_round_trip() {
+        // Transcripts from before content parts still load.
+        let old: Item =
+            serde_json::from_str(r#"{"type":"message","role":"user","content":"hi"}"#).unwrap();
+        let Item::Message { content, .. } = &old else {
+            panic!("expected a message");
+        };
+        assert!(matches!(content, MessageContent::Text(t) if t == "hi"));
+        assert_eq!(
+            serde_json::to_value(&old).unwrap(),
+            serde_json::json!({"type": "message", "role": "user", "content": "hi"})
+        );
+
+        let multimodal = Item::Message {
+            role: Role::User,
+            content: MessageContent::Parts(vec![
+                ContentPart::InputText { text: "What is this?".to_string() },
+                ContentPart::InputImage {
+                    image_url: "data:image/png;base64,AAAA".to_string(),
+                    detail: None,
+                },
+            ]),
+        };
+        let json = serde_json::to_value(&multimodal).unwrap();
+        assert_eq!(
+            json["content"],
+            serde_json::json!([
+                {"type": "input_text", "text": "What is this?"},
+                {"type": "input_image", "image_url": "data:image/png;base64,AAAA"}
+            ])
+        );
+        let Item::Message { content, .. } = serde_json::from_value(json).unwrap() else {
+            panic!("expected a message");
+        };
+        assert!(content.has_images());
+        assert_eq!(content.text(), "What is this?");
+    }
+}
@@ -0,0 +1,79 @@
+//! Response-language hints: detect the language of an inbound message and
+//! normalize user-supplied `/lang` values to a language name.
+
+use whatlang::Lang;
+
+/// Messages shorter than this are too ambiguous to detect reliably
+/// ("ok", "thanks!", a URL...).
+const MIN_DETECT_CHARS: usize = 20;
+
+/// Detect the language of `text`, returning its English name (e.g.
+/// "Spanish"). Returns `None` for short or ambiguous text.
+pub fn detect_language(text: &str) -> Option<String> {
+    if text.trim().chars().count() < MIN_DETECT_CHARS {
+        return None;
+    }
+    let info = whatlang::detect(text)?;
+    if !info.is_reliable() {
+        return None;
+    }
+    Some(info.lang().eng_name().to_string())
+}
+
+/// Normalize a `/lang` argument — an ISO 639-1/639-3 code or a language
+/// name — to its English name. Unknown values are kept as given so users
+/// can still ask for e.g. "Klingon" or "British English".
+pub fn language_name(input: &str) -> String {
+    let input = input.trim();
+    let lower = input.to_lowercase();
+    let lang = match lower.as_str() {
+        "en" => Some(Lang::Eng),
+        "es" => Some(Lang::Spa),
+        "fr" => Some(Lang::Fra),
+        "de" => Some(Lang::Deu),
+        "it" => Some(Lang::Ita),
+        "pt" => Some(Lang::Por),
+        "nl" => Some(Lang::Nld),
+        "ru" => Some(Lang::Rus),
+        "uk" => Some(Lang::Ukr),
+        "pl" => Some(Lang::Pol),
+        "tr" => Some(Lang::Tur),
+        "ar" => Some(Lang::Ara),
+        "hi" => Some(Lang::Hin),
+        "ja" => Some(Lang::Jpn),
+        "ko" => Some(Lang::Kor),
+        "zh" => Some(Lang::Cmn),
+        _ => Lang::from_code(&lower).or_else(|| {
+            Lang::all()
+                .iter()
+                .copied()
+                .find(|l| l.eng_name().eq_ignore_ascii_case(&lower) || l.name() == input)
+        }),
+    };
+    match lang {
+        Some(lang) => lang.eng_name().to_string(),
+        None => input.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_name() {
+        assert_eq!(language_name("es"), "Spanish");
+        assert_eq!(language_name("deu"), "German");
+        assert_eq!(language_name("french"), "French");
+        assert_eq!(language_name("Klingon"), "Klingon");
+    }
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(
+            detect_language("¿Dónde está la biblioteca? Necesito encontrar un libro."),
+            Some("Spanish".to_string())
+        );
+        assert_eq!(detect_language("ok thanks"), None);
+    }
+}
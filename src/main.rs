@@ -1,13 +1,14 @@
+use std::io::IsTerminal;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use chrono::{DateTime, Utc};
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+#[cfg(feature = "server")]
 use tokio::sync::mpsc;
-use tracing::info;
+use tracing::{info, warn};
 use tracing_subscriber::EnvFilter;
 
-use neko::channels::Channel;
 use neko::config::Config;
 use neko::error::{NekoError, Result};
 
@@ -18,6 +19,22 @@ struct Cli {
     #[arg(short, long)]
     config: Option<PathBuf>,
 
+    /// Only log errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Log more (-v debug, -vv trace). RUST_LOG takes precedence.
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Disable colored output (also honors NO_COLOR)
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    /// Only print ASCII symbols
+    #[arg(long, global = true)]
+    ascii: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -31,9 +48,19 @@ enum Commands {
         interactive: bool,
     },
     /// Start the gateway server
-    Start,
+    Start {
+        /// Run in the background, logging to ~/.neko/neko.log
+        #[arg(short, long)]
+        detach: bool,
+    },
     /// Stop the running gateway
     Stop,
+    /// Stop the running gateway, wait for it to exit, then start it again
+    Restart {
+        /// Run the new gateway in the background
+        #[arg(short, long)]
+        detach: bool,
+    },
     /// Show gateway status
     Status,
     /// Show recent logs
@@ -46,6 +73,23 @@ enum Commands {
     Message {
         /// The message text to send
         text: String,
+        /// Continue the persistent session with this key instead of
+        /// starting from an empty history
+        #[arg(short, long)]
+        session: Option<String>,
+        /// Give up after this many seconds (exits with status 124)
+        #[arg(short, long)]
+        timeout: Option<u64>,
+    },
+    /// Time N identical turns against the configured model (no tools,
+    /// fresh history each) and report latency and token usage
+    Bench {
+        /// Number of turns
+        #[arg(short = 'n', long, default_value_t = 5)]
+        turns: usize,
+        /// Prompt sent on every turn
+        #[arg(short, long, default_value = "Reply with a one-sentence summary of what you can do.")]
+        prompt: String,
     },
     /// Config management
     Config {
@@ -54,11 +98,17 @@ enum Commands {
     },
     /// Session management
     Sessions {
+        /// Named agent (`[[agents]]`) whose workspace to use
+        #[arg(long, global = true)]
+        agent: Option<String>,
         #[command(subcommand)]
         action: SessionAction,
     },
     /// Memory management
     Memory {
+        /// Named agent (`[[agents]]`) whose workspace to use
+        #[arg(long, global = true)]
+        agent: Option<String>,
         #[command(subcommand)]
         action: MemoryAction,
     },
@@ -69,9 +119,29 @@ enum Commands {
     },
     /// Cron job management
     Cron {
+        /// Named agent (`[[agents]]`) whose workspace to use
+        #[arg(long, global = true)]
+        agent: Option<String>,
         #[command(subcommand)]
         action: CronAction,
     },
+    /// Workspace maintenance
+    Workspace {
+        /// Named agent (`[[agents]]`) whose workspace to use
+        #[arg(long, global = true)]
+        agent: Option<String>,
+        #[command(subcommand)]
+        action: WorkspaceAction,
+    },
+    /// Print the OpenAPI 3 spec for the HTTP API
+    ExportOpenapi {
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Show version information. With --verbose, also the commit,
+    /// toolchain and config/workspace paths (for bug reports)
+    Version,
 }
 
 #[derive(Subcommand)]
@@ -82,87 +152,126 @@ enum ConfigAction {
     Edit,
 }
 
+/// `--format` for the list commands. Without it, each prints its usual
+/// output (tab-separated for sessions and cron).
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum ListFormat {
+    /// Aligned columns
+    Table,
+    Json,
+    /// One tab-separated line per entry
+    Tsv,
+}
+
 #[derive(Subcommand)]
 enum SessionAction {
     /// List active sessions
-    List,
+    List {
+        #[arg(long, value_enum)]
+        format: Option<ListFormat>,
+    },
     /// Clear all sessions
-    Clear,
+    Clear {
+        /// Also delete recall logs and reset MEMORY.md, so nothing from
+        /// past conversations reaches the next turn
+        #[arg(long)]
+        hard: bool,
+        /// Skip the confirmation prompt for --hard
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Check transcripts against sessions.json
+    Verify {
+        /// Delete orphaned transcripts, drop sessions whose transcript is
+        /// missing and remove unparseable transcript lines
+        #[arg(long)]
+        repair: bool,
+    },
+    /// Delete sessions with no activity for a while
+    Prune {
+        /// Remove sessions last updated longer ago than this (e.g. 30d, 12h, 2w)
+        #[arg(long, default_value = "30d")]
+        older_than: String,
+        /// List what would be deleted without removing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 #[derive(Subcommand)]
 enum MemoryAction {
     /// List memory files
     List,
+    /// Show memory size, recall log range and core-memory budget
+    Stats,
     /// Search memory files for a query
     Search {
         /// Text to search for (case-insensitive)
         query: String,
     },
+    /// Restore a memory file from a backup made by memory_write
+    Restore {
+        /// Memory filename (e.g. MEMORY.md)
+        file: String,
+        /// Backup name to restore (defaults to the most recent)
+        #[arg(short, long)]
+        backup: Option<String>,
+        /// List available backups instead of restoring
+        #[arg(short, long)]
+        list: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum WorkspaceAction {
+    /// Prune archived transcripts, old recall logs, rotated cron history and .tmp files
+    Gc {
+        /// Only remove files older than this (e.g. 30d, 12h, 2w)
+        #[arg(long, default_value = "30d")]
+        older_than: String,
+        /// List what would be deleted without removing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 #[derive(Subcommand)]
 enum SkillAction {
     /// List installed skills
-    List,
+    List {
+        #[arg(long, value_enum)]
+        format: Option<ListFormat>,
+    },
     /// Install a skill from a path
     Install {
         /// Path to a SKILL.md file or a directory containing one
         path: String,
     },
+    /// Check a skill without installing it
+    Validate {
+        /// Path to a SKILL.md file or a directory containing one
+        path: String,
+    },
     /// Remove an installed skill
     Remove {
         /// Skill name to remove
         name: String,
     },
-    /// Reload and list all skills
+    /// Re-scan skills and tell the running gateway to pick them up
     Reload,
 }
 
 #[derive(Subcommand)]
 enum CronAction {
     /// List all cron jobs
-    List,
-    /// Add a new cron job
-    Add {
-        /// The prompt to send to the agent
-        prompt: String,
-        /// Cron expression (e.g. "0 0 9 * * *" for daily at 9am)
-        #[arg(short, long)]
-        schedule: Option<String>,
-        /// One-shot datetime (e.g. "2026-02-17 09:00")
-        #[arg(long)]
-        at: Option<String>,
-        /// Human-readable name for the job
-        #[arg(short, long)]
-        name: Option<String>,
-        /// Announce results to a channel (e.g. "telegram:123456")
-        #[arg(long)]
-        announce: Option<String>,
-        /// Keep one-shot jobs after execution
-        #[arg(long)]
-        keep_after_run: bool,
+    List {
+        #[arg(long, value_enum)]
+        format: Option<ListFormat>,
     },
+    /// Add a new cron job
+    Add(CronAddArgs),
     /// Edit an existing cron job
-    Edit {
-        /// Job ID or name
-        id: String,
-        /// Update the prompt
-        #[arg(short, long)]
-        prompt: Option<String>,
-        /// Update the cron schedule
-        #[arg(short, long)]
-        schedule: Option<String>,
-        /// Update the name
-        #[arg(short, long)]
-        name: Option<String>,
-        /// Enable or disable the job
-        #[arg(short, long)]
-        enabled: Option<bool>,
-        /// Set announce target (e.g. "telegram:123456"), or "none" to clear
-        #[arg(long)]
-        announce: Option<String>,
-    },
+    Edit(CronEditArgs),
     /// Remove a cron job
     Remove {
         /// Job ID or name
@@ -176,12 +285,84 @@ enum CronAction {
     },
 }
 
+#[derive(Args)]
+struct CronAddArgs {
+    /// The prompt to send to the agent
+    prompt: String,
+    /// Cron expression (e.g. "0 0 9 * * *" for daily at 9am)
+    #[arg(short, long)]
+    schedule: Option<String>,
+    /// Named schedule instead of an expression (times are UTC): hourly,
+    /// daily-9am-utc, weekdays-8am-utc, weekly-monday-9am-utc
+    #[arg(long, conflicts_with_all = ["schedule", "at"])]
+    preset: Option<String>,
+    /// One-shot datetime (e.g. "2026-02-17 09:00")
+    #[arg(long)]
+    at: Option<String>,
+    /// Human-readable name for the job
+    #[arg(short, long)]
+    name: Option<String>,
+    /// Announce results to a channel (e.g. "telegram:123456")
+    #[arg(long)]
+    announce: Option<String>,
+    /// Keep one-shot jobs after execution
+    #[arg(long)]
+    keep_after_run: bool,
+    /// Run in a persistent session with this key so each run sees the
+    /// previous ones
+    #[arg(long)]
+    session: Option<String>,
+    /// Delay each run by a random 0..=N seconds to spread out jobs
+    /// scheduled for the same minute (at most 3600)
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u32).range(0..=3600))]
+    jitter: Option<u32>,
+}
+
+#[derive(Args)]
+struct CronEditArgs {
+    /// Job ID or name
+    id: String,
+    /// Update the prompt
+    #[arg(short, long)]
+    prompt: Option<String>,
+    /// Update the cron schedule
+    #[arg(short, long)]
+    schedule: Option<String>,
+    /// Update the schedule to a named preset (see `cron add --preset`)
+    #[arg(long, conflicts_with = "schedule")]
+    preset: Option<String>,
+    /// Update the name
+    #[arg(short, long)]
+    name: Option<String>,
+    /// Enable or disable the job
+    #[arg(short, long)]
+    enabled: Option<bool>,
+    /// Set announce target (e.g. "telegram:123456"), or "none" to clear
+    #[arg(long)]
+    announce: Option<String>,
+    /// Set the persistent session key, or "none" to run statelessly
+    #[arg(long)]
+    session: Option<String>,
+    /// Set the random start delay in seconds (0 to disable, at most 3600)
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u32).range(0..=3600))]
+    jitter: Option<u32>,
+}
+
 #[tokio::main]
 async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
+    neko::output::init(neko::output::Style::detect(cli.no_color, cli.ascii));
 
     // Initialize tracing — file + stderr for `start`, stderr only otherwise
-    init_tracing(matches!(&cli.command, Commands::Start))?;
+    let with_file = matches!(
+        &cli.command,
+        Commands::Start { detach: false } | Commands::Restart { detach: false }
+    );
+    init_tracing(
+        with_file,
+        log_level(cli.quiet, cli.verbose, with_file),
+        neko::output::color_allowed(cli.no_color),
+    )?;
 
     match cli.command {
         Commands::Init { interactive } => {
@@ -191,11 +372,22 @@ async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
                 cmd_init()?;
             }
         }
-        Commands::Start => cmd_start(&cli.config).await?,
+        Commands::Start { detach: false } => cmd_start(&cli.config).await?,
+        Commands::Start { detach: true } => {
+            cmd_start_detached(&cli.config, cli.quiet, cli.verbose)?
+        }
         Commands::Stop => cmd_stop()?,
-        Commands::Status => cmd_status().await?,
+        Commands::Restart { detach } => {
+            cmd_restart(&cli.config, detach, cli.quiet, cli.verbose).await?
+        }
+        Commands::Status => cmd_status(&cli.config).await?,
         Commands::Logs { lines } => cmd_logs(lines)?,
-        Commands::Message { text } => cmd_message(&cli.config, &text).await?,
+        Commands::Message {
+            text,
+            session,
+            timeout,
+        } => cmd_message(&cli.config, &text, session.as_deref(), timeout).await?,
+        Commands::Bench { turns, prompt } => cmd_bench(&cli.config, turns, &prompt).await?,
         Commands::Config { action } => match action {
             ConfigAction::Show => {
                 let path = cli.config.unwrap_or_else(Config::default_path);
@@ -208,41 +400,60 @@ async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
                 std::process::Command::new(editor).arg(&path).status()?;
             }
         },
-        Commands::Sessions { action } => match action {
-            SessionAction::List => cmd_sessions_list(&cli.config).await?,
-            SessionAction::Clear => cmd_sessions_clear(&cli.config).await?,
-        },
-        Commands::Memory { action } => match action {
-            MemoryAction::List => cmd_memory_list(&cli.config)?,
-            MemoryAction::Search { query } => cmd_memory_search(&cli.config, &query)?,
-        },
+        Commands::Sessions { agent, action } => {
+            let agent = agent.as_deref();
+            match action {
+                SessionAction::List { format } => {
+                    cmd_sessions_list(&cli.config, agent, format).await?
+                }
+                SessionAction::Clear { hard, yes } => {
+                    cmd_sessions_clear(&cli.config, agent, hard, yes).await?
+                }
+                SessionAction::Verify { repair } => {
+                    cmd_sessions_verify(&cli.config, agent, repair)?
+                }
+                SessionAction::Prune {
+                    older_than,
+                    dry_run,
+                } => cmd_sessions_prune(&cli.config, agent, &older_than, dry_run).await?,
+            }
+        }
+        Commands::Memory { agent, action } => {
+            let agent = agent.as_deref();
+            match action {
+                MemoryAction::List => cmd_memory_list(&cli.config, agent)?,
+                MemoryAction::Stats => cmd_memory_stats(&cli.config, agent)?,
+                MemoryAction::Search { query } => cmd_memory_search(&cli.config, agent, &query)?,
+                MemoryAction::Restore { file, backup, list } => {
+                    cmd_memory_restore(&cli.config, agent, &file, backup.as_deref(), list)?
+                }
+            }
+        }
         Commands::Skills { action } => match action {
-            SkillAction::List => cmd_skills_list(&cli.config)?,
+            SkillAction::List { format } => cmd_skills_list(&cli.config, format)?,
             SkillAction::Install { path } => cmd_skills_install(&cli.config, &path)?,
+            SkillAction::Validate { path } => cmd_skills_validate(&cli.config, &path)?,
             SkillAction::Remove { name } => cmd_skills_remove(&cli.config, &name)?,
-            SkillAction::Reload => cmd_skills_list(&cli.config)?,
+            SkillAction::Reload => cmd_skills_reload(&cli.config)?,
         },
-        Commands::Cron { action } => match action {
-            CronAction::List => cmd_cron_list(&cli.config)?,
-            CronAction::Add {
-                prompt,
-                schedule,
-                at,
-                name,
-                announce,
-                keep_after_run,
-            } => cmd_cron_add(&cli.config, &prompt, schedule, at, name, announce, keep_after_run)?,
-            CronAction::Edit {
-                id,
-                prompt,
-                schedule,
-                name,
-                enabled,
-                announce,
-            } => cmd_cron_edit(&cli.config, &id, prompt, schedule, name, enabled, announce)?,
-            CronAction::Remove { id } => cmd_cron_remove(&cli.config, &id)?,
-            CronAction::History { lines } => cmd_cron_history(&cli.config, lines)?,
+        Commands::Cron { agent, action } => {
+            let agent = agent.as_deref();
+            match action {
+                CronAction::List { format } => cmd_cron_list(&cli.config, agent, format)?,
+                CronAction::Add(args) => cmd_cron_add(&cli.config, agent, args)?,
+                CronAction::Edit(args) => cmd_cron_edit(&cli.config, agent, args)?,
+                CronAction::Remove { id } => cmd_cron_remove(&cli.config, agent, &id)?,
+                CronAction::History { lines } => cmd_cron_history(&cli.config, agent, lines)?,
+            }
+        }
+        Commands::Workspace { agent, action } => match action {
+            WorkspaceAction::Gc {
+                older_than,
+                dry_run,
+            } => cmd_workspace_gc(&cli.config, agent.as_deref(), &older_than, dry_run)?,
         },
+        Commands::ExportOpenapi { output } => cmd_export_openapi(output)?,
+        Commands::Version => cmd_version(&cli.config, cli.verbose > 0),
     }
 
     Ok(())
@@ -262,6 +473,12 @@ fn pid_file_path() -> PathBuf {
     neko_dir().join("neko.pid")
 }
 
+/// Held with an exclusive flock for as long as the gateway runs. Kept
+/// apart from the PID file, which is replaced atomically on write.
+fn lock_file_path() -> PathBuf {
+    neko_dir().join("neko.lock")
+}
+
 fn log_file_path() -> PathBuf {
     neko_dir().join("neko.log")
 }
@@ -277,6 +494,21 @@ fn load_config(path: &Option<PathBuf>) -> Result<Config> {
     Config::load(&config_path)
 }
 
+/// [`load_config`], switched to the named agent's profile (`--agent`) so
+/// its own workspace is used.
+fn load_agent_config(path: &Option<PathBuf>, agent: Option<&str>) -> Result<Config> {
+    let config = load_config(path)?;
+    let Some(name) = agent.filter(|name| *name != "default") else {
+        return Ok(config);
+    };
+    let profile = config
+        .agents
+        .iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| NekoError::Config(format!("Unknown agent '{name}'")))?;
+    Ok(config.for_profile(profile))
+}
+
 fn is_process_running(pid: u32) -> bool {
     std::process::Command::new("kill")
         .args(["-0", &pid.to_string()])
@@ -287,31 +519,97 @@ fn is_process_running(pid: u32) -> bool {
         .unwrap_or(false)
 }
 
-/// Read PID and bind address from the PID file.
-/// Format: line 1 = PID, line 2 = bind address.
-fn read_pid_file() -> Option<(u32, String)> {
+/// Contents of the PID file written by `neko start`.
+struct PidFile {
+    pid: u32,
+    bind: String,
+    /// Absent in PID files written by older versions.
+    started_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Read the PID file.
+/// Format: line 1 = PID, line 2 = bind address, line 3 = start time (RFC 3339).
+fn read_pid_file() -> Option<PidFile> {
     let content = std::fs::read_to_string(pid_file_path()).ok()?;
     let mut lines = content.lines();
     let pid: u32 = lines.next()?.trim().parse().ok()?;
     let bind = lines.next().unwrap_or("127.0.0.1:3000").trim().to_string();
-    Some((pid, bind))
+    let started_at = lines
+        .next()
+        .and_then(|l| chrono::DateTime::parse_from_rfc3339(l.trim()).ok())
+        .map(|t| t.to_utc());
+    Some(PidFile {
+        pid,
+        bind,
+        started_at,
+    })
+}
+
+/// Take the gateway lock, failing with `busy` (given the running gateway's
+/// " (PID n)", if known) while a gateway holds it. The lock is released when
+/// the returned file is dropped (or the process dies).
+fn acquire_gateway_lock(busy: impl FnOnce(&str) -> String) -> Result<std::fs::File> {
+    let path = lock_file_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)?;
+    match file.try_lock() {
+        Ok(()) => Ok(file),
+        Err(std::fs::TryLockError::WouldBlock) => {
+            let running = read_pid_file()
+                .map(|p| format!(" (PID {})", p.pid))
+                .unwrap_or_default();
+            Err(NekoError::Config(busy(&running)))
+        }
+        Err(std::fs::TryLockError::Error(e)) => Err(e.into()),
+    }
+}
+
+/// Take the gateway lock, failing if another `neko start` holds it.
+#[cfg(feature = "server")]
+fn acquire_start_lock() -> Result<std::fs::File> {
+    acquire_gateway_lock(|running| {
+        format!("Neko is already running{running}. Use `neko stop` first.")
+    })
 }
 
-fn init_tracing(with_file: bool) -> std::result::Result<(), Box<dyn std::error::Error>> {
+/// Default log level from `-q`/`-v`: the gateway logs at info, one-shot
+/// commands at warn.
+fn log_level(quiet: bool, verbose: u8, with_file: bool) -> &'static str {
+    match (quiet, verbose) {
+        (true, _) => "error",
+        (false, 0) if with_file => "info",
+        (false, 0) => "warn",
+        (false, 1) => "debug",
+        (false, _) => "trace",
+    }
+}
+
+fn init_tracing(
+    with_file: bool,
+    level: &str,
+    color: bool,
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
     use tracing_subscriber::layer::SubscriberExt;
     use tracing_subscriber::util::SubscriberInitExt;
 
-    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
-        if with_file {
-            "info".into()
-        } else {
-            "warn".into()
-        }
-    });
+    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| level.into());
 
-    let stderr_layer = tracing_subscriber::fmt::layer();
+    let stderr_layer =
+        tracing_subscriber::fmt::layer().with_ansi(color && std::io::stderr().is_terminal());
 
-    if with_file {
+    // A detached gateway's stderr is the log file already
+    if std::env::var_os(DETACHED_ENV).is_some() {
+        tracing_subscriber::registry()
+            .with(env_filter)
+            .with(stderr_layer.with_ansi(false))
+            .init();
+    } else if with_file {
         let dir = neko_dir();
         let _ = std::fs::create_dir_all(&dir);
         let file = std::fs::OpenOptions::new()
@@ -350,37 +648,147 @@ async fn build_agent_from_config(config: &Config) -> Result<neko::agent::Agent>
         })?;
 
     let workspace = config.workspace_path();
-    let skills = neko::skills::load_skills(&workspace)?;
+    let skills = neko::skills::load_skills(&workspace, &config.skills.extra_dirs())?;
 
     let mut registry = neko::tools::ToolRegistry::new();
     neko::tools::register_core_tools(&mut registry, &config.tools);
+    registry.register(Box::new(neko::tools::skill_search::SkillSearchTool::new(
+        config.skills.extra_dirs(),
+    )));
+    if config.tools.send_message.enabled {
+        if config.tools.send_message.admins.is_empty() {
+            warn!(
+                "send_message is enabled but tools.send_message.admins is empty, \
+                 so no sender may use it (\"*\" allows anyone)"
+            );
+        }
+        let mut channels = Vec::new();
+        if cfg!(feature = "telegram") && config.channels.telegram.as_ref().is_some_and(|tg| tg.enabled) {
+            channels.push("telegram".to_string());
+        }
+        registry.register(Box::new(neko::tools::send_message::SendMessageTool::new(
+            channels,
+            config.tools.send_message.admins.clone(),
+        )));
+    }
+
+    let embedder = build_embedder(config)?;
+    if let Some(embedder) = &embedder {
+        registry.register(Box::new(
+            neko::tools::memory_search::MemorySearchTool::default().with_embedder(embedder.clone()),
+        ));
+    }
+
+    // Built-in tool schemas are our own bug — refuse to start on them
+    let problems = registry.lint_schemas();
+    if !problems.is_empty() {
+        return Err(NekoError::Tool(format!(
+            "Invalid tool parameter schemas:\n  {}",
+            problems.join("\n  ")
+        )));
+    }
 
-    let mcp_clients = neko::mcp::connect_all(&config.mcp).await?;
-    for client in &mcp_clients {
-        let mcp_tools = client.list_tools().await?;
-        for tool_def in &mcp_tools {
-            registry.register(Box::new(neko::mcp::McpTool::new(tool_def, client.clone())));
+    #[cfg(feature = "mcp")]
+    {
+        use neko::tools::Tool;
+
+        let mcp_clients = neko::mcp::connect_all(&config.mcp).await?;
+        for client in &mcp_clients {
+            let mcp_tools = client.list_tools().await?;
+            for tool_def in &mcp_tools {
+                let tool = neko::mcp::McpTool::new(tool_def, client.clone());
+                let problems = neko::tools::schema_lint::lint_schema(&tool.parameters_schema());
+                if !problems.is_empty() {
+                    warn!(
+                        "Skipping MCP tool '{}': invalid parameter schema ({})",
+                        tool_def.name,
+                        problems.join("; ")
+                    );
+                    continue;
+                }
+                registry.register(Box::new(tool));
+            }
         }
     }
+    #[cfg(not(feature = "mcp"))]
+    if !config.mcp.is_empty() {
+        warn!("[mcp] servers are configured, but neko was built without the `mcp` feature");
+    }
+
+    registry.set_descriptions(config.tools.descriptions.clone());
+    for name in registry.unknown_descriptions() {
+        warn!("[tools.descriptions] names unknown tool '{name}'");
+    }
 
-    let llm_client = neko::llm::Client::new(&provider.base_url, provider.api_key.as_deref());
+    let api_key = provider.resolve_key()?;
+    let llm_client = neko::llm::Client::new(&provider.base_url, api_key.as_deref())
+        .with_headers(provider.headers.clone())
+        .with_timeouts(
+            std::time::Duration::from_secs(provider.timeout_secs),
+            std::time::Duration::from_secs(provider.connect_timeout_secs),
+        )
+        .with_circuit_breaker(
+            provider.circuit_breaker_threshold,
+            std::time::Duration::from_secs(provider.circuit_breaker_cooldown_secs),
+        );
 
     let tool_count = registry.names().len();
     info!(
         "Agent ready: provider={}, model={}, tools={}, skills={}",
         config.agent.provider,
-        config.agent.model,
+        provider.resolve_model(&config.agent.model),
         tool_count,
         skills.len(),
     );
 
+    let file_root = config.file_root_path()?;
+    if let Some(root) = &file_root {
+        std::fs::create_dir_all(root)?;
+    }
+
     Ok(
         neko::agent::Agent::new(llm_client, registry, config.agent.clone())
+            .with_model_map(provider.model_map.clone())
             .with_workspace(workspace)
-            .with_skills(skills),
+            .with_file_root(file_root)
+            .with_internal_paths(config.tools.allow_internal_paths)
+            .with_workspace_quota(config.workspace_quota_bytes())
+            .with_max_tool_output(config.tools.max_output_bytes)
+            .with_embedder(embedder)
+            .with_skills(skills)
+            .with_skills_config(config.skills.clone()),
     )
 }
 
+/// Embeddings client for semantic memory search, if `[agent.memory]
+/// embeddings` is enabled.
+fn build_embedder(config: &Config) -> Result<Option<Arc<neko::memory_index::Embedder>>> {
+    let memory = &config.agent.memory;
+    if !memory.embeddings {
+        return Ok(None);
+    }
+    let provider_name = memory
+        .embedding_provider
+        .as_deref()
+        .unwrap_or(&config.agent.provider);
+    let provider = config.providers.get(provider_name).ok_or_else(|| {
+        NekoError::Config(format!(
+            "Embedding provider '{provider_name}' not found in config"
+        ))
+    })?;
+    let api_key = provider.resolve_key()?;
+    let client = neko::llm::Client::new(&provider.base_url, api_key.as_deref())
+        .with_headers(provider.headers.clone())
+        .with_timeouts(
+            std::time::Duration::from_secs(provider.timeout_secs),
+            std::time::Duration::from_secs(provider.connect_timeout_secs),
+        );
+    Ok(Some(Arc::new(neko::memory_index::Embedder::new(
+        client,
+        provider.resolve_model(&memory.embedding_model).to_string(),
+    ))))
+}
+
 fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<()> {
     std::fs::create_dir_all(dst)?;
     for entry in std::fs::read_dir(src)? {
@@ -488,7 +896,7 @@ fn cmd_init_interactive() -> Result<()> {
             .prompt()
             .map_err(|e| NekoError::Config(format!("Prompt cancelled: {e}")))?;
 
-        let users = Text::new("Allowed Telegram user IDs (comma-separated):")
+        let users = Text::new("Allowed Telegram users (IDs or @usernames, comma-separated):")
             .with_default("")
             .with_help_message("Leave empty to allow all users")
             .prompt()
@@ -510,7 +918,18 @@ fn cmd_init_interactive() -> Result<()> {
             let users_array = if telegram_users.trim().is_empty() {
                 "[]".to_string()
             } else {
-                let ids: Vec<&str> = telegram_users.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()).collect();
+                let ids: Vec<String> = telegram_users
+                    .split(',')
+                    .map(|s| s.trim())
+                    .filter(|s| !s.is_empty())
+                    .map(|s| {
+                        if s.parse::<i64>().is_ok() {
+                            s.to_string()
+                        } else {
+                            format!("\"{s}\"")
+                        }
+                    })
+                    .collect();
                 format!("[{}]", ids.join(", "))
             };
             format!(
@@ -585,10 +1004,7 @@ interval_secs = 3600
 
     let memory_md = memory_dir.join("MEMORY.md");
     if !memory_md.exists() {
-        std::fs::write(
-            &memory_md,
-            "# Memory\n\nThis file is always loaded into the agent's context.\n",
-        )?;
+        std::fs::write(&memory_md, neko::workspace::DEFAULT_MEMORY_MD)?;
         println!("Created {}", memory_md.display());
     }
 
@@ -620,10 +1036,7 @@ fn cmd_init() -> Result<()> {
 
     let memory_md = memory_dir.join("MEMORY.md");
     if !memory_md.exists() {
-        std::fs::write(
-            &memory_md,
-            "# Memory\n\nThis file is always loaded into the agent's context.\n",
-        )?;
+        std::fs::write(&memory_md, neko::workspace::DEFAULT_MEMORY_MD)?;
         println!("Created {}", memory_md.display());
     }
 
@@ -631,11 +1044,27 @@ fn cmd_init() -> Result<()> {
     Ok(())
 }
 
+#[cfg(not(feature = "server"))]
+fn missing_feature(feature: &str, what: &str) -> NekoError {
+    NekoError::Config(format!(
+        "{what} needs the `{feature}` feature; rebuild with `cargo build --release --features full`"
+    ))
+}
+
+#[cfg(not(feature = "server"))]
+async fn cmd_start(_config_path: &Option<PathBuf>) -> Result<()> {
+    Err(missing_feature("server", "`neko start`"))
+}
+
+#[cfg(feature = "server")]
 async fn cmd_start(config_path: &Option<PathBuf>) -> Result<()> {
     let config = load_config(config_path)?;
 
+    // Held until the server exits, so a concurrent start fails here
+    let _start_lock = acquire_start_lock()?;
+
     // Check if already running
-    if let Some((pid, _)) = read_pid_file() {
+    if let Some(PidFile { pid, .. }) = read_pid_file() {
         if is_process_running(pid) {
             return Err(NekoError::Config(format!(
                 "Neko is already running (PID {pid}). Use `neko stop` first."
@@ -653,84 +1082,129 @@ async fn cmd_start(config_path: &Option<PathBuf>) -> Result<()> {
     let sessions_dir = workspace.join("sessions");
     let _ = std::fs::create_dir_all(&sessions_dir);
 
+    // Daily token budget shared by every agent, session and cron job
+    let daily_budget = config
+        .gateway
+        .daily_token_budget
+        .map(|limit| Arc::new(neko::budget::DailyBudget::load(&workspace, limit)));
+    if let Some(budget) = &daily_budget {
+        info!("Daily token budget: {} of {} used today", budget.used_today(), budget.limit());
+    }
+
     // Build agent
-    let agent = Arc::new(build_agent_from_config(&config).await?);
+    let agent = Arc::new(
+        build_agent_from_config(&config)
+            .await?
+            .with_daily_budget(daily_budget.clone()),
+    );
 
     // Build session store
+    let llm_permits = agent.llm_permits();
     let session_store = Arc::new(neko::session::SessionStore::new(
         sessions_dir,
         config.session.clone(),
     ));
     session_store.load_from_disk().await?;
 
+    // Outbound channel — shared between Telegram, tools and cron scheduler.
+    // Created unconditionally so the cron scheduler can always announce.
+    let (outbound_tx, outbound_rx) = mpsc::channel::<neko::channels::OutboundMessage>(64);
+
     // Build gateway
     let config_arc = Arc::new(config.clone());
-    let gateway = Arc::new(neko::gateway::Gateway::new(
+    let mut gateway = neko::gateway::Gateway::new(
         agent,
         session_store.clone(),
         config_arc.clone(),
-    ));
+    )
+    .with_daily_budget(daily_budget.clone())
+    .with_config_path(config_path.clone().unwrap_or_else(Config::default_path));
+    for profile in &config.agents {
+        let profile_config = config.for_profile(profile);
+        let profile_workspace = profile_config.workspace_path();
+        std::fs::create_dir_all(profile_workspace.join("memory"))?;
+        // One LLM semaphore for every agent: max_concurrent_requests is a
+        // gateway-wide limit
+        let named = build_agent_from_config(&profile_config)
+            .await?
+            .with_daily_budget(daily_budget.clone())
+            .with_llm_permits(llm_permits.clone());
+        // Each agent keeps its conversations in its own workspace
+        let profile_sessions = Arc::new(neko::session::SessionStore::new(
+            profile_workspace.join("sessions"),
+            profile_config.session.clone(),
+        ));
+        profile_sessions.load_from_disk().await?;
+        info!(
+            "Agent '{}' ready: workspace={}, routes={:?}",
+            profile.name,
+            profile_workspace.display(),
+            profile.routes
+        );
+        gateway = gateway.with_agent(
+            profile.name.clone(),
+            profile.routes.clone(),
+            Arc::new(named),
+            profile_sessions,
+        );
+    }
+    let telegram_enabled = config.channels.telegram.as_ref().is_some_and(|tg| tg.enabled);
+    // Only Telegram drains the outbound queue today
+    let cron_outbound_tx = (telegram_enabled && cfg!(feature = "telegram")).then(|| outbound_tx.clone());
+    if cron_outbound_tx.is_some() {
+        gateway = gateway.with_outbound(outbound_tx.clone());
+    }
+    let gateway = Arc::new(gateway);
 
-    // Outbound channel — shared between Telegram and cron scheduler.
-    // Created unconditionally so the cron scheduler can always announce.
-    let (outbound_tx, outbound_rx) = mpsc::channel::<neko::channels::OutboundMessage>(64);
-    let mut cron_outbound_tx: Option<mpsc::Sender<neko::channels::OutboundMessage>> = None;
+    // `neko skills reload` sends SIGHUP to pick up edited skills
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let gateway = gateway.clone();
+        match signal(SignalKind::hangup()) {
+            Ok(mut hangup) => {
+                tokio::spawn(async move {
+                    while hangup.recv().await.is_some() {
+                        gateway.reload_skills();
+                    }
+                });
+            }
+            Err(e) => warn!("Cannot listen for SIGHUP; skills reload needs a restart: {e}"),
+        }
+    }
 
     // Start Telegram channel if configured
-    if let Some(ref tg_config) = config.channels.telegram {
-        if tg_config.enabled {
-            let tg_channel = neko::channels::telegram::TelegramChannel::new(tg_config.clone())?;
-            let (inbound_tx, mut inbound_rx) = mpsc::channel::<neko::channels::InboundMessage>(64);
-
-            // Clone outbound_tx for the message handler before moving outbound_rx
-            let outbound_tx_handler = outbound_tx.clone();
-            cron_outbound_tx = Some(outbound_tx.clone());
-
-            // Spawn Telegram polling loop
-            tokio::spawn(async move {
-                if let Err(e) = tg_channel.start(inbound_tx, outbound_rx).await {
-                    tracing::error!("Telegram channel error: {e}");
-                }
-            });
-
-            // Spawn message handler: inbound → gateway → outbound
-            let gw = gateway.clone();
-            tokio::spawn(async move {
-                while let Some(inbound) = inbound_rx.recv().await {
-                    let gw = gw.clone();
-                    let tx = outbound_tx_handler.clone();
-                    tokio::spawn(async move {
-                        match gw.handle_message(inbound).await {
-                            Ok(outbound) => {
-                                if let Err(e) = tx.send(outbound).await {
-                                    tracing::error!("Failed to send outbound: {e}");
-                                }
-                            }
-                            Err(e) => {
-                                tracing::error!("Gateway error: {e}");
-                            }
-                        }
-                    });
-                }
-            });
-
-            info!("Telegram channel started");
-        }
+    #[cfg(feature = "telegram")]
+    if let Some(tg_config) = config.channels.telegram.as_ref().filter(|tg| tg.enabled) {
+        start_telegram(
+            tg_config,
+            &config,
+            &workspace,
+            gateway.clone(),
+            outbound_tx.clone(),
+            outbound_rx,
+        )?;
+    }
+    #[cfg(not(feature = "telegram"))]
+    if telegram_enabled {
+        drop(outbound_rx);
+        tracing::warn!("Telegram is enabled in config, but neko was built without the `telegram` feature");
     }
 
-    // Start cron scheduler
+    // Start a cron scheduler per agent, for the jobs in its workspace
     let cron_jobs = neko::cron::load_jobs(&workspace).unwrap_or_default();
-    neko::cron::spawn_scheduler(
-        gateway.agent.clone(),
-        workspace.clone(),
-        cron_outbound_tx,
-    );
+    for handle in gateway.agents() {
+        neko::cron::spawn_scheduler(
+            handle.agent.clone(),
+            handle.agent.workspace().to_path_buf(),
+            handle.sessions.clone(),
+            cron_outbound_tx.clone(),
+        );
+    }
 
     // Build HTTP server
-    let state = Arc::new(neko::api::AppState {
-        gateway,
-        api_token,
-    });
+    let state = Arc::new(neko::api::AppState::new(gateway, api_token));
 
     let app = neko::api::router(state);
 
@@ -742,16 +1216,40 @@ async fn cmd_start(config_path: &Option<PathBuf>) -> Result<()> {
         NekoError::Config(format!("Failed to get local address: {e}"))
     })?;
 
-    // Write PID file (PID + bind address)
+    // Write PID file (PID + bind address + start time), atomically so
+    // `neko status`/`stop` never read a half-written file
     let pid = std::process::id();
-    std::fs::write(
-        pid_file_path(),
-        format!("{pid}\n{local_addr}\n"),
+    let started_at = chrono::Utc::now().to_rfc3339();
+    neko::fs_util::write_atomic(
+        &pid_file_path(),
+        format!("{pid}\n{local_addr}\n{started_at}\n").as_bytes(),
     )?;
 
     println!("Neko v{} started", env!("CARGO_PKG_VERSION"));
     println!("  Bind:      {local_addr}");
-    println!("  Workspace: {}", workspace.display());
+    let workspace_size = neko::workspace::dir_size(&workspace);
+    match config.workspace_quota_bytes() {
+        Some(max) => {
+            println!(
+                "  Workspace: {} ({} of {})",
+                workspace.display(),
+                neko::workspace::format_bytes(workspace_size),
+                neko::workspace::format_bytes(max)
+            );
+            if workspace_size > max {
+                tracing::warn!(
+                    "Workspace exceeds quota ({} > {}); file writes will be refused",
+                    neko::workspace::format_bytes(workspace_size),
+                    neko::workspace::format_bytes(max)
+                );
+            }
+        }
+        None => println!(
+            "  Workspace: {} ({})",
+            workspace.display(),
+            neko::workspace::format_bytes(workspace_size)
+        ),
+    }
     println!(
         "  Provider:  {} ({})",
         config.agent.provider, config.agent.model
@@ -767,24 +1265,93 @@ async fn cmd_start(config_path: &Option<PathBuf>) -> Result<()> {
     }
     println!();
     println!("Press Ctrl+C to stop.");
+    // Listener bound, channels and cron running
+    #[cfg(feature = "systemd")]
+    neko::systemd::notify("READY=1");
 
     let shutdown = async {
-        tokio::signal::ctrl_c().await.ok();
+        shutdown_signal().await;
         println!("\nShutting down...");
+        #[cfg(feature = "systemd")]
+        neko::systemd::notify("STOPPING=1");
     };
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown)
-        .await
-        .map_err(|e| NekoError::Config(format!("Server error: {e}")))?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown)
+    .await
+    .map_err(|e| NekoError::Config(format!("Server error: {e}")))?;
 
     let _ = std::fs::remove_file(pid_file_path());
     println!("Neko stopped.");
     Ok(())
 }
 
+/// Run the Telegram channel: poll for messages, route them through the
+/// gateway and deliver replies (plus tool/cron messages) from `outbound_rx`.
+#[cfg(feature = "telegram")]
+fn start_telegram(
+    tg_config: &neko::config::TelegramConfig,
+    config: &Config,
+    workspace: &Path,
+    gateway: Arc<neko::gateway::Gateway>,
+    outbound_tx: mpsc::Sender<neko::channels::OutboundMessage>,
+    outbound_rx: mpsc::Receiver<neko::channels::OutboundMessage>,
+) -> Result<()> {
+    use neko::channels::Channel;
+
+    // Inbound files land where the handling agent's file tools can open them
+    let router = gateway.clone();
+    let inbox: neko::channels::InboxRouter =
+        Arc::new(move |ids: &[&str]| router.inbox_for("telegram", ids));
+    let tg_channel = neko::channels::telegram::TelegramChannel::new(tg_config.clone())?
+        .with_workspace(workspace.to_path_buf())
+        .with_inbox(inbox)
+        .with_workspace_quota(config.workspace_quota_bytes());
+    let (inbound_tx, mut inbound_rx) = mpsc::channel::<neko::channels::InboundMessage>(64);
+
+    // Spawn Telegram polling loop
+    tokio::spawn(async move {
+        if let Err(e) = tg_channel.start(inbound_tx, outbound_rx).await {
+            tracing::error!("Telegram channel error: {e}");
+        }
+    });
+
+    // Spawn message handler: inbound → gateway → outbound
+    tokio::spawn(async move {
+        while let Some(inbound) = inbound_rx.recv().await {
+            let gw = gateway.clone();
+            let tx = outbound_tx.clone();
+            tokio::spawn(async move {
+                match gw.handle_message(inbound).await {
+                    Ok(outbound) => {
+                        if let Err(e) = tx.send(outbound).await {
+                            tracing::error!("Failed to send outbound: {e}");
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!("Gateway error: {e}");
+                    }
+                }
+            });
+        }
+    });
+
+    info!("Telegram channel started");
+    Ok(())
+}
+
+/// How long `neko stop` waits for the gateway to exit before giving up.
+const STOP_WAIT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// How long `neko restart` waits; the gateway must be gone before a new one
+/// can bind the same address.
+const RESTART_STOP_WAIT: std::time::Duration = std::time::Duration::from_secs(30);
+
 fn cmd_stop() -> Result<()> {
-    let Some((pid, _)) = read_pid_file() else {
+    let Some(PidFile { pid, .. }) = read_pid_file() else {
         println!("Neko is not running (no PID file found).");
         return Ok(());
     };
@@ -795,72 +1362,271 @@ fn cmd_stop() -> Result<()> {
         return Ok(());
     }
 
-    // Send SIGTERM
-    let status = std::process::Command::new("kill")
-        .args(["-TERM", &pid.to_string()])
-        .stdout(std::process::Stdio::null())
-        .stderr(std::process::Stdio::null())
-        .status();
-
-    match status {
-        Ok(s) if s.success() => {
-            println!("Sent stop signal to Neko (PID {pid}).");
-            // Wait briefly for process to exit, then clean up PID file
-            for _ in 0..10 {
-                std::thread::sleep(std::time::Duration::from_millis(200));
-                if !is_process_running(pid) {
-                    let _ = std::fs::remove_file(pid_file_path());
-                    println!("Neko stopped.");
-                    return Ok(());
-                }
-            }
-            println!("Process {pid} still running. It may take a moment to shut down.");
-        }
-        _ => {
-            println!("Failed to send stop signal to PID {pid}.");
-        }
+    if !send_sigterm(pid) {
+        println!("Failed to send stop signal to PID {pid}.");
+        return Ok(());
+    }
+    println!("Sent stop signal to Neko (PID {pid}).");
+    if wait_for_exit(pid, STOP_WAIT) {
+        println!("Neko stopped.");
+    } else {
+        println!("Process {pid} still running. It may take a moment to shut down.");
     }
 
     Ok(())
 }
 
-async fn cmd_status() -> Result<()> {
-    let Some((pid, bind)) = read_pid_file() else {
-        println!("Neko is not running.");
-        return Ok(());
-    };
-
-    if !is_process_running(pid) {
-        let _ = std::fs::remove_file(pid_file_path());
-        println!("Neko is not running (stale PID file, cleaned up).");
-        return Ok(());
-    }
-
-    // Try health endpoint
-    let url = format!("http://{bind}/health");
-    match reqwest::get(&url).await {
-        Ok(resp) if resp.status().is_success() => {
-            println!("Neko is running (PID {pid}) on {bind}");
-            if let Ok(body) = resp.text().await {
-                println!("  Health: {body}");
+async fn cmd_restart(
+    config_path: &Option<PathBuf>,
+    detach: bool,
+    quiet: bool,
+    verbose: u8,
+) -> Result<()> {
+    // Validate the config before taking the running gateway down
+    load_config(config_path)?;
+
+    match read_pid_file() {
+        Some(PidFile { pid, .. }) if is_process_running(pid) => {
+            if !send_sigterm(pid) {
+                return Err(NekoError::Config(format!(
+                    "Failed to send stop signal to PID {pid}; not restarting."
+                )));
             }
+            println!("Stopping Neko (PID {pid})...");
+            if !wait_for_exit(pid, RESTART_STOP_WAIT) {
+                return Err(NekoError::Config(format!(
+                    "Neko (PID {pid}) did not exit within {}s; not starting a second instance.",
+                    RESTART_STOP_WAIT.as_secs()
+                )));
+            }
+            println!("Neko stopped.");
         }
-        _ => {
-            println!("Neko process is running (PID {pid}) but health check failed on {bind}");
+        Some(_) => {
+            let _ = std::fs::remove_file(pid_file_path());
         }
+        None => println!("Neko was not running."),
     }
 
-    Ok(())
+    if detach {
+        cmd_start_detached(config_path, quiet, verbose)
+    } else {
+        cmd_start(config_path).await
+    }
 }
 
-fn cmd_logs(num_lines: usize) -> Result<()> {
-    let path = log_file_path();
+/// Set on the background process spawned by `neko start --detach`; its
+/// stdout/stderr already go to the log file.
+const DETACHED_ENV: &str = "NEKO_DETACHED";
 
-    if !path.exists() {
-        println!("No log file found at {}", path.display());
-        println!("Start the server first: neko start");
-        return Ok(());
-    }
+/// How long `neko start --detach` waits for the background gateway to
+/// write its PID file.
+#[cfg(feature = "server")]
+const DETACH_STARTUP_WAIT: std::time::Duration = std::time::Duration::from_secs(10);
+
+#[cfg(not(feature = "server"))]
+fn cmd_start_detached(_config_path: &Option<PathBuf>, _quiet: bool, _verbose: u8) -> Result<()> {
+    Err(missing_feature("server", "`neko start`"))
+}
+
+/// Re-run `neko start` as a background process with output appended to the
+/// log file, and return once it is up.
+#[cfg(feature = "server")]
+fn cmd_start_detached(config_path: &Option<PathBuf>, quiet: bool, verbose: u8) -> Result<()> {
+    // Fail here, not silently in the background
+    load_config(config_path)?;
+    if let Some(PidFile { pid, .. }) = read_pid_file() {
+        if is_process_running(pid) {
+            return Err(NekoError::Config(format!(
+                "Neko is already running (PID {pid}). Use `neko stop` first."
+            )));
+        }
+    }
+
+    std::fs::create_dir_all(neko_dir())?;
+    let log = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_file_path())?;
+
+    let mut command = std::process::Command::new(std::env::current_exe()?);
+    if let Some(path) = config_path {
+        command.arg("--config").arg(path);
+    }
+    if quiet {
+        command.arg("--quiet");
+    }
+    for _ in 0..verbose {
+        command.arg("--verbose");
+    }
+    command
+        .arg("start")
+        .env(DETACHED_ENV, "1")
+        .stdin(std::process::Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+    // Own process group, so Ctrl+C in this shell doesn't reach it
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    let mut child = command.spawn()?;
+    let pid = child.id();
+
+    let deadline = std::time::Instant::now() + DETACH_STARTUP_WAIT;
+    while std::time::Instant::now() < deadline {
+        if let Some(status) = child.try_wait()? {
+            return Err(NekoError::Config(format!(
+                "Neko exited during startup ({status}); see {}",
+                log_file_path().display()
+            )));
+        }
+        if read_pid_file().is_some_and(|p| p.pid == pid) {
+            println!("Neko started in the background (PID {pid}).");
+            println!("  Log: {}", log_file_path().display());
+            return Ok(());
+        }
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    }
+    println!(
+        "Neko is still starting in the background (PID {pid}); check `neko status` or {}",
+        log_file_path().display()
+    );
+    Ok(())
+}
+
+fn send_sigterm(pid: u32) -> bool {
+    send_signal(pid, "-TERM")
+}
+
+/// Resolve on Ctrl+C, or on SIGTERM (`neko stop`, systemd) where available.
+#[cfg(feature = "server")]
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(e) => warn!("Cannot listen for SIGTERM; only Ctrl+C stops gracefully: {e}"),
+        }
+    }
+    tokio::signal::ctrl_c().await.ok();
+}
+
+fn send_signal(pid: u32, signal: &str) -> bool {
+    std::process::Command::new("kill")
+        .args([signal, &pid.to_string()])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// Poll until `pid` exits (removing its PID file) or `timeout` passes.
+/// Returns whether it exited.
+fn wait_for_exit(pid: u32, timeout: std::time::Duration) -> bool {
+    let poll = std::time::Duration::from_millis(200);
+    let deadline = std::time::Instant::now() + timeout;
+    while std::time::Instant::now() < deadline {
+        std::thread::sleep(poll);
+        if !is_process_running(pid) {
+            let _ = std::fs::remove_file(pid_file_path());
+            return true;
+        }
+    }
+    false
+}
+
+async fn cmd_status(config_path: &Option<PathBuf>) -> Result<()> {
+    let Some(PidFile {
+        pid,
+        bind,
+        started_at,
+    }) = read_pid_file()
+    else {
+        println!("Neko is not running.");
+        print_workspace_usage(config_path);
+        return Ok(());
+    };
+
+    if !is_process_running(pid) {
+        let _ = std::fs::remove_file(pid_file_path());
+        println!("Neko is not running (stale PID file, cleaned up).");
+        print_workspace_usage(config_path);
+        return Ok(());
+    }
+
+    // Try health endpoint
+    let url = format!("http://{bind}/health");
+    match reqwest::get(&url).await {
+        Ok(resp) if resp.status().is_success() => {
+            println!(
+                "{} (PID {pid}) on {bind}",
+                neko::output::paint("Neko is running", neko::output::Color::Green)
+            );
+            if let Some(started_at) = started_at {
+                let uptime = (chrono::Utc::now() - started_at).to_std().unwrap_or_default();
+                println!(
+                    "  Started: {} (up {})",
+                    started_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S"),
+                    format_uptime(uptime)
+                );
+            }
+            if let Ok(body) = resp.text().await {
+                println!("  Health: {body}");
+            }
+        }
+        _ => {
+            println!(
+                "Neko process is running (PID {pid}) but {} on {bind}",
+                neko::output::paint("health check failed", neko::output::Color::Red)
+            );
+        }
+    }
+    print_workspace_usage(config_path);
+
+    Ok(())
+}
+
+/// Coarse uptime, e.g. "2d 3h", "5h 12m" or "4m".
+fn format_uptime(uptime: std::time::Duration) -> String {
+    let mins = uptime.as_secs() / 60;
+    let (days, hours, mins) = (mins / 1440, mins / 60 % 24, mins % 60);
+    match (days, hours) {
+        (0, 0) => format!("{mins}m"),
+        (0, _) => format!("{hours}h {mins}m"),
+        _ => format!("{days}d {hours}h"),
+    }
+}
+
+fn print_workspace_usage(config_path: &Option<PathBuf>) {
+    let Ok(config) = load_config(config_path) else {
+        return;
+    };
+    let workspace = config.workspace_path();
+    let used = neko::workspace::format_bytes(neko::workspace::dir_size(&workspace));
+    match config.workspace_quota_bytes() {
+        Some(max) => println!(
+            "  Workspace: {} ({used} of {})",
+            workspace.display(),
+            neko::workspace::format_bytes(max)
+        ),
+        None => println!("  Workspace: {} ({used})", workspace.display()),
+    }
+}
+
+fn cmd_logs(num_lines: usize) -> Result<()> {
+    let path = log_file_path();
+
+    if !path.exists() {
+        println!("No log file found at {}", path.display());
+        println!("Start the server first: neko start");
+        return Ok(());
+    }
 
     let content = std::fs::read_to_string(&path)?;
     let lines: Vec<&str> = content.lines().collect();
@@ -881,16 +1647,104 @@ fn cmd_logs(num_lines: usize) -> Result<()> {
     Ok(())
 }
 
-async fn cmd_message(config_path: &Option<PathBuf>, text: &str) -> Result<()> {
+async fn cmd_message(
+    config_path: &Option<PathBuf>,
+    text: &str,
+    session: Option<&str>,
+    timeout_secs: Option<u64>,
+) -> Result<()> {
     let config = load_config(config_path)?;
     let agent = build_agent_from_config(&config).await?;
-    let response = agent.run_turn(text).await?;
+
+    // A running gateway keeps its own copy of sessions.json and would drop
+    // a session written behind its back; hold the lock so none can start.
+    let _gateway_lock = match session {
+        Some(_) => Some(acquire_gateway_lock(|running| {
+            format!(
+                "The gateway is running{running} and owns the sessions. \
+                 Stop it first, or send the message over its HTTP API."
+            )
+        })?),
+        None => None,
+    };
+    let store = match session {
+        Some(_) => {
+            let sessions_dir = config.workspace_path().join("sessions");
+            std::fs::create_dir_all(&sessions_dir)?;
+            let store = neko::session::SessionStore::new(sessions_dir, config.session.clone());
+            store.load_from_disk().await?;
+            Some(store)
+        }
+        None => None,
+    };
+    let key = session.map(neko::session::SessionKey::cli);
+    let turn = agent.run_turn(text, store.as_ref().zip(key.as_ref()));
+    let response = match timeout_secs {
+        Some(secs) => match tokio::time::timeout(std::time::Duration::from_secs(secs), turn).await {
+            Ok(result) => result?,
+            Err(_) => {
+                eprintln!("Error: no response from the agent within {secs}s");
+                std::process::exit(124);
+            }
+        },
+        None => turn.await?,
+    };
     println!("{response}");
     Ok(())
 }
 
-fn cmd_memory_list(config_path: &Option<PathBuf>) -> Result<()> {
+async fn cmd_bench(config_path: &Option<PathBuf>, turns: usize, prompt: &str) -> Result<()> {
+    if turns == 0 {
+        return Err(NekoError::Config("--turns must be at least 1".to_string()));
+    }
     let config = load_config(config_path)?;
+    // Bench turns stay out of the recall log
+    let agent = build_agent_from_config(&config).await?.with_recall_log(false);
+    let overrides = neko::agent::TurnOverrides {
+        allowed_tools: Some(Vec::new()),
+        ..Default::default()
+    };
+
+    println!(
+        "Benchmarking {} ({}), {turns} turn(s)\n",
+        config.agent.provider, config.agent.model
+    );
+    let mut latencies = Vec::with_capacity(turns);
+    let (mut input_tokens, mut output_tokens) = (0u64, 0u64);
+    for turn in 1..=turns {
+        let started = std::time::Instant::now();
+        let result = agent
+            .run_turn_with_history(Vec::new(), prompt, None, None, None, &overrides)
+            .await?;
+        let elapsed = started.elapsed();
+        latencies.push(elapsed);
+        let usage = result.usage.as_ref();
+        let (input, output) = usage.map_or((0, 0), |u| (u.input_tokens, u.output_tokens));
+        input_tokens += u64::from(input);
+        output_tokens += u64::from(output);
+        println!(
+            "  Turn {turn:>3}: {:>7.2}s  {input} in / {output} out",
+            elapsed.as_secs_f64()
+        );
+    }
+
+    latencies.sort();
+    // Nearest-rank percentile over the sorted latencies
+    let percentile = |p: usize| latencies[(latencies.len() * p).div_ceil(100).max(1) - 1];
+    println!();
+    println!("  Min:     {:.2}s", latencies[0].as_secs_f64());
+    println!("  Median:  {:.2}s", percentile(50).as_secs_f64());
+    println!("  p95:     {:.2}s", percentile(95).as_secs_f64());
+    println!("  Max:     {:.2}s", latencies[latencies.len() - 1].as_secs_f64());
+    println!(
+        "  Tokens:  {} ({input_tokens} in / {output_tokens} out)",
+        input_tokens + output_tokens
+    );
+    Ok(())
+}
+
+fn cmd_memory_list(config_path: &Option<PathBuf>, agent: Option<&str>) -> Result<()> {
+    let config = load_agent_config(config_path, agent)?;
     let mem_dir = config.workspace_path().join("memory");
 
     if !mem_dir.exists() {
@@ -924,8 +1778,65 @@ fn cmd_memory_list(config_path: &Option<PathBuf>) -> Result<()> {
     Ok(())
 }
 
-fn cmd_memory_search(config_path: &Option<PathBuf>, query: &str) -> Result<()> {
-    let config = load_config(config_path)?;
+fn cmd_memory_stats(config_path: &Option<PathBuf>, agent: Option<&str>) -> Result<()> {
+    use neko::agent::context::{memory_files, MAX_CORE_MEMORY_CHARS};
+
+    let config = load_agent_config(config_path, agent)?;
+    let workspace = config.workspace_path();
+    if !workspace.join("memory").exists() {
+        eprintln!("Memory directory not found. Run `neko init` first.");
+        return Ok(());
+    }
+
+    // Same listing the system prompt is built from
+    let files = memory_files(&workspace);
+    let total: usize = files.iter().map(|(_, chars)| chars).sum();
+    let rows: Vec<Vec<String>> = files
+        .iter()
+        .map(|(path, chars)| vec![path.clone(), chars.to_string()])
+        .collect();
+    if !rows.is_empty() {
+        print!("{}", neko::output::table(&["FILE", "CHARS"], &rows));
+        println!();
+    }
+    println!(
+        "Total: {} files, {total} chars ({})",
+        files.len(),
+        neko::workspace::format_bytes(total as u64)
+    );
+
+    let mut recall_days: Vec<chrono::NaiveDate> = files
+        .iter()
+        .filter_map(|(path, _)| path.strip_prefix("memory/recall/")?.strip_suffix(".md"))
+        .filter_map(|day| chrono::NaiveDate::parse_from_str(day, "%Y-%m-%d").ok())
+        .collect();
+    recall_days.sort();
+    match (recall_days.first(), recall_days.last()) {
+        (Some(first), Some(last)) => {
+            println!("Recall: {} files, {first} to {last}", recall_days.len())
+        }
+        _ => println!("Recall: no files"),
+    }
+
+    match files.iter().find(|(path, _)| path == "memory/MEMORY.md") {
+        Some((_, chars)) if *chars > MAX_CORE_MEMORY_CHARS => println!(
+            "MEMORY.md: {chars}/{MAX_CORE_MEMORY_CHARS} chars ({} {})",
+            neko::output::symbol("\u{26a0}", "!"),
+            neko::output::paint("over budget, compact it", neko::output::Color::Yellow)
+        ),
+        Some((_, chars)) => println!("MEMORY.md: {chars}/{MAX_CORE_MEMORY_CHARS} chars"),
+        None => println!("MEMORY.md: missing"),
+    }
+
+    Ok(())
+}
+
+fn cmd_memory_search(
+    config_path: &Option<PathBuf>,
+    agent: Option<&str>,
+    query: &str,
+) -> Result<()> {
+    let config = load_agent_config(config_path, agent)?;
     let mem_dir = config.workspace_path().join("memory");
 
     if !mem_dir.exists() {
@@ -967,12 +1878,149 @@ fn cmd_memory_search(config_path: &Option<PathBuf>, query: &str) -> Result<()> {
     Ok(())
 }
 
-async fn cmd_sessions_list(config_path: &Option<PathBuf>) -> Result<()> {
-    let config = load_config(config_path)?;
+fn cmd_memory_restore(
+    config_path: &Option<PathBuf>,
+    agent: Option<&str>,
+    file: &str,
+    backup: Option<&str>,
+    list: bool,
+) -> Result<()> {
+    use neko::tools::memory_flush;
+
+    let config = load_agent_config(config_path, agent)?;
+    let mem_dir = config.workspace_path().join("memory");
+
+    if list {
+        let backups = memory_flush::list_backups(&mem_dir, file)?;
+        if backups.is_empty() {
+            println!("No backups found for {file}.");
+        }
+        for path in backups {
+            let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            println!("{name}\t{size} bytes");
+        }
+        return Ok(());
+    }
+
+    let restored = memory_flush::restore_backup(&mem_dir, file, backup)?;
+    println!(
+        "Restored memory/{file} from {}",
+        restored.file_name().unwrap_or_default().to_string_lossy()
+    );
+    Ok(())
+}
+
+fn cmd_version(config_path: &Option<PathBuf>, verbose: bool) {
+    println!("neko {}", env!("CARGO_PKG_VERSION"));
+    if !verbose {
+        return;
+    }
+
+    // vergen emits this placeholder when git wasn't available at build time
+    let known = |v: &'static str| if v == "VERGEN_IDEMPOTENT_OUTPUT" { "unknown" } else { v };
+    let dirty = if env!("VERGEN_GIT_DIRTY") == "true" { " (dirty)" } else { "" };
+    println!("commit:    {}{dirty}", known(env!("VERGEN_GIT_SHA")));
+    println!("rustc:     {}", known(env!("VERGEN_RUSTC_SEMVER")));
+    println!("target:    {}", known(env!("VERGEN_RUSTC_HOST_TRIPLE")));
+    println!("os:        {} {}", std::env::consts::OS, std::env::consts::ARCH);
+    // What this binary was built with, not what the config asks for
+    let features: Vec<&str> = [
+        ("server", cfg!(feature = "server")),
+        ("telegram", cfg!(feature = "telegram")),
+        ("mcp", cfg!(feature = "mcp")),
+        ("python", cfg!(feature = "python")),
+        ("systemd", cfg!(feature = "systemd")),
+    ]
+    .into_iter()
+    .filter_map(|(name, enabled)| enabled.then_some(name))
+    .collect();
+    let features = if features.is_empty() { "none".to_string() } else { features.join(", ") };
+    println!("features:  {features}");
+
+    let path = config_path.clone().unwrap_or_else(Config::default_path);
+    match Config::load(&path) {
+        Ok(config) => {
+            println!("config:    {}", path.display());
+            println!("workspace: {}", config.workspace_path().display());
+        }
+        Err(e) => println!("config:    {} ({e})", path.display()),
+    }
+}
+
+#[cfg(not(feature = "server"))]
+fn cmd_export_openapi(_output: Option<PathBuf>) -> Result<()> {
+    Err(missing_feature("server", "`neko export-openapi`"))
+}
+
+#[cfg(feature = "server")]
+fn cmd_export_openapi(output: Option<PathBuf>) -> Result<()> {
+    let json = serde_json::to_string_pretty(&neko::api::openapi::spec())?;
+    match output {
+        Some(path) => {
+            std::fs::write(&path, json + "\n")?;
+            println!("Wrote OpenAPI spec to {}", path.display());
+        }
+        None => println!("{json}"),
+    }
+    Ok(())
+}
+
+fn cmd_workspace_gc(
+    config_path: &Option<PathBuf>,
+    agent: Option<&str>,
+    older_than: &str,
+    dry_run: bool,
+) -> Result<()> {
+    use neko::workspace;
+
+    let config = load_agent_config(config_path, agent)?;
+    let age = workspace::parse_age(older_than)?;
+    let ws = config.workspace_path();
+    let entries = workspace::gc_candidates(&ws, age);
+
+    if entries.is_empty() {
+        println!("Nothing to clean up (older than {older_than}).");
+        return Ok(());
+    }
+
+    let total: u64 = entries.iter().map(|e| e.size).sum();
+    if dry_run {
+        for entry in &entries {
+            let rel = entry.path.strip_prefix(&ws).unwrap_or(&entry.path);
+            println!("{}\t{}", rel.display(), workspace::format_bytes(entry.size));
+        }
+        println!(
+            "\nWould remove {} files ({}).",
+            entries.len(),
+            workspace::format_bytes(total)
+        );
+        return Ok(());
+    }
+
+    let (removed, reclaimed) = workspace::gc_remove(&entries);
+    println!(
+        "Removed {removed} files, reclaimed {}.",
+        workspace::format_bytes(reclaimed)
+    );
+    Ok(())
+}
+
+async fn cmd_sessions_list(
+    config_path: &Option<PathBuf>,
+    agent: Option<&str>,
+    format: Option<ListFormat>,
+) -> Result<()> {
+    let config = load_agent_config(config_path, agent)?;
     let sessions_dir = config.workspace_path().join("sessions");
 
     if !sessions_dir.exists() {
-        println!("No sessions directory found.");
+        // Scripts parsing JSON get an empty list, not prose
+        if format == Some(ListFormat::Json) {
+            println!("[]");
+        } else {
+            println!("No sessions directory found.");
+        }
         return Ok(());
     }
 
@@ -980,11 +2028,35 @@ async fn cmd_sessions_list(config_path: &Option<PathBuf>) -> Result<()> {
     store.load_from_disk().await?;
 
     let metas = store.list().await;
+    if format == Some(ListFormat::Json) {
+        println!("{}", serde_json::to_string_pretty(&metas)?);
+        return Ok(());
+    }
     if metas.is_empty() {
         println!("No active sessions.");
         return Ok(());
     }
 
+    if format == Some(ListFormat::Table) {
+        let rows: Vec<Vec<String>> = metas
+            .iter()
+            .map(|meta| {
+                vec![
+                    meta.key.clone(),
+                    meta.session_id[..8].to_string(),
+                    meta.turn_count.to_string(),
+                    format!("{}/{}", meta.input_tokens, meta.output_tokens),
+                    meta.channel.clone().unwrap_or_else(|| "-".into()),
+                    meta.display_name.clone().unwrap_or_else(|| "-".into()),
+                    meta.updated_at.format("%Y-%m-%d %H:%M").to_string(),
+                ]
+            })
+            .collect();
+        let headers = ["KEY", "ID", "TURNS", "TOKENS IN/OUT", "CHANNEL", "NAME", "UPDATED"];
+        print!("{}", neko::output::table(&headers, &rows));
+        return Ok(());
+    }
+
     for meta in metas {
         let channel = meta.channel.as_deref().unwrap_or("-");
         let name = meta.display_name.as_deref().unwrap_or("-");
@@ -1004,8 +2076,100 @@ async fn cmd_sessions_list(config_path: &Option<PathBuf>) -> Result<()> {
     Ok(())
 }
 
-async fn cmd_sessions_clear(config_path: &Option<PathBuf>) -> Result<()> {
-    let config = load_config(config_path)?;
+async fn cmd_sessions_clear(
+    config_path: &Option<PathBuf>,
+    agent: Option<&str>,
+    hard: bool,
+    yes: bool,
+) -> Result<()> {
+    let config = load_agent_config(config_path, agent)?;
+    let workspace = config.workspace_path();
+    let sessions_dir = workspace.join("sessions");
+
+    if hard {
+        let recall_logs = neko::workspace::recall_logs(&workspace).len();
+        println!("This will permanently delete:");
+        println!("  - all session transcripts and sessions/sessions.json");
+        println!("  - {recall_logs} recall log(s) in memory/recall/");
+        println!("  - the contents of memory/MEMORY.md (reset to the default stub; a backup is kept in memory/.backups/)");
+        println!("Other memory files, memory/kv.json, skills and cron jobs are kept.");
+        if !yes {
+            let confirmed = inquire::Confirm::new("Continue?")
+                .with_default(false)
+                .prompt()
+                .map_err(|e| NekoError::Config(format!("Prompt cancelled: {e}")))?;
+            if !confirmed {
+                println!("Nothing deleted.");
+                return Ok(());
+            }
+        }
+    }
+
+    if sessions_dir.exists() {
+        let store = neko::session::SessionStore::new(sessions_dir, config.session.clone());
+        store.load_from_disk().await?;
+        store.clear_all().await?;
+        println!("All sessions cleared.");
+    } else {
+        println!("No sessions directory found.");
+    }
+
+    if hard {
+        let removed = neko::workspace::reset_memory(&workspace)?;
+        println!("Removed {removed} recall log(s) and reset memory/MEMORY.md.");
+    }
+    Ok(())
+}
+
+fn cmd_sessions_verify(
+    config_path: &Option<PathBuf>,
+    agent: Option<&str>,
+    repair: bool,
+) -> Result<()> {
+    let config = load_agent_config(config_path, agent)?;
+    let sessions_dir = config.workspace_path().join("sessions");
+
+    if !sessions_dir.exists() {
+        println!("No sessions directory found.");
+        return Ok(());
+    }
+
+    let report = neko::session::verify_sessions(&sessions_dir, repair)?;
+    if report.is_clean() {
+        println!("All sessions are consistent.");
+        return Ok(());
+    }
+
+    for path in &report.orphaned_transcripts {
+        println!("orphaned transcript\t{}", path.display());
+    }
+    for key in &report.missing_transcripts {
+        println!("missing transcript\t{key}");
+    }
+    for (path, count) in &report.bad_lines {
+        println!("unparseable lines\t{}\t{count}", path.display());
+    }
+
+    if repair {
+        println!("\nRepaired. Restart the gateway if it is running so it reloads sessions.");
+    } else {
+        println!("\nRun with --repair to fix these.");
+    }
+    Ok(())
+}
+
+async fn cmd_sessions_prune(
+    config_path: &Option<PathBuf>,
+    agent: Option<&str>,
+    older_than: &str,
+    dry_run: bool,
+) -> Result<()> {
+    let config = load_agent_config(config_path, agent)?;
+    let age = neko::workspace::parse_age(older_than)?;
+    let cutoff = chrono::Duration::from_std(age)
+        .ok()
+        .and_then(|age| chrono::Utc::now().checked_sub_signed(age))
+        .ok_or_else(|| NekoError::Config(format!("Age out of range: {older_than}")))?;
     let sessions_dir = config.workspace_path().join("sessions");
 
     if !sessions_dir.exists() {
@@ -1015,21 +2179,108 @@ async fn cmd_sessions_clear(config_path: &Option<PathBuf>) -> Result<()> {
 
     let store = neko::session::SessionStore::new(sessions_dir, config.session.clone());
     store.load_from_disk().await?;
-    store.clear_all().await?;
 
-    println!("All sessions cleared.");
+    let mut stale: Vec<_> = store
+        .list()
+        .await
+        .into_iter()
+        .filter(|meta| meta.updated_at < cutoff)
+        .collect();
+    stale.sort_by_key(|meta| meta.updated_at);
+
+    if stale.is_empty() {
+        println!("No sessions inactive for longer than {older_than}.");
+        return Ok(());
+    }
+
+    for meta in &stale {
+        if dry_run {
+            println!(
+                "{}\t{}\tupdated={}",
+                meta.key,
+                &meta.session_id[..8],
+                meta.updated_at.format("%Y-%m-%d %H:%M"),
+            );
+        } else {
+            store.delete(&meta.session_id).await?;
+        }
+    }
+
+    if dry_run {
+        println!("\nWould remove {} session(s).", stale.len());
+    } else {
+        println!("Removed {} session(s) inactive for longer than {older_than}.", stale.len());
+    }
     Ok(())
 }
 
-fn cmd_skills_list(config_path: &Option<PathBuf>) -> Result<()> {
-    let config = load_config(config_path)?;
-    let skills = neko::skills::load_skills(&config.workspace_path())?;
+fn cmd_skills_reload(config_path: &Option<PathBuf>) -> Result<()> {
+    cmd_skills_list(config_path, None)?;
+
+    match read_pid_file() {
+        Some(PidFile { pid, .. }) if is_process_running(pid) => {
+            if send_signal(pid, "-HUP") {
+                println!("Asked the running gateway (PID {pid}) to reload skills.");
+            } else {
+                println!("Failed to signal PID {pid}; restart the gateway to load skill changes.");
+            }
+        }
+        _ => println!("Neko is not running; skills load on the next start."),
+    }
+    Ok(())
+}
 
+fn cmd_skills_list(config_path: &Option<PathBuf>, format: Option<ListFormat>) -> Result<()> {
+    let config = load_config(config_path)?;
+    let skills = neko::skills::load_skills(&config.workspace_path(), &config.skills.extra_dirs())?;
+
+    if format == Some(ListFormat::Json) {
+        let skills: Vec<serde_json::Value> = skills
+            .iter()
+            .map(|skill| {
+                serde_json::json!({
+                    "name": skill.name,
+                    "description": skill.description,
+                    "license": skill.license,
+                    "compatibility": skill.compatibility,
+                    "allowed_tools": skill.allowed_tools,
+                    "path": skill.path,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&skills)?);
+        return Ok(());
+    }
     if skills.is_empty() {
         println!("No skills installed.");
         return Ok(());
     }
 
+    if let Some(format) = format {
+        let rows: Vec<Vec<String>> = skills
+            .iter()
+            .map(|skill| {
+                vec![
+                    skill.name.clone(),
+                    skill.license.clone().unwrap_or_else(|| "-".into()),
+                    match skill.allowed_tools.as_slice() {
+                        [] => "-".to_string(),
+                        tools => tools.join(","),
+                    },
+                    skill.description.clone(),
+                ]
+            })
+            .collect();
+        match format {
+            ListFormat::Table => {
+                let headers = ["NAME", "LICENSE", "TOOLS", "DESCRIPTION"];
+                print!("{}", neko::output::table(&headers, &rows));
+            }
+            _ => print!("{}", neko::output::tsv(&rows)),
+        }
+        return Ok(());
+    }
+
     for skill in &skills {
         let license = skill
             .license
@@ -1047,21 +2298,18 @@ fn cmd_skills_list(config_path: &Option<PathBuf>) -> Result<()> {
     Ok(())
 }
 
-fn cmd_skills_install(config_path: &Option<PathBuf>, path: &str) -> Result<()> {
-    let config = load_config(config_path)?;
-    let skills_dir = config.workspace_path().join("skills");
+/// The directory and SKILL.md for a skill given as either path.
+fn resolve_skill_source(path: &str) -> Result<(PathBuf, PathBuf)> {
     let source = PathBuf::from(path);
-
-    // Determine source directory and SKILL.md path
-    let (source_dir, skill_md) = if source.is_file()
+    if source.is_file()
         && source
             .file_name()
             .map_or(false, |n| n == "SKILL.md")
     {
-        (
+        Ok((
             source.parent().unwrap_or(&source).to_path_buf(),
             source.clone(),
-        )
+        ))
     } else if source.is_dir() {
         let md = source.join("SKILL.md");
         if !md.exists() {
@@ -1070,13 +2318,61 @@ fn cmd_skills_install(config_path: &Option<PathBuf>, path: &str) -> Result<()> {
                 source.display()
             )));
         }
-        (source.clone(), md)
+        Ok((source.clone(), md))
     } else {
-        return Err(NekoError::Config(format!(
+        Err(NekoError::Config(format!(
             "Invalid skill path: {} (expected a directory or SKILL.md file)",
             source.display()
-        )));
-    };
+        )))
+    }
+}
+
+fn cmd_skills_validate(config_path: &Option<PathBuf>, path: &str) -> Result<()> {
+    let (_, skill_md) = resolve_skill_source(path)?;
+    let skill = neko::skills::Skill::load(&skill_md)?;
+
+    // Known tools depend on [tools] (e.g. python); fall back to defaults
+    let tools_config = load_config(config_path).map(|c| c.tools).unwrap_or_default();
+    let mut registry = neko::tools::ToolRegistry::new();
+    neko::tools::register_core_tools(&mut registry, &tools_config);
+    let mut known = registry.names();
+    known.extend(["send_message", "skill_search"]);
+
+    println!("Name:          {}", skill.name);
+    println!("Description:   {}", skill.description);
+    if let Some(license) = &skill.license {
+        println!("License:       {license}");
+    }
+    if let Some(compatibility) = &skill.compatibility {
+        println!("Compatibility: {compatibility}");
+    }
+    if !skill.allowed_tools.is_empty() {
+        println!("Allowed tools: {}", skill.allowed_tools.join(" "));
+    }
+    let mut metadata: Vec<_> = skill.metadata.iter().collect();
+    metadata.sort();
+    for (key, value) in metadata {
+        println!("Metadata:      {key} = {value}");
+    }
+    println!("Instructions:  {} chars", skill.instructions.len());
+
+    let warnings = neko::skills::lint_skill(&skill, &known);
+    println!();
+    if warnings.is_empty() {
+        println!("{}", neko::output::paint("Valid, no warnings.", neko::output::Color::Green));
+    } else {
+        for warning in &warnings {
+            println!("{} {warning}", neko::output::paint("Warning:", neko::output::Color::Yellow));
+        }
+        println!("Valid, {} warning(s).", warnings.len());
+    }
+    Ok(())
+}
+
+fn cmd_skills_install(config_path: &Option<PathBuf>, path: &str) -> Result<()> {
+    let config = load_config(config_path)?;
+    let skills_dir = config.workspace_path().join("skills");
+    let (source_dir, skill_md) = resolve_skill_source(path)?;
 
     // Validate the skill parses correctly
     let skill = neko::skills::Skill::load(&skill_md)?;
@@ -1098,7 +2394,8 @@ fn cmd_skills_install(config_path: &Option<PathBuf>, path: &str) -> Result<()> {
 
 fn cmd_skills_remove(config_path: &Option<PathBuf>, name: &str) -> Result<()> {
     let config = load_config(config_path)?;
-    let skills = neko::skills::load_skills(&config.workspace_path())?;
+    // Only workspace skills; shared `[skills] paths` are left alone
+    let skills = neko::skills::load_skills(&config.workspace_path(), &[])?;
 
     let skill = skills
         .iter()
@@ -1114,22 +2411,37 @@ fn cmd_skills_remove(config_path: &Option<PathBuf>, name: &str) -> Result<()> {
 // Cron commands
 // ---------------------------------------------------------------------------
 
-fn cmd_cron_list(config_path: &Option<PathBuf>) -> Result<()> {
-    let config = load_config(config_path)?;
-    let jobs = neko::cron::load_jobs(&config.workspace_path())?;
+fn cmd_cron_list(
+    config_path: &Option<PathBuf>,
+    agent: Option<&str>,
+    format: Option<ListFormat>,
+) -> Result<()> {
+    let config = load_agent_config(config_path, agent)?;
+    let (jobs, skipped) = neko::cron::load_jobs_counting_skipped(&config.workspace_path())?;
+    if skipped > 0 {
+        eprintln!(
+            "{} skipped {skipped} malformed job(s) in cron/jobs.json (copied to cron/jobs.rejected.json)",
+            neko::output::paint("Warning:", neko::output::Color::Yellow)
+        );
+    }
 
+    if format == Some(ListFormat::Json) {
+        println!("{}", serde_json::to_string_pretty(&jobs)?);
+        return Ok(());
+    }
     if jobs.is_empty() {
         println!("No cron jobs configured.");
         return Ok(());
     }
 
+    let mut rows = Vec::with_capacity(jobs.len());
     for job in &jobs {
         let name = job.name.as_deref().unwrap_or("-");
         let status = if job.enabled { "enabled" } else { "disabled" };
         let schedule = match &job.schedule {
             neko::cron::Schedule::Cron { expr } => format!("cron: {expr}"),
             neko::cron::Schedule::At { datetime } => {
-                format!("at: {}", datetime.format("%Y-%m-%d %H:%M"))
+                format!("at: {}", neko::cron::local_time(*datetime))
             }
         };
         let announce = job
@@ -1139,34 +2451,85 @@ fn cmd_cron_list(config_path: &Option<PathBuf>) -> Result<()> {
             .unwrap_or_else(|| "-".into());
         let last = job
             .last_run_at
-            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+            .map(neko::cron::local_time)
             .unwrap_or_else(|| "never".into());
         let failures = job.retry.consecutive_failures;
+        let session = job.session_key.as_deref().unwrap_or("-");
 
+        if format == Some(ListFormat::Table) {
+            rows.push(vec![
+                job.id.clone(),
+                name.to_string(),
+                status.to_string(),
+                schedule,
+                announce,
+                session.to_string(),
+                last,
+                failures.to_string(),
+            ]);
+            continue;
+        }
         println!(
-            "{}\t{}\t{}\t{}\tannounce={}\tlast={}\tfailures={}",
-            job.id, name, status, schedule, announce, last, failures
+            "{}\t{}\t{}\t{}\tannounce={}\tsession={}\tlast={}\tfailures={}",
+            job.id, name, status, schedule, announce, session, last, failures
         );
     }
 
+    if !rows.is_empty() {
+        let headers = ["ID", "NAME", "STATUS", "SCHEDULE", "ANNOUNCE", "SESSION", "LAST RUN", "FAILURES"];
+        print!("{}", neko::output::table(&headers, &rows));
+    }
+    Ok(())
+}
+
+/// Expand `--preset` to its cron expression; otherwise pass `--schedule`
+/// through (clap rejects giving both).
+fn preset_or_schedule(preset: Option<String>, schedule: Option<String>) -> Result<Option<String>> {
+    match preset {
+        Some(name) => Ok(Some(neko::cron::preset_expr(&name)?.to_string())),
+        None => Ok(schedule),
+    }
+}
+
+/// Show what a cron expression means and when it fires next, so a
+/// misread field is caught before the job is saved.
+fn print_schedule_preview(expr: &str) -> Result<()> {
+    println!("Schedule: {}", neko::cron::describe_cron_expr(expr));
+    println!("Next runs:");
+    for time in neko::cron::next_fire_times(expr, 3)? {
+        println!(
+            "  {}  ({} local)",
+            time.format("%Y-%m-%d %H:%M:%S UTC"),
+            time.with_timezone(&chrono::Local).format("%a %H:%M")
+        );
+    }
     Ok(())
 }
 
 fn cmd_cron_add(
     config_path: &Option<PathBuf>,
-    prompt: &str,
-    schedule: Option<String>,
-    at: Option<String>,
-    name: Option<String>,
-    announce: Option<String>,
-    keep_after_run: bool,
+    agent: Option<&str>,
+    args: CronAddArgs,
 ) -> Result<()> {
-    let config = load_config(config_path)?;
+    let config = load_agent_config(config_path, agent)?;
     let workspace = config.workspace_path();
+    let CronAddArgs {
+        prompt,
+        schedule,
+        preset,
+        at,
+        name,
+        announce,
+        keep_after_run,
+        session,
+        jitter,
+    } = args;
+    let schedule = preset_or_schedule(preset, schedule)?;
 
     let sched = match (schedule, at) {
         (Some(expr), None) => {
             neko::cron::validate_cron_expr(&expr)?;
+            print_schedule_preview(&expr)?;
             neko::cron::Schedule::Cron { expr }
         }
         (None, Some(dt_str)) => {
@@ -1190,14 +2553,17 @@ fn cmd_cron_add(
     let job = neko::cron::CronJob {
         id: neko::cron::new_job_id(),
         name,
-        prompt: prompt.to_string(),
+        prompt,
         schedule: sched,
         announce: announce_target,
         enabled: true,
         keep_after_run,
         created_at: Utc::now(),
         last_run_at: None,
+        last_success_at: None,
         retry: neko::cron::RetryState::default(),
+        session_key: session,
+        jitter_secs: jitter.filter(|&j| j > 0),
     };
 
     let mut jobs = neko::cron::load_jobs(&workspace)?;
@@ -1211,25 +2577,34 @@ fn cmd_cron_add(
 
 fn cmd_cron_edit(
     config_path: &Option<PathBuf>,
-    id_or_name: &str,
-    prompt: Option<String>,
-    schedule: Option<String>,
-    name: Option<String>,
-    enabled: Option<bool>,
-    announce: Option<String>,
+    agent: Option<&str>,
+    args: CronEditArgs,
 ) -> Result<()> {
-    let config = load_config(config_path)?;
+    let config = load_agent_config(config_path, agent)?;
     let workspace = config.workspace_path();
     let mut jobs = neko::cron::load_jobs(&workspace)?;
+    let CronEditArgs {
+        id,
+        prompt,
+        schedule,
+        preset,
+        name,
+        enabled,
+        announce,
+        session,
+        jitter,
+    } = args;
+    let schedule = preset_or_schedule(preset, schedule)?;
 
-    let idx = neko::cron::find_job(&jobs, id_or_name)
-        .ok_or_else(|| NekoError::Cron(format!("job '{id_or_name}' not found")))?;
+    let idx = neko::cron::find_job(&jobs, &id)
+        .ok_or_else(|| NekoError::Cron(format!("job '{id}' not found")))?;
 
     if let Some(p) = prompt {
         jobs[idx].prompt = p;
     }
     if let Some(expr) = schedule {
         neko::cron::validate_cron_expr(&expr)?;
+        print_schedule_preview(&expr)?;
         jobs[idx].schedule = neko::cron::Schedule::Cron { expr };
     }
     if let Some(n) = name {
@@ -1249,14 +2624,24 @@ fn cmd_cron_edit(
             jobs[idx].announce = Some(neko::cron::parse_announce(&a)?);
         }
     }
+    if let Some(key) = session {
+        jobs[idx].session_key = (key != "none").then_some(key);
+    }
+    if let Some(secs) = jitter {
+        jobs[idx].jitter_secs = (secs > 0).then_some(secs);
+    }
 
     neko::cron::save_jobs(&workspace, &jobs)?;
     println!("Updated job: {}", jobs[idx].name.as_deref().unwrap_or(&jobs[idx].id));
     Ok(())
 }
 
-fn cmd_cron_remove(config_path: &Option<PathBuf>, id_or_name: &str) -> Result<()> {
-    let config = load_config(config_path)?;
+fn cmd_cron_remove(
+    config_path: &Option<PathBuf>,
+    agent: Option<&str>,
+    id_or_name: &str,
+) -> Result<()> {
+    let config = load_agent_config(config_path, agent)?;
     let workspace = config.workspace_path();
     let mut jobs = neko::cron::load_jobs(&workspace)?;
 
@@ -1271,8 +2656,12 @@ fn cmd_cron_remove(config_path: &Option<PathBuf>, id_or_name: &str) -> Result<()
     Ok(())
 }
 
-fn cmd_cron_history(config_path: &Option<PathBuf>, lines: usize) -> Result<()> {
-    let config = load_config(config_path)?;
+fn cmd_cron_history(
+    config_path: &Option<PathBuf>,
+    agent: Option<&str>,
+    lines: usize,
+) -> Result<()> {
+    let config = load_agent_config(config_path, agent)?;
     let entries = neko::cron::read_history(&config.workspace_path(), lines)?;
 
     if entries.is_empty() {
@@ -1303,7 +2692,7 @@ fn cmd_cron_history(config_path: &Option<PathBuf>, lines: usize) -> Result<()> {
 
         println!(
             "{}\t{}\t{}\t{:.1}s\t{}",
-            entry.started_at.format("%Y-%m-%d %H:%M:%S"),
+            entry.started_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S"),
             name,
             status,
             duration,
@@ -123,8 +123,9 @@ pub struct McpTool {
 
 impl McpTool {
     pub fn new(def: &McpToolDef, client: Arc<McpClient>) -> Self {
-        let input_schema = serde_json::to_value(&*def.input_schema)
+        let mut input_schema = serde_json::to_value(&*def.input_schema)
             .unwrap_or_else(|_| serde_json::json!({"type": "object", "properties": {}}));
+        crate::tools::schema_lint::fill_missing_properties(&mut input_schema);
 
         Self {
             name: def.name.to_string(),
@@ -0,0 +1,293 @@
+//! Optional embeddings index over memory files, backing
+//! `memory_search(semantic=true)`.
+//!
+//! Memory `.md` files are split into paragraph chunks, embedded through the
+//! provider's `/v1/embeddings` endpoint and stored at
+//! `memory/.index/embeddings.json`. Syncing is incremental: vectors are
+//! reused for chunks whose text hasn't changed, so appending to a recall log
+//! only embeds the new entries.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::{debug, info};
+use walkdir::WalkDir;
+
+use crate::error::Result;
+use crate::llm;
+
+/// Chunks longer than this are split further before embedding.
+const MAX_CHUNK_CHARS: usize = 1500;
+
+/// Inputs per embeddings request.
+const EMBED_BATCH: usize = 64;
+
+/// Serializes syncs so a background sync and a search don't race on the file.
+static SYNC_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// Embeddings endpoint + model used to index and query memory.
+pub struct Embedder {
+    client: llm::Client,
+    model: String,
+}
+
+impl Embedder {
+    pub fn new(client: llm::Client, model: impl Into<String>) -> Self {
+        Self {
+            client,
+            model: model.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Chunk {
+    /// 1-based line where the chunk starts.
+    line: usize,
+    text: String,
+    vector: Vec<f32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    model: String,
+    /// Workspace-relative path -> chunks.
+    files: BTreeMap<String, Vec<Chunk>>,
+}
+
+/// A semantic search result.
+pub struct Hit {
+    pub rel_path: String,
+    pub line: usize,
+    pub text: String,
+    pub score: f32,
+}
+
+fn index_path(workspace: &Path) -> PathBuf {
+    workspace.join("memory").join(".index").join("embeddings.json")
+}
+
+fn load(workspace: &Path) -> Index {
+    std::fs::read_to_string(index_path(workspace))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save(workspace: &Path, index: &Index) -> Result<()> {
+    let path = index_path(workspace);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_string(index)?)?;
+    std::fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+/// Split a file into blank-line-separated paragraphs (recall entries, list
+/// blocks, sections), capping each at `MAX_CHUNK_CHARS`. Returns
+/// (start line, text) pairs.
+fn chunk(content: &str) -> Vec<(usize, String)> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut start = 0;
+
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                chunks.push((start, std::mem::take(&mut current)));
+            }
+            continue;
+        }
+        if current.is_empty() {
+            start = i + 1;
+        } else if current.len() + line.len() > MAX_CHUNK_CHARS {
+            chunks.push((start, std::mem::take(&mut current)));
+            start = i + 1;
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(line);
+    }
+    if !current.is_empty() {
+        chunks.push((start, current));
+    }
+    chunks
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/// Bring the index up to date with the memory directory. Returns the number
+/// of chunks embedded.
+pub async fn sync(workspace: &Path, embedder: &Embedder) -> Result<usize> {
+    let _guard = SYNC_LOCK.lock().await;
+
+    let mut index = load(workspace);
+    if index.model != embedder.model {
+        index = Index {
+            model: embedder.model.clone(),
+            files: BTreeMap::new(),
+        };
+    }
+
+    // Reuse vectors by chunk text, regardless of which file they came from.
+    let mut cached: HashMap<String, Vec<f32>> = HashMap::new();
+    for chunks in index.files.values() {
+        for c in chunks {
+            cached.insert(c.text.clone(), c.vector.clone());
+        }
+    }
+
+    let memory_dir = workspace.join("memory");
+    let mut files: BTreeMap<String, Vec<(usize, String)>> = BTreeMap::new();
+    for entry in WalkDir::new(&memory_dir)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|e| e.ok())
+    {
+        let path = entry.path();
+        if !path.is_file() || path.extension().and_then(|e| e.to_str()) != Some("md") {
+            continue;
+        }
+        let Ok(content) = std::fs::read_to_string(path) else {
+            continue;
+        };
+        let rel_path = path
+            .strip_prefix(workspace)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string();
+        files.insert(rel_path, chunk(&content));
+    }
+
+    let mut missing: Vec<String> = files
+        .values()
+        .flatten()
+        .map(|(_, text)| text.clone())
+        .filter(|text| !cached.contains_key(text))
+        .collect();
+    missing.sort();
+    missing.dedup();
+
+    for batch in missing.chunks(EMBED_BATCH) {
+        let vectors = embedder
+            .client
+            .create_embeddings(&embedder.model, batch.to_vec())
+            .await?;
+        for (text, vector) in batch.iter().zip(vectors) {
+            cached.insert(text.clone(), vector);
+        }
+    }
+
+    let old_layout: BTreeMap<String, Vec<(usize, String)>> = index
+        .files
+        .iter()
+        .map(|(p, chunks)| (p.clone(), chunks.iter().map(|c| (c.line, c.text.clone())).collect()))
+        .collect();
+    let unchanged = missing.is_empty() && old_layout == files;
+
+    index.files = files
+        .into_iter()
+        .map(|(rel_path, chunks)| {
+            let chunks = chunks
+                .into_iter()
+                .filter_map(|(line, text)| {
+                    let vector = cached.get(&text)?.clone();
+                    Some(Chunk { line, text, vector })
+                })
+                .collect();
+            (rel_path, chunks)
+        })
+        .collect();
+
+    if !unchanged {
+        save(workspace, &index)?;
+    }
+    if !missing.is_empty() {
+        info!("Memory index: embedded {} new chunk(s)", missing.len());
+    }
+    Ok(missing.len())
+}
+
+/// Sync the index, then return the `limit` chunks nearest to `query` among
+/// files accepted by `filter` (workspace-relative path).
+pub async fn search(
+    workspace: &Path,
+    embedder: &Embedder,
+    query: &str,
+    limit: usize,
+    filter: impl Fn(&str) -> bool,
+) -> Result<Vec<Hit>> {
+    sync(workspace, embedder).await?;
+
+    let query_vector = embedder
+        .client
+        .create_embeddings(&embedder.model, vec![query.to_string()])
+        .await?
+        .pop()
+        .ok_or_else(|| llm::LlmError::Other("Embeddings API returned no vector".to_string()))?;
+
+    let index = {
+        let _guard = SYNC_LOCK.lock().await;
+        load(workspace)
+    };
+    debug!("Semantic memory search over {} file(s)", index.files.len());
+
+    let mut hits: Vec<Hit> = index
+        .files
+        .iter()
+        .filter(|(rel_path, _)| filter(rel_path))
+        .flat_map(|(rel_path, chunks)| {
+            chunks.iter().map(|c| Hit {
+                rel_path: rel_path.clone(),
+                line: c.line,
+                text: c.text.clone(),
+                score: cosine(&query_vector, &c.vector),
+            })
+        })
+        .collect();
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(limit);
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_paragraphs() {
+        let content = "# Title\n\n### 09:00\n**User:** hi\n**Assistant:** hello\n\n\n### 10:00\n**User:** bye\n";
+        let chunks = chunk(content);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0], (1, "# Title".to_string()));
+        assert_eq!(chunks[1].0, 3);
+        assert!(chunks[1].1.ends_with("hello"));
+        assert_eq!(chunks[2].0, 8);
+
+        let long = "x".repeat(1000);
+        let chunks = chunk(&format!("{long}\n{long}\n"));
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].0, 2);
+    }
+
+    #[test]
+    fn test_cosine() {
+        assert!((cosine(&[1.0, 0.0], &[1.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert_eq!(cosine(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+}
@@ -0,0 +1,155 @@
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+
+/// How CLI output may be decorated. Colors need a terminal and no opt-out;
+/// symbols outside ASCII need a UTF-8 locale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+    pub color: bool,
+    pub unicode: bool,
+}
+
+impl Style {
+    /// No colors, ASCII only. Used until [`init`] is called.
+    pub const PLAIN: Style = Style {
+        color: false,
+        unicode: false,
+    };
+
+    /// Work out the style for this process from `--no-color`, `--ascii`,
+    /// `NO_COLOR`, `TERM` and the locale.
+    pub fn detect(no_color: bool, ascii: bool) -> Self {
+        Style {
+            color: color_allowed(no_color) && std::io::stdout().is_terminal(),
+            unicode: !ascii && !dumb_terminal() && utf8_locale(),
+        }
+    }
+}
+
+static STYLE: OnceLock<Style> = OnceLock::new();
+
+/// Set the process-wide style. Only the first call takes effect.
+pub fn init(style: Style) {
+    let _ = STYLE.set(style);
+}
+
+/// The style set by [`init`], or [`Style::PLAIN`].
+pub fn style() -> Style {
+    STYLE.get().copied().unwrap_or(Style::PLAIN)
+}
+
+/// Whether colors are allowed at all, before checking for a terminal:
+/// not with `--no-color`, a non-empty `NO_COLOR` (https://no-color.org) or
+/// `TERM=dumb`.
+pub fn color_allowed(no_color: bool) -> bool {
+    !no_color && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && !dumb_terminal()
+}
+
+fn dumb_terminal() -> bool {
+    std::env::var("TERM").is_ok_and(|term| term == "dumb")
+}
+
+/// The first of `LC_ALL`, `LC_CTYPE` and `LANG` that is set decides.
+fn utf8_locale() -> bool {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .is_some_and(|value| {
+            let value = value.to_lowercase();
+            value.contains("utf-8") || value.contains("utf8")
+        })
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+    Bold,
+}
+
+/// Wrap `text` in the ANSI codes for `color` when colors are enabled.
+pub fn paint(text: &str, color: Color) -> String {
+    if !style().color {
+        return text.to_string();
+    }
+    let code = match color {
+        Color::Red => "31",
+        Color::Green => "32",
+        Color::Yellow => "33",
+        Color::Bold => "1",
+    };
+    format!("\x1b[{code}m{text}\x1b[0m")
+}
+
+/// `unicode` when the terminal can render it, `ascii` otherwise.
+pub fn symbol(unicode: &'static str, ascii: &'static str) -> &'static str {
+    if style().unicode { unicode } else { ascii }
+}
+
+/// Lay `rows` out in left-aligned columns under `headers`, two spaces
+/// apart. Widths count chars, which is close enough for the ASCII-heavy
+/// values the CLI lists.
+pub fn table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut out = String::new();
+    let header: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+    for row in std::iter::once(&header).chain(rows) {
+        let mut line = String::new();
+        for (i, (cell, width)) in row.iter().zip(&widths).enumerate() {
+            if i > 0 {
+                line.push_str("  ");
+            }
+            line.push_str(cell);
+            line.extend(std::iter::repeat_n(' ', width - cell.chars().count()));
+        }
+        let line = line.trim_end();
+        if out.is_empty() {
+            out.push_str(&paint(line, Color::Bold));
+        } else {
+            out.push_str(line);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// One tab-separated line per row.
+pub fn tsv(rows: &[Vec<String>]) -> String {
+    rows.iter().map(|row| row.join("\t") + "\n").collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_aligns_columns() {
+        let rows = vec![
+            vec!["daily".to_string(), "enabled".to_string()],
+            vec!["a-much-longer-name".to_string(), "-".to_string()],
+        ];
+        assert_eq!(
+            table(&["NAME", "STATUS"], &rows),
+            "NAME                STATUS\n\
+             daily               enabled\n\
+             a-much-longer-name  -\n"
+        );
+        assert_eq!(tsv(&rows), "daily\tenabled\na-much-longer-name\t-\n");
+    }
+
+    #[test]
+    fn test_plain_style_is_undecorated() {
+        // Nothing calls init() in tests, so output stays plain.
+        assert_eq!(style(), Style::PLAIN);
+        assert_eq!(paint("ok", Color::Green), "ok");
+        assert_eq!(symbol("\u{2713}", "ok"), "ok");
+    }
+}
@@ -1,8 +1,8 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use chrono::{DateTime, Local, Timelike, Utc};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 use tokio::sync::{Mutex, RwLock};
 use tracing::{debug, info, warn};
@@ -34,6 +34,16 @@ impl SessionKey {
         Self(format!("neko:{channel}:group:{group_id}"))
     }
 
+    /// `neko message --session` session: `neko:cli:<name>`
+    pub fn cli(name: &str) -> Self {
+        Self(format!("neko:cli:{name}"))
+    }
+
+    /// Cron job session: `neko:cron:<name>`
+    pub fn cron(name: &str) -> Self {
+        Self(format!("neko:cron:{name}"))
+    }
+
     /// Convert colons to underscores for safe filenames.
     pub fn to_filename(&self) -> String {
         self.0.replace(':', "_")
@@ -65,10 +75,44 @@ pub struct SessionMeta {
     pub display_name: Option<String>,
     /// Last API response ID — enables `previous_response_id` chaining so the
     /// API can automatically handle reasoning-item pairing across turns.
-    /// Cleared on session reset; becomes `None` after a server restart (the
-    /// API may have forgotten it), causing a graceful fallback to full-history.
+    /// Cleared on session reset, and discarded once older than
+    /// `response_id_ttl_minutes` (e.g. after a restart, when the API may have
+    /// forgotten it), causing a graceful fallback to full-history.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub last_response_id: Option<String>,
+    /// When `last_response_id` was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_response_at: Option<DateTime<Utc>>,
+    /// Per-session instructions that replace `agent.instructions` (set via
+    /// `/persona` or the API). Survives session resets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instructions_override: Option<String>,
+    /// History items kept for this session, replacing `agent.max_history`
+    /// (set via `/history` or the API). Survives session resets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_history_override: Option<u32>,
+    /// Language the agent should respond in (e.g. "Spanish").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// Set when the user chose `locale` with `/lang`; auto-detection then
+    /// leaves it alone.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub locale_pinned: bool,
+    /// Risky tool calls wait for the user's yes/no (set via `/confirm`).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub confirm_tools: bool,
+    /// UTC day that `day_tokens` counts toward.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage_day: Option<NaiveDate>,
+    /// Tokens (input + output) used on `usage_day`, checked against
+    /// `max_tokens_per_day`. Unlike the history, not cleared by `/new`.
+    #[serde(default)]
+    pub day_tokens: u64,
+    /// The agent's `greeting` has been sent (or the session already had
+    /// turns when greetings were introduced). Survives resets, so only
+    /// first contact is greeted.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub greeted: bool,
 }
 
 // ---------------------------------------------------------------------------
@@ -78,8 +122,16 @@ pub struct SessionMeta {
 pub struct Session {
     pub meta: SessionMeta,
     pub history: Vec<llm::Item>,
+    /// Items in the on-disk transcript. Grows past `history.len()` as
+    /// history is trimmed; see `TRANSCRIPT_SLACK_ITEMS`.
+    transcript_len: usize,
 }
 
+/// Once the transcript holds this many more items than the in-memory
+/// history, it is rewritten to match (the old file is archived), keeping
+/// disk usage and reload time proportional to the context actually used.
+const TRANSCRIPT_SLACK_ITEMS: usize = 200;
+
 // ---------------------------------------------------------------------------
 // SessionStore
 // ---------------------------------------------------------------------------
@@ -117,14 +169,24 @@ impl SessionStore {
         let mut sessions = self.sessions.write().await;
         let mut key_index = self.key_index.write().await;
 
-        for (key, meta) in meta_map {
+        for (key, mut meta) in meta_map {
+            if self.response_id_expired(&meta) {
+                debug!("Discarding stale response ID for session {}", meta.session_id);
+                meta.last_response_id = None;
+                meta.last_response_at = None;
+            }
             let session_id = meta.session_id.clone();
             let history = self.load_transcript(&session_id)?;
+            let transcript_len = history.len();
 
             key_index.insert(key, session_id.clone());
             sessions.insert(
                 session_id,
-                Arc::new(Mutex::new(Session { meta, history })),
+                Arc::new(Mutex::new(Session {
+                    meta,
+                    history,
+                    transcript_len,
+                })),
             );
         }
 
@@ -182,11 +244,21 @@ impl SessionStore {
             channel: channel.map(String::from),
             display_name: display_name.map(String::from),
             last_response_id: None,
+            last_response_at: None,
+            instructions_override: None,
+            max_history_override: None,
+            locale: None,
+            locale_pinned: false,
+            confirm_tools: false,
+            usage_day: None,
+            day_tokens: 0,
+            greeted: false,
         };
 
         let session = Session {
             meta,
             history: Vec::new(),
+            transcript_len: 0,
         };
 
         let mut sessions = self.sessions.write().await;
@@ -216,7 +288,122 @@ impl SessionStore {
             .get(session_id)
             .ok_or_else(|| NekoError::Session(format!("Session not found: {session_id}")))?;
         let session = session_lock.lock().await;
-        Ok((session.history.clone(), session.meta.last_response_id.clone()))
+        let prev_id = if self.response_id_expired(&session.meta) {
+            None
+        } else {
+            session.meta.last_response_id.clone()
+        };
+        Ok((session.history.clone(), prev_id))
+    }
+
+    /// Whether this store holds `session_id`.
+    pub async fn contains(&self, session_id: &str) -> bool {
+        self.sessions.read().await.contains_key(session_id)
+    }
+
+    /// Metadata snapshot for a session.
+    pub async fn meta(&self, session_id: &str) -> Result<SessionMeta> {
+        let sessions = self.sessions.read().await;
+        let session_lock = sessions
+            .get(session_id)
+            .ok_or_else(|| NekoError::Session(format!("Session not found: {session_id}")))?;
+        let session = session_lock.lock().await;
+        Ok(session.meta.clone())
+    }
+
+    /// Whether this is first contact: the session has never been greeted
+    /// nor had a turn.
+    pub async fn needs_greeting(&self, session_id: &str) -> Result<bool> {
+        let meta = self.meta(session_id).await?;
+        Ok(!meta.greeted && meta.turn_count == 0)
+    }
+
+    /// Record that the session's greeting was sent.
+    pub async fn mark_greeted(&self, session_id: &str) -> Result<()> {
+        let sessions = self.sessions.read().await;
+        let session_lock = sessions
+            .get(session_id)
+            .ok_or_else(|| NekoError::Session(format!("Session not found: {session_id}")))?;
+        session_lock.lock().await.meta.greeted = true;
+        drop(sessions);
+        self.persist_meta().await
+    }
+
+    /// Set or clear (`None`) the session's instructions override.
+    pub async fn set_instructions_override(
+        &self,
+        session_id: &str,
+        instructions: Option<String>,
+    ) -> Result<()> {
+        let sessions = self.sessions.read().await;
+        let session_lock = sessions
+            .get(session_id)
+            .ok_or_else(|| NekoError::Session(format!("Session not found: {session_id}")))?;
+        session_lock.lock().await.meta.instructions_override = instructions;
+        drop(sessions);
+        self.persist_meta().await
+    }
+
+    /// Set or clear how many history items the session keeps.
+    pub async fn set_max_history_override(
+        &self,
+        session_id: &str,
+        max_history: Option<u32>,
+    ) -> Result<()> {
+        let sessions = self.sessions.read().await;
+        let session_lock = sessions
+            .get(session_id)
+            .ok_or_else(|| NekoError::Session(format!("Session not found: {session_id}")))?;
+        session_lock.lock().await.meta.max_history_override = max_history;
+        drop(sessions);
+        self.persist_meta().await
+    }
+
+    /// Set or clear the session's response language. `pinned` marks a user
+    /// choice that auto-detection must not overwrite.
+    pub async fn set_locale(
+        &self,
+        session_id: &str,
+        locale: Option<String>,
+        pinned: bool,
+    ) -> Result<()> {
+        let sessions = self.sessions.read().await;
+        let session_lock = sessions
+            .get(session_id)
+            .ok_or_else(|| NekoError::Session(format!("Session not found: {session_id}")))?;
+        {
+            let mut session = session_lock.lock().await;
+            session.meta.locale = locale;
+            session.meta.locale_pinned = pinned;
+        }
+        drop(sessions);
+        self.persist_meta().await
+    }
+
+    /// Turn tool-call confirmation on or off for a session.
+    pub async fn set_confirm_tools(&self, session_id: &str, enabled: bool) -> Result<()> {
+        let sessions = self.sessions.read().await;
+        let session_lock = sessions
+            .get(session_id)
+            .ok_or_else(|| NekoError::Session(format!("Session not found: {session_id}")))?;
+        session_lock.lock().await.meta.confirm_tools = enabled;
+        drop(sessions);
+        self.persist_meta().await
+    }
+
+    /// True if the stored response ID is older than the configured TTL.
+    /// IDs without a timestamp (older sessions.json) are treated as expired.
+    fn response_id_expired(&self, meta: &SessionMeta) -> bool {
+        if meta.last_response_id.is_none() {
+            return false;
+        }
+        match meta.last_response_at {
+            Some(at) => {
+                Utc::now().signed_duration_since(at).num_minutes()
+                    >= self.config.response_id_ttl_minutes as i64
+            }
+            None => true,
+        }
     }
 
     /// Update session history after an agent turn completes.
@@ -242,19 +429,36 @@ impl SessionStore {
             &history[..]
         };
 
-        // Append new items to JSONL transcript
-        if !new_items.is_empty() {
+        // Append new items to JSONL transcript, or rewrite it once it has
+        // drifted too far from the (trimmed) history.
+        if session.transcript_len + new_items.len() > history.len() + TRANSCRIPT_SLACK_ITEMS {
+            self.rewrite_transcript(session_id, &history)?;
+            debug!(
+                "Rewrote transcript for {session_id}: {} -> {} items",
+                session.transcript_len + new_items.len(),
+                history.len()
+            );
+            session.transcript_len = history.len();
+        } else if !new_items.is_empty() {
             self.append_to_transcript_inner(session_id, new_items)?;
+            session.transcript_len += new_items.len();
         }
 
         session.history = history;
         session.meta.updated_at = Utc::now();
         session.meta.turn_count += 1;
+        session.meta.last_response_at = last_response_id.as_ref().map(|_| Utc::now());
         session.meta.last_response_id = last_response_id;
 
         if let Some(u) = usage {
             session.meta.input_tokens += u.input_tokens;
             session.meta.output_tokens += u.output_tokens;
+            let today = Utc::now().date_naive();
+            if session.meta.usage_day != Some(today) {
+                session.meta.usage_day = Some(today);
+                session.meta.day_tokens = 0;
+            }
+            session.meta.day_tokens += u64::from(u.input_tokens) + u64::from(u.output_tokens);
         }
 
         drop(session);
@@ -264,6 +468,48 @@ impl SessionStore {
         Ok(())
     }
 
+    /// Replace a session's history with what `compact` makes of it, if that
+    /// is shorter. The session stays locked until then, so a turn finishing
+    /// meanwhile waits instead of being overwritten. The old transcript is
+    /// archived and the new history becomes the transcript; the response
+    /// chain is dropped since it no longer matches. Returns (before, after)
+    /// item counts.
+    pub async fn compact_with<F, Fut>(&self, session_id: &str, compact: F) -> Result<(usize, usize)>
+    where
+        F: FnOnce(Vec<llm::Item>) -> Fut,
+        Fut: std::future::Future<Output = Result<Vec<llm::Item>>>,
+    {
+        // Hold the session, not the whole map, across the summary call.
+        let session_lock = self
+            .sessions
+            .read()
+            .await
+            .get(session_id)
+            .cloned()
+            .ok_or_else(|| NekoError::Session(format!("Session not found: {session_id}")))?;
+        let mut session = session_lock.lock().await;
+
+        let before = session.history.len();
+        let history = compact(session.history.clone()).await?;
+        let after = history.len();
+        if after >= before {
+            return Ok((before, after));
+        }
+
+        self.rewrite_transcript(session_id, &history)?;
+
+        session.transcript_len = history.len();
+        session.history = history;
+        session.meta.updated_at = Utc::now();
+        session.meta.last_response_id = None;
+        session.meta.last_response_at = None;
+
+        drop(session);
+        self.persist_meta().await?;
+
+        Ok((before, after))
+    }
+
     /// Check if a session should be reset (daily or idle).
     /// Returns true if the session was reset.
     pub async fn check_reset(&self, session_id: &str) -> Result<bool> {
@@ -286,28 +532,38 @@ impl SessionStore {
         Ok(false)
     }
 
+    /// If the session has used up `max_tokens_per_day`, when its budget
+    /// resets.
+    pub async fn budget_reset_at(&self, session_id: &str) -> Result<Option<DateTime<Utc>>> {
+        let Some(limit) = self.config.max_tokens_per_day else {
+            return Ok(None);
+        };
+        let meta = self.meta(session_id).await?;
+        let today = Utc::now().date_naive();
+        if meta.usage_day != Some(today) || meta.day_tokens < limit {
+            return Ok(None);
+        }
+        Ok(Some(crate::budget::next_utc_midnight(Utc::now())))
+    }
+
     fn should_reset(&self, meta: &SessionMeta) -> bool {
         let now = Utc::now();
+        let policy = self.config.reset_policy(meta.channel.as_deref());
 
-        let daily_triggered = match self.config.reset_mode {
+        let daily_triggered = match policy.mode {
             ResetMode::Daily | ResetMode::Both => {
-                let local_now = Local::now();
                 let local_updated: DateTime<Local> = meta.updated_at.into();
 
-                // Reset if updated_at was before today's reset hour and now is after
-                let reset_hour = self.config.reset_at_hour;
-                if local_now.date_naive() > local_updated.date_naive() {
-                    local_now.hour() >= reset_hour
-                } else {
-                    false
-                }
+                // Reset if a reset hour has passed since the last update
+                last_reset_time(Local::now().naive_local(), &policy.at_hours)
+                    .is_some_and(|reset_at| local_updated.naive_local() < reset_at)
             }
-            ResetMode::Idle => false,
+            ResetMode::Idle | ResetMode::Never => false,
         };
 
-        let idle_triggered = match self.config.reset_mode {
+        let idle_triggered = match policy.mode {
             ResetMode::Idle | ResetMode::Both => {
-                if let Some(idle_mins) = self.config.idle_minutes {
+                if let Some(idle_mins) = policy.idle_minutes {
                     let elapsed = now
                         .signed_duration_since(meta.updated_at)
                         .num_minutes();
@@ -316,7 +572,7 @@ impl SessionStore {
                     false
                 }
             }
-            ResetMode::Daily => false,
+            ResetMode::Daily | ResetMode::Never => false,
         };
 
         daily_triggered || idle_triggered
@@ -332,22 +588,16 @@ impl SessionStore {
         let mut session = session_lock.lock().await;
 
         // Archive old transcript
-        let transcript_path = self.transcript_path(session_id);
-        if transcript_path.exists() {
-            let timestamp = Utc::now().format("%Y%m%dT%H%M%S");
-            let archive_name = format!("{session_id}.{timestamp}.jsonl");
-            let archive_path = self.sessions_dir.join(archive_name);
-            if let Err(e) = std::fs::rename(&transcript_path, &archive_path) {
-                warn!("Failed to archive transcript: {e}");
-            } else {
-                debug!("Archived transcript to {}", archive_path.display());
-            }
-        }
+        self.archive_transcript(session_id);
 
         session.history.clear();
+        session.transcript_len = 0;
         session.meta.updated_at = Utc::now();
+        // A reset conversation is not a new peer
+        session.meta.greeted |= session.meta.turn_count > 0;
         session.meta.turn_count = 0;
         session.meta.last_response_id = None;
+        session.meta.last_response_at = None;
 
         info!("Reset session {session_id}");
 
@@ -429,6 +679,72 @@ impl SessionStore {
         self.sessions_dir.join(format!("{session_id}.jsonl"))
     }
 
+    /// An unused `<id>.<timestamp>.jsonl` path. Timestamps have microsecond
+    /// resolution, with a counter added on the rare clash.
+    fn archive_path(&self, session_id: &str) -> PathBuf {
+        let timestamp = Utc::now().format("%Y%m%dT%H%M%S%.6f");
+        let mut path = self.sessions_dir.join(format!("{session_id}.{timestamp}.jsonl"));
+        let mut n = 1;
+        while path.exists() {
+            path = self.sessions_dir.join(format!("{session_id}.{timestamp}-{n}.jsonl"));
+            n += 1;
+        }
+        path
+    }
+
+    /// Move `<id>.jsonl` aside as `<id>.<timestamp>.jsonl`.
+    fn archive_transcript(&self, session_id: &str) {
+        let transcript_path = self.transcript_path(session_id);
+        if !transcript_path.exists() {
+            return;
+        }
+        let archive_path = self.archive_path(session_id);
+        if let Err(e) = std::fs::rename(&transcript_path, &archive_path) {
+            warn!("Failed to archive transcript: {e}");
+        } else {
+            debug!("Archived transcript to {}", archive_path.display());
+        }
+    }
+
+    /// Replace the transcript with `items`. The old lines `items` no longer
+    /// holds (the prefix trimmed off, or everything after a compaction) are
+    /// archived; the kept tail isn't duplicated. The new file is written
+    /// first so a failure leaves the old transcript intact.
+    fn rewrite_transcript(&self, session_id: &str, items: &[llm::Item]) -> Result<()> {
+        use std::io::Write;
+        let lines = items
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| NekoError::Session(format!("Failed to serialize item: {e}")))?;
+        let tmp_path = self.sessions_dir.join(format!("{session_id}.jsonl.tmp"));
+        let mut file = std::fs::File::create(&tmp_path)?;
+        for line in &lines {
+            writeln!(file, "{line}")?;
+        }
+        file.sync_all()?;
+        drop(file);
+
+        let path = self.transcript_path(session_id);
+        let old = std::fs::read_to_string(&path).unwrap_or_default();
+        let old: Vec<&str> = old.lines().collect();
+        // The first old line that starts the run `items` continues from
+        let dropped = (0..=old.len())
+            .find(|&k| {
+                old.len() - k <= lines.len() && old[k..].iter().zip(&lines).all(|(a, b)| a == b)
+            })
+            .unwrap_or(old.len());
+        if dropped > 0 {
+            let archive_path = self.archive_path(session_id);
+            let mut archived = old[..dropped].join("\n");
+            archived.push('\n');
+            std::fs::write(&archive_path, archived)?;
+            debug!("Archived {dropped} transcript line(s) to {}", archive_path.display());
+        }
+        std::fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
     fn append_to_transcript_inner(&self, session_id: &str, items: &[llm::Item]) -> Result<()> {
         use std::io::Write;
         let path = self.transcript_path(session_id);
@@ -496,3 +812,308 @@ impl SessionStore {
         Ok(())
     }
 }
+
+/// The most recent of the daily reset `hours` at or before `now` (local
+/// time), or `None` if no hours are configured.
+fn last_reset_time(now: NaiveDateTime, hours: &[u32]) -> Option<NaiveDateTime> {
+    hours
+        .iter()
+        .filter_map(|&h| now.date().and_hms_opt(h, 0, 0))
+        .map(|t| if t <= now { t } else { t - chrono::Duration::days(1) })
+        .max()
+}
+
+// ---------------------------------------------------------------------------
+// Integrity check
+// ---------------------------------------------------------------------------
+
+/// Inconsistencies between `sessions.json` and the transcripts on disk.
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    /// `<id>.jsonl` files with no session in `sessions.json`.
+    pub orphaned_transcripts: Vec<PathBuf>,
+    /// Keys of sessions that have turns but no transcript.
+    pub missing_transcripts: Vec<String>,
+    /// Transcripts with lines that don't parse, and how many.
+    pub bad_lines: Vec<(PathBuf, usize)>,
+}
+
+impl VerifyReport {
+    pub fn is_clean(&self) -> bool {
+        self.orphaned_transcripts.is_empty()
+            && self.missing_transcripts.is_empty()
+            && self.bad_lines.is_empty()
+    }
+}
+
+/// Cross-check `sessions.json` against the transcripts in `sessions_dir`.
+/// Works on the files directly, since a store with a corrupt transcript
+/// won't load. With `repair`, orphaned transcripts are deleted, sessions
+/// whose transcript is missing are dropped from `sessions.json`, and
+/// unparseable lines are removed. Archived transcripts are left alone.
+pub fn verify_sessions(sessions_dir: &Path, repair: bool) -> Result<VerifyReport> {
+    let mut report = VerifyReport::default();
+    let meta_path = sessions_dir.join("sessions.json");
+    let mut meta_map: HashMap<String, SessionMeta> = match std::fs::read_to_string(&meta_path) {
+        Ok(content) => serde_json::from_str(&content)
+            .map_err(|e| NekoError::Session(format!("Failed to parse sessions.json: {e}")))?,
+        Err(_) => HashMap::new(),
+    };
+    let known: HashSet<&str> = meta_map.values().map(|m| m.session_id.as_str()).collect();
+
+    let mut transcripts = HashSet::new();
+    for entry in std::fs::read_dir(sessions_dir)? {
+        let path = entry?.path();
+        // Live transcripts are `<id>.jsonl`; archives are `<id>.<ts>.jsonl`.
+        let Some(session_id) = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_suffix(".jsonl"))
+            .filter(|id| !id.contains('.'))
+        else {
+            continue;
+        };
+        if !known.contains(session_id) {
+            report.orphaned_transcripts.push(path);
+            continue;
+        }
+        transcripts.insert(session_id.to_string());
+
+        let content = std::fs::read_to_string(&path)?;
+        let lines: Vec<&str> = content.lines().filter(|l| !l.trim().is_empty()).collect();
+        let good: Vec<&str> = lines
+            .iter()
+            .copied()
+            .filter(|l| serde_json::from_str::<llm::Item>(l).is_ok())
+            .collect();
+        if good.len() < lines.len() {
+            report.bad_lines.push((path, lines.len() - good.len()));
+        }
+    }
+
+    report.missing_transcripts = meta_map
+        .iter()
+        .filter(|(_, m)| m.turn_count > 0 && !transcripts.contains(&m.session_id))
+        .map(|(key, _)| key.clone())
+        .collect();
+    report.orphaned_transcripts.sort();
+    report.missing_transcripts.sort();
+    report.bad_lines.sort();
+
+    if repair {
+        for path in &report.orphaned_transcripts {
+            std::fs::remove_file(path)?;
+        }
+        for (path, _) in &report.bad_lines {
+            let content = std::fs::read_to_string(path)?;
+            let mut kept = String::new();
+            for line in content.lines() {
+                if serde_json::from_str::<llm::Item>(line).is_ok() {
+                    kept.push_str(line);
+                    kept.push('\n');
+                }
+            }
+            let tmp_path = path.with_extension("jsonl.tmp");
+            std::fs::write(&tmp_path, kept)?;
+            std::fs::rename(&tmp_path, path)?;
+        }
+        if !report.missing_transcripts.is_empty() {
+            meta_map.retain(|key, _| !report.missing_transcripts.contains(key));
+            let json = serde_json::to_string_pretty(&meta_map)
+                .map_err(|e| NekoError::Session(format!("Failed to serialize sessions: {e}")))?;
+            let tmp_path = sessions_dir.join("sessions.json.tmp");
+            std::fs::write(&tmp_path, json.as_bytes())?;
+            std::fs::rename(&tmp_path, &meta_path)?;
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_verify_sessions_repairs() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SessionStore::new(dir.path().to_path_buf(), SessionConfig::default());
+        let kept = store.get_or_create(&SessionKey::main_dm(), None, None).await.unwrap();
+        let history = vec![llm::Item::Message {
+            role: llm::Role::User,
+            content: "hi".into(),
+        }];
+        store.update_history(&kept, history.clone(), None, None).await.unwrap();
+        let lost = store
+            .get_or_create(&SessionKey::cli("lost"), None, None)
+            .await
+            .unwrap();
+        store.update_history(&lost, history, None, None).await.unwrap();
+
+        std::fs::remove_file(dir.path().join(format!("{lost}.jsonl"))).unwrap();
+        std::fs::write(dir.path().join("orphan.jsonl"), "").unwrap();
+        let transcript = dir.path().join(format!("{kept}.jsonl"));
+        let mut content = std::fs::read_to_string(&transcript).unwrap();
+        content.push_str("{not json\n");
+        std::fs::write(&transcript, content).unwrap();
+
+        let report = verify_sessions(dir.path(), true).unwrap();
+        assert_eq!(report.orphaned_transcripts, vec![dir.path().join("orphan.jsonl")]);
+        assert_eq!(report.missing_transcripts, vec!["neko:cli:lost".to_string()]);
+        assert_eq!(report.bad_lines, vec![(transcript, 1)]);
+
+        assert!(verify_sessions(dir.path(), false).unwrap().is_clean());
+        let store = SessionStore::new(dir.path().to_path_buf(), SessionConfig::default());
+        store.load_from_disk().await.unwrap();
+        assert_eq!(store.list().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_archives_only_dropped_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SessionStore::new(dir.path().to_path_buf(), SessionConfig::default());
+        let sid = store.get_or_create(&SessionKey::main_dm(), None, None).await.unwrap();
+        let items: Vec<_> = ["a", "b", "c", "d", "e"]
+            .into_iter()
+            .map(|text| llm::Item::Message {
+                role: llm::Role::User,
+                content: text.into(),
+            })
+            .collect();
+        store.update_history(&sid, items[..4].to_vec(), None, None).await.unwrap();
+        let archives = || {
+            let mut contents: Vec<String> = std::fs::read_dir(dir.path())
+                .unwrap()
+                .map(|e| e.unwrap().path())
+                .filter(|p| {
+                    let name = p.file_name().unwrap().to_string_lossy();
+                    name.starts_with(&format!("{sid}.")) && name != format!("{sid}.jsonl")
+                })
+                .map(|p| std::fs::read_to_string(p).unwrap())
+                .collect();
+            contents.sort_by_key(|c| c.lines().count());
+            contents
+        };
+        let line = |item: &llm::Item| serde_json::to_string(item).unwrap();
+
+        // History trimmed to c, d plus a new e: only a, b are archived.
+        store.rewrite_transcript(&sid, &items[2..]).unwrap();
+        assert_eq!(archives(), vec![format!("{}\n{}\n", line(&items[0]), line(&items[1]))]);
+        assert_eq!(store.load_transcript(&sid).unwrap().len(), 3);
+
+        // Twice within a second still gets its own archive.
+        store.rewrite_transcript(&sid, &items[..1]).unwrap();
+        assert_eq!(archives().len(), 2);
+        assert_eq!(archives()[1].lines().count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_compaction_holds_session_until_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SessionStore::new(dir.path().to_path_buf(), SessionConfig::default());
+        let sid = store.get_or_create(&SessionKey::main_dm(), None, None).await.unwrap();
+        let message = |text: &str| llm::Item::Message {
+            role: llm::Role::User,
+            content: text.into(),
+        };
+        let history: Vec<_> = (0..4).map(|i| message(&i.to_string())).collect();
+        store.update_history(&sid, history.clone(), None, None).await.unwrap();
+
+        // A turn finishes while the summary is being written: it must land
+        // after the compaction rather than be overwritten by it.
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let compact = store.compact_with(&sid, |_| async move {
+            rx.await.ok();
+            Ok(vec![message("summary")])
+        });
+        let mut turn = history.clone();
+        turn.push(message("new"));
+        let finish_turn = async {
+            tokio::task::yield_now().await;
+            tx.send(()).unwrap();
+            store.update_history(&sid, turn, None, None).await
+        };
+        let (compacted, finished) = tokio::join!(compact, finish_turn);
+        assert_eq!(compacted.unwrap(), (4, 1));
+        finished.unwrap();
+        assert_eq!(store.get_history(&sid).await.unwrap().0.len(), 5);
+
+        assert!(store.compact_with("missing", |h| async { Ok(h) }).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_max_history_override_survives_reset_and_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SessionStore::new(dir.path().to_path_buf(), SessionConfig::default());
+        let id = store.get_or_create(&SessionKey::main_dm(), None, None).await.unwrap();
+        store.set_max_history_override(&id, Some(200)).await.unwrap();
+        store.reset(&id).await.unwrap();
+        assert_eq!(store.meta(&id).await.unwrap().max_history_override, Some(200));
+
+        let reloaded = SessionStore::new(dir.path().to_path_buf(), SessionConfig::default());
+        reloaded.load_from_disk().await.unwrap();
+        assert_eq!(reloaded.meta(&id).await.unwrap().max_history_override, Some(200));
+    }
+
+    #[tokio::test]
+    async fn test_greeting_only_on_first_contact() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SessionStore::new(dir.path().to_path_buf(), SessionConfig::default());
+        let id = store.get_or_create(&SessionKey::main_dm(), None, None).await.unwrap();
+        assert!(store.needs_greeting(&id).await.unwrap());
+        store.mark_greeted(&id).await.unwrap();
+        store.reset(&id).await.unwrap();
+        assert!(!store.needs_greeting(&id).await.unwrap());
+
+        // Sessions from before greetings existed aren't greeted after a reset
+        let other = SessionKey::channel_peer("telegram", "7");
+        let old = store.get_or_create(&other, None, None).await.unwrap();
+        store.update_history(&old, Vec::new(), None, None).await.unwrap();
+        store.reset(&old).await.unwrap();
+        assert!(!store.needs_greeting(&old).await.unwrap());
+
+        let reloaded = SessionStore::new(dir.path().to_path_buf(), SessionConfig::default());
+        reloaded.load_from_disk().await.unwrap();
+        assert!(!reloaded.needs_greeting(&id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_channel_reset_override() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = SessionConfig {
+            reset_mode: ResetMode::Idle,
+            idle_minutes: Some(0),
+            ..Default::default()
+        };
+        config.channel_reset.insert(
+            "http".to_string(),
+            crate::config::ChannelResetConfig {
+                reset_mode: Some(ResetMode::Never),
+                ..Default::default()
+            },
+        );
+        let store = SessionStore::new(dir.path().to_path_buf(), config);
+        let chat = store
+            .get_or_create(&SessionKey::channel_peer("telegram", "1"), Some("telegram"), None)
+            .await
+            .unwrap();
+        let api = store
+            .get_or_create(&SessionKey::channel_peer("http", "1"), Some("http"), None)
+            .await
+            .unwrap();
+        assert!(store.check_reset(&chat).await.unwrap());
+        assert!(!store.check_reset(&api).await.unwrap());
+    }
+
+    #[test]
+    fn test_last_reset_time() {
+        let at = |d: u32, h: u32, m: u32| {
+            NaiveDate::from_ymd_opt(2025, 3, d).unwrap().and_hms_opt(h, m, 0).unwrap()
+        };
+        assert_eq!(last_reset_time(at(10, 17, 30), &[4, 16]), Some(at(10, 16, 0)));
+        assert_eq!(last_reset_time(at(10, 10, 0), &[4, 16]), Some(at(10, 4, 0)));
+        assert_eq!(last_reset_time(at(10, 2, 0), &[4, 16]), Some(at(9, 16, 0)));
+        assert_eq!(last_reset_time(at(10, 4, 0), &[4]), Some(at(10, 4, 0)));
+        assert_eq!(last_reset_time(at(10, 2, 0), &[]), None);
+    }
+}
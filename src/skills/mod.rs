@@ -1,9 +1,11 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
 
 use serde::Deserialize;
 
+use crate::config::{SkillToolPolicy, SkillsConfig};
 use crate::error::{NekoError, Result};
+use crate::llm;
 
 /// AgentSkills.io-compatible skill.
 /// See https://agentskills.io/specification
@@ -22,7 +24,7 @@ pub struct Skill {
     pub path: PathBuf,
 }
 
-/// YAML frontmatter parsed from SKILL.md
+/// Frontmatter parsed from SKILL.md (YAML, TOML or JSON)
 #[derive(Debug, Deserialize)]
 struct SkillFrontmatter {
     name: String,
@@ -37,30 +39,56 @@ struct SkillFrontmatter {
     allowed_tools: Option<String>,
 }
 
+/// Split SKILL.md into its frontmatter and body. The frontmatter is YAML
+/// between `---` fences (the default), TOML between `+++` fences, or a
+/// leading JSON object.
+fn parse_frontmatter<'a>(content: &'a str, path: &Path) -> Result<(SkillFrontmatter, &'a str)> {
+    if content.starts_with('{') {
+        let mut stream = serde_json::Deserializer::from_str(content).into_iter();
+        let frontmatter: SkillFrontmatter = stream
+            .next()
+            .transpose()
+            .map_err(|e| NekoError::Config(format!("Failed to parse skill JSON: {e}")))?
+            .ok_or_else(|| {
+                NekoError::Config(format!(
+                    "Skill at {} has invalid frontmatter format",
+                    path.display()
+                ))
+            })?;
+        return Ok((frontmatter, &content[stream.byte_offset()..]));
+    }
+
+    let fence = if content.starts_with("+++") { "+++" } else { "---" };
+    if !content.starts_with(fence) {
+        return Err(NekoError::Config(format!(
+            "Skill at {} missing frontmatter (expected ---, +++ or a JSON object)",
+            path.display()
+        )));
+    }
+
+    let parts: Vec<&str> = content.splitn(3, fence).collect();
+    if parts.len() < 3 {
+        return Err(NekoError::Config(format!(
+            "Skill at {} has invalid frontmatter format",
+            path.display()
+        )));
+    }
+
+    let frontmatter = if fence == "+++" {
+        toml::from_str(parts[1])
+            .map_err(|e| NekoError::Config(format!("Failed to parse skill TOML: {e}")))?
+    } else {
+        serde_yaml::from_str(parts[1])
+            .map_err(|e| NekoError::Config(format!("Failed to parse skill YAML: {e}")))?
+    };
+    Ok((frontmatter, parts[2]))
+}
+
 impl Skill {
     /// Load a skill from a SKILL.md file.
     pub fn load(path: &Path) -> Result<Self> {
         let content = std::fs::read_to_string(path)?;
-
-        if !content.starts_with("---") {
-            return Err(NekoError::Config(format!(
-                "Skill at {} missing YAML frontmatter",
-                path.display()
-            )));
-        }
-
-        let parts: Vec<&str> = content.splitn(3, "---").collect();
-        if parts.len() < 3 {
-            return Err(NekoError::Config(format!(
-                "Skill at {} has invalid frontmatter format",
-                path.display()
-            )));
-        }
-
-        let frontmatter: SkillFrontmatter =
-            serde_yaml::from_str(parts[1]).map_err(|e| {
-                NekoError::Config(format!("Failed to parse skill YAML: {e}"))
-            })?;
+        let (frontmatter, body) = parse_frontmatter(&content, path)?;
 
         // Validate name
         validate_skill_name(&frontmatter.name)?;
@@ -77,7 +105,7 @@ impl Skill {
             compatibility: frontmatter.compatibility,
             metadata: frontmatter.metadata,
             allowed_tools,
-            instructions: parts[2].trim().to_string(),
+            instructions: body.trim().to_string(),
             path: path.parent().unwrap_or(path).to_path_buf(),
         })
     }
@@ -86,26 +114,7 @@ impl Skill {
     /// Used for progressive disclosure.
     pub fn load_metadata(path: &Path) -> Result<(String, String, PathBuf)> {
         let content = std::fs::read_to_string(path)?;
-
-        if !content.starts_with("---") {
-            return Err(NekoError::Config(format!(
-                "Skill at {} missing YAML frontmatter",
-                path.display()
-            )));
-        }
-
-        let parts: Vec<&str> = content.splitn(3, "---").collect();
-        if parts.len() < 3 {
-            return Err(NekoError::Config(format!(
-                "Skill at {} has invalid frontmatter format",
-                path.display()
-            )));
-        }
-
-        let frontmatter: SkillFrontmatter =
-            serde_yaml::from_str(parts[1]).map_err(|e| {
-                NekoError::Config(format!("Failed to parse skill YAML: {e}"))
-            })?;
+        let (frontmatter, _) = parse_frontmatter(&content, path)?;
 
         Ok((
             frontmatter.name,
@@ -115,6 +124,44 @@ impl Skill {
     }
 }
 
+/// Problems with a parsed skill that don't stop it from loading, for
+/// `neko skills validate`. `known_tools` are the tools this install
+/// offers; MCP tools can't be known up front.
+pub fn lint_skill(skill: &Skill, known_tools: &[&str]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let dir_name = skill.path.file_name().and_then(|n| n.to_str());
+    if dir_name.is_some_and(|d| d != skill.name) {
+        warnings.push(format!(
+            "directory name '{}' differs from skill name '{}' (install uses the skill name)",
+            dir_name.unwrap_or_default(),
+            skill.name
+        ));
+    }
+    if skill.description.trim().is_empty() {
+        warnings.push("description is empty; the agent picks skills by it".to_string());
+    } else if skill.description.chars().count() > 1024 {
+        warnings.push("description is longer than 1024 characters".to_string());
+    }
+    if let Some(compatibility) = &skill.compatibility {
+        if compatibility.trim().is_empty() {
+            warnings.push("compatibility is empty".to_string());
+        } else if compatibility.chars().count() > 500 {
+            warnings.push("compatibility is longer than 500 characters".to_string());
+        }
+    }
+    for tool in &skill.allowed_tools {
+        if !known_tools.contains(&tool.as_str()) {
+            warnings.push(format!(
+                "allowed-tools names '{tool}', which is not a built-in tool (fine if an MCP server provides it)"
+            ));
+        }
+    }
+    if skill.instructions.trim().is_empty() {
+        warnings.push("SKILL.md has no instructions after the frontmatter".to_string());
+    }
+    warnings
+}
+
 fn validate_skill_name(name: &str) -> Result<()> {
     if name.is_empty() || name.len() > 64 {
         return Err(NekoError::Config(
@@ -139,16 +186,40 @@ fn validate_skill_name(name: &str) -> Result<()> {
     Ok(())
 }
 
-/// Discover and load all skills from the workspace skills directory.
-pub fn load_skills(workspace: &Path) -> Result<Vec<Skill>> {
-    let skills_dir = workspace.join("skills");
+/// Discover and load all skills from the workspace skills directory, then
+/// from `extra_dirs` (`[skills] paths`) in order. A skill whose name is
+/// already loaded is skipped, so workspace skills override shared ones.
+pub fn load_skills(workspace: &Path, extra_dirs: &[PathBuf]) -> Result<Vec<Skill>> {
+    let mut skills = load_skills_dir(&workspace.join("skills"));
+    for dir in extra_dirs {
+        if !dir.is_dir() {
+            tracing::warn!("Skills path {} is not a directory; skipping", dir.display());
+            continue;
+        }
+        for skill in load_skills_dir(dir) {
+            if skills.iter().any(|s| s.name == skill.name) {
+                tracing::debug!(
+                    "Skill '{}' in {} is overridden by an earlier one",
+                    skill.name,
+                    dir.display()
+                );
+                continue;
+            }
+            skills.push(skill);
+        }
+    }
+    Ok(skills)
+}
+
+/// Load every SKILL.md under `skills_dir`.
+fn load_skills_dir(skills_dir: &Path) -> Vec<Skill> {
     if !skills_dir.exists() {
-        return Ok(vec![]);
+        return vec![];
     }
 
     let mut skills = Vec::new();
 
-    for entry in walkdir::WalkDir::new(&skills_dir)
+    for entry in walkdir::WalkDir::new(skills_dir)
         .follow_links(true)
         .into_iter()
         .filter_map(|e| e.ok())
@@ -162,12 +233,13 @@ pub fn load_skills(workspace: &Path) -> Result<Vec<Skill>> {
         }
     }
 
-    Ok(skills)
+    skills
 }
 
 /// Generate XML for available skills in system prompt (progressive disclosure).
-pub fn skills_to_prompt_xml(skills: &[Skill]) -> String {
-    if skills.is_empty() {
+pub fn skills_to_prompt_xml<'a>(skills: impl IntoIterator<Item = &'a Skill>) -> String {
+    let mut skills = skills.into_iter().peekable();
+    if skills.peek().is_none() {
         return String::new();
     }
 
@@ -184,6 +256,117 @@ pub fn skills_to_prompt_xml(skills: &[Skill]) -> String {
     xml
 }
 
+/// Common English words that say nothing about which skill fits.
+const STOPWORDS: &[&str] = &[
+    "the", "and", "for", "you", "your", "with", "this", "that", "are", "was", "but", "not",
+    "can", "all", "from", "have", "has", "what", "when", "how", "please", "about", "into",
+    "some", "any", "our", "out", "its", "use", "get",
+];
+
+/// Lowercase words of three or more characters in `text`, without
+/// stopwords.
+pub(crate) fn keywords(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() >= 3 && !STOPWORDS.contains(w))
+        .map(String::from)
+        .collect()
+}
+
+/// How many of `words` appear in the skill's name or description.
+pub(crate) fn relevance(skill: &Skill, words: &[String]) -> usize {
+    let haystack = format!("{} {}", skill.name, skill.description).to_lowercase();
+    words.iter().filter(|w| haystack.contains(w.as_str())).count()
+}
+
+/// The skills to list in the system prompt: all of them, or with
+/// `max > 0` at most `max`, preferring those that share words with
+/// `message`. Ties keep install order.
+pub fn select_for_prompt<'a>(skills: &'a [Skill], max: usize, message: &str) -> Vec<&'a Skill> {
+    if max == 0 || skills.len() <= max {
+        return skills.iter().collect();
+    }
+    let words = keywords(message);
+    let mut scored: Vec<(usize, &Skill)> =
+        skills.iter().map(|s| (relevance(s, &words), s)).collect();
+    scored.sort_by_key(|s| std::cmp::Reverse(s.0));
+    scored.into_iter().take(max).map(|(_, s)| s).collect()
+}
+
+/// Skills the agent has activated, i.e. whose SKILL.md it has read with
+/// `read_file` somewhere in `history`.
+pub fn active_skills<'a>(skills: &'a [Skill], history: &[llm::Item]) -> Vec<&'a Skill> {
+    let read_paths: Vec<String> = history
+        .iter()
+        .filter_map(|item| match item {
+            llm::Item::FunctionCall { name, arguments, .. } if name == "read_file" => {
+                serde_json::from_str::<serde_json::Value>(arguments)
+                    .ok()?
+                    .get("path")?
+                    .as_str()
+                    .map(String::from)
+            }
+            _ => None,
+        })
+        .collect();
+
+    skills
+        .iter()
+        .filter(|skill| {
+            let Some(dir) = skill.path.file_name() else {
+                return false;
+            };
+            // Component-wise, so `notweb/SKILL.md` doesn't activate `web`
+            let suffix = Path::new(dir).join("SKILL.md");
+            read_paths.iter().any(|p| Path::new(p).ends_with(&suffix))
+        })
+        .collect()
+}
+
+/// Tools the model may use given the active skills, or `None` for no
+/// restriction.
+///
+/// A skill without `allowed-tools` allows everything, so it makes a union
+/// unrestricted and drops out of an intersection. `config.base_tools` are
+/// always added. If the skills' lists don't overlap at all, only the base
+/// tools remain: conflicting restrictions never widen the tool set.
+pub fn resolve_allowed_tools(
+    config: &SkillsConfig,
+    active: &[&Skill],
+) -> Option<BTreeSet<String>> {
+    let constrained: Vec<&Vec<String>> = active
+        .iter()
+        .map(|s| &s.allowed_tools)
+        .filter(|tools| !tools.is_empty())
+        .collect();
+    if constrained.is_empty() {
+        return None;
+    }
+
+    let mut allowed: BTreeSet<String> = match config.tool_policy {
+        SkillToolPolicy::Ignore => return None,
+        SkillToolPolicy::Union => {
+            if constrained.len() < active.len() {
+                return None;
+            }
+            constrained.into_iter().flatten().cloned().collect()
+        }
+        SkillToolPolicy::Intersection => {
+            let mut lists = constrained.into_iter();
+            let first: BTreeSet<String> = lists.next()?.iter().cloned().collect();
+            lists.fold(first, |acc, tools| {
+                acc.into_iter().filter(|t| tools.contains(t)).collect()
+            })
+        }
+    };
+    if allowed.is_empty() {
+        tracing::warn!("Active skills allow no common tools; only base_tools remain");
+    }
+
+    allowed.extend(config.base_tools.iter().cloned());
+    Some(allowed)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -236,6 +419,45 @@ Do the test thing.
         assert_eq!(skill.metadata.get("author").unwrap(), "test");
     }
 
+    #[test]
+    fn test_load_skill_toml_frontmatter() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("SKILL.md");
+        std::fs::write(
+            &path,
+            "+++\nname = \"toml-skill\"\ndescription = \"Written in TOML.\"\n\
+             allowed-tools = \"exec read_file\"\n\n[metadata]\nauthor = \"ada\"\n+++\n\nBody here.\n",
+        )
+        .unwrap();
+
+        let skill = Skill::load(&path).unwrap();
+        assert_eq!(skill.name, "toml-skill");
+        assert_eq!(skill.allowed_tools, vec!["exec", "read_file"]);
+        assert_eq!(skill.metadata.get("author").unwrap(), "ada");
+        assert_eq!(skill.instructions, "Body here.");
+    }
+
+    #[test]
+    fn test_load_skill_json_frontmatter() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("SKILL.md");
+        std::fs::write(
+            &path,
+            "{\"name\": \"json-skill\", \"description\": \"Written in JSON.\", \"license\": \"MIT\"}\n\n# Steps\nDo it.\n",
+        )
+        .unwrap();
+
+        let skill = Skill::load(&path).unwrap();
+        assert_eq!(skill.name, "json-skill");
+        assert_eq!(skill.license.as_deref(), Some("MIT"));
+        assert_eq!(skill.instructions, "# Steps\nDo it.");
+
+        std::fs::write(&path, "{\"name\": \"broken\"\n").unwrap();
+        assert!(Skill::load(&path).is_err());
+        std::fs::write(&path, "no frontmatter").unwrap();
+        assert!(Skill::load(&path).is_err());
+    }
+
     #[test]
     fn test_load_skills_from_workspace() {
         let tmp = TempDir::new().unwrap();
@@ -249,11 +471,35 @@ Do the test thing.
         )
         .unwrap();
 
-        let skills = load_skills(tmp.path()).unwrap();
+        let skills = load_skills(tmp.path(), &[]).unwrap();
         assert_eq!(skills.len(), 1);
         assert_eq!(skills[0].name, "my-skill");
     }
 
+    #[test]
+    fn test_load_skills_shared_paths() {
+        let tmp = TempDir::new().unwrap();
+        let write = |dir: PathBuf, name: &str, description: &str| {
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(
+                dir.join("SKILL.md"),
+                format!("---\nname: {name}\ndescription: {description}\n---\n\nBody.\n"),
+            )
+            .unwrap();
+        };
+        let shared = tmp.path().join("shared");
+        write(tmp.path().join("skills/notes"), "notes", "Local notes.");
+        write(shared.join("notes"), "notes", "Shared notes.");
+        write(shared.join("pdf"), "pdf", "Shared pdf.");
+
+        let skills = load_skills(tmp.path(), &[shared, tmp.path().join("missing")]).unwrap();
+        let loaded: Vec<(&str, &str)> = skills
+            .iter()
+            .map(|s| (s.name.as_str(), s.description.as_str()))
+            .collect();
+        assert_eq!(loaded, vec![("notes", "Local notes."), ("pdf", "Shared pdf.")]);
+    }
+
     #[test]
     fn test_skills_to_prompt_xml() {
         let skills = vec![Skill {
@@ -272,4 +518,137 @@ Do the test thing.
         assert!(xml.contains("<name>test</name>"));
         assert!(xml.contains("<description>Test skill</description>"));
     }
+
+    fn skill_with_tools(name: &str, tools: &[&str]) -> Skill {
+        Skill {
+            name: name.to_string(),
+            description: String::new(),
+            license: None,
+            compatibility: None,
+            metadata: HashMap::new(),
+            allowed_tools: tools.iter().map(|t| t.to_string()).collect(),
+            instructions: String::new(),
+            path: PathBuf::from(format!("/ws/skills/{name}")),
+        }
+    }
+
+    fn resolve(policy: SkillToolPolicy, active: &[&Skill]) -> Option<Vec<String>> {
+        let config = SkillsConfig {
+            tool_policy: policy,
+            ..SkillsConfig::default()
+        };
+        resolve_allowed_tools(&config, active).map(|set| set.into_iter().collect())
+    }
+
+    #[test]
+    fn test_resolve_allowed_tools_policies() {
+        let web = skill_with_tools("web", &["http_request", "exec"]);
+        let shell = skill_with_tools("shell", &["exec", "write_file"]);
+        let open = skill_with_tools("open", &[]);
+        let disjoint = skill_with_tools("notes", &["memory_write"]);
+
+        assert_eq!(resolve(SkillToolPolicy::Ignore, &[&web, &shell]), None);
+        assert_eq!(
+            resolve(SkillToolPolicy::Union, &[&web, &shell]),
+            Some(vec!["exec".into(), "http_request".into(), "read_file".into(), "write_file".into()])
+        );
+        assert_eq!(
+            resolve(SkillToolPolicy::Intersection, &[&web, &shell]),
+            Some(vec!["exec".into(), "read_file".into()])
+        );
+
+        // A skill without allowed-tools allows everything.
+        assert_eq!(resolve(SkillToolPolicy::Union, &[&web, &open]), None);
+        assert_eq!(
+            resolve(SkillToolPolicy::Intersection, &[&web, &open]),
+            Some(vec!["exec".into(), "http_request".into(), "read_file".into()])
+        );
+
+        // No overlap leaves only the base tools, never the full tool set.
+        assert_eq!(
+            resolve(SkillToolPolicy::Intersection, &[&web, &disjoint]),
+            Some(vec!["read_file".into()])
+        );
+        assert_eq!(resolve(SkillToolPolicy::Union, &[]), None);
+    }
+
+    #[test]
+    fn test_disjoint_skills_fall_back_to_base_tools() {
+        let web = skill_with_tools("web", &["http_request"]);
+        let notes = skill_with_tools("notes", &["memory_write"]);
+        let mut config = SkillsConfig {
+            tool_policy: SkillToolPolicy::Intersection,
+            base_tools: vec!["list_files".to_string(), "read_file".to_string()],
+            ..SkillsConfig::default()
+        };
+        let allowed = resolve_allowed_tools(&config, &[&web, &notes]).unwrap();
+        assert_eq!(allowed.into_iter().collect::<Vec<_>>(), ["list_files", "read_file"]);
+
+        // With no base tools, nothing is allowed rather than everything.
+        config.base_tools.clear();
+        assert_eq!(resolve_allowed_tools(&config, &[&web, &notes]), Some(BTreeSet::new()));
+    }
+
+    #[test]
+    fn test_lint_skill() {
+        let mut skill = skill_with_tools("web", &["http_request", "Bash"]);
+        skill.description = "Fetch pages".to_string();
+        skill.instructions = "Do it.".to_string();
+        assert_eq!(lint_skill(&skill, &["http_request"]).len(), 1);
+
+        skill.path = PathBuf::from("/src/web-skill");
+        skill.compatibility = Some(String::new());
+        let warnings = lint_skill(&skill, &["http_request", "Bash"]);
+        assert_eq!(warnings.len(), 2, "{warnings:?}");
+    }
+
+    #[test]
+    fn test_select_for_prompt() {
+        let mut skills = vec![
+            skill_with_tools("pdf-processing", &[]),
+            skill_with_tools("web-scraper", &[]),
+            skill_with_tools("calendar", &[]),
+        ];
+        skills[2].description = "Manage calendar events and meetings".to_string();
+        let names = |selected: Vec<&Skill>| -> Vec<String> {
+            selected.iter().map(|s| s.name.clone()).collect()
+        };
+
+        assert_eq!(select_for_prompt(&skills, 0, "anything").len(), 3);
+        assert_eq!(select_for_prompt(&skills, 5, "anything").len(), 3);
+        assert_eq!(
+            names(select_for_prompt(&skills, 1, "Book a meeting for Monday")),
+            vec!["calendar"]
+        );
+        assert_eq!(
+            names(select_for_prompt(&skills, 2, "hello")),
+            vec!["pdf-processing", "web-scraper"]
+        );
+        // "and" appears in the calendar description but doesn't count
+        assert_eq!(keywords("Scrape the web and more"), vec!["scrape", "web", "more"]);
+        assert_eq!(
+            names(select_for_prompt(&skills, 1, "scrape the web and save it")),
+            vec!["web-scraper"]
+        );
+    }
+
+    #[test]
+    fn test_active_skills_from_history() {
+        let skills = vec![skill_with_tools("web", &["exec"]), skill_with_tools("shell", &[])];
+        let read = |path: &str| {
+            vec![llm::Item::FunctionCall {
+                id: "fc_1".to_string(),
+                call_id: "call_1".to_string(),
+                name: "read_file".to_string(),
+                arguments: format!(r#"{{"path": "{path}"}}"#),
+            }]
+        };
+        let active: Vec<&str> = active_skills(&skills, &read("/ws/skills/web/SKILL.md"))
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(active, vec!["web"]);
+        assert!(active_skills(&skills, &read("skills/notweb/SKILL.md")).is_empty());
+        assert!(active_skills(&skills, &[]).is_empty());
+    }
 }
@@ -0,0 +1,41 @@
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::UnixDatagram;
+
+use tracing::{debug, warn};
+
+/// Send an `sd_notify` state (e.g. `READY=1`, `STOPPING=1`) to systemd via
+/// `$NOTIFY_SOCKET`. Does nothing when not started by systemd.
+pub fn notify(state: &str) {
+    let Some(path) = std::env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    match send(&path, state) {
+        Ok(()) => debug!("sd_notify: {state}"),
+        Err(e) => warn!("sd_notify {state} failed: {e}"),
+    }
+}
+
+fn send(path: &OsStr, state: &str) -> std::io::Result<()> {
+    let socket = UnixDatagram::unbound()?;
+    // A leading '@' names a socket in the abstract namespace (Linux only).
+    if let Some(name) = path.as_bytes().strip_prefix(b"@") {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            socket.send_to_addr(state.as_bytes(), &addr)?;
+            return Ok(());
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = name;
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "abstract NOTIFY_SOCKET is only supported on Linux",
+            ));
+        }
+    }
+    socket.send_to(state.as_bytes(), path)?;
+    Ok(())
+}
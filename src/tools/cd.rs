@@ -39,19 +39,11 @@ impl Tool for CdTool {
             Err(e) => return Ok(ToolResult::error(format!("Cannot resolve path: {e}"))),
         };
 
-        let workspace_canonical = match ctx.workspace.canonicalize() {
-            Ok(p) => p,
-            Err(e) => {
-                return Ok(ToolResult::error(format!(
-                    "Cannot resolve workspace: {e}"
-                )))
-            }
+        let root_canonical = match ctx.check_file_access(&canonical) {
+            Ok(root) => root,
+            Err(e) => return Ok(ToolResult::error(e)),
         };
 
-        if !canonical.starts_with(&workspace_canonical) {
-            return Ok(ToolResult::error("Path is outside workspace boundary"));
-        }
-
         if !canonical.is_dir() {
             return Ok(ToolResult::error(format!(
                 "Not a directory: {}",
@@ -62,9 +54,9 @@ impl Tool for CdTool {
         // Update the shared cwd
         *ctx.cwd.lock().unwrap() = canonical.clone();
 
-        // Show path relative to workspace for readability
+        // Show path relative to the file root for readability
         let display = canonical
-            .strip_prefix(&workspace_canonical)
+            .strip_prefix(&root_canonical)
             .map(|p| {
                 if p.as_os_str().is_empty() {
                     ".".to_string()
@@ -33,6 +33,11 @@ impl Tool for CronManageTool {
                     "type": "string",
                     "description": "(add/edit) Cron expression with 6 fields: 'sec min hour day month weekday' (e.g. '0 0 9 * * *' for daily at 9am)"
                 },
+                "preset": {
+                    "type": "string",
+                    "enum": cron::SCHEDULE_PRESETS.iter().map(|(name, _)| *name).collect::<Vec<_>>(),
+                    "description": "(add/edit) Named schedule to use instead of a cron expression; times are UTC. Mutually exclusive with schedule and at."
+                },
                 "at": {
                     "type": "string",
                     "description": "(add) One-shot datetime in 'YYYY-MM-DD HH:MM' format (local time). Mutually exclusive with schedule."
@@ -52,6 +57,14 @@ impl Tool for CronManageTool {
                 "enabled": {
                     "type": "boolean",
                     "description": "(edit) Enable or disable the job"
+                },
+                "session_key": {
+                    "type": "string",
+                    "description": "(add/edit) Run in a persistent session with this key so each run remembers earlier ones. Use 'none' to clear."
+                },
+                "jitter_secs": {
+                    "type": "integer",
+                    "description": "(add/edit) Delay each run by a random 0..N seconds to avoid many jobs starting at once. 0 disables; at most 3600."
                 }
             }),
             &["action"],
@@ -80,7 +93,11 @@ impl CronManageTool {
             _ => return Ok(ToolResult::error("'prompt' is required for add")),
         };
 
-        let schedule_str = params["schedule"].as_str().filter(|s| !s.is_empty());
+        let schedule_str = match schedule_param(params) {
+            Ok(s) => s,
+            Err(msg) => return Ok(ToolResult::error(msg)),
+        };
+        let schedule_str = schedule_str.as_deref();
         let at_str = params["at"].as_str().filter(|s| !s.is_empty());
 
         let schedule = match (schedule_str, at_str) {
@@ -101,12 +118,12 @@ impl CronManageTool {
             }
             (Some(_), Some(_)) => {
                 return Ok(ToolResult::error(
-                    "Specify either 'schedule' or 'at', not both",
+                    "Specify either 'schedule'/'preset' or 'at', not both",
                 ));
             }
             (None, None) => {
                 return Ok(ToolResult::error(
-                    "Must specify 'schedule' (cron expr) or 'at' (datetime)",
+                    "Must specify 'schedule' (cron expr), 'preset' or 'at' (datetime)",
                 ));
             }
         };
@@ -135,7 +152,13 @@ impl CronManageTool {
             keep_after_run: false,
             created_at: chrono::Utc::now(),
             last_run_at: None,
+            last_success_at: None,
             retry: cron::RetryState::default(),
+            session_key: params["session_key"]
+                .as_str()
+                .filter(|s| !s.is_empty() && *s != "none")
+                .map(String::from),
+            jitter_secs: jitter_param(params),
         };
 
         let mut jobs = match cron::load_jobs(&ctx.workspace) {
@@ -173,7 +196,7 @@ impl CronManageTool {
             let sched = match &job.schedule {
                 cron::Schedule::Cron { expr } => format!("cron: {expr}"),
                 cron::Schedule::At { datetime } => {
-                    format!("at: {}", datetime.format("%Y-%m-%d %H:%M"))
+                    format!("at: {}", cron::local_time(*datetime))
                 }
             };
             let announce = job
@@ -212,17 +235,25 @@ impl CronManageTool {
         if let Some(p) = params["prompt"].as_str().filter(|s| !s.is_empty()) {
             jobs[idx].prompt = p.to_string();
         }
-        if let Some(expr) = params["schedule"].as_str().filter(|s| !s.is_empty()) {
-            if let Err(e) = cron::validate_cron_expr(expr) {
+        let schedule_str = match schedule_param(params) {
+            Ok(s) => s,
+            Err(msg) => return Ok(ToolResult::error(msg)),
+        };
+        if let Some(expr) = schedule_str {
+            if let Err(e) = cron::validate_cron_expr(&expr) {
                 return Ok(ToolResult::error(format!("{e}")));
             }
-            jobs[idx].schedule = cron::Schedule::Cron {
-                expr: expr.to_string(),
-            };
+            jobs[idx].schedule = cron::Schedule::Cron { expr };
         }
         if let Some(n) = params["name"].as_str().filter(|s| !s.is_empty()) {
             jobs[idx].name = Some(n.to_string());
         }
+        if let Some(key) = params["session_key"].as_str().filter(|s| !s.is_empty()) {
+            jobs[idx].session_key = (key != "none").then(|| key.to_string());
+        }
+        if params.get("jitter_secs").is_some_and(|v| !v.is_null()) {
+            jobs[idx].jitter_secs = jitter_param(params);
+        }
         if let Some(e) = params["enabled"].as_bool() {
             jobs[idx].enabled = e;
             if e {
@@ -286,6 +317,29 @@ impl CronManageTool {
     }
 }
 
+/// The `schedule` expression, or the one `preset` names.
+fn schedule_param(params: &serde_json::Value) -> std::result::Result<Option<String>, String> {
+    let schedule = params["schedule"].as_str().filter(|s| !s.is_empty());
+    let preset = params["preset"].as_str().filter(|s| !s.is_empty());
+    match (schedule, preset) {
+        (Some(_), Some(_)) => Err("Specify either 'schedule' or 'preset', not both".to_string()),
+        (Some(expr), None) => Ok(Some(expr.to_string())),
+        (None, Some(name)) => cron::preset_expr(name)
+            .map(|expr| Some(expr.to_string()))
+            .map_err(|e| e.to_string()),
+        (None, None) => Ok(None),
+    }
+}
+
+/// `jitter_secs`, with 0 or a missing value meaning no jitter. Values above
+/// [`MAX_JITTER_SECS`](crate::cron::MAX_JITTER_SECS) are clamped.
+fn jitter_param(params: &serde_json::Value) -> Option<u32> {
+    params["jitter_secs"]
+        .as_u64()
+        .filter(|&s| s > 0)
+        .map(|s| s.min(u64::from(crate::cron::MAX_JITTER_SECS)) as u32)
+}
+
 fn parse_datetime_tool(s: &str) -> std::result::Result<chrono::DateTime<chrono::Utc>, String> {
     use chrono::Utc;
 
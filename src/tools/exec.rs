@@ -1,12 +1,32 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
+use regex::Regex;
 use serde_json::json;
+use tokio::sync::mpsc;
 
-use super::process_manager::{ProcessManager, SpawnResult};
-use super::{schema_object, Tool, ToolContext, ToolResult};
+use super::process_manager::{BackgroundSession, ProcessManager, SpawnResult};
+use super::{schema_object, ChannelContext, Tool, ToolContext, ToolResult};
+use crate::channels::OutboundMessage;
 use crate::error::Result;
 
+/// How often a streamed background command pushes new output to the channel.
+const STREAM_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Maximum characters of output per streamed update (the tail is kept).
+const STREAM_CHUNK_CHARS: usize = 3500;
+
+/// Commands that delete, overwrite or kill things; running one needs the
+/// user's go-ahead in sessions with confirmation enabled.
+const RISKY_COMMANDS: &[&str] = &[
+    "rm", "rmdir", "mv", "dd", "mkfs", "shred", "truncate", "chmod", "chown", "kill",
+    "pkill", "killall", "sudo", "shutdown", "reboot",
+];
+
+/// `git` subcommands that rewrite or discard history/work.
+const RISKY_GIT_SUBCOMMANDS: &[&str] = &["push", "reset", "clean", "checkout", "rebase"];
+
 pub struct ExecTool {
     allowlist: Vec<String>,
     timeout_secs: u64,
@@ -49,12 +69,32 @@ impl Tool for ExecTool {
                 "timeout": {
                     "type": "integer",
                     "description": "Optional per-command timeout in seconds (overrides default)"
+                },
+                "stream": {
+                    "type": "boolean",
+                    "description": "If the command is backgrounded, periodically push its new output to the user's chat (for long builds/tests). Avoid for noisy commands."
+                },
+                "grep": {
+                    "type": "string",
+                    "description": "Only return output lines matching this regex (applied after the command runs; no shell pipe needed)"
+                },
+                "head": {
+                    "type": "integer",
+                    "description": "Only return the first N output lines (after grep)"
+                },
+                "tail": {
+                    "type": "integer",
+                    "description": "Only return the last N output lines (after grep and head)"
                 }
             }),
             &["command"],
         )
     }
 
+    fn requires_confirmation(&self, params: &serde_json::Value) -> bool {
+        is_risky_command(params["command"].as_str().unwrap_or_default())
+    }
+
     async fn execute(&self, params: serde_json::Value, ctx: &ToolContext) -> Result<ToolResult> {
         let command = params["command"].as_str().unwrap_or_default();
 
@@ -72,10 +112,18 @@ impl Tool for ExecTool {
             .as_u64()
             .unwrap_or(self.timeout_secs);
 
+        let stream = params["stream"].as_bool().unwrap_or(false);
+
+        let filter = match OutputFilter::from_params(&params) {
+            Ok(filter) => filter,
+            Err(e) => return Ok(ToolResult::error(e)),
+        };
+
         let cwd = ctx.cwd.lock().unwrap().clone();
 
         match self.process_manager.spawn_or_yield(command, &cwd, timeout).await {
             Ok(SpawnResult::Completed { output, success }) => {
+                let output = filter.apply(&output);
                 if success {
                     Ok(ToolResult::success(output))
                 } else {
@@ -87,9 +135,33 @@ impl Tool for ExecTool {
                     "Command backgrounded as {session_id} (still running).\n\
                      Use `process` tool with action \"poll\" to check output."
                 );
+                if stream {
+                    let streaming = match (&ctx.channel, &ctx.outbound_tx) {
+                        (Some(channel), Some(tx)) => {
+                            match self.process_manager.get_session(&session_id).await {
+                                Some(session) => {
+                                    spawn_output_stream(
+                                        session,
+                                        output_so_far.len(),
+                                        channel.clone(),
+                                        tx.clone(),
+                                    );
+                                    true
+                                }
+                                None => false,
+                            }
+                        }
+                        _ => false,
+                    };
+                    msg.push_str(if streaming {
+                        "\nNew output will be streamed to the user's chat."
+                    } else {
+                        "\nStreaming unavailable (no live channel for this conversation)."
+                    });
+                }
                 if !output_so_far.is_empty() {
                     msg.push_str("\n\nOutput so far:\n");
-                    msg.push_str(&output_so_far);
+                    msg.push_str(&filter.apply(&output_so_far));
                 }
                 Ok(ToolResult::success(msg))
             }
@@ -97,3 +169,137 @@ impl Tool for ExecTool {
         }
     }
 }
+
+/// True if any command in a shell pipeline/list is in [`RISKY_COMMANDS`]
+/// (or a risky `git` subcommand).
+fn is_risky_command(command: &str) -> bool {
+    command
+        .split([';', '&', '|', '\n', '(', ')', '`'])
+        .any(|segment| {
+            let mut words = segment
+                .split_whitespace()
+                .skip_while(|w| w.contains('=') && !w.starts_with('-'));
+            let Some(program) = words.next() else {
+                return false;
+            };
+            let program = program.rsplit('/').next().unwrap_or(program);
+            if program == "git" {
+                return words
+                    .find(|w| !w.starts_with('-'))
+                    .is_some_and(|sub| RISKY_GIT_SUBCOMMANDS.contains(&sub));
+            }
+            RISKY_COMMANDS.contains(&program)
+        })
+}
+
+/// Line filters applied to captured output, so the model can narrow big
+/// output without shell pipes (which an allowlist may block).
+struct OutputFilter {
+    grep: Option<Regex>,
+    head: Option<usize>,
+    tail: Option<usize>,
+}
+
+impl OutputFilter {
+    fn from_params(params: &serde_json::Value) -> std::result::Result<Self, String> {
+        let grep = match params["grep"].as_str() {
+            Some(pattern) => Some(
+                Regex::new(pattern).map_err(|e| format!("Invalid grep pattern '{pattern}': {e}"))?,
+            ),
+            None => None,
+        };
+        Ok(Self {
+            grep,
+            head: params["head"].as_u64().map(|n| n as usize),
+            tail: params["tail"].as_u64().map(|n| n as usize),
+        })
+    }
+
+    fn apply(&self, output: &str) -> String {
+        if self.grep.is_none() && self.head.is_none() && self.tail.is_none() {
+            return output.to_string();
+        }
+
+        let all: Vec<&str> = output.lines().collect();
+        let mut lines: Vec<&str> = match &self.grep {
+            Some(re) => all.iter().copied().filter(|l| re.is_match(l)).collect(),
+            None => all.clone(),
+        };
+        if let Some(head) = self.head {
+            lines.truncate(head);
+        }
+        if let Some(tail) = self.tail {
+            let start = lines.len().saturating_sub(tail);
+            lines.drain(..start);
+        }
+
+        if lines.is_empty() {
+            return format!("[no matching lines; {} lines of output]", all.len());
+        }
+        let mut filtered = lines.join("\n");
+        if lines.len() < all.len() {
+            filtered.push_str(&format!("\n[showing {} of {} lines]", lines.len(), all.len()));
+        }
+        filtered
+    }
+}
+
+/// Push a background session's new output to the channel every
+/// `STREAM_INTERVAL` until it exits.
+fn spawn_output_stream(
+    session: Arc<BackgroundSession>,
+    mut offset: usize,
+    channel: ChannelContext,
+    tx: mpsc::Sender<OutboundMessage>,
+) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(STREAM_INTERVAL).await;
+            let (output, new_offset, status) = session.output_since(offset).await;
+            offset = new_offset;
+
+            let mut text = String::new();
+            if !output.trim().is_empty() {
+                let chars: Vec<char> = output.chars().collect();
+                let start = chars.len().saturating_sub(STREAM_CHUNK_CHARS);
+                if start > 0 {
+                    text.push_str("…\n");
+                }
+                text.extend(&chars[start..]);
+            }
+            if let Some(code) = status {
+                text.push_str(&format!("\n(exited with code {code})"));
+            }
+            if !text.trim().is_empty() {
+                let msg = OutboundMessage {
+                    channel: channel.channel.clone(),
+                    recipient_id: channel.recipient_id.clone(),
+                    text: format!("[{}] {}", session.id, text.trim_start()),
+                    attachments: Vec::new(),
+                };
+                if tx.try_send(msg).is_err() {
+                    tracing::debug!("Dropped streamed output for {}", session.id);
+                }
+            }
+            if status.is_some() || tx.is_closed() {
+                break;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_risky_command() {
+        assert!(is_risky_command("rm -rf build"));
+        assert!(is_risky_command("cargo build && /bin/rm target/x"));
+        assert!(is_risky_command("FOO=1 sudo apt install jq"));
+        assert!(is_risky_command("git push --force origin main"));
+        assert!(!is_risky_command("git status"));
+        assert!(!is_risky_command("ls -la | grep rm"));
+        assert!(!is_risky_command("echo 'format' > notes.txt"));
+    }
+}
@@ -95,13 +95,8 @@ impl Tool for HttpRequestTool {
         match req.send().await {
             Ok(resp) => {
                 let status = resp.status().as_u16();
+                // Long bodies are cut by the agent's `tools.max_output_bytes`
                 let body = resp.text().await.unwrap_or_default();
-                // Truncate very long responses
-                let body = if body.len() > 10_000 {
-                    format!("{}... [truncated, {} total bytes]", &body[..10_000], body.len())
-                } else {
-                    body
-                };
                 Ok(ToolResult::success(format!("HTTP {status}\n{body}")))
             }
             Err(e) => Ok(ToolResult::error(format!("HTTP request failed: {e}"))),
@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use serde_json::json;
+
+use super::{schema_object, Tool, ToolContext, ToolResult};
+use crate::error::Result;
+
+/// Largest serialized value accepted by `set`.
+const MAX_VALUE_BYTES: usize = 4096;
+
+/// Largest key or namespace name.
+const MAX_KEY_CHARS: usize = 128;
+
+const DEFAULT_NAMESPACE: &str = "default";
+
+/// Serializes read-modify-write of `kv.json` across concurrent turns.
+static KV_LOCK: Mutex<()> = Mutex::new(());
+
+/// namespace -> key -> value
+type KvData = BTreeMap<String, BTreeMap<String, serde_json::Value>>;
+
+fn load(path: &Path) -> std::result::Result<KvData, String> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content)
+            .map_err(|e| format!("memory/kv.json is corrupt: {e}")),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(KvData::new()),
+        Err(e) => Err(format!("Failed to read memory/kv.json: {e}")),
+    }
+}
+
+/// Write via a temp file + rename so a crash never leaves half a file.
+fn save(path: &Path, data: &KvData) -> std::result::Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create memory/: {e}"))?;
+    }
+    let json = serde_json::to_string_pretty(data).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json)
+        .and_then(|_| std::fs::rename(&tmp, path))
+        .map_err(|e| format!("Failed to write memory/kv.json: {e}"))
+}
+
+fn validate_name(kind: &str, name: &str) -> std::result::Result<(), String> {
+    if name.is_empty() {
+        return Err(format!("{kind} is required"));
+    }
+    if name.chars().count() > MAX_KEY_CHARS {
+        return Err(format!("{kind} is longer than {MAX_KEY_CHARS} chars"));
+    }
+    Ok(())
+}
+
+/// Persistent JSON key-value store at `workspace/memory/kv.json`, for
+/// structured state (counters, flags, small records) that prose memory
+/// handles badly.
+pub struct KvTool;
+
+#[async_trait]
+impl Tool for KvTool {
+    fn name(&self) -> &str {
+        "kv"
+    }
+
+    fn description(&self) -> &str {
+        "Persistent key-value store for structured state (counters, flags, small records). \
+         Values are arbitrary JSON. Actions: get, set, delete, list."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        schema_object(
+            json!({
+                "action": {
+                    "type": "string",
+                    "enum": ["get", "set", "delete", "list"],
+                    "description": "Operation to perform"
+                },
+                "namespace": {
+                    "type": "string",
+                    "description": "Key namespace (default: \"default\"). For list, omit to list namespaces."
+                },
+                "key": {
+                    "type": "string",
+                    "description": "Key (required for get, set, delete)"
+                },
+                "value": {
+                    "description": "JSON value to store (set only)"
+                }
+            }),
+            &["action"],
+        )
+    }
+
+    async fn execute(&self, params: serde_json::Value, ctx: &ToolContext) -> Result<ToolResult> {
+        let action = params["action"].as_str().unwrap_or_default();
+        let namespace = params["namespace"].as_str();
+        let key = params["key"].as_str().unwrap_or_default();
+        let path = ctx.workspace.join("memory").join("kv.json");
+
+        if action == "set" {
+            if let Some(msg) = crate::workspace::check_quota(&ctx.workspace, ctx.workspace_quota) {
+                tracing::warn!("{msg}");
+                return Ok(ToolResult::error(msg));
+            }
+        }
+
+        let result = {
+            let _guard = KV_LOCK.lock().unwrap();
+            run_action(&path, action, namespace, key, &params["value"])
+        };
+        Ok(match result {
+            Ok(output) => ToolResult::success(output),
+            Err(e) => ToolResult::error(e),
+        })
+    }
+}
+
+fn run_action(
+    path: &Path,
+    action: &str,
+    namespace: Option<&str>,
+    key: &str,
+    value: &serde_json::Value,
+) -> std::result::Result<String, String> {
+    let mut data = load(path)?;
+
+    if action == "list" {
+        return Ok(match namespace {
+            None => {
+                if data.is_empty() {
+                    "No keys stored.".to_string()
+                } else {
+                    data.iter()
+                        .map(|(ns, keys)| format!("{ns} ({} keys)", keys.len()))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                }
+            }
+            Some(ns) => match data.get(ns) {
+                Some(keys) if !keys.is_empty() => {
+                    keys.keys().cloned().collect::<Vec<_>>().join("\n")
+                }
+                _ => format!("No keys in namespace '{ns}'."),
+            },
+        });
+    }
+
+    let namespace = namespace.unwrap_or(DEFAULT_NAMESPACE);
+    validate_name("namespace", namespace)?;
+    validate_name("key", key)?;
+
+    match action {
+        "get" => data
+            .get(namespace)
+            .and_then(|keys| keys.get(key))
+            .map(|v| v.to_string())
+            .ok_or_else(|| format!("Key not found: {namespace}/{key}")),
+        "set" => {
+            if value.is_null() {
+                return Err("value is required for set (use delete to remove a key)".to_string());
+            }
+            let size = value.to_string().len();
+            if size > MAX_VALUE_BYTES {
+                return Err(format!(
+                    "Value is {size} bytes (max {MAX_VALUE_BYTES}); store large content in a memory file instead"
+                ));
+            }
+            data.entry(namespace.to_string())
+                .or_default()
+                .insert(key.to_string(), value.clone());
+            save(path, &data)?;
+            Ok(format!("Set {namespace}/{key}"))
+        }
+        "delete" => {
+            let removed = data
+                .get_mut(namespace)
+                .and_then(|keys| keys.remove(key))
+                .is_some();
+            if !removed {
+                return Err(format!("Key not found: {namespace}/{key}"));
+            }
+            if data.get(namespace).is_some_and(|keys| keys.is_empty()) {
+                data.remove(namespace);
+            }
+            save(path, &data)?;
+            Ok(format!("Deleted {namespace}/{key}"))
+        }
+        other => Err(format!(
+            "Unknown action '{other}'. Use get, set, delete, or list."
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_kv_roundtrip() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("memory").join("kv.json");
+        let none = serde_json::Value::Null;
+
+        run_action(&path, "set", Some("stats"), "count", &json!(3)).unwrap();
+        run_action(&path, "set", None, "user", &json!({"name": "ada"})).unwrap();
+        assert_eq!(run_action(&path, "get", Some("stats"), "count", &none).unwrap(), "3");
+        assert_eq!(
+            run_action(&path, "list", None, "", &none).unwrap(),
+            "default (1 keys)\nstats (1 keys)"
+        );
+
+        run_action(&path, "delete", Some("stats"), "count", &none).unwrap();
+        assert!(run_action(&path, "get", Some("stats"), "count", &none).is_err());
+
+        let big = json!("x".repeat(MAX_VALUE_BYTES));
+        assert!(run_action(&path, "set", None, "big", &big).is_err());
+    }
+}
@@ -1,10 +1,111 @@
+use std::path::{Path, PathBuf};
+
 use async_trait::async_trait;
 use serde_json::json;
 
 use super::{schema_object, Tool, ToolContext, ToolResult};
-use crate::error::Result;
+use crate::error::{NekoError, Result};
+use crate::fs_util::write_atomic;
+
+/// Number of backups kept per memory file.
+const MAX_BACKUPS: usize = 10;
+
+/// Suffix of backup names, `<file>.<timestamp>`.
+const BACKUP_TIMESTAMP: &str = "%Y%m%dT%H%M%S%3f";
+
+pub struct MemoryFlushTool {
+    /// Largest resulting file size in bytes; 0 = unlimited.
+    max_file_bytes: u64,
+}
+
+impl MemoryFlushTool {
+    pub fn new(max_file_bytes: u64) -> Self {
+        Self { max_file_bytes }
+    }
+}
+
+fn backups_dir(memory_dir: &Path) -> PathBuf {
+    memory_dir.join(".backups")
+}
+
+/// Copy `memory/<file>` to `memory/.backups/<file>.<timestamp>` and prune
+/// all but the newest `MAX_BACKUPS` copies.
+pub fn backup_file(memory_dir: &Path, file: &str) -> Result<Option<PathBuf>> {
+    let source = memory_dir.join(file);
+    if !source.is_file() {
+        return Ok(None);
+    }
+
+    let dir = backups_dir(memory_dir);
+    std::fs::create_dir_all(&dir)?;
+    let timestamp = chrono::Utc::now().format(BACKUP_TIMESTAMP);
+    let target = dir.join(format!("{file}.{timestamp}"));
+    std::fs::copy(&source, &target)?;
+
+    let backups = list_backups(memory_dir, file)?;
+    if backups.len() > MAX_BACKUPS {
+        for old in &backups[MAX_BACKUPS..] {
+            let _ = std::fs::remove_file(old);
+        }
+    }
+
+    Ok(Some(target))
+}
+
+/// Header for a new daily log, if `file` is named like one (`YYYY-MM-DD.md`).
+/// Logs are created on their first append rather than up front.
+fn daily_log_header(file: &str) -> Option<String> {
+    let day = file.strip_suffix(".md")?;
+    chrono::NaiveDate::parse_from_str(day, "%Y-%m-%d").ok()?;
+    Some(format!("# Daily Log: {day}\n\n"))
+}
+
+/// List backups of a memory file, newest first.
+pub fn list_backups(memory_dir: &Path, file: &str) -> Result<Vec<PathBuf>> {
+    let dir = backups_dir(memory_dir);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    // Only `<file>.<timestamp>`: backups of `notes.md.old` also start
+    // with `notes.md.`
+    let is_backup = |name: &str| {
+        name.strip_prefix(file)
+            .and_then(|rest| rest.strip_prefix('.'))
+            .is_some_and(|ts| chrono::NaiveDateTime::parse_from_str(ts, BACKUP_TIMESTAMP).is_ok())
+    };
+    let mut backups: Vec<PathBuf> = std::fs::read_dir(&dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.file_name().and_then(|n| n.to_str()).is_some_and(is_backup))
+        .collect();
+    // Timestamps sort lexicographically.
+    backups.sort();
+    backups.reverse();
+    Ok(backups)
+}
 
-pub struct MemoryFlushTool;
+/// Restore `memory/<file>` from a backup (the newest one if `backup` is
+/// `None`). The current contents are backed up first so a restore can
+/// itself be undone.
+pub fn restore_backup(memory_dir: &Path, file: &str, backup: Option<&str>) -> Result<PathBuf> {
+    let backups = list_backups(memory_dir, file)?;
+    let chosen = match backup {
+        Some(name) => backups
+            .into_iter()
+            .find(|p| p.file_name().and_then(|n| n.to_str()) == Some(name))
+            .ok_or_else(|| NekoError::Memory(format!("Backup '{name}' not found")))?,
+        None => backups
+            .into_iter()
+            .next()
+            .ok_or_else(|| NekoError::Memory(format!("No backups found for {file}")))?,
+    };
+
+    let content = std::fs::read(&chosen)?;
+    backup_file(memory_dir, file)?;
+    write_atomic(&memory_dir.join(file), &content)?;
+    Ok(chosen)
+}
 
 #[async_trait]
 impl Tool for MemoryFlushTool {
@@ -13,7 +114,8 @@ impl Tool for MemoryFlushTool {
     }
 
     fn description(&self) -> &str {
-        "Write or append content to a memory file. Creates the file if it doesn't exist."
+        "Write or append content to a memory file. Creates the file if it doesn't exist. \
+         Overwritten files are backed up to memory/.backups/."
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
@@ -46,12 +148,43 @@ impl Tool for MemoryFlushTool {
             return Ok(ToolResult::error("Invalid filename: must not contain path separators or '..'"));
         }
 
+        let incoming = content.len() as u64;
+        if let Some(msg) =
+            crate::workspace::check_quota_for(&ctx.workspace, ctx.workspace_quota, incoming)
+        {
+            tracing::warn!("{msg}");
+            return Ok(ToolResult::error(msg));
+        }
+
         let memory_dir = ctx.workspace.join("memory");
         if let Err(e) = std::fs::create_dir_all(&memory_dir) {
             return Ok(ToolResult::error(format!("Failed to create memory dir: {e}")));
         }
 
         let file_path = memory_dir.join(file);
+        let header = if append && !file_path.exists() {
+            daily_log_header(file)
+        } else {
+            None
+        };
+
+        if self.max_file_bytes > 0 {
+            let existing = if append {
+                std::fs::metadata(&file_path).map(|m| m.len()).unwrap_or(0)
+            } else {
+                0
+            };
+            let header_len = header.as_ref().map_or(0, |h| h.len() as u64);
+            // Appends add a trailing newline.
+            let new_size = existing + header_len + content.len() as u64 + u64::from(append);
+            if new_size > self.max_file_bytes {
+                return Ok(ToolResult::error(format!(
+                    "memory/{file} would be {new_size} bytes (max {}). \
+                     Condense it with memory_write(append=false) or move details to another file.",
+                    self.max_file_bytes
+                )));
+            }
+        }
 
         if append {
             use std::io::Write;
@@ -63,16 +196,20 @@ impl Tool for MemoryFlushTool {
                 Ok(f) => f,
                 Err(e) => return Ok(ToolResult::error(format!("Failed to open file: {e}"))),
             };
-            if let Err(e) = writeln!(f, "{content}") {
+            if let Err(e) = write!(f, "{}", header.unwrap_or_default())
+                .and_then(|()| writeln!(f, "{content}"))
+            {
                 return Ok(ToolResult::error(format!("Failed to write: {e}")));
             }
         } else {
-            if let Err(e) = std::fs::write(&file_path, content) {
+            if let Err(e) = backup_file(&memory_dir, file) {
+                return Ok(ToolResult::error(format!("Failed to back up existing file: {e}")));
+            }
+            if let Err(e) = write_atomic(&file_path, content.as_bytes()) {
                 return Ok(ToolResult::error(format!("Failed to write file: {e}")));
             }
         }
 
-
         Ok(ToolResult::success(format!(
             "{} {} to memory/{}",
             if append { "Appended" } else { "Written" },
@@ -81,3 +218,95 @@ impl Tool for MemoryFlushTool {
         )))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_daily_log_header() {
+        assert_eq!(
+            daily_log_header("2025-03-10.md").as_deref(),
+            Some("# Daily Log: 2025-03-10\n\n")
+        );
+        assert_eq!(daily_log_header("notes.md"), None);
+        assert_eq!(daily_log_header("2025-13-40.md"), None);
+    }
+
+    #[tokio::test]
+    async fn test_memory_write_size_guard() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = ToolContext::new(dir.path().to_path_buf());
+        let tool = MemoryFlushTool::new(10);
+        let write = |content: &str, append: bool| {
+            json!({ "file": "notes.md", "content": content, "append": append })
+        };
+
+        // "12345678" plus the appended newline is 9 bytes
+        let ok = tool.execute(write("12345678", true), &ctx).await.unwrap();
+        assert!(!ok.is_error, "{}", ok.output);
+        let refused = tool.execute(write("ab", true), &ctx).await.unwrap();
+        assert!(refused.is_error);
+        assert!(refused.output.contains("would be 12 bytes (max 10)"), "{}", refused.output);
+
+        // Overwriting replaces the file, so only the new size counts
+        let ok = tool.execute(write("0123456789", false), &ctx).await.unwrap();
+        assert!(!ok.is_error, "{}", ok.output);
+        let refused = tool.execute(write("0123456789X", false), &ctx).await.unwrap();
+        assert!(refused.is_error);
+        let path = dir.path().join("memory/notes.md");
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "0123456789");
+    }
+
+    /// Seed `memory/.backups/<file>.<timestamp>` with `content`.
+    fn seed_backup(memory_dir: &Path, file: &str, timestamp: &str, content: &str) {
+        let dir = backups_dir(memory_dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(format!("{file}.{timestamp}")), content).unwrap();
+    }
+
+    #[test]
+    fn test_backup_file_prunes_only_its_own_backups() {
+        let tmp = tempfile::tempdir().unwrap();
+        let memory_dir = tmp.path();
+        std::fs::write(memory_dir.join("notes.md"), "current").unwrap();
+        for i in 0..MAX_BACKUPS {
+            seed_backup(memory_dir, "notes.md", &format!("20250101T0000{i:02}000"), "old");
+        }
+        seed_backup(memory_dir, "notes.md.old", "20240101T000000000", "other file");
+
+        let target = backup_file(memory_dir, "notes.md").unwrap().unwrap();
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "current");
+
+        let backups = list_backups(memory_dir, "notes.md").unwrap();
+        assert_eq!(backups.len(), MAX_BACKUPS);
+        assert_eq!(backups[0], target);
+        assert!(!backups_dir(memory_dir).join("notes.md.20250101T000000000").exists());
+        assert_eq!(list_backups(memory_dir, "notes.md.old").unwrap().len(), 1);
+        assert!(backup_file(memory_dir, "missing.md").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_restore_backup() {
+        let tmp = tempfile::tempdir().unwrap();
+        let memory_dir = tmp.path();
+        std::fs::write(memory_dir.join("notes.md"), "current").unwrap();
+        seed_backup(memory_dir, "notes.md", "20250101T000000000", "first");
+        seed_backup(memory_dir, "notes.md", "20250102T000000000", "second");
+        // Newer, but belongs to another file
+        seed_backup(memory_dir, "notes.md.old", "20260101T000000000", "other file");
+
+        let restored = restore_backup(memory_dir, "notes.md", None).unwrap();
+        assert!(restored.ends_with("notes.md.20250102T000000000"));
+        assert_eq!(std::fs::read_to_string(memory_dir.join("notes.md")).unwrap(), "second");
+        // The overwritten contents were backed up first
+        let newest = &list_backups(memory_dir, "notes.md").unwrap()[0];
+        assert_eq!(std::fs::read_to_string(newest).unwrap(), "current");
+
+        restore_backup(memory_dir, "notes.md", Some("notes.md.20250101T000000000")).unwrap();
+        assert_eq!(std::fs::read_to_string(memory_dir.join("notes.md")).unwrap(), "first");
+        let other = "notes.md.old.20260101T000000000";
+        assert!(restore_backup(memory_dir, "notes.md", Some(other)).is_err());
+        assert!(restore_backup(memory_dir, "other.md", None).is_err());
+    }
+}
@@ -5,10 +5,71 @@ use grep_searcher::Searcher;
 use serde_json::json;
 use walkdir::WalkDir;
 
+use std::sync::Arc;
+
 use super::{schema_object, Tool, ToolContext, ToolResult};
 use crate::error::Result;
+use crate::memory_index::{self, Embedder};
+
+#[derive(Default)]
+pub struct MemorySearchTool {
+    /// Set when `[agent.memory] embeddings` is enabled.
+    embedder: Option<Arc<Embedder>>,
+}
+
+impl MemorySearchTool {
+    /// Enable `semantic=true` searches through the embeddings index.
+    pub fn with_embedder(mut self, embedder: Arc<Embedder>) -> Self {
+        self.embedder = Some(embedder);
+        self
+    }
+}
+
+/// Upper bound on candidate matches collected before ranking.
+const MAX_CANDIDATES: usize = 1000;
 
-pub struct MemorySearchTool;
+struct Match {
+    rel_path: String,
+    line_num: u64,
+    line: String,
+    /// Query matched as a whole word rather than inside another word.
+    word_match: bool,
+    modified: std::time::SystemTime,
+    /// Context lines around the match, formatted for display.
+    snippet: Option<String>,
+}
+
+/// Format `context` lines on either side of `line_num` (1-based).
+fn context_snippet(lines: &[&str], line_num: usize, context: usize) -> String {
+    let idx = line_num.saturating_sub(1);
+    let start = idx.saturating_sub(context);
+    let end = (idx + context + 1).min(lines.len());
+    let mut out = Vec::new();
+    for (i, line) in lines.iter().enumerate().take(end).skip(start) {
+        let marker = if i == idx { '>' } else { ' ' };
+        out.push(format!("  {marker} {}| {}", i + 1, line.trim_end()));
+    }
+    out.join("\n")
+}
+
+/// Date of a recall log (`memory/recall/YYYY-MM-DD.md`), given its
+/// workspace-relative path.
+fn recall_file_date(rel_path: &str) -> Option<chrono::NaiveDate> {
+    let name = rel_path.strip_prefix("memory/recall/")?.strip_suffix(".md")?;
+    chrono::NaiveDate::parse_from_str(name, "%Y-%m-%d").ok()
+}
+
+fn parse_date_param(
+    params: &serde_json::Value,
+    key: &str,
+) -> std::result::Result<Option<chrono::NaiveDate>, String> {
+    match params[key].as_str().filter(|s| !s.is_empty()) {
+        Some(s) => chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .map(Some)
+            .map_err(|_| format!("Invalid '{key}' date: '{s}' (expected YYYY-MM-DD)")),
+        None => Ok(None),
+    }
+}
 
 #[async_trait]
 impl Tool for MemorySearchTool {
@@ -17,7 +78,8 @@ impl Tool for MemorySearchTool {
     }
 
     fn description(&self) -> &str {
-        "Search across all memory files for matching text. Case-insensitive. Supports regex patterns when regex=true."
+        "Search across all memory files for matching text. Case-insensitive. Supports regex patterns when regex=true. \
+         Results are ranked: whole-word matches first, then more recently modified files."
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
@@ -34,6 +96,26 @@ impl Tool for MemorySearchTool {
                 "regex": {
                     "type": "boolean",
                     "description": "Treat query as a regex pattern instead of literal text. Default: false"
+                },
+                "context_lines": {
+                    "type": "integer",
+                    "description": "Lines of surrounding context to show per match. Default: 0"
+                },
+                "since": {
+                    "type": "string",
+                    "description": "Only search recall logs dated on or after this day (YYYY-MM-DD)"
+                },
+                "until": {
+                    "type": "string",
+                    "description": "Only search recall logs dated on or before this day (YYYY-MM-DD)"
+                },
+                "recall_only": {
+                    "type": "boolean",
+                    "description": "Search only recall logs, skipping other memory files. Default: false"
+                },
+                "semantic": {
+                    "type": "boolean",
+                    "description": "Rank passages by meaning instead of matching text (finds related wording). Only available when memory embeddings are enabled. Default: false"
                 }
             }),
             &["query"],
@@ -48,12 +130,57 @@ impl Tool for MemorySearchTool {
 
         let use_regex = params["regex"].as_bool().unwrap_or(false);
         let max_results = params["max_results"].as_u64().unwrap_or(20) as usize;
+        let context_lines = params["context_lines"].as_u64().unwrap_or(0).min(10) as usize;
+        let recall_only = params["recall_only"].as_bool().unwrap_or(false);
+        let since = match parse_date_param(&params, "since") {
+            Ok(d) => d,
+            Err(e) => return Ok(ToolResult::error(e)),
+        };
+        let until = match parse_date_param(&params, "until") {
+            Ok(d) => d,
+            Err(e) => return Ok(ToolResult::error(e)),
+        };
         let memory_dir = ctx.workspace.join("memory");
+        let recall_dir = memory_dir.join("recall");
 
         if !memory_dir.exists() {
             return Ok(ToolResult::success("No memory directory found. No results."));
         }
 
+        if params["semantic"].as_bool().unwrap_or(false) {
+            let Some(embedder) = &self.embedder else {
+                return Ok(ToolResult::error(
+                    "Semantic search is not enabled (set [agent.memory] embeddings = true)",
+                ));
+            };
+            // Same date/recall scoping as the keyword path.
+            let in_scope = |rel_path: &str| match recall_file_date(rel_path) {
+                Some(date) => {
+                    !(since.is_some_and(|s| date < s) || until.is_some_and(|u| date > u))
+                }
+                None => !recall_only,
+            };
+            let hits =
+                match memory_index::search(&ctx.workspace, embedder, query, max_results, in_scope)
+                    .await
+                {
+                    Ok(hits) => hits,
+                    Err(e) => return Ok(ToolResult::error(format!("Semantic search failed: {e}"))),
+                };
+            if hits.is_empty() {
+                return Ok(ToolResult::success(format!("No matches found for \"{query}\"")));
+            }
+            let output = hits
+                .iter()
+                .map(|h| format!("{}:{} (score {:.2}):\n{}", h.rel_path, h.line, h.score, h.text))
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            return Ok(ToolResult::success(format!(
+                "{} passage(s) found:\n{output}",
+                hits.len()
+            )));
+        }
+
         let pattern = if use_regex {
             query.to_string()
         } else {
@@ -68,7 +195,13 @@ impl Tool for MemorySearchTool {
             Err(e) => return Ok(ToolResult::error(format!("Invalid search pattern: {e}"))),
         };
 
-        let mut matches = Vec::new();
+        // Used only for ranking — a failure just means no word-match boost.
+        let word_re = regex::RegexBuilder::new(&format!(r"\b(?:{pattern})\b"))
+            .case_insensitive(true)
+            .build()
+            .ok();
+
+        let mut matches: Vec<Match> = Vec::new();
         let mut searcher = Searcher::new();
 
         for entry in WalkDir::new(&memory_dir)
@@ -83,43 +216,109 @@ impl Tool for MemorySearchTool {
                 continue;
             }
 
+            // Recall logs are named YYYY-MM-DD.md — scope them by date.
+            let recall_date = if path.starts_with(&recall_dir) {
+                path.file_stem()
+                    .and_then(|s| s.to_str())
+                    .and_then(|s| chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").ok())
+            } else {
+                None
+            };
+            match recall_date {
+                Some(date)
+                    if since.is_some_and(|s| date < s) || until.is_some_and(|u| date > u) =>
+                {
+                    continue
+                }
+                Some(_) => {}
+                None if recall_only => continue,
+                None => {}
+            }
+
             let rel_path = path
                 .strip_prefix(&ctx.workspace)
                 .unwrap_or(path)
                 .to_string_lossy()
                 .to_string();
+            let modified = entry
+                .metadata()
+                .ok()
+                .and_then(|m| m.modified().ok())
+                .unwrap_or(std::time::UNIX_EPOCH);
 
+            let file_start = matches.len();
             let _ = searcher.search_path(
                 &matcher,
                 path,
                 UTF8(|line_num, line| {
-                    if matches.len() < max_results {
-                        matches.push(format!(
-                            "{}:{}: {}",
-                            rel_path,
-                            line_num,
-                            line.trim_end()
-                        ));
-                    }
-                    Ok(matches.len() < max_results)
+                    matches.push(Match {
+                        rel_path: rel_path.clone(),
+                        line_num,
+                        line: line.trim_end().to_string(),
+                        word_match: word_re.as_ref().is_some_and(|re| re.is_match(line)),
+                        modified,
+                        snippet: None,
+                    });
+                    Ok(matches.len() < MAX_CANDIDATES)
                 }),
             );
 
-            if matches.len() >= max_results {
+            if context_lines > 0 && matches.len() > file_start {
+                if let Ok(content) = std::fs::read_to_string(path) {
+                    let lines: Vec<&str> = content.lines().collect();
+                    for m in &mut matches[file_start..] {
+                        m.snippet = Some(context_snippet(&lines, m.line_num as usize, context_lines));
+                    }
+                }
+            }
+
+            if matches.len() >= MAX_CANDIDATES {
                 break;
             }
         }
 
         if matches.is_empty() {
-            Ok(ToolResult::success(format!(
+            return Ok(ToolResult::success(format!(
                 "No matches found for \"{query}\""
-            )))
-        } else {
-            let count = matches.len();
-            let output = matches.join("\n");
-            Ok(ToolResult::success(format!(
-                "{count} match(es) found:\n{output}"
-            )))
+            )));
         }
+
+        // Whole-word hits first, then newest files, then file order.
+        matches.sort_by(|a, b| {
+            b.word_match
+                .cmp(&a.word_match)
+                .then(b.modified.cmp(&a.modified))
+                .then(a.rel_path.cmp(&b.rel_path))
+                .then(a.line_num.cmp(&b.line_num))
+        });
+        matches.truncate(max_results);
+
+        let count = matches.len();
+        let output = matches
+            .iter()
+            .map(|m| match &m.snippet {
+                Some(snippet) => format!("{}:{}:\n{snippet}", m.rel_path, m.line_num),
+                None => format!("{}:{}: {}", m.rel_path, m.line_num, m.line),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(ToolResult::success(format!(
+            "{count} match(es) found:\n{output}"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_snippet() {
+        let lines = vec!["a", "b", "c", "d", "e"];
+        let snippet = context_snippet(&lines, 3, 1);
+        assert_eq!(snippet, "    2| b\n  > 3| c\n    4| d");
+        // Clamped at file boundaries
+        let snippet = context_snippet(&lines, 1, 2);
+        assert_eq!(snippet.lines().count(), 3);
     }
 }
@@ -7,22 +7,30 @@ pub mod memory_flush;
 pub mod memory_search;
 pub mod cd;
 pub mod memory_replace;
+pub mod kv;
+#[cfg(feature = "python")]
 pub mod run_python;
 pub mod process_manager;
 pub mod process;
 pub mod send_file;
 pub mod cron_manage;
+pub mod status_update;
+pub mod send_message;
+pub mod skill_search;
+pub mod structured;
+pub mod schema_lint;
 
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
 use async_trait::async_trait;
 use serde_json::json;
+use tokio::sync::mpsc;
 
 use self::process_manager::ProcessManager;
 
-use crate::channels::Attachment;
+use crate::channels::{Attachment, OutboundMessage};
 use crate::config::ToolsConfig;
 use crate::error::Result;
 use crate::llm::types::ToolDefinition;
@@ -32,6 +40,8 @@ use crate::llm::types::ToolDefinition;
 pub struct ChannelContext {
     pub channel: String,
     pub recipient_id: String,
+    /// Who sent the message, when known (absent for cron turns).
+    pub sender_id: Option<String>,
 }
 
 /// Context passed to tool execution.
@@ -45,6 +55,122 @@ pub struct ToolContext {
     pub pending_attachments: Arc<Mutex<Vec<Attachment>>>,
     /// The channel this message arrived from (if any).
     pub channel: Option<ChannelContext>,
+    /// Egress for mid-turn messages to channels. `None` for CLI/one-shot turns.
+    pub outbound_tx: Option<mpsc::Sender<OutboundMessage>>,
+    /// Soft workspace quota in bytes — writes are refused once exceeded.
+    pub workspace_quota: Option<u64>,
+    /// Directory file tools are confined to (`[tools] file_root`); the
+    /// workspace unless configured.
+    pub file_root: PathBuf,
+    /// Let file tools into the paths in [`INTERNAL_PATHS`]
+    /// (`[tools] allow_internal_paths`).
+    pub allow_internal_paths: bool,
+    /// Directories outside the file root that may be read but not written
+    /// (shared `[skills] paths`).
+    pub read_only_dirs: Vec<PathBuf>,
+}
+
+/// Workspace paths holding Neko's own state (transcripts, cron jobs,
+/// memory indexes, channel state, usage counters). File tools stay out of
+/// them unless `[tools] allow_internal_paths` is set; the memory tools are
+/// unaffected.
+pub const INTERNAL_PATHS: &[&str] = &[
+    "sessions",
+    "cron",
+    "channels",
+    "telegram/approved_users.json",
+    "usage",
+    "memory/recall",
+    "memory/.index",
+    "memory/.backups",
+    "memory/kv.json",
+];
+
+impl ToolContext {
+    /// A context for `workspace` with nothing else attached: no channel,
+    /// no quota, file tools confined to the workspace.
+    pub fn new(workspace: PathBuf) -> Self {
+        Self {
+            cwd: Arc::new(Mutex::new(workspace.clone())),
+            file_root: workspace.clone(),
+            workspace,
+            pending_attachments: Arc::new(Mutex::new(Vec::new())),
+            channel: None,
+            outbound_tx: None,
+            workspace_quota: None,
+            allow_internal_paths: false,
+            read_only_dirs: Vec::new(),
+        }
+    }
+
+    /// Fail unless `canonical` (an already canonicalized path) is inside
+    /// `file_root` and not one of the [`INTERNAL_PATHS`]. Returns the
+    /// canonical root.
+    pub fn check_file_access(&self, canonical: &Path) -> std::result::Result<PathBuf, String> {
+        let root = self
+            .file_root
+            .canonicalize()
+            .map_err(|e| format!("Cannot resolve workspace: {e}"))?;
+        if !canonical.starts_with(&root) {
+            return Err("Path is outside workspace boundary".to_string());
+        }
+        if !self.allow_internal_paths {
+            let workspace = self
+                .workspace
+                .canonicalize()
+                .map_err(|e| format!("Cannot resolve workspace: {e}"))?;
+            if let Some(internal) = internal_path(canonical, &workspace) {
+                return Err(format!(
+                    "{internal} holds Neko's internal data and is not accessible to file tools"
+                ));
+            }
+        }
+        Ok(root)
+    }
+
+    /// Like [`check_file_access`](Self::check_file_access), but also
+    /// allows paths inside `read_only_dirs`.
+    pub fn check_read_access(&self, canonical: &Path) -> std::result::Result<(), String> {
+        let Err(e) = self.check_file_access(canonical) else {
+            return Ok(());
+        };
+        let readable = self
+            .read_only_dirs
+            .iter()
+            .filter_map(|dir| dir.canonicalize().ok())
+            .any(|dir| canonical.starts_with(dir));
+        if readable {
+            Ok(())
+        } else {
+            Err(e)
+        }
+    }
+
+    /// Queue a text message for a channel without blocking the turn.
+    /// Fails if this turn has no outbound egress or the queue is full.
+    pub fn send_message(
+        &self,
+        channel: &str,
+        recipient_id: &str,
+        text: impl Into<String>,
+    ) -> std::result::Result<(), String> {
+        let Some(tx) = &self.outbound_tx else {
+            return Err("no outbound channel for this turn".to_string());
+        };
+        tx.try_send(OutboundMessage {
+            channel: channel.to_string(),
+            recipient_id: recipient_id.to_string(),
+            text: text.into(),
+            attachments: Vec::new(),
+        })
+        .map_err(|e| e.to_string())
+    }
+}
+
+/// The entry of [`INTERNAL_PATHS`] that `canonical` falls under, if any.
+fn internal_path(canonical: &Path, workspace: &Path) -> Option<&'static str> {
+    let rel = canonical.strip_prefix(workspace).ok()?;
+    INTERNAL_PATHS.iter().copied().find(|p| rel.starts_with(p))
 }
 
 /// Result of a tool execution
@@ -74,20 +200,45 @@ pub trait Tool: Send + Sync {
     fn name(&self) -> &str;
     fn description(&self) -> &str;
     fn parameters_schema(&self) -> serde_json::Value;
+    /// Whether this call should wait for the user's go-ahead in sessions
+    /// that have confirmation enabled (`/confirm on`).
+    fn requires_confirmation(&self, _params: &serde_json::Value) -> bool {
+        false
+    }
     async fn execute(&self, params: serde_json::Value, ctx: &ToolContext) -> Result<ToolResult>;
 }
 
 pub struct ToolRegistry {
     tools: HashMap<String, Box<dyn Tool>>,
+    /// `[tools.descriptions]`: replacements for built-in descriptions.
+    descriptions: HashMap<String, String>,
 }
 
 impl ToolRegistry {
     pub fn new() -> Self {
         Self {
             tools: HashMap::new(),
+            descriptions: HashMap::new(),
         }
     }
 
+    /// Replace the descriptions the model sees for the named tools.
+    pub fn set_descriptions(&mut self, descriptions: HashMap<String, String>) {
+        self.descriptions = descriptions;
+    }
+
+    /// Description overrides naming tools that aren't registered.
+    pub fn unknown_descriptions(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .descriptions
+            .keys()
+            .filter(|name| !self.tools.contains_key(*name))
+            .map(|name| name.as_str())
+            .collect();
+        names.sort();
+        names
+    }
+
     pub fn register(&mut self, tool: Box<dyn Tool>) {
         self.tools.insert(tool.name().to_string(), tool);
     }
@@ -102,7 +253,10 @@ impl ToolRegistry {
             .map(|t| ToolDefinition {
                 tool_type: "function".to_string(),
                 name: t.name().to_string(),
-                description: t.description().to_string(),
+                description: self
+                    .descriptions
+                    .get(t.name())
+                    .map_or_else(|| t.description().to_string(), |d| d.clone()),
                 parameters: t.parameters_schema(),
             })
             .collect()
@@ -111,6 +265,21 @@ impl ToolRegistry {
     pub fn names(&self) -> Vec<&str> {
         self.tools.keys().map(|s| s.as_str()).collect()
     }
+
+    /// Schema problems across all registered tools, as `"<tool>: <problem>"`.
+    pub fn lint_schemas(&self) -> Vec<String> {
+        let mut problems: Vec<String> = self
+            .tools
+            .values()
+            .flat_map(|t| {
+                schema_lint::lint_schema(&t.parameters_schema())
+                    .into_iter()
+                    .map(move |p| format!("{}: {p}", t.name()))
+            })
+            .collect();
+        problems.sort();
+        problems
+    }
 }
 
 /// Register core tools, respecting the config's enabled tools list.
@@ -118,7 +287,10 @@ pub fn register_core_tools(
     registry: &mut ToolRegistry,
     config: &ToolsConfig,
 ) {
-    let pm = Arc::new(ProcessManager::new(config.exec_yield_ms));
+    let pm = Arc::new(
+        ProcessManager::new(config.exec_yield_ms)
+            .with_env(&config.exec_env_passthrough, &config.exec_env),
+    );
 
     registry.register(Box::new(read_file::ReadFileTool));
     registry.register(Box::new(write_file::WriteFileTool));
@@ -133,19 +305,33 @@ pub fn register_core_tools(
         config.http_allowed_domains.clone(),
     )));
     registry.register(Box::new(cd::CdTool));
-    registry.register(Box::new(memory_flush::MemoryFlushTool));
-    registry.register(Box::new(memory_search::MemorySearchTool));
+    registry.register(Box::new(memory_flush::MemoryFlushTool::new(
+        config.memory_max_file_bytes,
+    )));
+    registry.register(Box::new(memory_search::MemorySearchTool::default()));
     registry.register(Box::new(memory_replace::MemoryReplaceTool));
+    registry.register(Box::new(kv::KvTool));
 
-    registry.register(Box::new(send_file::SendFileTool));
+    registry.register(Box::new(send_file::SendFileTool::new(
+        config.http_allowed_domains.clone(),
+        config.send_file_allowed_mime.clone(),
+    )));
     registry.register(Box::new(cron_manage::CronManageTool));
+    registry.register(Box::new(status_update::StatusUpdateTool));
+    registry.register(Box::new(structured::StructuredReadTool));
+    registry.register(Box::new(structured::StructuredEditTool));
 
+    #[cfg(feature = "python")]
     if config.python.enabled {
         registry.register(Box::new(run_python::RunPythonTool::new(
             config.python.clone(),
             config.http_allowed_domains.clone(),
         )));
     }
+    #[cfg(not(feature = "python"))]
+    if config.python.enabled {
+        tracing::warn!("tools.python.enabled is set, but neko was built without the `python` feature");
+    }
 }
 
 /// Helper to build a JSON Schema object with given properties.
@@ -156,3 +342,93 @@ pub fn schema_object(properties: serde_json::Value, required: &[&str]) -> serde_
         "required": required,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_internal_path() {
+        let ws = Path::new("/ws");
+        assert_eq!(internal_path(Path::new("/ws/sessions/abc.jsonl"), ws), Some("sessions"));
+        assert_eq!(internal_path(Path::new("/ws/cron"), ws), Some("cron"));
+        assert_eq!(internal_path(Path::new("/ws/memory/kv.json"), ws), Some("memory/kv.json"));
+        assert_eq!(
+            internal_path(Path::new("/ws/memory/.index/embeddings.json"), ws),
+            Some("memory/.index")
+        );
+        assert_eq!(internal_path(Path::new("/ws/memory/notes.md"), ws), None);
+        assert_eq!(internal_path(Path::new("/ws/sessions-archive"), ws), None);
+        assert_eq!(internal_path(Path::new("/ws/projects/cron"), ws), None);
+        assert_eq!(
+            internal_path(Path::new("/ws/telegram/approved_users.json"), ws),
+            Some("telegram/approved_users.json")
+        );
+        assert_eq!(internal_path(Path::new("/ws/telegram/notes.md"), ws), None);
+    }
+
+    #[tokio::test]
+    async fn test_file_tools_refuse_internal_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = dir.path().to_path_buf();
+        let state_files = [
+            "sessions/main.jsonl",
+            "cron/jobs.json",
+            "telegram/approved_users.json",
+            "usage/daily_tokens.json",
+            "channels/telegram.offset",
+            "channels/telegram.dry-run.jsonl",
+            "channels/undelivered.jsonl",
+        ];
+        for path in state_files {
+            let file = ws.join(path);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(&file, "[]").unwrap();
+        }
+        let ctx = ToolContext::new(ws.clone());
+
+        for path in state_files.into_iter().chain(["./cron/../cron/jobs.json"]) {
+            let read = read_file::ReadFileTool
+                .execute(serde_json::json!({ "path": path }), &ctx)
+                .await
+                .unwrap();
+            assert!(read.is_error, "read_file {path}: {}", read.output);
+
+            let write = write_file::WriteFileTool
+                .execute(serde_json::json!({ "path": path, "content": "x" }), &ctx)
+                .await
+                .unwrap();
+            assert!(write.is_error, "write_file {path}: {}", write.output);
+        }
+        for path in state_files {
+            assert_eq!(std::fs::read_to_string(ws.join(path)).unwrap(), "[]", "{path}");
+        }
+
+        let ctx = ToolContext {
+            allow_internal_paths: true,
+            ..ToolContext::new(ws)
+        };
+        let read = read_file::ReadFileTool
+            .execute(serde_json::json!({ "path": "cron/jobs.json" }), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(read.output, "[]");
+    }
+
+    #[test]
+    fn test_description_override() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Box::new(cd::CdTool));
+        registry.register(Box::new(kv::KvTool));
+        registry.set_descriptions(HashMap::from([
+            ("cd".to_string(), "Wechselt das Verzeichnis.".to_string()),
+            ("nope".to_string(), "unused".to_string()),
+        ]));
+
+        let defs = registry.tool_definitions();
+        let desc = |name: &str| &defs.iter().find(|d| d.name == name).unwrap().description;
+        assert_eq!(desc("cd"), "Wechselt das Verzeichnis.");
+        assert_eq!(desc("kv"), kv::KvTool.description());
+        assert_eq!(registry.unknown_descriptions(), vec!["nope"]);
+    }
+}
@@ -50,6 +50,14 @@ impl Tool for ProcessTool {
                 "eof": {
                     "type": "boolean",
                     "description": "Close stdin after writing (for input action). Signals end-of-input."
+                },
+                "tail": {
+                    "type": "integer",
+                    "description": "Only return the last N lines of new output (for poll). Useful for noisy logs."
+                },
+                "since": {
+                    "type": "integer",
+                    "description": "Return output after this offset, as reported by an earlier poll, instead of since the last poll"
                 }
             }),
             &["action"],
@@ -65,7 +73,9 @@ impl Tool for ProcessTool {
                 let session_id = params["session_id"]
                     .as_str()
                     .ok_or_else(|| crate::error::NekoError::Tool("session_id is required for poll".into()))?;
-                self.action_poll(session_id).await
+                let tail = params["tail"].as_u64().map(|n| n as usize);
+                let since = params["since"].as_u64().map(|n| n as usize);
+                self.action_poll(session_id, tail, since).await
             }
             "input" => {
                 let session_id = params["session_id"]
@@ -107,14 +117,19 @@ impl ProcessTool {
         Ok(ToolResult::success(out))
     }
 
-    async fn action_poll(&self, session_id: &str) -> Result<ToolResult> {
+    async fn action_poll(
+        &self,
+        session_id: &str,
+        tail: Option<usize>,
+        since: Option<usize>,
+    ) -> Result<ToolResult> {
         let session = self
             .process_manager
             .get_session(session_id)
             .await
             .ok_or_else(|| crate::error::NekoError::Tool(format!("Session '{session_id}' not found")))?;
 
-        let (new_output, exit_status) = session.poll_output().await;
+        let (new_output, offset, exit_status) = session.poll_output(since).await;
 
         let mut msg = String::new();
 
@@ -123,11 +138,17 @@ impl ProcessTool {
             // Auto-remove completed session after poll
             self.process_manager.remove_session(session_id).await;
         } else {
-            msg.push_str("[still running]\n");
+            msg.push_str(&format!("[still running, offset {offset}]\n"));
         }
 
         if new_output.is_empty() {
             msg.push_str("(no new output)");
+        } else if let Some(n) = tail {
+            let (kept, skipped) = tail_lines(&new_output, n);
+            if skipped > 0 {
+                msg.push_str(&format!("[{skipped} earlier line(s) omitted]\n"));
+            }
+            msg.push_str(kept);
         } else {
             msg.push_str(&new_output);
         }
@@ -176,3 +197,32 @@ impl ProcessTool {
         Ok(ToolResult::success(msg))
     }
 }
+
+/// The last `n` lines of `text`, and how many lines came before them.
+fn tail_lines(text: &str, n: usize) -> (&str, usize) {
+    let body = text.strip_suffix('\n').unwrap_or(text);
+    let total = body.lines().count();
+    if total <= n {
+        return (text, 0);
+    }
+    let skipped = total - n;
+    let start = if n == 0 {
+        text.len()
+    } else {
+        body.match_indices('\n').nth(skipped - 1).map_or(0, |(i, _)| i + 1)
+    };
+    (&text[start..], skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tail_lines() {
+        assert_eq!(tail_lines("a\nb\nc\n", 2), ("b\nc\n", 1));
+        assert_eq!(tail_lines("a\nb\nc", 1), ("c", 2));
+        assert_eq!(tail_lines("a\nb\n", 5), ("a\nb\n", 0));
+        assert_eq!(tail_lines("a\nb\n", 0), ("", 2));
+    }
+}
@@ -14,10 +14,16 @@ const MAX_OUTPUT_BYTES: usize = 1_048_576;
 /// Exited sessions older than this are removed during lazy cleanup.
 const CLEANUP_AGE: Duration = Duration::from_secs(300);
 
+/// How long to wait, once a process exits, for its output readers to reach
+/// EOF. Bounded because a backgrounded grandchild can hold the pipes open.
+const READER_DRAIN: Duration = Duration::from_secs(2);
+
 pub struct ProcessManager {
     sessions: RwLock<HashMap<String, Arc<BackgroundSession>>>,
     next_id: AtomicU64,
     yield_ms: u64,
+    /// Complete environment for spawned commands (parent env is cleared).
+    env: HashMap<String, String>,
 }
 
 pub struct BackgroundSession {
@@ -43,9 +49,24 @@ impl ProcessManager {
             sessions: RwLock::new(HashMap::new()),
             next_id: AtomicU64::new(1),
             yield_ms,
+            env: std::env::var("PATH")
+                .map(|path| HashMap::from([("PATH".to_string(), path)]))
+                .unwrap_or_default(),
         }
     }
 
+    /// Set the environment for spawned commands: `passthrough` names are
+    /// copied from Neko's environment, then `overrides` are applied.
+    pub fn with_env(mut self, passthrough: &[String], overrides: &HashMap<String, String>) -> Self {
+        let mut env: HashMap<String, String> = passthrough
+            .iter()
+            .filter_map(|name| std::env::var(name).ok().map(|v| (name.clone(), v)))
+            .collect();
+        env.extend(overrides.iter().map(|(k, v)| (k.clone(), v.clone())));
+        self.env = env;
+        self
+    }
+
     pub async fn spawn_or_yield(
         &self,
         command: &str,
@@ -58,6 +79,8 @@ impl ProcessManager {
             .arg("-c")
             .arg(command)
             .current_dir(cwd)
+            .env_clear()
+            .envs(&self.env)
             .stdin(std::process::Stdio::piped())
             .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::piped())
@@ -73,9 +96,10 @@ impl ProcessManager {
         let exit_status: Arc<TokioMutex<Option<i32>>> = Arc::new(TokioMutex::new(None));
 
         // Spawn reader tasks
+        let mut readers = Vec::new();
         if let Some(stdout) = stdout {
             let buf = Arc::clone(&output_buf);
-            tokio::spawn(async move {
+            readers.push(tokio::spawn(async move {
                 let mut lines = tokio::io::BufReader::new(stdout).lines();
                 while let Ok(Some(line)) = lines.next_line().await {
                     let mut b = buf.lock().await;
@@ -84,12 +108,12 @@ impl ProcessManager {
                         b.push('\n');
                     }
                 }
-            });
+            }));
         }
 
         if let Some(stderr) = stderr {
             let buf = Arc::clone(&output_buf);
-            tokio::spawn(async move {
+            readers.push(tokio::spawn(async move {
                 let mut lines = tokio::io::BufReader::new(stderr).lines();
                 while let Ok(Some(line)) = lines.next_line().await {
                     let mut b = buf.lock().await;
@@ -99,7 +123,7 @@ impl ProcessManager {
                         b.push('\n');
                     }
                 }
-            });
+            }));
         }
 
         // Wait up to yield_ms for completion
@@ -145,7 +169,9 @@ impl ProcessManager {
                     stdin: TokioMutex::new(stdin),
                 });
 
-                // Spawn exit-status watcher
+                // Spawn exit-status watcher. The status is published only
+                // after the readers have flushed, so whoever sees it also
+                // sees the final output.
                 let session_ref = Arc::clone(&session);
                 tokio::spawn(async move {
                     let mut child_guard = session_ref.child.lock().await;
@@ -154,6 +180,8 @@ impl ProcessManager {
                             Ok(s) => s.code().unwrap_or(-1),
                             Err(_) => -1,
                         };
+                        let drained = futures::future::join_all(readers);
+                        let _ = tokio::time::timeout(READER_DRAIN, drained).await;
                         *session_ref.exit_status.lock().await = Some(code);
                     }
                 });
@@ -223,18 +251,20 @@ impl ProcessManager {
 }
 
 impl BackgroundSession {
-    /// Return output accumulated since the last poll.
-    pub async fn poll_output(&self) -> (String, Option<i32>) {
+    /// Return output accumulated since the last poll, or since byte
+    /// `since` when given, with the offset to pass as `since` next time.
+    /// Either way the poll cursor moves to the end of the output.
+    pub async fn poll_output(&self, since: Option<usize>) -> (String, usize, Option<i32>) {
         let buf = self.output_buf.lock().await;
         let mut cursor = self.cursor.lock().await;
-        let new_output = if *cursor < buf.len() {
-            buf[*cursor..].to_string()
-        } else {
-            String::new()
-        };
+        let mut start = since.unwrap_or(*cursor).min(buf.len());
+        while !buf.is_char_boundary(start) {
+            start += 1;
+        }
+        let new_output = buf[start..].to_string();
         *cursor = buf.len();
         let status = *self.exit_status.lock().await;
-        (new_output, status)
+        (new_output, buf.len(), status)
     }
 
     /// Write data to the process's stdin. If `eof` is true, drop the stdin
@@ -276,6 +306,18 @@ impl BackgroundSession {
         Ok(())
     }
 
+    /// Output appended after byte `offset`, the new offset, and the exit
+    /// status. Independent of the `poll_output` cursor. Once the status is
+    /// `Some`, the returned output is complete.
+    pub async fn output_since(&self, offset: usize) -> (String, usize, Option<i32>) {
+        // Status first: it is set after the readers finish, so the buffer
+        // read below already holds the final lines.
+        let status = *self.exit_status.lock().await;
+        let buf = self.output_buf.lock().await;
+        let new_output = buf.get(offset..).unwrap_or_default().to_string();
+        (new_output, buf.len(), status)
+    }
+
     /// Drain all remaining output.
     pub async fn drain_output(&self) -> String {
         tokio::time::sleep(Duration::from_millis(100)).await;
@@ -290,3 +332,37 @@ pub struct SessionInfo {
     pub elapsed_secs: u64,
     pub exit_status: Option<i32>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_output_since_delivers_final_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ProcessManager::new(50);
+        let command = "sleep 0.3; echo first; echo last >&2";
+        let Ok(SpawnResult::Backgrounded { session_id, .. }) =
+            manager.spawn_or_yield(command, dir.path(), 30).await
+        else {
+            panic!("expected the command to be backgrounded");
+        };
+        let session = manager.get_session(&session_id).await.unwrap();
+
+        let mut seen = String::new();
+        let mut offset = 0;
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let status = loop {
+            let (output, new_offset, status) = session.output_since(offset).await;
+            seen.push_str(&output);
+            offset = new_offset;
+            if status.is_some() || Instant::now() > deadline {
+                break status;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+
+        assert_eq!(status, Some(0));
+        assert_eq!(seen, "first\n[stderr] last\n");
+    }
+}
@@ -42,13 +42,8 @@ impl Tool for ReadFileTool {
             Err(e) => return Ok(ToolResult::error(format!("Cannot resolve path: {e}"))),
         };
 
-        let workspace_canonical = match ctx.workspace.canonicalize() {
-            Ok(p) => p,
-            Err(e) => return Ok(ToolResult::error(format!("Cannot resolve workspace: {e}"))),
-        };
-
-        if !canonical.starts_with(&workspace_canonical) {
-            return Ok(ToolResult::error("Path is outside workspace boundary"));
+        if let Err(e) = ctx.check_read_access(&canonical) {
+            return Ok(ToolResult::error(e));
         }
 
         match std::fs::read_to_string(&canonical) {
@@ -0,0 +1,123 @@
+//! Startup checks for tool `parameters_schema()` values, so authoring
+//! mistakes surface when the registry is built rather than when the model
+//! first calls the tool and the API rejects the request.
+
+use serde_json::Value;
+
+/// Problems with a tool parameter schema. Empty means the schema is usable.
+///
+/// Checks that the schema is an object with `"type": "object"`, that
+/// `properties` is present and maps names to schema objects, and that every
+/// `required` entry names a declared property.
+pub fn lint_schema(schema: &Value) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let Some(obj) = schema.as_object() else {
+        problems.push("schema is not a JSON object".to_string());
+        return problems;
+    };
+
+    match obj.get("type") {
+        Some(Value::String(t)) if t == "object" => {}
+        Some(other) => problems.push(format!("type must be \"object\", got {other}")),
+        None => problems.push("missing \"type\": \"object\"".to_string()),
+    }
+
+    let properties = match obj.get("properties") {
+        Some(Value::Object(props)) => {
+            for (name, prop) in props {
+                if !prop.is_object() {
+                    problems.push(format!("property '{name}' is not a schema object"));
+                }
+            }
+            Some(props)
+        }
+        Some(_) => {
+            problems.push("properties must be an object".to_string());
+            None
+        }
+        None => {
+            problems.push("missing properties".to_string());
+            None
+        }
+    };
+
+    match obj.get("required") {
+        None => {}
+        Some(Value::Array(required)) => {
+            for entry in required {
+                match entry.as_str() {
+                    Some(name) if properties.is_some_and(|p| p.contains_key(name)) => {}
+                    Some(name) if properties.is_some() => {
+                        problems.push(format!("required '{name}' is not in properties"));
+                    }
+                    Some(_) => {}
+                    None => problems.push(format!("required entry {entry} is not a string")),
+                }
+            }
+        }
+        Some(_) => problems.push("required must be an array".to_string()),
+    }
+
+    problems
+}
+
+/// Add an empty `properties` to an object schema that has none. MCP servers
+/// send `{"type": "object"}` for tools without parameters, which is valid
+/// JSON Schema but rejected by the API.
+pub fn fill_missing_properties(schema: &mut Value) {
+    if let Some(obj) = schema.as_object_mut() {
+        if obj.get("type").and_then(Value::as_str) == Some("object") {
+            obj.entry("properties").or_insert_with(|| Value::Object(Default::default()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ToolsConfig;
+    use crate::tools::{register_core_tools, ToolRegistry};
+    use serde_json::json;
+
+    #[test]
+    fn test_core_tool_schemas_are_valid() {
+        let mut registry = ToolRegistry::new();
+        register_core_tools(&mut registry, &ToolsConfig::default());
+        assert_eq!(registry.lint_schemas(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_lint_schema_violations() {
+        assert!(lint_schema(&json!({"type": "object", "properties": {}})).is_empty());
+        assert_eq!(lint_schema(&json!("nope")).len(), 1);
+        assert_eq!(
+            lint_schema(&json!({"type": "string", "properties": {}})),
+            vec!["type must be \"object\", got \"string\""]
+        );
+        assert_eq!(
+            lint_schema(&json!({
+                "type": "object",
+                "properties": {"path": {"type": "string"}},
+                "required": ["path", "mode"],
+            })),
+            vec!["required 'mode' is not in properties"]
+        );
+        assert_eq!(
+            lint_schema(&json!({"type": "object"})),
+            vec!["missing properties"]
+        );
+    }
+
+    #[test]
+    fn test_fill_missing_properties() {
+        let mut schema = json!({"type": "object"});
+        fill_missing_properties(&mut schema);
+        assert_eq!(schema, json!({"type": "object", "properties": {}}));
+        assert!(lint_schema(&schema).is_empty());
+
+        let mut other = json!({"type": "string"});
+        fill_missing_properties(&mut other);
+        assert_eq!(other, json!({"type": "string"}));
+    }
+}
@@ -1,3 +1,5 @@
+use std::path::{Path, PathBuf};
+
 use async_trait::async_trait;
 use serde_json::json;
 
@@ -5,9 +7,132 @@ use super::{schema_object, Tool, ToolContext, ToolResult};
 use crate::channels::Attachment;
 use crate::error::Result;
 
-pub struct SendFileTool;
+/// Maximum size of a file downloaded via `url` (20 MB).
+const MAX_DOWNLOAD_BYTES: usize = 20 * 1024 * 1024;
+
+pub struct SendFileTool {
+    allowed_domains: Vec<String>,
+    allowed_mime: Vec<String>,
+}
+
+impl SendFileTool {
+    pub fn new(allowed_domains: Vec<String>, allowed_mime: Vec<String>) -> Self {
+        Self {
+            allowed_domains,
+            allowed_mime,
+        }
+    }
+
+    /// Check a MIME type against the allowlist. Entries may end in `/*`
+    /// to match a whole family (e.g. `image/*`).
+    fn mime_allowed(&self, mime: &str) -> bool {
+        if self.allowed_mime.is_empty() {
+            return true;
+        }
+        let mime = mime.to_lowercase();
+        self.allowed_mime.iter().any(|allowed| {
+            let allowed = allowed.to_lowercase();
+            match allowed.strip_suffix("/*") {
+                Some(family) => mime.split('/').next() == Some(family),
+                None => allowed == "*" || allowed == mime,
+            }
+        })
+    }
+
+    /// Download `url` into `<root>/outbox/`, returning the saved path and
+    /// the MIME type reported by the server (if any).
+    async fn download(
+        &self,
+        url: &str,
+        root: &Path,
+    ) -> std::result::Result<(PathBuf, Option<String>), String> {
+        let parsed = url::Url::parse(url).map_err(|e| format!("Invalid URL: {e}"))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(format!("Unsupported URL scheme: {}", parsed.scheme()));
+        }
+
+        // Check domain allowlist
+        if !self.allowed_domains.is_empty() {
+            let domain = parsed.host_str().unwrap_or_default();
+            if !self.allowed_domains.iter().any(|d| domain.ends_with(d)) {
+                return Err(format!(
+                    "Domain '{domain}' is not in the allowed domains list"
+                ));
+            }
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(60))
+            .build()
+            .map_err(|e| format!("Failed to build HTTP client: {e}"))?;
+
+        let mut resp = client
+            .get(parsed.clone())
+            .send()
+            .await
+            .map_err(|e| format!("Download failed: {e}"))?;
 
-fn guess_mime(ext: &str) -> Option<&'static str> {
+        if !resp.status().is_success() {
+            return Err(format!("Download failed: HTTP {}", resp.status()));
+        }
+
+        if let Some(len) = resp.content_length() {
+            if len as usize > MAX_DOWNLOAD_BYTES {
+                return Err(format!(
+                    "File too large: {len} bytes (max {MAX_DOWNLOAD_BYTES})"
+                ));
+            }
+        }
+
+        let content_type = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.split(';').next().unwrap_or(v).trim().to_lowercase())
+            .filter(|v| !v.is_empty() && v != "application/octet-stream");
+
+        let mut data = Vec::new();
+        while let Some(chunk) = resp
+            .chunk()
+            .await
+            .map_err(|e| format!("Download failed: {e}"))?
+        {
+            if data.len() + chunk.len() > MAX_DOWNLOAD_BYTES {
+                return Err(format!(
+                    "File too large: exceeds {MAX_DOWNLOAD_BYTES} bytes"
+                ));
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        // Derive a filename from the URL path, falling back to a random one.
+        let mut filename = parsed
+            .path_segments()
+            .and_then(|mut s| s.next_back())
+            .filter(|s| !s.is_empty())
+            .map(sanitize_filename)
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()[..8].to_string());
+        if Path::new(&filename).extension().is_none() {
+            if let Some(ext) = content_type.as_deref().and_then(guess_ext) {
+                filename.push('.');
+                filename.push_str(ext);
+            }
+        }
+
+        let outbox = root.join("outbox");
+        std::fs::create_dir_all(&outbox)
+            .map_err(|e| format!("Failed to create outbox dir: {e}"))?;
+
+        // Avoid clobbering earlier downloads with the same name.
+        let prefix = &uuid::Uuid::new_v4().to_string()[..8];
+        let path = outbox.join(format!("{prefix}-{filename}"));
+        std::fs::write(&path, &data).map_err(|e| format!("Failed to save download: {e}"))?;
+
+        Ok((path, content_type))
+    }
+}
+
+pub(crate) fn guess_mime(ext: &str) -> Option<&'static str> {
     match ext {
         "png" => Some("image/png"),
         "jpg" | "jpeg" => Some("image/jpeg"),
@@ -38,6 +163,43 @@ fn guess_mime(ext: &str) -> Option<&'static str> {
     }
 }
 
+fn guess_ext(mime: &str) -> Option<&'static str> {
+    match mime {
+        "image/png" => Some("png"),
+        "image/jpeg" => Some("jpg"),
+        "image/gif" => Some("gif"),
+        "image/webp" => Some("webp"),
+        "image/svg+xml" => Some("svg"),
+        "audio/mpeg" => Some("mp3"),
+        "audio/ogg" => Some("ogg"),
+        "audio/wav" => Some("wav"),
+        "video/mp4" => Some("mp4"),
+        "video/webm" => Some("webm"),
+        "application/pdf" => Some("pdf"),
+        "application/zip" => Some("zip"),
+        "application/json" => Some("json"),
+        "text/csv" => Some("csv"),
+        "text/plain" => Some("txt"),
+        "text/html" => Some("html"),
+        _ => None,
+    }
+}
+
+/// Keep only characters that are safe in a filename.
+fn sanitize_filename(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>()
+        .trim_start_matches('.')
+        .to_string()
+}
+
 #[async_trait]
 impl Tool for SendFileTool {
     fn name(&self) -> &str {
@@ -46,8 +208,8 @@ impl Tool for SendFileTool {
 
     fn description(&self) -> &str {
         "Queue a file to be sent as media (image, audio, video, or document) in the response. \
-         Path is relative to the current directory. MIME type is auto-detected from extension \
-         but can be overridden."
+         Path is relative to the current directory. Alternatively pass a `url` to download the \
+         file first. MIME type is auto-detected but can be overridden."
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
@@ -57,39 +219,52 @@ impl Tool for SendFileTool {
                     "type": "string",
                     "description": "File path relative to current directory"
                 },
+                "url": {
+                    "type": "string",
+                    "description": "URL to download and send instead of a local path"
+                },
                 "mime_type": {
                     "type": "string",
                     "description": "Optional MIME type override (e.g. 'image/png'). Auto-detected from extension if omitted."
                 }
             }),
-            &["path"],
+            &[],
         )
     }
 
     async fn execute(&self, params: serde_json::Value, ctx: &ToolContext) -> Result<ToolResult> {
         let path = params["path"].as_str().unwrap_or_default();
-        if path.is_empty() {
-            return Ok(ToolResult::error("path is required"));
+        let url = params["url"].as_str().unwrap_or_default();
+        if path.is_empty() && url.is_empty() {
+            return Ok(ToolResult::error("path or url is required"));
+        }
+        if !path.is_empty() && !url.is_empty() {
+            return Ok(ToolResult::error("Specify either 'path' or 'url', not both"));
         }
 
-        let cwd = ctx.cwd.lock().unwrap().clone();
-        let full_path = cwd.join(path);
+        let downloaded = !url.is_empty();
+        let (full_path, downloaded_mime) = if !downloaded {
+            let cwd = ctx.cwd.lock().unwrap().clone();
+            (cwd.join(path), None)
+        } else {
+            // Into the file root, where the access check below allows it
+            match self.download(url, &ctx.file_root).await {
+                Ok(r) => r,
+                Err(e) => return Ok(ToolResult::error(e)),
+            }
+        };
 
-        // Resolve and validate within workspace
+        // Same boundary as the other file tools: the file root, minus
+        // Neko's internal data
         let canonical = match full_path.canonicalize() {
             Ok(p) => p,
             Err(e) => return Ok(ToolResult::error(format!("Cannot resolve path: {e}"))),
         };
-
-        let workspace_canonical = match ctx.workspace.canonicalize() {
-            Ok(p) => p,
-            Err(e) => return Ok(ToolResult::error(format!("Cannot resolve workspace: {e}"))),
+        let root = match ctx.check_file_access(&canonical) {
+            Ok(root) => root,
+            Err(e) => return Ok(ToolResult::error(e)),
         };
 
-        if !canonical.starts_with(&workspace_canonical) {
-            return Ok(ToolResult::error("Path is outside workspace boundary"));
-        }
-
         // Must be a regular file
         let metadata = match std::fs::metadata(&canonical) {
             Ok(m) => m,
@@ -100,10 +275,10 @@ impl Tool for SendFileTool {
             return Ok(ToolResult::error("Path is not a regular file"));
         }
 
-        // Determine MIME type
-        let mime_type = if let Some(explicit) = params["mime_type"].as_str() {
-            explicit.to_string()
-        } else {
+        // Determine MIME type. An explicit type only relabels the file; the
+        // allowlist applies to the detected type as well, so the model can't
+        // pass any file off as an allowed one.
+        let detected = downloaded_mime.unwrap_or_else(|| {
             let ext = canonical
                 .extension()
                 .and_then(|e| e.to_str())
@@ -112,7 +287,18 @@ impl Tool for SendFileTool {
             guess_mime(&ext)
                 .unwrap_or("application/octet-stream")
                 .to_string()
-        };
+        });
+        let explicit = params["mime_type"].as_str().filter(|m| !m.is_empty());
+        let mime_type = explicit.map_or_else(|| detected.clone(), String::from);
+
+        if let Some(denied) = [&detected, &mime_type].into_iter().find(|m| !self.mime_allowed(m)) {
+            if downloaded {
+                let _ = std::fs::remove_file(&canonical);
+            }
+            return Ok(ToolResult::error(format!(
+                "File type '{denied}' is not allowed by send_file_allowed_mime"
+            )));
+        }
 
         let attachment = Attachment {
             path: canonical.clone(),
@@ -122,7 +308,7 @@ impl Tool for SendFileTool {
         ctx.pending_attachments.lock().unwrap().push(attachment);
 
         let display_path = canonical
-            .strip_prefix(&workspace_canonical)
+            .strip_prefix(&root)
             .unwrap_or(&canonical)
             .display();
 
@@ -131,3 +317,55 @@ impl Tool for SendFileTool {
         )))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_explicit_mime_cannot_bypass_allowlist() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("dump.zip"), b"PK").unwrap();
+        std::fs::write(dir.path().join("chart.png"), b"png").unwrap();
+        let ctx = ToolContext::new(dir.path().to_path_buf());
+        let tool = SendFileTool::new(Vec::new(), vec!["image/*".to_string()]);
+
+        let result = tool
+            .execute(json!({"path": "dump.zip", "mime_type": "image/png"}), &ctx)
+            .await
+            .unwrap();
+        assert!(result.is_error);
+        assert!(result.output.contains("application/zip"));
+
+        let result = tool.execute(json!({"path": "chart.png"}), &ctx).await.unwrap();
+        assert!(!result.is_error);
+        assert_eq!(ctx.pending_attachments.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_send_stays_inside_file_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(workspace.join("project")).unwrap();
+        std::fs::write(workspace.join("project/report.txt"), b"ok").unwrap();
+        std::fs::write(workspace.join("secret.txt"), b"no").unwrap();
+        std::fs::create_dir_all(workspace.join("sessions")).unwrap();
+        std::fs::write(workspace.join("sessions/abc.jsonl"), b"{}").unwrap();
+        let ctx = ToolContext {
+            file_root: workspace.join("project"),
+            ..ToolContext::new(workspace.clone())
+        };
+        *ctx.cwd.lock().unwrap() = workspace.join("project");
+        let tool = SendFileTool::new(Vec::new(), Vec::new());
+
+        let result = tool.execute(json!({"path": "../secret.txt"}), &ctx).await.unwrap();
+        assert!(result.is_error, "{}", result.output);
+        let result = tool.execute(json!({"path": "report.txt"}), &ctx).await.unwrap();
+        assert!(!result.is_error, "{}", result.output);
+        assert!(result.output.contains("Queued report.txt"));
+
+        let ctx = ToolContext::new(workspace.clone());
+        let result = tool.execute(json!({"path": "sessions/abc.jsonl"}), &ctx).await.unwrap();
+        assert!(result.is_error);
+    }
+}
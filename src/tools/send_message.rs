@@ -0,0 +1,125 @@
+use async_trait::async_trait;
+use serde_json::json;
+
+use super::{schema_object, Tool, ToolContext, ToolResult};
+use crate::cron::parse_announce;
+use crate::error::Result;
+
+pub struct SendMessageTool {
+    /// Channels with a live outbound sender (e.g. "telegram").
+    enabled_channels: Vec<String>,
+    /// `channel:sender_id` entries allowed to use this tool, or `*` for
+    /// anyone. Empty = nobody.
+    admins: Vec<String>,
+}
+
+impl SendMessageTool {
+    pub fn new(enabled_channels: Vec<String>, admins: Vec<String>) -> Self {
+        Self {
+            enabled_channels,
+            admins,
+        }
+    }
+
+    fn sender_is_admin(&self, ctx: &ToolContext) -> bool {
+        if self.admins.iter().any(|a| a == "*") {
+            return true;
+        }
+        let Some(channel) = &ctx.channel else {
+            return false;
+        };
+        let Some(sender) = &channel.sender_id else {
+            return false;
+        };
+        let key = format!("{}:{sender}", channel.channel);
+        self.admins.contains(&key)
+    }
+}
+
+#[async_trait]
+impl Tool for SendMessageTool {
+    fn name(&self) -> &str {
+        "send_message"
+    }
+
+    fn description(&self) -> &str {
+        "Send a message to another chat right now, independent of your reply to the current conversation (e.g. notify a team channel that a build finished). Target format: channel:recipient_id, e.g. telegram:123456."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        schema_object(
+            json!({
+                "to": {
+                    "type": "string",
+                    "description": "Target as channel:recipient_id (e.g. telegram:123456)"
+                },
+                "text": {
+                    "type": "string",
+                    "description": "Message text"
+                }
+            }),
+            &["to", "text"],
+        )
+    }
+
+    async fn execute(&self, params: serde_json::Value, ctx: &ToolContext) -> Result<ToolResult> {
+        let to = params["to"].as_str().unwrap_or_default();
+        let text = params["text"].as_str().unwrap_or_default();
+
+        if text.trim().is_empty() {
+            return Ok(ToolResult::error("text must not be empty"));
+        }
+        if !self.sender_is_admin(ctx) {
+            return Ok(ToolResult::error(
+                "send_message is restricted to admins and this sender is not one",
+            ));
+        }
+
+        let target = match parse_announce(to) {
+            Ok(t) => t,
+            Err(e) => return Ok(ToolResult::error(e.to_string())),
+        };
+        if !self.enabled_channels.contains(&target.channel) {
+            return Ok(ToolResult::error(format!(
+                "Channel '{}' is not enabled. Enabled: {}",
+                target.channel,
+                if self.enabled_channels.is_empty() {
+                    "none".to_string()
+                } else {
+                    self.enabled_channels.join(", ")
+                }
+            )));
+        }
+
+        match ctx.send_message(&target.channel, &target.recipient_id, text) {
+            Ok(()) => Ok(ToolResult::success(format!("Message queued for {to}."))),
+            Err(e) => Ok(ToolResult::error(format!("Failed to send message: {e}"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::ChannelContext;
+
+    #[test]
+    fn test_admins_deny_by_default() {
+        let ctx = ToolContext {
+            channel: Some(ChannelContext {
+                channel: "telegram".to_string(),
+                recipient_id: "42".to_string(),
+                sender_id: Some("42".to_string()),
+            }),
+            ..ToolContext::new(std::env::temp_dir())
+        };
+        let tool = |admins: &[&str]| {
+            SendMessageTool::new(Vec::new(), admins.iter().map(|a| a.to_string()).collect())
+        };
+
+        assert!(!tool(&[]).sender_is_admin(&ctx));
+        assert!(!tool(&["telegram:7"]).sender_is_admin(&ctx));
+        assert!(tool(&["telegram:42"]).sender_is_admin(&ctx));
+        assert!(tool(&["*"]).sender_is_admin(&ctx));
+    }
+}
@@ -0,0 +1,196 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use grep_regex::RegexMatcherBuilder;
+use grep_searcher::sinks::UTF8;
+use grep_searcher::Searcher;
+use serde_json::json;
+
+use super::{schema_object, Tool, ToolContext, ToolResult};
+use crate::error::Result;
+use crate::skills::{self, Skill};
+
+/// Most matches returned per search.
+const MAX_RESULTS: usize = 10;
+
+/// Matching instruction lines shown per skill.
+const MAX_LINES_PER_SKILL: usize = 2;
+
+pub struct SkillSearchTool {
+    /// `[skills] paths`, searched along with `workspace/skills`.
+    extra_dirs: Vec<PathBuf>,
+}
+
+impl SkillSearchTool {
+    pub fn new(extra_dirs: Vec<PathBuf>) -> Self {
+        Self { extra_dirs }
+    }
+}
+
+struct Hit<'a> {
+    skill: &'a Skill,
+    /// Query words found in the name or description (1 for a regex match).
+    score: usize,
+    /// Matching lines of the instructions, as (line number, text).
+    lines: Vec<(u64, String)>,
+}
+
+/// Skills whose name, description or instructions match `query`: those
+/// matching in name/description first, then by number of matching
+/// instruction lines. Without `use_regex`, any word of the query matches.
+fn search_skills<'a>(
+    skills: &'a [Skill],
+    query: &str,
+    use_regex: bool,
+) -> std::result::Result<Vec<Hit<'a>>, String> {
+    let mut words = skills::keywords(query);
+    if words.is_empty() {
+        words.push(query.to_lowercase());
+    }
+    let pattern = if use_regex {
+        query.to_string()
+    } else {
+        words.iter().map(|w| regex::escape(w)).collect::<Vec<_>>().join("|")
+    };
+
+    let matcher = RegexMatcherBuilder::new()
+        .case_insensitive(true)
+        .build(&pattern)
+        .map_err(|e| format!("Invalid search pattern: {e}"))?;
+    let meta_re = regex::RegexBuilder::new(&pattern)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| format!("Invalid search pattern: {e}"))?;
+
+    let mut searcher = Searcher::new();
+    let mut hits = Vec::new();
+    for skill in skills {
+        let score = if use_regex {
+            usize::from(meta_re.is_match(&format!("{} {}", skill.name, skill.description)))
+        } else {
+            skills::relevance(skill, &words)
+        };
+        let mut lines = Vec::new();
+        let _ = searcher.search_slice(
+            &matcher,
+            skill.instructions.as_bytes(),
+            UTF8(|line_num, line| {
+                lines.push((line_num, line.trim_end().to_string()));
+                Ok(true)
+            }),
+        );
+        if score > 0 || !lines.is_empty() {
+            hits.push(Hit { skill, score, lines });
+        }
+    }
+
+    hits.sort_by(|a, b| b.score.cmp(&a.score).then(b.lines.len().cmp(&a.lines.len())));
+    Ok(hits)
+}
+
+#[async_trait]
+impl Tool for SkillSearchTool {
+    fn name(&self) -> &str {
+        "skill_search"
+    }
+
+    fn description(&self) -> &str {
+        "Search all installed skills, including ones not listed in the prompt, by name, \
+         description and instructions. Returns matching skills with their SKILL.md locations; \
+         read one to activate it."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        schema_object(
+            json!({
+                "query": {
+                    "type": "string",
+                    "description": "Keywords describing the task (e.g. 'convert pdf to text'); any word may match"
+                },
+                "regex": {
+                    "type": "boolean",
+                    "description": "Treat query as a regular expression. Default: false"
+                }
+            }),
+            &["query"],
+        )
+    }
+
+    async fn execute(&self, params: serde_json::Value, ctx: &ToolContext) -> Result<ToolResult> {
+        let query = params["query"].as_str().unwrap_or_default().trim();
+        if query.is_empty() {
+            return Ok(ToolResult::error("query must not be empty"));
+        }
+        let use_regex = params["regex"].as_bool().unwrap_or(false);
+
+        let installed = skills::load_skills(&ctx.workspace, &self.extra_dirs)?;
+        let hits = match search_skills(&installed, query, use_regex) {
+            Ok(hits) => hits,
+            Err(e) => return Ok(ToolResult::error(e)),
+        };
+        if hits.is_empty() {
+            return Ok(ToolResult::success(format!(
+                "No skills match '{query}' ({} installed).",
+                installed.len()
+            )));
+        }
+
+        let mut out = String::new();
+        for hit in hits.iter().take(MAX_RESULTS) {
+            out.push_str(&format!(
+                "{}: {}\n  {}/SKILL.md\n",
+                hit.skill.name,
+                hit.skill.description,
+                hit.skill.path.display()
+            ));
+            for (line_num, line) in hit.lines.iter().take(MAX_LINES_PER_SKILL) {
+                out.push_str(&format!("  {line_num}| {line}\n"));
+            }
+        }
+        if hits.len() > MAX_RESULTS {
+            out.push_str(&format!(
+                "({} more; refine the query)\n",
+                hits.len() - MAX_RESULTS
+            ));
+        }
+        Ok(ToolResult::success(out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn skill(name: &str, description: &str, instructions: &str) -> Skill {
+        Skill {
+            name: name.to_string(),
+            description: description.to_string(),
+            license: None,
+            compatibility: None,
+            metadata: HashMap::new(),
+            allowed_tools: vec![],
+            instructions: instructions.to_string(),
+            path: PathBuf::from(format!("/ws/skills/{name}")),
+        }
+    }
+
+    #[test]
+    fn test_search_skills() {
+        let skills = vec![
+            skill("invoices", "Bill customers", "# Steps\nExport the PDF with exec.\n"),
+            skill("pdf-processing", "Extract text from PDF files", "Use pdftotext.\n"),
+            skill("calendar", "Manage events", "Nothing relevant.\n"),
+        ];
+
+        let hits = search_skills(&skills, "pdf", false).unwrap();
+        let names: Vec<&str> = hits.iter().map(|h| h.skill.name.as_str()).collect();
+        assert_eq!(names, vec!["pdf-processing", "invoices"]);
+        assert_eq!(hits[1].lines, vec![(2, "Export the PDF with exec.".to_string())]);
+
+        let hits = search_skills(&skills, r"^#\s+steps", true).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert!(search_skills(&skills, "spreadsheet", false).unwrap().is_empty());
+        assert!(search_skills(&skills, "(", true).is_err());
+    }
+}
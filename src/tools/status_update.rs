@@ -0,0 +1,55 @@
+use async_trait::async_trait;
+use serde_json::json;
+
+use super::{schema_object, Tool, ToolContext, ToolResult};
+use crate::error::Result;
+
+/// Longest status message forwarded to the channel.
+const MAX_STATUS_CHARS: usize = 200;
+
+pub struct StatusUpdateTool;
+
+#[async_trait]
+impl Tool for StatusUpdateTool {
+    fn name(&self) -> &str {
+        "status_update"
+    }
+
+    fn description(&self) -> &str {
+        "Send a short progress message (e.g. \"Searching files…\") to the user immediately, while you keep working. Use during long multi-step tasks. Does not replace your final response."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        schema_object(
+            json!({
+                "message": {
+                    "type": "string",
+                    "description": "Short progress note to show the user"
+                }
+            }),
+            &["message"],
+        )
+    }
+
+    async fn execute(&self, params: serde_json::Value, ctx: &ToolContext) -> Result<ToolResult> {
+        let message = params["message"].as_str().unwrap_or_default().trim();
+        if message.is_empty() {
+            return Ok(ToolResult::error("message must not be empty"));
+        }
+
+        let Some(channel) = &ctx.channel else {
+            return Ok(ToolResult::success(
+                "No live channel for this conversation; status not sent. Continue with the task.",
+            ));
+        };
+
+        // Fire-and-forget: never block the turn on a busy outbound queue.
+        let text: String = message.chars().take(MAX_STATUS_CHARS).collect();
+        match ctx.send_message(&channel.channel, &channel.recipient_id, text) {
+            Ok(()) => Ok(ToolResult::success("Status sent.")),
+            Err(e) => Ok(ToolResult::success(format!(
+                "Status not sent ({e}). Continue with the task."
+            ))),
+        }
+    }
+}
@@ -0,0 +1,418 @@
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use super::{schema_object, Tool, ToolContext, ToolResult};
+use crate::error::Result;
+
+/// A step in a query path such as `a.b[0].c`.
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Parse `a.b[0].c` into segments. An empty path is the whole document.
+fn parse_path(path: &str) -> std::result::Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    for part in path.split('.').filter(|p| !p.is_empty()) {
+        let (key, mut rest) = part.split_at(part.find('[').unwrap_or(part.len()));
+        if !key.is_empty() {
+            segments.push(Segment::Key(key.to_string()));
+        }
+        while let Some(inner) = rest.strip_prefix('[') {
+            let end = inner
+                .find(']')
+                .ok_or_else(|| format!("Unclosed '[' in path '{path}'"))?;
+            let index = inner[..end]
+                .parse()
+                .map_err(|_| format!("Invalid index '{}' in path '{path}'", &inner[..end]))?;
+            segments.push(Segment::Index(index));
+            rest = &inner[end + 1..];
+        }
+        if !rest.is_empty() {
+            return Err(format!("Unexpected '{rest}' in path '{path}'"));
+        }
+    }
+    Ok(segments)
+}
+
+fn describe(segments: &[Segment]) -> String {
+    let mut out = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Key(key) if out.is_empty() => out.push_str(key),
+            Segment::Key(key) => out.push_str(&format!(".{key}")),
+            Segment::Index(i) => out.push_str(&format!("[{i}]")),
+        }
+    }
+    out
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl Format {
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "json" => Some(Format::Json),
+            "toml" => Some(Format::Toml),
+            "yaml" | "yml" => Some(Format::Yaml),
+            _ => None,
+        }
+    }
+}
+
+/// A parsed document. `serde_yaml` mappings keep their key order, so JSON
+/// and YAML edits don't reshuffle the file the way `serde_json::Value`
+/// (sorted keys) would.
+type Doc = serde_yaml::Value;
+
+/// Parse a document of any supported format for reading.
+fn parse_document(content: &str, format: Format) -> std::result::Result<Doc, String> {
+    match format {
+        Format::Json => serde_json::from_str(content).map_err(|e| format!("Invalid JSON: {e}")),
+        Format::Toml => {
+            let value: toml::Value =
+                toml::from_str(content).map_err(|e| format!("Invalid TOML: {e}"))?;
+            serde_yaml::to_value(value).map_err(|e| e.to_string())
+        }
+        Format::Yaml => serde_yaml::from_str(content).map_err(|e| format!("Invalid YAML: {e}")),
+    }
+}
+
+fn lookup<'a>(mut value: &'a Doc, segments: &[Segment]) -> std::result::Result<&'a Doc, String> {
+    for (i, segment) in segments.iter().enumerate() {
+        let next = match segment {
+            Segment::Key(key) => value.get(key.as_str()),
+            Segment::Index(index) => value.get(*index),
+        };
+        value = next.ok_or_else(|| format!("Nothing at '{}'", describe(&segments[..=i])))?;
+    }
+    Ok(value)
+}
+
+/// Set the value at `segments`, creating the last key if it is missing.
+/// Returns the updated document text.
+fn edit_document(
+    content: &str,
+    format: Format,
+    segments: &[Segment],
+    new_value: Value,
+) -> std::result::Result<String, String> {
+    let Some((last, parents)) = segments.split_last() else {
+        return Err("path must not be empty".to_string());
+    };
+    // Fail on a missing parent before touching anything
+    lookup(&parse_document(content, format)?, parents)?;
+
+    match format {
+        Format::Json => {
+            let mut doc: Doc = serde_json::from_str(content).map_err(|e| e.to_string())?;
+            set_value(&mut doc, segments, to_doc(&new_value)?)?;
+            // Keep compact files compact
+            let mut out = if content.trim_end().contains('\n') {
+                serde_json::to_string_pretty(&doc)
+            } else {
+                serde_json::to_string(&doc)
+            }
+            .map_err(|e| e.to_string())?;
+            if content.ends_with('\n') {
+                out.push('\n');
+            }
+            Ok(out)
+        }
+        Format::Yaml => {
+            let mut doc: Doc = serde_yaml::from_str(content).map_err(|e| e.to_string())?;
+            set_value(&mut doc, segments, to_doc(&new_value)?)?;
+            serde_yaml::to_string(&doc).map_err(|e| e.to_string())
+        }
+        Format::Toml => {
+            let mut doc: toml_edit::DocumentMut =
+                content.parse().map_err(|e| format!("Invalid TOML: {e}"))?;
+            let mut item = doc.as_item_mut();
+            for segment in parents {
+                item = match segment {
+                    Segment::Key(key) => item.get_mut(key.as_str()),
+                    Segment::Index(index) => item.get_mut(*index),
+                }
+                .ok_or_else(|| format!("Nothing at '{}'", describe(parents)))?;
+            }
+            let slot = match last {
+                Segment::Key(key) if item.is_table_like() => item.get_mut(key.as_str()),
+                Segment::Index(index) => item.get_mut(*index),
+                Segment::Key(_) => None,
+            }
+            .ok_or_else(|| format!("Cannot set '{}'", describe(segments)))?;
+            *slot = toml_edit::Item::Value(to_toml(&new_value)?);
+            Ok(doc.to_string())
+        }
+    }
+}
+
+fn set_value(doc: &mut Doc, segments: &[Segment], new_value: Doc) -> std::result::Result<(), String> {
+    let Some((last, parents)) = segments.split_last() else {
+        return Err("path must not be empty".to_string());
+    };
+    let mut parent = doc;
+    for segment in parents {
+        parent = match segment {
+            Segment::Key(key) => parent.get_mut(key.as_str()),
+            Segment::Index(index) => parent.get_mut(*index),
+        }
+        .ok_or_else(|| format!("Nothing at '{}'", describe(parents)))?;
+    }
+    match (last, parent) {
+        // An existing key keeps its position
+        (Segment::Key(key), Doc::Mapping(map)) => {
+            map.insert(Doc::String(key.clone()), new_value);
+        }
+        (Segment::Index(index), Doc::Sequence(items)) if *index < items.len() => {
+            items[*index] = new_value;
+        }
+        (Segment::Index(index), Doc::Sequence(items)) if *index == items.len() => {
+            items.push(new_value);
+        }
+        _ => return Err(format!("Cannot set '{}'", describe(segments))),
+    }
+    Ok(())
+}
+
+fn to_doc(value: &Value) -> std::result::Result<Doc, String> {
+    serde_yaml::to_value(value).map_err(|e| format!("Invalid value: {e}"))
+}
+
+fn to_toml(value: &Value) -> std::result::Result<toml_edit::Value, String> {
+    Ok(match value {
+        Value::Null => return Err("TOML has no null value".to_string()),
+        Value::Bool(b) => (*b).into(),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => i.into(),
+            None => n.as_f64().unwrap_or_default().into(),
+        },
+        Value::String(s) => s.as_str().into(),
+        Value::Array(items) => {
+            let mut array = toml_edit::Array::new();
+            for item in items {
+                array.push(to_toml(item)?);
+            }
+            toml_edit::Value::Array(array)
+        }
+        Value::Object(map) => {
+            let mut table = toml_edit::InlineTable::new();
+            for (key, item) in map {
+                table.insert(key, to_toml(item)?);
+            }
+            toml_edit::Value::InlineTable(table)
+        }
+    })
+}
+
+/// Resolve `path` against the cwd for a file tool, enforcing the file root.
+fn resolve_file(ctx: &ToolContext, path: &str) -> std::result::Result<(PathBuf, Format), String> {
+    let cwd = ctx.cwd.lock().unwrap().clone();
+    let canonical = cwd
+        .join(path)
+        .canonicalize()
+        .map_err(|e| format!("Cannot resolve path: {e}"))?;
+    ctx.check_file_access(&canonical)?;
+    let format = Format::from_path(&canonical)
+        .ok_or("Unsupported file type (expected .json, .toml, .yaml or .yml)")?;
+    Ok((canonical, format))
+}
+
+pub struct StructuredReadTool;
+
+#[async_trait]
+impl Tool for StructuredReadTool {
+    fn name(&self) -> &str {
+        "structured_read"
+    }
+
+    fn description(&self) -> &str {
+        "Read one value from a JSON, TOML or YAML file by query path (e.g. `package.version`, \
+         `servers[0].port`). Returns the value as JSON. Path is relative to the current directory."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        schema_object(
+            json!({
+                "path": {
+                    "type": "string",
+                    "description": "File path relative to current directory (.json, .toml, .yaml, .yml)"
+                },
+                "query": {
+                    "type": "string",
+                    "description": "Dotted path with [n] for array items, e.g. a.b[0].c. Empty = whole document"
+                }
+            }),
+            &["path"],
+        )
+    }
+
+    async fn execute(&self, params: serde_json::Value, ctx: &ToolContext) -> Result<ToolResult> {
+        let path = params["path"].as_str().unwrap_or_default();
+        let query = params["query"].as_str().unwrap_or_default();
+
+        let result = resolve_file(ctx, path).and_then(|(file, format)| {
+            let segments = parse_path(query)?;
+            let content =
+                std::fs::read_to_string(&file).map_err(|e| format!("Failed to read file: {e}"))?;
+            let doc = parse_document(&content, format)?;
+            let value = lookup(&doc, &segments)?;
+            serde_json::to_string_pretty(value).map_err(|e| e.to_string())
+        });
+        match result {
+            Ok(value) => Ok(ToolResult::success(value)),
+            Err(e) => Ok(ToolResult::error(e)),
+        }
+    }
+}
+
+pub struct StructuredEditTool;
+
+#[async_trait]
+impl Tool for StructuredEditTool {
+    fn name(&self) -> &str {
+        "structured_edit"
+    }
+
+    fn description(&self) -> &str {
+        "Set one value in a JSON, TOML or YAML file by query path and write it back (e.g. bump \
+         `package.version`, toggle `features.beta`). A missing last key is created. TOML keeps \
+         its comments and layout. JSON and YAML keep key order but are rewritten: JSON is \
+         re-indented with 2 spaces and YAML loses its comments and formatting."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        schema_object(
+            json!({
+                "path": {
+                    "type": "string",
+                    "description": "File path relative to current directory (.json, .toml, .yaml, .yml)"
+                },
+                "query": {
+                    "type": "string",
+                    "description": "Dotted path with [n] for array items, e.g. a.b[0].c"
+                },
+                "value": {
+                    "type": ["string", "number", "integer", "boolean", "array", "object"],
+                    "description": "New value (e.g. 3, true, \"text\", [1, 2]). A string holding valid JSON is stored as that value; other text is stored as a string."
+                }
+            }),
+            &["path", "query", "value"],
+        )
+    }
+
+    async fn execute(&self, params: serde_json::Value, ctx: &ToolContext) -> Result<ToolResult> {
+        let path = params["path"].as_str().unwrap_or_default();
+        let query = params["query"].as_str().unwrap_or_default();
+        let new_value = match params.get("value") {
+            None | Some(Value::Null) => return Ok(ToolResult::error("Missing 'value'")),
+            Some(Value::String(raw)) => {
+                serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.clone()))
+            }
+            Some(other) => other.clone(),
+        };
+
+        if let Some(msg) = crate::workspace::check_quota(&ctx.workspace, ctx.workspace_quota) {
+            tracing::warn!("{msg}");
+            return Ok(ToolResult::error(msg));
+        }
+
+        let result = resolve_file(ctx, path).and_then(|(file, format)| {
+            let segments = parse_path(query)?;
+            let content =
+                std::fs::read_to_string(&file).map_err(|e| format!("Failed to read file: {e}"))?;
+            let updated = edit_document(&content, format, &segments, new_value)?;
+            // A crash mid-write must not leave a truncated config behind
+            crate::fs_util::write_atomic(&file, updated.as_bytes())
+                .map_err(|e| format!("Failed to write file: {e}"))?;
+            Ok(format!("Set {} in {path}", describe(&segments)))
+        });
+        match result {
+            Ok(msg) => Ok(ToolResult::success(msg)),
+            Err(e) => Ok(ToolResult::error(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_path() {
+        assert_eq!(
+            parse_path("a.b[0].c").unwrap(),
+            vec![
+                Segment::Key("a".into()),
+                Segment::Key("b".into()),
+                Segment::Index(0),
+                Segment::Key("c".into()),
+            ]
+        );
+        assert_eq!(parse_path("[1][2]").unwrap(), vec![Segment::Index(1), Segment::Index(2)]);
+        assert!(parse_path("").unwrap().is_empty());
+        assert!(parse_path("a[x]").is_err());
+        assert!(parse_path("a[0").is_err());
+    }
+
+    #[test]
+    fn test_edit_toml_keeps_comments() {
+        let content = "# app config\n[package]\nname = \"app\" # the name\nversion = \"1.0.0\"\n\n[[bin]]\nport = 80\n";
+        let segments = parse_path("package.version").unwrap();
+        let updated = edit_document(content, Format::Toml, &segments, json!("1.1.0")).unwrap();
+        assert!(updated.contains("# app config"));
+        assert!(updated.contains("name = \"app\" # the name"));
+        assert!(updated.contains("version = \"1.1.0\""));
+
+        let segments = parse_path("bin[0].port").unwrap();
+        let updated = edit_document(&updated, Format::Toml, &segments, json!(8080)).unwrap();
+        let doc = parse_document(&updated, Format::Toml).unwrap();
+        assert_eq!(lookup(&doc, &segments).unwrap(), &Doc::from(8080));
+
+        let missing = parse_path("nope.flag").unwrap();
+        assert!(edit_document(content, Format::Toml, &missing, json!(true)).is_err());
+    }
+
+    #[test]
+    fn test_edit_json_and_yaml() {
+        let content = "{\n  \"b\": 1,\n  \"a\": {\"flags\": [true, false]}\n}\n";
+        let segments = parse_path("a.flags[1]").unwrap();
+        let updated = edit_document(content, Format::Json, &segments, json!(true)).unwrap();
+        assert!(updated.find("\"b\"").unwrap() < updated.find("\"a\"").unwrap());
+        let doc = parse_document(&updated, Format::Json).unwrap();
+        assert_eq!(lookup(&doc, &segments).unwrap(), &Doc::Bool(true));
+
+        let content = "name: app\nreplicas: 1\n";
+        let segments = parse_path("replicas").unwrap();
+        let updated = edit_document(content, Format::Yaml, &segments, json!(3)).unwrap();
+        assert_eq!(updated, "name: app\nreplicas: 3\n");
+    }
+
+    #[tokio::test]
+    async fn test_edit_takes_any_json_value() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("app.json");
+        std::fs::write(&file, "{\"z\": 1, \"a\": 2}").unwrap();
+        let ctx = ToolContext::new(dir.path().canonicalize().unwrap());
+        let edit = |value: Value| {
+            let params = json!({ "path": "app.json", "query": "z", "value": value });
+            StructuredEditTool.execute(params, &ctx)
+        };
+
+        assert!(!edit(json!(["x", 2])).await.unwrap().is_error);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "{\"z\":[\"x\",2],\"a\":2}");
+        assert!(!edit(json!("{\"on\": true}")).await.unwrap().is_error);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "{\"z\":{\"on\":true},\"a\":2}");
+
+        let params = json!({ "path": "app.json", "query": "z" });
+        assert!(StructuredEditTool.execute(params, &ctx).await.unwrap().is_error);
+    }
+}
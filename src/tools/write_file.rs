@@ -32,6 +32,17 @@ impl Tool for WriteFileTool {
         )
     }
 
+    /// Absolute paths, `..` and hidden files (`.env`, `.git/...`) are risky;
+    /// ordinary workspace files are not.
+    fn requires_confirmation(&self, params: &serde_json::Value) -> bool {
+        let path = std::path::Path::new(params["path"].as_str().unwrap_or_default());
+        path.components().any(|c| match c {
+            std::path::Component::Normal(name) => name.to_string_lossy().starts_with('.'),
+            std::path::Component::CurDir => false,
+            _ => true,
+        })
+    }
+
     async fn execute(&self, params: serde_json::Value, ctx: &ToolContext) -> Result<ToolResult> {
         let path = params["path"].as_str().unwrap_or_default();
         let content = params["content"].as_str().unwrap_or_default();
@@ -39,18 +50,30 @@ impl Tool for WriteFileTool {
         let cwd = ctx.cwd.lock().unwrap().clone();
         let full_path = cwd.join(path);
 
+        // Count only what the write adds; an overwrite replaces the old file
+        let existing = std::fs::metadata(&full_path).map_or(0, |m| m.len());
+        let incoming = (content.len() as u64).saturating_sub(existing);
+        if let Some(msg) =
+            crate::workspace::check_quota_for(&ctx.workspace, ctx.workspace_quota, incoming)
+        {
+            tracing::warn!("{msg}");
+            return Ok(ToolResult::error(msg));
+        }
+
         // Security: use parent check since file may not exist yet
         if let Some(parent) = full_path.parent() {
             if let Err(e) = std::fs::create_dir_all(parent) {
                 return Ok(ToolResult::error(format!("Failed to create directories: {e}")));
             }
 
-            // Verify parent is within workspace
-            if let (Ok(parent_canonical), Ok(workspace_canonical)) =
-                (parent.canonicalize(), ctx.workspace.canonicalize())
-            {
-                if !parent_canonical.starts_with(&workspace_canonical) {
-                    return Ok(ToolResult::error("Path is outside workspace boundary"));
+            // Verify the target is within the file root
+            if let Ok(parent_canonical) = parent.canonicalize() {
+                let target = match full_path.file_name() {
+                    Some(name) => parent_canonical.join(name),
+                    None => parent_canonical,
+                };
+                if let Err(e) = ctx.check_file_access(&target) {
+                    return Ok(ToolResult::error(e));
                 }
             }
         }
@@ -61,3 +84,28 @@ impl Tool for WriteFileTool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_write_file_refused_over_quota() {
+        let tmp = TempDir::new().unwrap();
+        std::fs::write(tmp.path().join("big.bin"), vec![0u8; 3000]).unwrap();
+        let ctx = ToolContext {
+            workspace_quota: Some(4096),
+            ..ToolContext::new(tmp.path().to_path_buf())
+        };
+        let write = |content: String| json!({ "path": "out.txt", "content": content });
+
+        let result = WriteFileTool.execute(write("x".repeat(2000)), &ctx).await.unwrap();
+        assert!(result.is_error);
+        assert!(result.output.contains("Workspace quota exceeded"), "{}", result.output);
+        assert!(!tmp.path().join("out.txt").exists());
+
+        let result = WriteFileTool.execute(write("x".repeat(500)), &ctx).await.unwrap();
+        assert!(!result.is_error, "{}", result.output);
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+
+use walkdir::WalkDir;
+
+use crate::error::{NekoError, Result};
+
+/// Total size in bytes of all regular files under `path`.
+pub fn dir_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Format a byte count for display (e.g. "12.3 MB").
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+/// How long a measured workspace size is trusted before the tree is
+/// walked again. Admitted writes are added to it in between.
+const USAGE_TTL: Duration = Duration::from_secs(60);
+
+/// Workspace size as of `measured_at`, plus the bytes admitted since.
+struct CachedUsage {
+    bytes: u64,
+    measured_at: Instant,
+}
+
+fn usage_cache() -> &'static Mutex<HashMap<PathBuf, CachedUsage>> {
+    static CACHE: OnceLock<Mutex<HashMap<PathBuf, CachedUsage>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// Returns an error message if the workspace exceeds `max_bytes`.
+pub fn check_quota(workspace: &Path, max_bytes: Option<u64>) -> Option<String> {
+    check_quota_for(workspace, max_bytes, 0)
+}
+
+/// Returns an error message if adding `incoming` bytes would take the
+/// workspace past `max_bytes`; otherwise counts them as used.
+///
+/// The size comes from a cache rather than a walk per write: the tree is
+/// walked at most every [`USAGE_TTL`], or again before refusing, so files
+/// deleted since the last walk don't block writes.
+pub fn check_quota_for(workspace: &Path, max_bytes: Option<u64>, incoming: u64) -> Option<String> {
+    let max = max_bytes?;
+    let mut cache = usage_cache().lock().unwrap();
+    let measure = || CachedUsage {
+        bytes: dir_size(workspace),
+        measured_at: Instant::now(),
+    };
+    let mut fresh = false;
+    let usage = cache.entry(workspace.to_path_buf()).or_insert_with(|| {
+        fresh = true;
+        measure()
+    });
+    let stale = usage.measured_at.elapsed() > USAGE_TTL;
+    if !fresh && (stale || usage.bytes.saturating_add(incoming) > max) {
+        *usage = measure();
+    }
+    let used = usage.bytes;
+    if used.saturating_add(incoming) <= max {
+        usage.bytes = used.saturating_add(incoming);
+        return None;
+    }
+    let room = if incoming == 0 {
+        String::new()
+    } else {
+        format!(", no room for {} more", format_bytes(incoming))
+    };
+    Some(format!(
+        "Workspace quota exceeded: {} used of {} allowed{room}. Free up space before writing more.",
+        format_bytes(used),
+        format_bytes(max)
+    ))
+}
+
+// ---------------------------------------------------------------------------
+// Garbage collection
+// ---------------------------------------------------------------------------
+
+/// A file eligible for removal by `neko workspace gc`.
+#[derive(Debug, Clone)]
+pub struct GcEntry {
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// Parse an age like "30d", "12h", "2w" or "45m" into a duration.
+pub fn parse_age(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let n: u64 = num
+        .parse()
+        .map_err(|_| NekoError::Config(format!("Invalid age '{s}': expected e.g. 30d, 12h")))?;
+    let unit_secs: u64 = match unit {
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        _ => {
+            return Err(NekoError::Config(format!(
+                "Invalid age unit '{unit}': use m, h, d or w"
+            )))
+        }
+    };
+    // A wrapped value would be a tiny age, and gc would delete recent files
+    let secs = n
+        .checked_mul(unit_secs)
+        .ok_or_else(|| NekoError::Config(format!("Age '{s}' is too large")))?;
+    Ok(Duration::from_secs(secs))
+}
+
+/// Collect prunable files older than `older_than`:
+/// archived transcripts (`sessions/<id>.<timestamp>.jsonl`), recall logs
+/// (`memory/recall/*.md`), rotated cron history (`cron/history.*` other than
+/// the live `history.jsonl`) and stray `.tmp` files anywhere in the workspace.
+pub fn gc_candidates(workspace: &Path, older_than: Duration) -> Vec<GcEntry> {
+    let cutoff = SystemTime::now()
+        .checked_sub(older_than)
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+
+    let mut entries: Vec<GcEntry> = WalkDir::new(workspace)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| is_prunable(workspace, e.path()))
+        .filter_map(|e| {
+            let meta = e.metadata().ok()?;
+            let modified = meta.modified().ok()?;
+            (modified < cutoff).then(|| GcEntry {
+                path: e.path().to_path_buf(),
+                size: meta.len(),
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    entries
+}
+
+fn is_prunable(workspace: &Path, path: &Path) -> bool {
+    let Ok(rel) = path.strip_prefix(workspace) else {
+        return false;
+    };
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if name.ends_with(".tmp") {
+        return true;
+    }
+    let parent = rel.parent().unwrap_or(Path::new(""));
+
+    if parent == Path::new("sessions") {
+        // Live transcripts are `<id>.jsonl`; archives carry an extra timestamp.
+        return name
+            .strip_suffix(".jsonl")
+            .is_some_and(|stem| stem.contains('.'));
+    }
+    if parent == Path::new("memory/recall") {
+        return name.ends_with(".md");
+    }
+    if parent == Path::new("cron") {
+        return name.starts_with("history.") && name != "history.jsonl";
+    }
+    false
+}
+
+/// Delete the given entries, returning (files removed, bytes reclaimed).
+pub fn gc_remove(entries: &[GcEntry]) -> (usize, u64) {
+    let mut removed = 0;
+    let mut reclaimed = 0;
+    for entry in entries {
+        match std::fs::remove_file(&entry.path) {
+            Ok(()) => {
+                removed += 1;
+                reclaimed += entry.size;
+            }
+            Err(e) => tracing::warn!("Failed to remove {}: {e}", entry.path.display()),
+        }
+    }
+    (removed, reclaimed)
+}
+
+// ---------------------------------------------------------------------------
+// Memory reset
+// ---------------------------------------------------------------------------
+
+/// Contents of a fresh `memory/MEMORY.md`.
+pub const DEFAULT_MEMORY_MD: &str =
+    "# Memory\n\nThis file is always loaded into the agent's context.\n";
+
+/// Recall logs (`memory/recall/*.md`), sorted by path.
+pub fn recall_logs(workspace: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(workspace.join("memory").join("recall")) else {
+        return Vec::new();
+    };
+    let mut logs: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "md"))
+        .collect();
+    logs.sort();
+    logs
+}
+
+/// Forget what the agent has picked up across sessions: delete all recall
+/// logs and reset `memory/MEMORY.md` to `DEFAULT_MEMORY_MD` (the old copy is
+/// kept in `memory/.backups/`). Other memory files and `kv.json` are left
+/// alone. Returns the number of recall logs removed.
+pub fn reset_memory(workspace: &Path) -> Result<usize> {
+    let logs = recall_logs(workspace);
+    for log in &logs {
+        std::fs::remove_file(log)?;
+    }
+
+    let memory_dir = workspace.join("memory");
+    std::fs::create_dir_all(&memory_dir)?;
+    crate::tools::memory_flush::backup_file(&memory_dir, "MEMORY.md")?;
+    crate::fs_util::write_atomic(&memory_dir.join("MEMORY.md"), DEFAULT_MEMORY_MD.as_bytes())?;
+    Ok(logs.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_dir_size_and_quota() {
+        let tmp = TempDir::new().unwrap();
+        std::fs::create_dir_all(tmp.path().join("a")).unwrap();
+        std::fs::write(tmp.path().join("a").join("f"), vec![0u8; 1500]).unwrap();
+        std::fs::write(tmp.path().join("g"), vec![0u8; 500]).unwrap();
+
+        assert_eq!(dir_size(tmp.path()), 2000);
+        assert!(check_quota(tmp.path(), None).is_none());
+        assert!(check_quota(tmp.path(), Some(4096)).is_none());
+        assert!(check_quota(tmp.path(), Some(1024)).is_some());
+        assert!(check_quota_for(tmp.path(), Some(4096), 2000).is_none());
+        assert!(check_quota_for(tmp.path(), Some(4096), 3000).is_some());
+    }
+
+    #[test]
+    fn test_quota_usage_is_cached_between_writes() {
+        let tmp = TempDir::new().unwrap();
+        std::fs::write(tmp.path().join("f"), vec![0u8; 1000]).unwrap();
+        assert!(check_quota_for(tmp.path(), Some(4096), 1000).is_none());
+        std::fs::write(tmp.path().join("g"), vec![0u8; 1000]).unwrap();
+
+        // Admitted writes count without another walk
+        assert!(check_quota_for(tmp.path(), Some(4096), 2000).is_none());
+        std::fs::write(tmp.path().join("h"), vec![0u8; 2000]).unwrap();
+        assert!(check_quota_for(tmp.path(), Some(4096), 2000).is_some());
+
+        // A refusal re-measures, so space freed since the last walk counts
+        std::fs::remove_file(tmp.path().join("h")).unwrap();
+        assert!(check_quota_for(tmp.path(), Some(4096), 2000).is_none());
+    }
+
+    #[test]
+    fn test_gc_candidates() {
+        let tmp = TempDir::new().unwrap();
+        let ws = tmp.path();
+        for dir in ["sessions", "memory/recall", "cron"] {
+            std::fs::create_dir_all(ws.join(dir)).unwrap();
+        }
+        for file in [
+            "sessions/abc.jsonl",
+            "sessions/abc.20260101T000000.jsonl",
+            "sessions/sessions.json.tmp",
+            "memory/recall/2026-01-01.md",
+            "memory/MEMORY.md",
+            "cron/history.jsonl",
+            "cron/history.1.jsonl",
+        ] {
+            std::fs::write(ws.join(file), "x").unwrap();
+        }
+
+        let found: Vec<String> = gc_candidates(ws, Duration::ZERO)
+            .iter()
+            .map(|e| e.path.strip_prefix(ws).unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                "cron/history.1.jsonl",
+                "memory/recall/2026-01-01.md",
+                "sessions/abc.20260101T000000.jsonl",
+                "sessions/sessions.json.tmp",
+            ]
+        );
+        assert!(gc_candidates(ws, Duration::from_secs(3600)).is_empty());
+        assert_eq!(parse_age("30d").unwrap(), Duration::from_secs(30 * 86_400));
+        assert!(parse_age("3x").is_err());
+    }
+
+    #[test]
+    fn test_parse_age_overflow() {
+        assert_eq!(parse_age("2w").unwrap(), Duration::from_secs(14 * 86_400));
+        assert!(parse_age(&format!("{}w", u64::MAX / 86_400)).is_err());
+        assert!(parse_age("99999999999999999999d").is_err());
+        let max_minutes = u64::MAX / 60;
+        assert_eq!(
+            parse_age(&format!("{max_minutes}m")).unwrap(),
+            Duration::from_secs(max_minutes * 60)
+        );
+    }
+
+    #[test]
+    fn test_reset_memory() {
+        let tmp = TempDir::new().unwrap();
+        let ws = tmp.path();
+        std::fs::create_dir_all(ws.join("memory/recall")).unwrap();
+        for file in [
+            "memory/recall/2026-01-01.md",
+            "memory/recall/2026-01-02.md",
+            "memory/MEMORY.md",
+            "memory/notes.md",
+        ] {
+            std::fs::write(ws.join(file), "x").unwrap();
+        }
+
+        assert_eq!(reset_memory(ws).unwrap(), 2);
+        assert!(recall_logs(ws).is_empty());
+        assert_eq!(
+            std::fs::read_to_string(ws.join("memory/MEMORY.md")).unwrap(),
+            DEFAULT_MEMORY_MD
+        );
+        assert!(ws.join("memory/notes.md").exists());
+        let backups =
+            crate::tools::memory_flush::list_backups(&ws.join("memory"), "MEMORY.md").unwrap();
+        assert_eq!(backups.len(), 1);
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(5 * 1024 * 1024), "5.0 MB");
+    }
+}